@@ -0,0 +1,115 @@
+//! The steps of packaging on their own and all of them together, on synthetic apps of the
+//! shapes in `src/synthetic.rs`. Compare to an earlier run with
+//! `cargo bench -- --save-baseline before` and `cargo bench -- --baseline before`.
+
+#[macro_use]
+extern crate criterion;
+extern crate base64;
+extern crate ignore;
+extern crate serde_json;
+extern crate tempfile;
+
+#[path = "../src/synthetic.rs"]
+#[allow(dead_code)]
+mod synthetic;
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use ignore::WalkBuilder;
+use serde_json::{Map, Value};
+use std::{fs, path::Path, process::Command};
+use synthetic::SHAPES;
+use tempfile::{Builder, TempDir};
+
+fn synthetic_app(shape: synthetic::Shape) -> TempDir {
+    let app = Builder::new().prefix("hcdev-bench").tempdir().unwrap();
+    shape.generate(app.path()).unwrap();
+    app
+}
+
+fn files(dir: &Path) -> Vec<Vec<u8>> {
+    WalkBuilder::new(dir)
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .map(|e| fs::read(e.path()).unwrap())
+        .collect()
+}
+
+fn walk(c: &mut Criterion) {
+    let mut group = c.benchmark_group("walk");
+
+    for shape in SHAPES {
+        let app = synthetic_app(*shape);
+
+        group.bench_function(shape.name(), |b| {
+            b.iter(|| WalkBuilder::new(app.path()).build().count())
+        });
+    }
+
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("base64");
+
+    for size in &[4 << 10, 1 << 20, 16 << 20] {
+        let content = vec![0x5a; *size];
+
+        group.throughput(Throughput::Bytes(*size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &content, |b, content| {
+            b.iter(|| base64::encode(content))
+        });
+    }
+
+    group.finish();
+}
+
+fn serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("json");
+
+    for shape in SHAPES {
+        let app = synthetic_app(*shape);
+        let bundle: Map<String, Value> = files(app.path())
+            .iter()
+            .enumerate()
+            .map(|(i, content)| (i.to_string(), base64::encode(content).into()))
+            .collect();
+        let bundle = Value::Object(bundle);
+
+        group.bench_function(shape.name(), |b| {
+            b.iter(|| serde_json::to_vec(&bundle).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn package(c: &mut Criterion) {
+    let mut group = c.benchmark_group("package");
+    group.sample_size(10);
+
+    for shape in SHAPES {
+        let app = synthetic_app(*shape);
+        let output = Builder::new().prefix("hcdev-bench").tempdir().unwrap();
+        let bundle_path = output.path().join("bundle.json");
+
+        group.bench_function(shape.name(), |b| {
+            b.iter(|| {
+                let status = Command::new(env!("CARGO_BIN_EXE_hcdev"))
+                    .args(&["package", "-o"])
+                    .arg(&bundle_path)
+                    .current_dir(app.path())
+                    .output()
+                    .unwrap()
+                    .status;
+
+                assert!(status.success());
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, walk, encode, serialize, package);
+criterion_main!(benches);
@@ -0,0 +1,91 @@
+//! Command aliases from the `[alias]` tables of the project and global settings, which encode
+//! the conventions of a team like git's aliases do:
+//!
+//! ```toml
+//! [alias]
+//! release = "package --profile ci -o dist/app.json"
+//! ```
+//!
+//! `hcdev release --debug-info` then runs `hcdev package --profile ci -o dist/app.json
+//! --debug-info`. An alias is expanded once, before the command line is parsed, and only if
+//! there is no built-in command of the same name. Its arguments are split at whitespace.
+
+use cli::find_project_root;
+use config_files::{Global, Project};
+use error::DefaultResult;
+use std::{env, ffi::OsString, path::Path};
+
+/// The arguments the alias stands for, from the project settings of the given directory or
+/// else the global ones
+pub fn lookup(
+    name: &str,
+    config: Option<&Path>,
+    project_dir: Option<&Path>,
+) -> DefaultResult<Option<Vec<String>>> {
+    let dir = match project_dir {
+        Some(project_dir) => project_dir.to_path_buf(),
+        None => env::current_dir()?,
+    };
+
+    let project = match config {
+        Some(config) => Project::from_file(config)?,
+        None => Project::from_dir(find_project_root(&dir).unwrap_or(dir))?,
+    };
+
+    Ok(resolve(name, &project, &Global::load()?))
+}
+
+fn resolve(name: &str, project: &Project, global: &Global) -> Option<Vec<String>> {
+    project
+        .alias
+        .get(name)
+        .or_else(|| global.alias.get(name))
+        .map(|expansion| expansion.split_whitespace().map(String::from).collect())
+}
+
+/// Replaces the argument at `index`, the alias, with its expansion
+pub fn splice(argv: &[OsString], index: usize, expansion: Vec<String>) -> Vec<OsString> {
+    let mut expanded: Vec<OsString> = argv[..index].to_vec();
+    expanded.extend(expansion.into_iter().map(OsString::from));
+    expanded.extend(argv[index + 1..].iter().cloned());
+
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_aliases_override_global_ones() {
+        let mut project = Project::default();
+        let mut global = Global::default();
+
+        global.alias.insert("pr".into(), "package --locked".into());
+        global.alias.insert("st".into(), "status --ignored".into());
+        project
+            .alias
+            .insert("pr".into(), "package  --profile ci".into());
+
+        assert_eq!(
+            resolve("pr", &project, &global),
+            Some(vec!["package".into(), "--profile".into(), "ci".into()])
+        );
+        assert_eq!(
+            resolve("st", &project, &global),
+            Some(vec!["status".into(), "--ignored".into()])
+        );
+        assert_eq!(resolve("nope", &project, &global), None);
+
+        let argv: Vec<OsString> =
+            vec!["hcdev".into(), "--offline".into(), "pr".into(), "-o".into()];
+
+        assert_eq!(
+            splice(&argv, 2, vec!["package".into(), "--locked".into()]),
+            vec!["hcdev", "--offline", "package", "--locked", "-o"]
+                .into_iter()
+                .map(OsString::from)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,292 @@
+//! Errors and warnings in the formats CI systems show inline on pull requests.
+//!
+//! GitHub Actions picks up workflow commands like `::error file=...::` from the log, so these
+//! are printed right away. GitLab reads a Code Quality report instead, which is collected and
+//! written to `gl-code-quality-report.json` once the command is done.
+
+use error::DefaultResult;
+use serde_json;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+use util;
+
+pub const GITLAB_REPORT_FILE_NAME: &str = "gl-code-quality-report.json";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Flavor {
+    GitHub,
+    GitLab,
+}
+
+impl FromStr for Flavor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Flavor, String> {
+        match s {
+            "github" => Ok(Flavor::GitHub),
+            "gitlab" => Ok(Flavor::GitLab),
+            _ => Err(format!(
+                "unknown annotation flavor: {} (expected github or gitlab)",
+                s
+            )),
+        }
+    }
+}
+
+impl Flavor {
+    /// The CI system the current process runs in, if it's one annotations exist for
+    pub fn detect() -> Option<Flavor> {
+        let is_set = |name| env::var(name).map_or(false, |value| value == "true");
+
+        if is_set("GITHUB_ACTIONS") {
+            Some(Flavor::GitHub)
+        } else if is_set("GITLAB_CI") {
+            Some(Flavor::GitLab)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    pub severity: Severity,
+    pub title: String,
+    pub message: String,
+    /// Relative to the app's root directory
+    pub file: Option<PathBuf>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+}
+
+impl Annotation {
+    pub fn new<T: Into<String>, M: Into<String>>(
+        severity: Severity,
+        title: T,
+        message: M,
+    ) -> Annotation {
+        Annotation {
+            severity,
+            title: title.into(),
+            message: message.into(),
+            file: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    pub fn in_file<P: AsRef<Path>>(mut self, file: P) -> Annotation {
+        let file = file.as_ref();
+        self.file = Some(file.strip_prefix(".").unwrap_or(file).to_path_buf());
+        self
+    }
+
+    fn github(&self) -> String {
+        let mut properties = Vec::new();
+
+        if let Some(ref file) = self.file {
+            properties.push(format!("file={}", escape_property(&file.to_string_lossy())));
+        }
+
+        if let Some(line) = self.line {
+            properties.push(format!("line={}", line));
+        }
+
+        if let Some(column) = self.column {
+            properties.push(format!("col={}", column));
+        }
+
+        properties.push(format!("title={}", escape_property(&self.title)));
+
+        let command = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        format!(
+            "::{} {}::{}",
+            command,
+            properties.join(","),
+            escape_data(&self.message)
+        )
+    }
+
+    fn gitlab(&self) -> serde_json::Value {
+        let path = self
+            .file
+            .as_ref()
+            .map_or(".".to_string(), |file| file.to_string_lossy().into_owned());
+        let line = self.line.unwrap_or(1);
+
+        json!({
+            "description": self.message,
+            "check_name": self.title,
+            "fingerprint": util::sha256_hex(
+                format!("{}:{}:{}:{}", path, line, self.title, self.message).as_bytes()
+            ),
+            "severity": match self.severity {
+                Severity::Error => "blocker",
+                Severity::Warning => "minor",
+            },
+            "location": { "path": path, "lines": { "begin": line } }
+        })
+    }
+}
+
+struct State {
+    flavor: Option<Flavor>,
+    /// What goes into the GitLab report
+    collected: Vec<Annotation>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    flavor: None,
+    collected: Vec::new(),
+});
+
+fn lock() -> ::std::sync::MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Turns annotations on in the given flavor
+pub fn init(flavor: Option<Flavor>) {
+    lock().flavor = flavor;
+}
+
+pub fn is_enabled() -> bool {
+    lock().flavor.is_some()
+}
+
+pub fn annotate(annotation: Annotation) {
+    let mut state = lock();
+
+    match state.flavor {
+        // workflow commands only work on stdout
+        Some(Flavor::GitHub) => println!("{}", annotation.github()),
+        Some(Flavor::GitLab) => state.collected.push(annotation),
+        None => (),
+    }
+}
+
+/// Writes out what has to be written once all annotations are known
+pub fn finish() -> DefaultResult<()> {
+    let state = lock();
+
+    if state.flavor == Some(Flavor::GitLab) {
+        let report: Vec<_> = state.collected.iter().map(Annotation::gitlab).collect();
+
+        fs::write(GITLAB_REPORT_FILE_NAME, serde_json::to_vec_pretty(&report)?)?;
+    }
+
+    Ok(())
+}
+
+/// Finds the errors and warnings in the output of rustc, with the locations they refer to
+/// relative to the app's root directory
+pub fn compiler_diagnostics(stderr: &str, base_path: &Path) -> Vec<Annotation> {
+    let mut diagnostics = Vec::new();
+    let mut lines = stderr.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let (severity, rest) = if line.starts_with("error") {
+            (Severity::Error, &line["error".len()..])
+        } else if line.starts_with("warning") {
+            (Severity::Warning, &line["warning".len()..])
+        } else {
+            continue;
+        };
+
+        // `error[E0425]: message` or `error: message`
+        if !rest.starts_with('[') && !rest.starts_with(':') {
+            continue;
+        }
+
+        let (title, message) = match rest.find(": ") {
+            Some(index) => (&rest[..index], &rest[index + 2..]),
+            None => continue,
+        };
+        let title = format!(
+            "{}{}",
+            if severity == Severity::Error {
+                "error"
+            } else {
+                "warning"
+            },
+            title
+        );
+
+        let location = match lines.peek() {
+            Some(next) if next.trim_start().starts_with("--> ") => {
+                next.trim_start()[4..].to_string()
+            }
+            _ => continue,
+        };
+
+        let mut parts = location.rsplitn(3, ':');
+        let column = parts.next().and_then(|column| column.parse().ok());
+        let line_number = parts.next().and_then(|line| line.parse().ok());
+
+        if let Some(file) = parts.next() {
+            diagnostics.push(Annotation {
+                line: line_number,
+                column,
+                ..Annotation::new(severity, title, message).in_file(base_path.join(file))
+            });
+        }
+    }
+
+    diagnostics
+}
+
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_at_the_compiler_errors() {
+        let stderr = "   Compiling posts v0.1.0\n\
+                      error[E0425]: cannot find value `x` in this scope\n \
+                      --> src/lib.rs:3:5\n  |\n\
+                      3 |     x\n\
+                      error: aborting due to 1 previous error\n";
+
+        let diagnostics = compiler_diagnostics(stderr, Path::new("./zomes/posts/code"));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].github(),
+            "::error file=zomes/posts/code/src/lib.rs,line=3,col=5,title=error[E0425]::cannot \
+             find value `x` in this scope"
+        );
+
+        let warning = Annotation::new(Severity::Warning, "large-artifact", "100%\nlarge")
+            .in_file("zomes/posts");
+        assert_eq!(
+            warning.github(),
+            "::warning file=zomes/posts,title=large-artifact::100%25%0Alarge"
+        );
+        assert_eq!(warning.gitlab()["location"]["path"], "zomes/posts");
+    }
+}
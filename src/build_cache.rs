@@ -0,0 +1,390 @@
+//! Remembers what each Zome was last built from, so unchanged Zomes aren't rebuilt.
+//!
+//! A build is only reused if its fingerprint still matches: the sources, the `.build` file,
+//! the versions of the tools it runs (including `rustc -vV` for cargo builds) and the
+//! environment variables that influence the compiler, including those reproducible builds run
+//! with. A new compiler would produce different
+//! WASM, so it invalidates the cache as much as a changed source file does.
+//!
+//! Each of these is a key of its own. The `.build` file only counts by the build it describes,
+//! so reformatting it rebuilds nothing, and the manifests next to the code directory aren't
+//! part of the fingerprint at all: editing a DNA property never rebuilds WASM.
+//!
+//! The WASM of every build is also stored under a digest of what went into it. A Zome whose
+//! sources are back to what they were at some earlier build, after reverting a change or
+//! switching branches, gets that build back instead of being built again, as does a Zome of
+//! another app with the same sources.
+
+use cli::BUILD_CONFIG_FILE_NAME;
+use config_files::{dirs, Build};
+use error::DefaultResult;
+use file_lock::{self, FileLock};
+use ignore::WalkBuilder;
+use reproducible::{self, SOURCE_DATE_EPOCH_ENV_VAR};
+use serde_json;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::PathBuf,
+    process::Command,
+};
+use util;
+
+pub const BUILDS_DIR_NAME: &str = "builds";
+
+/// Environment variables that change what the compiler produces
+pub const FINGERPRINT_ENV_VARS: &[&str] = &[
+    "RUSTFLAGS",
+    "CARGO_ENCODED_RUSTFLAGS",
+    "CARGO_BUILD_RUSTFLAGS",
+    "RUSTC",
+    "RUSTC_WRAPPER",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_PROFILE_RELEASE_OPT_LEVEL",
+    "CARGO_PROFILE_RELEASE_LTO",
+    SOURCE_DATE_EPOCH_ENV_VAR,
+];
+
+const TARGET_DIR_NAME: &str = "target";
+
+/// The directory of the builds directory the WASM of the builds is stored in by its key
+const STORED_BUILDS_DIR_NAME: &str = "wasm";
+
+#[derive(Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct Fingerprint {
+    /// Digest of all files of the code directory except for build output
+    pub sources: String,
+    /// Digest of the build steps, artifact path and linked modules
+    pub build: String,
+    /// The version output of every tool the build runs
+    pub toolchain: BTreeMap<String, String>,
+    pub env: BTreeMap<String, String>,
+    /// Digest of the artifact, empty if there is none
+    pub artifact: String,
+}
+
+impl Fingerprint {
+    /// Takes the fingerprint of the Zome in the given code directory as it is right now
+    pub fn current(build: &Build, code_dir: &PathBuf) -> DefaultResult<Fingerprint> {
+        let steps: BTreeMap<_, _> = build.steps.iter().collect();
+        let artifact = code_dir.join(build.output());
+        let outputs = [code_dir.join(&build.artifact), artifact.clone()];
+
+        let build_digest = if build.modules.is_empty() {
+            serde_json::to_vec(&(steps, &build.artifact))?
+        } else {
+            serde_json::to_vec(&(steps, &build.artifact, &build.modules, build.link))?
+        };
+
+        Ok(Fingerprint {
+            sources: sources_digest(code_dir, &outputs)?,
+            build: util::sha256_hex(&build_digest),
+            toolchain: toolchain(build, code_dir),
+            env: fingerprint_env(reproducible::command_env()),
+            artifact: match fs::read(&artifact) {
+                Ok(bytes) => util::sha256_hex(&bytes),
+                Err(_) => String::new(),
+            },
+        })
+    }
+
+    /// Explains what differs from the fingerprint of the last build, nothing if it's the same
+    pub fn changes(&self, last: &Fingerprint) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        if self.sources != last.sources {
+            changes.push("the sources changed".to_string());
+        }
+
+        if self.build != last.build {
+            changes.push("the build steps changed".to_string());
+        }
+
+        for (tool, version) in &self.toolchain {
+            match last.toolchain.get(tool) {
+                Some(last_version) if last_version != version => {
+                    changes.push(format!(
+                        "{} changed: {}",
+                        tool,
+                        first_difference(last_version, version)
+                    ));
+                }
+                None => changes.push(format!("{} wasn't used before", tool)),
+                _ => (),
+            }
+        }
+
+        let names: BTreeSet<_> = self.env.keys().chain(last.env.keys()).collect();
+
+        for name in names {
+            let (last_value, value) = (last.env.get(name), self.env.get(name));
+
+            if last_value != value {
+                changes.push(format!(
+                    "{} changed from {} to {}",
+                    name,
+                    describe_var(last_value),
+                    describe_var(value)
+                ));
+            }
+        }
+
+        if self.artifact.is_empty() {
+            changes.push("the artifact doesn't exist".to_string());
+        } else if self.artifact != last.artifact {
+            changes.push("the artifact was modified".to_string());
+        }
+
+        changes
+    }
+
+    /// Explains why the Zome in the code directory needs to be built, nothing if its last build
+    /// has this fingerprint
+    pub fn rebuild_reasons(&self, code_dir: &PathBuf) -> DefaultResult<Vec<String>> {
+        Ok(match last_fingerprint(code_dir)? {
+            Some(last) => self.changes(&last),
+            None => vec!["it wasn't built before".to_string()],
+        })
+    }
+
+    /// The key a build is stored under, locally and in the remote build cache: the digest of
+    /// everything that goes into it, but not of the artifact it produces. Take the fingerprint
+    /// before building, which may add files like a Cargo.lock.
+    pub fn key(&self) -> DefaultResult<String> {
+        let inputs = serde_json::to_vec(&(&self.sources, &self.build, &self.toolchain, &self.env))?;
+
+        Ok(util::sha256_hex(&inputs))
+    }
+}
+
+/// The environment variables of `FINGERPRINT_ENV_VARS` as the build sees them, with those
+/// reproducible builds set on top, so reproducible builds never reuse the others
+fn fingerprint_env(reproducible_env: Vec<(String, String)>) -> BTreeMap<String, String> {
+    let mut vars: BTreeMap<String, String> = FINGERPRINT_ENV_VARS
+        .iter()
+        .filter_map(|name| env::var(name).ok().map(|value| (name.to_string(), value)))
+        .collect();
+
+    vars.extend(reproducible_env);
+    vars
+}
+
+/// Returns why the Zome in the given code directory needs to be built, nothing if the last
+/// build can be reused
+pub fn rebuild_reasons(build: &Build, code_dir: &PathBuf) -> DefaultResult<Vec<String>> {
+    Fingerprint::current(build, code_dir)?.rebuild_reasons(code_dir)
+}
+
+/// Remembers the fingerprint of a successful build
+pub fn record(build: &Build, code_dir: &PathBuf) -> DefaultResult<()> {
+    let path = entry_path(code_dir)?;
+    let fingerprint = serde_json::to_vec_pretty(&Fingerprint::current(build, code_dir)?)?;
+
+    let _lock = FileLock::acquire(&path)?;
+
+    file_lock::write_atomic(&path, &fingerprint)
+}
+
+/// The WASM of an earlier build under the key, if there was one that wasn't pruned since
+pub fn stored(key: &str) -> DefaultResult<Option<Vec<u8>>> {
+    match fs::read(stored_path(key)?) {
+        Ok(wasm) => Ok(Some(wasm)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Stores the WASM of a build under its key. Storing it again on every use keeps `prune` from
+/// taking it for unused.
+pub fn store(key: &str, wasm: &[u8]) -> DefaultResult<()> {
+    // other packaging runs may be reading it
+    file_lock::write_atomic(&stored_path(key)?, wasm)
+}
+
+fn stored_path(key: &str) -> DefaultResult<PathBuf> {
+    Ok(dirs::cache_dir()?
+        .join(BUILDS_DIR_NAME)
+        .join(STORED_BUILDS_DIR_NAME)
+        .join(format!("{}.wasm", key)))
+}
+
+fn last_fingerprint(code_dir: &PathBuf) -> DefaultResult<Option<Fingerprint>> {
+    match fs::read(entry_path(code_dir)?) {
+        // an unreadable entry just means the Zome gets built again
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+        Err(_) => Ok(None),
+    }
+}
+
+fn entry_path(code_dir: &PathBuf) -> DefaultResult<PathBuf> {
+    let key = util::sha256_hex(code_dir.canonicalize()?.to_string_lossy().as_bytes());
+
+    Ok(dirs::cache_dir()?
+        .join(BUILDS_DIR_NAME)
+        .join(format!("{}.json", key)))
+}
+
+/// The files of the code directory a build reads, everything but build output and the
+/// `.build` file, which is fingerprinted on its own
+pub fn source_files(code_dir: &PathBuf, outputs: &[PathBuf]) -> Vec<PathBuf> {
+    let build_config = code_dir.join(BUILD_CONFIG_FILE_NAME);
+
+    let mut files: Vec<_> = WalkBuilder::new(code_dir)
+        .filter_entry(|entry| entry.file_name() != TARGET_DIR_NAME)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().to_path_buf())
+        .filter(|path| path.is_file() && !outputs.contains(path) && *path != build_config)
+        .collect();
+
+    files.sort();
+
+    files
+}
+
+fn sources_digest(code_dir: &PathBuf, outputs: &[PathBuf]) -> DefaultResult<String> {
+    let mut digests = Vec::new();
+
+    for path in source_files(code_dir, outputs) {
+        let relative = path.strip_prefix(code_dir).unwrap_or(&path);
+
+        digests.push(format!(
+            "{} {}",
+            util::sha256_hex(&fs::read(&path)?),
+            relative.to_string_lossy()
+        ));
+    }
+
+    Ok(util::sha256_hex(digests.join("\n").as_bytes()))
+}
+
+/// The tools whose versions are part of the fingerprint
+pub fn tools(build: &Build) -> Vec<String> {
+    version_commands(build)
+        .into_iter()
+        .map(|(bin, _)| bin)
+        .collect()
+}
+
+fn version_commands(build: &Build) -> Vec<(String, &'static str)> {
+    let mut commands: Vec<(String, &str)> = build
+        .steps
+        .keys()
+        .map(|bin| (bin.clone(), "--version"))
+        .collect();
+
+    if build.steps.contains_key("cargo") {
+        commands.push(("rustc".to_string(), "-vV"));
+    }
+
+    if !build.modules.is_empty() {
+        commands.push((build.link.bin().to_string(), "--version"));
+    }
+
+    commands
+}
+
+/// Asks every tool of the build for its version, from the code directory so toolchain
+/// overrides like a `rust-toolchain` file are taken into account
+pub fn toolchain(build: &Build, code_dir: &PathBuf) -> BTreeMap<String, String> {
+    version_commands(build)
+        .into_iter()
+        .map(|(bin, arg)| {
+            let version = Command::new(&bin)
+                .arg(arg)
+                .current_dir(code_dir)
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+                .unwrap_or_else(|| "unavailable".to_string());
+
+            (bin, version)
+        })
+        .collect()
+}
+
+fn first_difference(last: &str, current: &str) -> String {
+    let mut last_lines = last.lines();
+
+    for line in current.lines() {
+        let last_line = last_lines.next().unwrap_or("");
+
+        if line != last_line {
+            return format!("{} -> {}", last_line, line);
+        }
+    }
+
+    format!("{} -> {}", last, current)
+}
+
+fn describe_var(value: Option<&String>) -> String {
+    match value {
+        Some(value) => format!("{:?}", value),
+        None => "unset".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_what_invalidates_a_build() {
+        let mut last = Fingerprint {
+            sources: "a".into(),
+            build: "b".into(),
+            artifact: "c".into(),
+            ..Fingerprint::default()
+        };
+        last.toolchain.insert(
+            "rustc".into(),
+            "rustc 1.30.0\nbinary: rustc\nrelease: 1.30.0".into(),
+        );
+
+        let mut current = last.clone();
+
+        assert!(current.changes(&last).is_empty());
+
+        current.toolchain.insert(
+            "rustc".into(),
+            "rustc 1.31.0\nbinary: rustc\nrelease: 1.31.0".into(),
+        );
+        current
+            .env
+            .insert("RUSTFLAGS".into(), "-C debuginfo=2".into());
+
+        assert_eq!(
+            current.changes(&last),
+            vec![
+                "rustc changed: rustc 1.30.0 -> rustc 1.31.0".to_string(),
+                "RUSTFLAGS changed from unset to \"-C debuginfo=2\"".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn reproducible_builds_have_a_fingerprint_of_their_own() {
+        let reproducible_env = vec![
+            (SOURCE_DATE_EPOCH_ENV_VAR.to_string(), "0".to_string()),
+            (
+                "RUSTFLAGS".to_string(),
+                "--remap-path-prefix=/home/alice/.cargo=/cargo".to_string(),
+            ),
+        ];
+        let current = Fingerprint {
+            env: fingerprint_env(reproducible_env),
+            ..Fingerprint::default()
+        };
+        let last = Fingerprint {
+            env: fingerprint_env(Vec::new()),
+            ..Fingerprint::default()
+        };
+
+        assert_eq!(current.env[SOURCE_DATE_EPOCH_ENV_VAR], "0");
+        assert_eq!(
+            current.env["RUSTFLAGS"],
+            "--remap-path-prefix=/home/alice/.cargo=/cargo"
+        );
+        assert_ne!(current.key().unwrap(), last.key().unwrap());
+    }
+}
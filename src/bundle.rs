@@ -0,0 +1,276 @@
+//! The header every bundle file starts with.
+//!
+//! The packager always writes the `holochain_bundle` key first, so a bundle can be recognized
+//! from its first bytes alone, without guessing from the rest of its content.
+//!
+//! Besides JSON, bundles can be encoded in MessagePack or CBOR. Their content is the same, but
+//! files and WASM are raw bytes instead of base64 strings. Reading a bundle tells the encoding
+//! apart by its first byte and returns the content as JSON either way.
+
+use cbor;
+use delta::DELTA_BUNDLE_KEY;
+use encryption::ENCRYPTED_BUNDLE_KEY;
+use error::DefaultResult;
+use interrupt::PartialFile;
+use json;
+use msgpack;
+use serde::Serialize;
+use serde_json::{self, Value};
+use std::{
+    fs::{self, File},
+    path::Path,
+    str::FromStr,
+};
+
+pub const HEADER_KEY: &str = "holochain_bundle";
+pub const FORMAT_VERSION: u32 = 1;
+pub const MIME_TYPE: &str = "application/vnd.holochain.bundle+json";
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BundleType {
+    App,
+    Delta,
+    Encrypted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Encoding {
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Json => "json",
+            Encoding::MessagePack => "msgpack",
+            Encoding::Cbor => "cbor",
+        }
+    }
+
+    /// Tells the encoding from the start of a bundle, which is a map in every encoding
+    pub fn detect(bytes: &[u8]) -> Option<Encoding> {
+        match bytes.first() {
+            Some(0x80..=0x8f) | Some(0xde) | Some(0xdf) => Some(Encoding::MessagePack),
+            Some(0xa0..=0xbf) => Some(Encoding::Cbor),
+            _ if bytes.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'{') => {
+                Some(Encoding::Json)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for Encoding {
+    fn default() -> Encoding {
+        Encoding::Json
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Encoding, String> {
+        match s {
+            "json" => Ok(Encoding::Json),
+            "msgpack" => Ok(Encoding::MessagePack),
+            "cbor" => Ok(Encoding::Cbor),
+            _ => Err(format!(
+                "unknown bundle encoding: {} (expected json, msgpack or cbor)",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Header {
+    pub version: u32,
+    #[serde(rename = "type")]
+    pub bundle_type: BundleType,
+}
+
+#[derive(Serialize)]
+struct BundleFile<'a, T: 'a> {
+    holochain_bundle: Header,
+    #[serde(flatten)]
+    content: &'a T,
+}
+
+/// Writes the bundle content to the given file, preceded by the header
+pub fn write<T: AsRef<Path>>(
+    path: T,
+    bundle_type: BundleType,
+    content: &Value,
+) -> DefaultResult<()> {
+    ensure!(
+        content.is_object(),
+        "bundle content has to be a JSON object"
+    );
+
+    write_serialized(path, bundle_type, content)
+}
+
+/// Writes content that serializes to a JSON object, like a bundle with its entries in another
+/// order than sorted by name
+pub fn write_serialized<P: AsRef<Path>, T: Serialize>(
+    path: P,
+    bundle_type: BundleType,
+    content: &T,
+) -> DefaultResult<()> {
+    let file = BundleFile {
+        holochain_bundle: Header {
+            version: FORMAT_VERSION,
+            bundle_type,
+        },
+        content,
+    };
+
+    let _partial = PartialFile::new(&path);
+
+    serde_json::to_writer_pretty(File::create(&path)?, &file)?;
+
+    Ok(())
+}
+
+/// Writes the content in MessagePack or CBOR, preceded by the header like in JSON
+pub fn write_binary<P: AsRef<Path>>(
+    path: P,
+    bundle_type: BundleType,
+    encoding: Encoding,
+    content: msgpack::Value,
+) -> DefaultResult<()> {
+    let header = serde_json::to_value(Header {
+        version: FORMAT_VERSION,
+        bundle_type,
+    })?;
+
+    let mut entries = vec![(HEADER_KEY.into(), msgpack::Value::from(&header))];
+
+    match content {
+        msgpack::Value::Map(content) => entries.extend(content),
+        _ => bail!("bundle content has to be a map"),
+    }
+
+    let bytes = match encoding {
+        Encoding::Json => bail!("JSON bundles are written with write_serialized"),
+        Encoding::MessagePack => msgpack::Value::Map(entries).encode(),
+        Encoding::Cbor => cbor::encode(&msgpack::Value::Map(entries)),
+    };
+
+    let _partial = PartialFile::new(&path);
+
+    fs::write(&path, bytes)?;
+
+    Ok(())
+}
+
+/// Reads a bundle file, returning its header and the content without the header
+pub fn read<T: AsRef<Path>>(path: T) -> DefaultResult<(Header, Value)> {
+    let path = path.as_ref();
+
+    fs::read(path)
+        .map_err(Into::into)
+        .and_then(|bytes| from_slice(&bytes))
+        .map_err(|err| format_err!("{:?}: {}", path, err))
+}
+
+/// Parses the raw content of a bundle file in any of the encodings. Bundles written before the
+/// header existed are accepted as well, their type is derived from their content.
+pub fn from_slice(bytes: &[u8]) -> DefaultResult<(Header, Value)> {
+    let mut content: Value = match Encoding::detect(bytes) {
+        Some(Encoding::Json) => json::from_slice(bytes),
+        Some(Encoding::MessagePack) => {
+            msgpack::Value::decode(bytes).and_then(|value| value.to_json())
+        }
+        Some(Encoding::Cbor) => cbor::decode(bytes).and_then(|value| value.to_json()),
+        None => bail!("not a Holochain bundle"),
+    }
+    .map_err(|err| format_err!("not a Holochain bundle: {}", err))?;
+
+    let header = match content
+        .as_object_mut()
+        .and_then(|obj| obj.remove(HEADER_KEY))
+    {
+        Some(header) => serde_json::from_value::<Header>(header)
+            .map_err(|err| format_err!("invalid bundle header: {}", err))?,
+        None => Header {
+            version: 0,
+            bundle_type: legacy_type(&content),
+        },
+    };
+
+    check_version(&header)?;
+
+    Ok((header, content))
+}
+
+/// Fails for bundles written by a newer hcdev in a format this one doesn't know
+pub fn check_version(header: &Header) -> DefaultResult<()> {
+    ensure!(
+        header.version <= FORMAT_VERSION,
+        "bundle format version {} is not supported, this hcdev supports up to version {}",
+        header.version,
+        FORMAT_VERSION
+    );
+
+    Ok(())
+}
+
+fn legacy_type(content: &Value) -> BundleType {
+    if content.get(ENCRYPTED_BUNDLE_KEY).is_some() {
+        BundleType::Encrypted
+    } else if content.get(DELTA_BUNDLE_KEY).is_some() {
+        BundleType::Delta
+    } else {
+        BundleType::App
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64;
+    use tempfile::Builder;
+
+    #[test]
+    fn writes_the_header_first() {
+        let tmp = Builder::new().prefix("bundle").tempdir().unwrap();
+        let path = tmp.path().join("bundle.json");
+        let content = json!({ "__META__": {}, "description": "app" });
+
+        write(&path, BundleType::App, &content).unwrap();
+
+        let raw = fs::read_to_string(&path).unwrap();
+        assert!(raw[1..].trim_start().starts_with("\"holochain_bundle\""));
+
+        let (header, read_content) = read(&path).unwrap();
+        assert_eq!(header.bundle_type, BundleType::App);
+        assert_eq!(read_content, content);
+
+        for encoding in &[Encoding::MessagePack, Encoding::Cbor] {
+            let content = msgpack::Value::Map(vec![
+                ("description".into(), "app".into()),
+                ("code".into(), msgpack::Value::Binary(b"\0asm".to_vec())),
+            ]);
+
+            write_binary(&path, BundleType::App, *encoding, content).unwrap();
+
+            assert_eq!(Encoding::detect(&fs::read(&path).unwrap()), Some(*encoding));
+
+            let (header, read_content) = read(&path).unwrap();
+            assert_eq!(header.bundle_type, BundleType::App);
+            assert_eq!(
+                read_content,
+                json!({ "description": "app", "code": base64::encode(b"\0asm") })
+            );
+        }
+
+        assert!(from_slice(b"[1, 2]").is_err());
+        assert!(
+            from_slice(b"{\"holochain_bundle\": {\"version\": 99, \"type\": \"app\"}}").is_err()
+        );
+    }
+}
@@ -0,0 +1,256 @@
+//! Just enough CBOR to read and write binary bundles.
+//!
+//! CBOR has the same data model as MessagePack, strings apart from binary data included, so
+//! values are kept in the tree of `msgpack` and can be written in either format. Only definite
+//! lengths are written and read, which is all the packager produces.
+
+use error::DefaultResult;
+use msgpack::Value;
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+const TAG: u8 = 6;
+const SIMPLE: u8 = 7;
+
+const FALSE: u8 = 20;
+const TRUE: u8 = 21;
+const NULL: u8 = 22;
+const UNDEFINED: u8 = 23;
+const HALF_FLOAT: u8 = 25;
+const SINGLE_FLOAT: u8 = 26;
+const DOUBLE_FLOAT: u8 = 27;
+const INDEFINITE: u8 = 31;
+
+/// Encodes the value, always picking the shortest length and integer representation
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    write(value, &mut out);
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> DefaultResult<Value> {
+    let mut reader = Reader { bytes };
+    let value = reader.value()?;
+
+    ensure!(
+        reader.bytes.is_empty(),
+        "unexpected data after the CBOR value"
+    );
+
+    Ok(value)
+}
+
+fn write(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Nil => out.push(SIMPLE << 5 | NULL),
+        Value::Bool(false) => out.push(SIMPLE << 5 | FALSE),
+        Value::Bool(true) => out.push(SIMPLE << 5 | TRUE),
+        Value::Int(value) if *value >= 0 => write_head(UNSIGNED, *value as u64, out),
+        // -1 - n, without overflowing for i64::MIN
+        Value::Int(value) => write_head(NEGATIVE, !(*value as u64), out),
+        Value::Float(value) => {
+            out.push(SIMPLE << 5 | DOUBLE_FLOAT);
+            out.extend_from_slice(&value.to_bits().to_be_bytes());
+        }
+        Value::String(value) => {
+            write_head(TEXT, value.len() as u64, out);
+            out.extend_from_slice(value.as_bytes());
+        }
+        Value::Binary(value) => {
+            write_head(BYTES, value.len() as u64, out);
+            out.extend_from_slice(value);
+        }
+        Value::Array(values) => {
+            write_head(ARRAY, values.len() as u64, out);
+
+            for value in values {
+                write(value, out);
+            }
+        }
+        Value::Map(entries) => {
+            write_head(MAP, entries.len() as u64, out);
+
+            for (key, value) in entries {
+                write(key, out);
+                write(value, out);
+            }
+        }
+    }
+}
+
+/// Writes the initial byte of an item with its argument, a length or an integer
+fn write_head(major: u8, argument: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+
+    if argument < 24 {
+        out.push(major | argument as u8);
+    } else if argument <= u64::from(u8::MAX) {
+        out.push(major | 24);
+        out.push(argument as u8);
+    } else if argument <= u64::from(u16::MAX) {
+        out.push(major | 25);
+        out.extend_from_slice(&(argument as u16).to_be_bytes());
+    } else if argument <= u64::from(u32::MAX) {
+        out.push(major | 26);
+        out.extend_from_slice(&(argument as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&argument.to_be_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn value(&mut self) -> DefaultResult<Value> {
+        let initial = self.byte()?;
+        let (major, additional) = (initial >> 5, initial & 0x1f);
+
+        if major == SIMPLE {
+            return self.simple(additional);
+        }
+
+        ensure!(
+            additional != INDEFINITE,
+            "CBOR items of indefinite length are not supported"
+        );
+
+        let argument = match additional {
+            0..=23 => u64::from(additional),
+            24 => self.uint(1)?,
+            25 => self.uint(2)?,
+            26 => self.uint(4)?,
+            27 => self.uint(8)?,
+            _ => bail!("invalid CBOR item 0x{:02x}", initial),
+        };
+
+        Ok(match major {
+            UNSIGNED => {
+                ensure!(argument <= i64::MAX as u64, "CBOR integer too large");
+
+                Value::Int(argument as i64)
+            }
+            NEGATIVE => {
+                ensure!(argument <= i64::MAX as u64, "CBOR integer too small");
+
+                Value::Int(!(argument as i64))
+            }
+            BYTES => Value::Binary(self.bytes(argument as usize)?.to_vec()),
+            TEXT => Value::String(
+                String::from_utf8(self.bytes(argument as usize)?.to_vec())
+                    .map_err(|_| format_err!("CBOR text isn't valid UTF-8"))?,
+            ),
+            ARRAY => {
+                let mut values = Vec::new();
+
+                for _ in 0..argument {
+                    values.push(self.value()?);
+                }
+
+                Value::Array(values)
+            }
+            MAP => {
+                let mut entries = Vec::new();
+
+                for _ in 0..argument {
+                    let key = self.value()?;
+                    entries.push((key, self.value()?));
+                }
+
+                Value::Map(entries)
+            }
+            TAG => bail!("CBOR tags are not supported"),
+            _ => unreachable!(),
+        })
+    }
+
+    fn simple(&mut self, additional: u8) -> DefaultResult<Value> {
+        Ok(match additional {
+            FALSE => Value::Bool(false),
+            TRUE => Value::Bool(true),
+            NULL | UNDEFINED => Value::Nil,
+            HALF_FLOAT => Value::Float(half_to_f64(self.uint(2)? as u16)),
+            SINGLE_FLOAT => Value::Float(f64::from(f32::from_bits(self.uint(4)? as u32))),
+            DOUBLE_FLOAT => Value::Float(f64::from_bits(self.uint(8)?)),
+            _ => bail!("unsupported CBOR simple value {}", additional),
+        })
+    }
+
+    /// Reads a big endian unsigned integer of the given number of bytes
+    fn uint(&mut self, size: usize) -> DefaultResult<u64> {
+        Ok(self
+            .bytes(size)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte)))
+    }
+
+    fn byte(&mut self) -> DefaultResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, count: usize) -> DefaultResult<&'a [u8]> {
+        ensure!(count <= self.bytes.len(), "unexpected end of CBOR data");
+
+        let (head, tail) = self.bytes.split_at(count);
+        self.bytes = tail;
+
+        Ok(head)
+    }
+}
+
+/// Widens an IEEE 754 half precision float, which other CBOR encoders use for short floats
+fn half_to_f64(half: u16) -> f64 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from(half >> 10 & 0x1f);
+    let mantissa = f64::from(half & 0x3ff);
+
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_all_types() {
+        let value = Value::Map(vec![
+            ("nil".into(), Value::Nil),
+            (
+                "flags".into(),
+                Value::Array(vec![Value::Bool(true), Value::Bool(false)]),
+            ),
+            (
+                "ints".into(),
+                Value::Array(
+                    [0, 23, 24, 65_536, -1, -25, -40_000, i64::MIN, i64::MAX]
+                        .iter()
+                        .map(|int| Value::Int(*int))
+                        .collect(),
+                ),
+            ),
+            ("float".into(), Value::Float(1.5)),
+            ("long".into(), Value::String("x".repeat(300))),
+            ("wasm".into(), Value::Binary(vec![0, 97, 115, 109])),
+        ]);
+
+        let encoded = encode(&value);
+
+        assert_eq!(decode(&encoded).unwrap(), value);
+        assert_eq!(encode(&Value::Int(-1)), vec![0x20]);
+        assert_eq!(encode(&Value::from("a")), vec![0x61, b'a']);
+        assert_eq!(decode(&[0xf9, 0x3e, 0x00]).unwrap(), Value::Float(1.5));
+        assert!(decode(&encoded[..encoded.len() - 1]).is_err());
+        assert!(decode(&[0xbf, 0xff]).is_err());
+    }
+}
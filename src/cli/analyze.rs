@@ -0,0 +1,313 @@
+use cli::{layout::Layout, package::BUILD_CONFIG_FILE_NAME};
+use config_files::Build;
+use error::DefaultResult;
+use output;
+use rustc_demangle::demangle;
+use std::{collections::BTreeMap, fs, path::PathBuf};
+use util;
+use wasm::Module;
+
+const TOP_FUNCTIONS: usize = 15;
+const TOP_GROUPS: usize = 10;
+
+/// Crates known to add a lot of code, with what to do about them
+const HEAVY_CRATES: &[(&str, u64, &str)] = &[
+    (
+        "serde_json",
+        100 * 1024,
+        "consider a more compact format like MessagePack or CBOR for internal data",
+    ),
+    (
+        "regex",
+        100 * 1024,
+        "consider matching by hand or a lighter crate for simple patterns",
+    ),
+    (
+        "dlmalloc",
+        8 * 1024,
+        "wee_alloc is much smaller, see hcdev generate --tiny",
+    ),
+    (
+        "core::fmt",
+        30 * 1024,
+        "format!, {:?} and panic messages pull in the formatting machinery, \
+         try panic = \"abort\" and opt-level = \"z\"",
+    ),
+];
+
+struct Group {
+    name: String,
+    count: usize,
+    size: u64,
+    largest: u64,
+}
+
+/// Reports what takes up the space in a Zome's built WASM artifact
+pub fn analyze_wasm(zome_path: &PathBuf) -> DefaultResult<()> {
+    let code_dir = Layout::of_app(".")?.code_dir(zome_path);
+    let build_file = code_dir.join(BUILD_CONFIG_FILE_NAME);
+
+    ensure!(
+        build_file.is_file(),
+        "{:?} is not a Zome with a build file",
+        zome_path
+    );
+
+    let artifact = code_dir.join(Build::from_file(&build_file)?.output());
+
+    ensure!(
+        artifact.is_file(),
+        "{:?} doesn't exist yet, build the Zome with hcdev package first",
+        artifact
+    );
+
+    let bytes = fs::read(&artifact)?;
+    let module = Module::parse(&bytes)?;
+
+    output::print(format!(
+        "{:?}: {}",
+        artifact,
+        util::format_size(bytes.len() as u64)
+    ));
+
+    output::print("");
+    output::print("Sections:");
+
+    let mut sections: Vec<_> = module.sections.iter().collect();
+    sections.sort_by(|a, b| b.size.cmp(&a.size));
+
+    for section in sections {
+        output::print(format!(
+            "  {:>10}  {}",
+            util::format_size(section.size),
+            section.name
+        ));
+    }
+
+    let mut functions: Vec<_> = module
+        .functions
+        .iter()
+        .map(|function| (function_name(function.index, &function.name), function.size))
+        .collect();
+    functions.sort_by(|a, b| b.1.cmp(&a.1));
+
+    output::print("");
+    output::print(format!("Largest functions (of {}):", functions.len()));
+
+    for (name, size) in functions.iter().take(TOP_FUNCTIONS) {
+        output::print(format!("  {:>10}  {}", util::format_size(*size), name));
+    }
+
+    let mut generics = group(&functions, strip_generics);
+    generics.retain(|group| group.count > 1);
+    // what could be saved if every generic function was only instantiated once
+    generics.sort_by_key(|group| ::std::cmp::Reverse(group.size - group.largest));
+
+    output::print("");
+    output::print("Monomorphization bloat:");
+
+    if generics.is_empty() {
+        output::print("  none found");
+    }
+
+    for group in generics.iter().take(TOP_GROUPS) {
+        output::print(format!(
+            "  {:>10}  {} ({} copies)",
+            util::format_size(group.size),
+            group.name,
+            group.count
+        ));
+    }
+
+    let mut crates = group(&functions, crate_name);
+    crates.sort_by(|a, b| b.size.cmp(&a.size));
+
+    output::print("");
+    output::print("Size by crate:");
+
+    for group in crates.iter().take(TOP_GROUPS) {
+        output::print(format!(
+            "  {:>10}  {}",
+            util::format_size(group.size),
+            group.name
+        ));
+    }
+
+    output::print("");
+    output::print("Suggestions:");
+
+    let suggestions = suggestions(&module, &functions);
+
+    if suggestions.is_empty() {
+        output::print("  none, nice and small");
+    }
+
+    for suggestion in suggestions {
+        output::print(format!("  - {}", suggestion));
+    }
+
+    Ok(())
+}
+
+fn suggestions(module: &Module, functions: &[(String, u64)]) -> Vec<String> {
+    let mut suggestions = Vec::new();
+
+    if !module.has_names() {
+        suggestions.push(
+            "the artifact has no name section, so functions can't be attributed to crates. \
+             Keep the names in release builds to see more (e.g. debug = true in \
+             [profile.release])"
+                .to_string(),
+        );
+    }
+
+    for (prefix, threshold, advice) in HEAVY_CRATES {
+        let size: u64 = functions
+            .iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(_, size)| size)
+            .sum();
+
+        if size > *threshold {
+            suggestions.push(format!(
+                "{} adds {}; {}",
+                prefix,
+                util::format_size(size),
+                advice
+            ));
+        }
+    }
+
+    suggestions
+}
+
+fn group(functions: &[(String, u64)], key: fn(&str) -> String) -> Vec<Group> {
+    let mut groups: BTreeMap<String, Group> = BTreeMap::new();
+
+    for (name, size) in functions {
+        let key = key(name);
+        let group = groups.entry(key.clone()).or_insert_with(|| Group {
+            name: key,
+            count: 0,
+            size: 0,
+            largest: 0,
+        });
+
+        group.count += 1;
+        group.size += size;
+        group.largest = group.largest.max(*size);
+    }
+
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+fn function_name(index: u32, name: &Option<String>) -> String {
+    match name {
+        // the alternate format leaves out the hash suffix of legacy mangled names
+        Some(name) => format!("{:#}", demangle(name)),
+        None => format!("function[{}]", index),
+    }
+}
+
+/// Turns `alloc::vec::Vec<T>::push` into `alloc::vec::Vec<_>::push`
+fn strip_generics(name: &str) -> String {
+    let mut stripped = String::new();
+    let mut depth = 0;
+
+    for c in name.chars() {
+        match c {
+            '<' => {
+                if depth == 0 {
+                    stripped.push_str("<_");
+                }
+                depth += 1;
+            }
+            '>' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    stripped.push('>');
+                }
+            }
+            _ if depth == 0 => stripped.push(c),
+            _ => (),
+        }
+    }
+
+    stripped
+}
+
+/// Guesses the crate a function belongs to from its path, trait impls count for the type
+fn crate_name(name: &str) -> String {
+    let path = name.trim_start_matches('<');
+
+    match path.find("::") {
+        Some(end) if !path[..end].contains(' ') => path[..end].to_string(),
+        _ => "(unknown)".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli::package::CODE_DIR_NAME;
+
+    #[test]
+    fn groups_functions_by_generic_and_crate() {
+        assert_eq!(
+            strip_generics("alloc::vec::Vec<T, A>::push<u8>"),
+            "alloc::vec::Vec<_>::push<_>"
+        );
+        assert_eq!(
+            crate_name("<serde_json::de::Deserializer as core::Foo>::bar"),
+            "serde_json"
+        );
+        assert_eq!(crate_name("main"), "(unknown)");
+
+        let functions = vec![
+            ("core::ptr::drop_in_place<A>".to_string(), 10),
+            ("core::ptr::drop_in_place<B>".to_string(), 30),
+            ("serde_json::to_string".to_string(), 5),
+        ];
+
+        let generics = group(&functions, strip_generics);
+
+        assert_eq!(generics[0].name, "core::ptr::drop_in_place<_>");
+        assert_eq!(generics[0].count, 2);
+        assert_eq!(generics[0].size, 40);
+        assert_eq!(generics[0].largest, 30);
+    }
+
+    #[test]
+    fn analyzes_the_zome_artifact() {
+        use assert_cmd::prelude::*;
+        use std::process::Command;
+        use tempfile::Builder;
+        use wasm::test_support;
+
+        let tmp = Builder::new().prefix("analyze").tempdir().unwrap();
+        let code_dir = tmp.path().join("zomes/chat").join(CODE_DIR_NAME);
+
+        fs::create_dir_all(&code_dir).unwrap();
+        Build::with_artifact("code.wasm")
+            .save_as(code_dir.join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+        fs::write(
+            code_dir.join("code.wasm"),
+            test_support::module(&[("chat::send_message", 200), ("chat::main", 20)]),
+        ).unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .args(&["analyze", "wasm", "zomes/chat"])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(stdout.contains("200 B  chat::send_message"));
+        assert!(stdout.contains("220 B  chat"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Prepares the UI assets of a bundle for serving.
+//!
+//! Asset file names get the start of their content hash appended, so they can be cached
+//! forever and a new release never serves stale files. HTML files keep their names, as they are
+//! what users navigate to. The asset manifest maps the original paths to the fingerprinted
+//! ones, along with precompressed variants the server can pick from by `Accept-Encoding`.
+
+use base64;
+use cli::package::{Object, META_FILE_ID, META_SECTION_NAME, META_TREE_SECTION_NAME};
+use config_files::UiConfig;
+use error::DefaultResult;
+use flate2::{write::GzEncoder, Compression};
+use serde_json::{self, Value};
+use std::{collections::BTreeMap, io::Write};
+use util;
+
+pub const UI_DIR_NAME: &str = "ui";
+pub const ASSET_MANIFEST_FILE_NAME: &str = "asset-manifest.json";
+
+pub const GZIP_ENCODING: &str = "gzip";
+
+/// Enough hex digits of the SHA-256 hash to tell releases apart
+const FINGERPRINT_LENGTH: usize = 8;
+
+const UNHASHED_EXTENSIONS: &[&str] = &["html", "htm"];
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Asset {
+    /// Where the asset ended up, relative to the UI directory
+    pub file: String,
+    pub size: u64,
+    pub sha256: String,
+    /// The precompressed variants by encoding
+    pub encodings: BTreeMap<String, String>,
+}
+
+/// Checks that every requested precompression is supported
+pub fn check_config(config: &UiConfig) -> DefaultResult<()> {
+    for encoding in &config.precompress {
+        ensure!(
+            encoding == GZIP_ENCODING,
+            "unsupported precompression {} in hc.toml, only {} is supported",
+            encoding,
+            GZIP_ENCODING
+        );
+    }
+
+    Ok(())
+}
+
+/// Fingerprints and precompresses the files of the bundled UI directory as configured and
+/// adds the asset manifest to it
+pub fn prepare(ui: &mut Object, config: &UiConfig) -> DefaultResult<()> {
+    let mut manifest = BTreeMap::new();
+
+    prepare_dir(ui, "", config, &mut manifest)?;
+
+    insert_file(
+        ui,
+        ASSET_MANIFEST_FILE_NAME,
+        &serde_json::to_vec_pretty(&manifest)?,
+    );
+
+    Ok(())
+}
+
+fn prepare_dir(
+    dir: &mut Object,
+    prefix: &str,
+    config: &UiConfig,
+    manifest: &mut BTreeMap<String, Asset>,
+) -> DefaultResult<()> {
+    let names: Vec<String> = dir
+        .keys()
+        .filter(|name| name.as_str() != META_SECTION_NAME)
+        .cloned()
+        .collect();
+
+    for name in names {
+        let path = format!("{}{}", prefix, name);
+
+        let content = match dir.get_mut(&name) {
+            Some(Value::Object(subdir)) => {
+                prepare_dir(subdir, &format!("{}/", path), config, manifest)?;
+                continue;
+            }
+            Some(Value::String(encoded)) => base64::decode(encoded.as_str())?,
+            _ => continue,
+        };
+
+        let sha256 = util::sha256_hex(&content);
+
+        let file_name = if config.hash_assets && !is_unhashed(&name) {
+            let hashed = fingerprinted(&name, &sha256[..FINGERPRINT_LENGTH]);
+
+            if let Some(entry) = dir.remove(&name) {
+                dir.insert(hashed.clone(), entry);
+            }
+
+            rename_in_tree(dir, &name, &hashed);
+            hashed
+        } else {
+            name.clone()
+        };
+
+        let mut encodings = BTreeMap::new();
+
+        if config.precompress.iter().any(|e| e == GZIP_ENCODING) {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(&content)?;
+            let compressed = encoder.finish()?;
+
+            // images and other compressed formats don't get any smaller
+            if compressed.len() < content.len() {
+                let compressed_name = format!("{}.gz", file_name);
+
+                insert_file(dir, &compressed_name, &compressed);
+                encodings.insert(
+                    GZIP_ENCODING.to_string(),
+                    format!("{}{}", prefix, compressed_name),
+                );
+            }
+        }
+
+        manifest.insert(
+            path,
+            Asset {
+                file: format!("{}{}", prefix, file_name),
+                size: content.len() as u64,
+                sha256,
+                encodings,
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Inserts the hash before the extension, e.g. `app.js` becomes `app.0123abcd.js`
+fn fingerprinted(name: &str, hash: &str) -> String {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => format!("{}.{}{}", &name[..dot], hash, &name[dot..]),
+        _ => format!("{}.{}", name, hash),
+    }
+}
+
+fn is_unhashed(name: &str) -> bool {
+    name.rsplit('.').next().map_or(false, |ext| {
+        UNHASHED_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+    })
+}
+
+fn insert_file(dir: &mut Object, name: &str, content: &[u8]) {
+    dir.insert(name.to_string(), base64::encode(content).into());
+
+    if let Some(tree) = meta_tree(dir) {
+        tree.insert(name.to_string(), META_FILE_ID.into());
+    }
+}
+
+fn rename_in_tree(dir: &mut Object, from: &str, to: &str) {
+    if let Some(tree) = meta_tree(dir) {
+        if let Some(node_type) = tree.remove(from) {
+            tree.insert(to.to_string(), node_type);
+        }
+    }
+}
+
+fn meta_tree(dir: &mut Object) -> Option<&mut Object> {
+    dir.get_mut(META_SECTION_NAME)
+        .and_then(|meta| meta.get_mut(META_TREE_SECTION_NAME))
+        .and_then(|tree| tree.as_object_mut())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprints_and_precompresses_assets() {
+        let script = "console.log('hello');\n".repeat(20);
+        let mut ui = json!({
+            "__META__": { "tree": { "index.html": "file", "js": "dir" } },
+            "index.html": base64::encode("<script src=\"js/app.js\"></script>"),
+            "js": {
+                "__META__": { "tree": { "app.js": "file" } },
+                "app.js": base64::encode(&script)
+            }
+        });
+
+        let config = UiConfig {
+            hash_assets: true,
+            precompress: vec![GZIP_ENCODING.to_string()],
+        };
+
+        prepare(ui.as_object_mut().unwrap(), &config).unwrap();
+
+        let hash = &util::sha256_hex(script.as_bytes())[..FINGERPRINT_LENGTH];
+        let hashed_name = format!("app.{}.js", hash);
+
+        assert!(ui["index.html"].is_string());
+        assert!(ui["js"][&hashed_name].is_string());
+        assert!(ui["js"][&format!("{}.gz", hashed_name)].is_string());
+        assert_eq!(ui["js"]["__META__"]["tree"][&hashed_name], "file");
+        assert!(ui["js"].get("app.js").is_none());
+
+        let manifest: Value = serde_json::from_slice(
+            &base64::decode(ui[ASSET_MANIFEST_FILE_NAME].as_str().unwrap()).unwrap(),
+        ).unwrap();
+
+        assert_eq!(manifest["js/app.js"]["file"], format!("js/{}", hashed_name));
+        assert_eq!(
+            manifest["js/app.js"]["encodings"]["gzip"],
+            format!("js/{}.gz", hashed_name)
+        );
+        assert_eq!(manifest["index.html"]["file"], "index.html");
+        assert_eq!(ui["__META__"]["tree"][ASSET_MANIFEST_FILE_NAME], "file");
+
+        assert!(check_config(&UiConfig {
+            hash_assets: false,
+            precompress: vec!["br".to_string()],
+        }).is_err());
+    }
+}
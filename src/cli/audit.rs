@@ -0,0 +1,257 @@
+use cli::{layout::Layout, license::project_license, package::CARGO_FILE_NAME};
+use colored::*;
+use error::DefaultResult;
+use licenses::{self, Verdict};
+use net;
+use output;
+use serde_json::{self, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+#[derive(Debug, PartialEq)]
+struct Package {
+    name: String,
+    version: String,
+    /// The SPDX expression, `None` if the crate doesn't declare one
+    license: Option<String>,
+}
+
+/// Reports the licenses of all crates that get compiled into the app's Zomes and whether they
+/// are compatible with the app's own license
+pub fn audit_licenses() -> DefaultResult<()> {
+    let code_dirs = Layout::of_app(".")?.code_dirs_with(Path::new("."), CARGO_FILE_NAME)?;
+
+    let app_license = project_license()?;
+    let mut packages: BTreeMap<(String, String), Option<String>> = BTreeMap::new();
+
+    for code_dir in code_dirs {
+        for package in compiled_packages(&cargo_metadata(&code_dir)?)? {
+            packages.insert((package.name, package.version), package.license);
+        }
+    }
+
+    match app_license {
+        Some(ref license) => output::print(format!("The app is licensed under {}", license)),
+        None => output::print(
+            "The app has no license set in hc.toml (config set license <SPDX id>), \
+             it is treated as proprietary",
+        ),
+    }
+
+    let mut by_license: BTreeMap<String, usize> = BTreeMap::new();
+    let mut findings: BTreeMap<Verdict, Vec<String>> = BTreeMap::new();
+
+    for ((name, version), license) in &packages {
+        let verdict = match license {
+            Some(license) => licenses::verdict(license, app_license.as_ref().map(|l| l.as_str())),
+            None => Verdict::Unknown,
+        };
+        let license = license.clone().unwrap_or_else(|| "(none)".to_string());
+
+        if verdict != Verdict::Compatible {
+            findings
+                .entry(verdict)
+                .or_insert_with(Vec::new)
+                .push(format!("{} {}: {}", name, version, license));
+        }
+
+        *by_license.entry(license).or_insert(0) += 1;
+    }
+
+    output::print("");
+    output::print(format!(
+        "Licenses of the {} crates compiled into the Zomes:",
+        packages.len()
+    ));
+
+    for (license, count) in &by_license {
+        output::print(format!("  {:>4}  {}", count, license));
+    }
+
+    let sections = [
+        (
+            Verdict::Incompatible,
+            "Incompatible with the app's license:".red().bold(),
+        ),
+        (
+            Verdict::Unknown,
+            "Unknown licenses, check these by hand:".yellow().bold(),
+        ),
+        (
+            Verdict::Notice,
+            "Weak copyleft, changes to these crates have to be published:".normal(),
+        ),
+    ];
+
+    for (verdict, title) in sections.iter() {
+        if let Some(crates) = findings.get(verdict) {
+            output::print("");
+            output::print(title);
+
+            for description in crates {
+                output::print(format!("  - {}", description));
+            }
+        }
+    }
+
+    let incompatible = findings
+        .get(&Verdict::Incompatible)
+        .map_or(0, |crates| crates.len());
+
+    ensure!(
+        incompatible == 0,
+        "{} crate(s) have licenses incompatible with the app's license",
+        incompatible
+    );
+
+    Ok(())
+}
+
+fn cargo_metadata(code_dir: &PathBuf) -> DefaultResult<Value> {
+    let mut command = Command::new("cargo");
+    command
+        .args(&["metadata", "--format-version", "1"])
+        .current_dir(code_dir);
+
+    if net::is_offline() {
+        command.env("CARGO_NET_OFFLINE", "true");
+    }
+
+    let output = command.output()?;
+
+    ensure!(
+        output.status.success(),
+        "cargo metadata failed in {:?}: {}",
+        code_dir,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Walks the resolved dependency graph from the Zome's crate, following only normal
+/// dependencies and leaving out proc macros, as only those end up in the WASM
+fn compiled_packages(metadata: &Value) -> DefaultResult<Vec<Package>> {
+    let packages: HashMap<&str, &Value> = metadata["packages"]
+        .as_array()
+        .ok_or_else(|| format_err!("cargo metadata without packages"))?
+        .iter()
+        .filter_map(|package| package["id"].as_str().map(|id| (id, package)))
+        .collect();
+
+    let nodes: HashMap<&str, &Value> = metadata["resolve"]["nodes"]
+        .as_array()
+        .ok_or_else(|| format_err!("cargo metadata without a resolved dependency graph"))?
+        .iter()
+        .filter_map(|node| node["id"].as_str().map(|id| (id, node)))
+        .collect();
+
+    let root = metadata["resolve"]["root"]
+        .as_str()
+        .ok_or_else(|| format_err!("cargo metadata without a root crate"))?;
+
+    let mut seen = BTreeSet::new();
+    let mut queue = vec![root];
+
+    while let Some(id) = queue.pop() {
+        let deps = match nodes.get(id).and_then(|node| node["deps"].as_array()) {
+            Some(deps) => deps,
+            None => continue,
+        };
+
+        for dep in deps {
+            let is_normal = match dep["dep_kinds"].as_array() {
+                Some(kinds) => kinds.iter().any(|kind| kind["kind"].is_null()),
+                // cargo versions before 1.41 don't tell dependency kinds apart
+                None => true,
+            };
+
+            let dep_id = match dep["pkg"].as_str() {
+                Some(dep_id) => dep_id,
+                None => continue,
+            };
+
+            if is_normal && !is_proc_macro(packages.get(dep_id)) && seen.insert(dep_id) {
+                queue.push(dep_id);
+            }
+        }
+    }
+
+    Ok(seen
+        .iter()
+        .filter_map(|id| packages.get(id))
+        .map(|package| Package {
+            name: package["name"].as_str().unwrap_or_default().to_string(),
+            version: package["version"].as_str().unwrap_or_default().to_string(),
+            license: package["license"].as_str().map(String::from),
+        })
+        .collect())
+}
+
+fn is_proc_macro(package: Option<&&Value>) -> bool {
+    package
+        .and_then(|package| package["targets"].as_array())
+        .map_or(false, |targets| {
+            targets.iter().any(|target| {
+                target["kind"]
+                    .as_array()
+                    .map_or(false, |kinds| kinds.iter().any(|kind| kind == "proc-macro"))
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(id: &str, license: Value, kind: &str) -> Value {
+        json!({
+            "id": id,
+            "name": id,
+            "version": "1.0.0",
+            "license": license,
+            "targets": [{ "kind": [kind] }]
+        })
+    }
+
+    #[test]
+    fn only_counts_crates_that_end_up_in_the_wasm() {
+        let normal = json!([{ "kind": null, "target": null }]);
+        let dev = json!([{ "kind": "dev", "target": null }]);
+
+        let metadata = json!({
+            "packages": [
+                package("code", json!(null), "cdylib"),
+                package("serde", json!("MIT OR Apache-2.0"), "lib"),
+                package("serde_derive", json!("MIT OR Apache-2.0"), "proc-macro"),
+                package("tester", json!("GPL-3.0"), "lib"),
+                package("itoa", json!(null), "lib")
+            ],
+            "resolve": {
+                "root": "code",
+                "nodes": [
+                    { "id": "code", "deps": [
+                        { "pkg": "serde", "dep_kinds": normal },
+                        { "pkg": "serde_derive", "dep_kinds": normal },
+                        { "pkg": "tester", "dep_kinds": dev }
+                    ] },
+                    { "id": "serde", "deps": [{ "pkg": "itoa", "dep_kinds": normal }] },
+                    { "id": "serde_derive", "deps": [] },
+                    { "id": "tester", "deps": [] },
+                    { "id": "itoa", "deps": [] }
+                ]
+            }
+        });
+
+        let packages = compiled_packages(&metadata).unwrap();
+
+        assert_eq!(
+            packages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(),
+            vec!["itoa", "serde"]
+        );
+        assert_eq!(packages[0].license, None);
+    }
+}
@@ -0,0 +1,133 @@
+//! Measures how fast this build of the CLI packages apps of different shapes, and fails if it
+//! got slower than a saved baseline, so performance regressions in packaging are caught.
+//!
+//! Unlike the criterion benches in `benches/`, this needs no checkout of the sources: every
+//! synthetic app is packaged a few times by the running binary, and the median time counts.
+
+use error::DefaultResult;
+use output;
+use serde_json;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::Instant,
+};
+use synthetic::{self, Shape};
+use tempfile::Builder;
+
+/// Median milliseconds it took to package each shape, by the name of the shape
+type Results = BTreeMap<String, f64>;
+
+/// Packages every synthetic app `runs` times and reports the medians, compared to the baseline
+/// if one is given. Fails if a shape got more than `max_regression` percent slower.
+pub fn bench_self(
+    runs: usize,
+    baseline: Option<PathBuf>,
+    save_baseline: Option<PathBuf>,
+    max_regression: f64,
+) -> DefaultResult<()> {
+    ensure!(runs > 0, "--runs has to be at least 1");
+
+    let baseline: Option<Results> = match baseline {
+        Some(path) => Some(
+            serde_json::from_slice(&fs::read(&path)?)
+                .map_err(|err| format_err!("{:?}: {}", path, err))?,
+        ),
+        None => None,
+    };
+
+    let mut results = Results::new();
+    let mut regressions = Vec::new();
+
+    for shape in synthetic::SHAPES {
+        let median = measure(*shape, runs)?;
+        let mut line = format!("{:<18}{:>10.1} ms", shape.name(), median);
+
+        if let Some(base) = baseline
+            .as_ref()
+            .and_then(|baseline| baseline.get(shape.name()))
+        {
+            let change = regression(*base, median);
+            line.push_str(&format!("  {:+.1}%", change));
+
+            if change > max_regression {
+                regressions.push(format!("{} by {:.1}%", shape.name(), change));
+            }
+        }
+
+        output::print(line);
+        results.insert(shape.name().to_string(), median);
+    }
+
+    if let Some(path) = save_baseline {
+        fs::write(&path, serde_json::to_string_pretty(&results)?)?;
+    }
+
+    ensure!(
+        regressions.is_empty(),
+        "packaging got more than {}% slower: {}",
+        max_regression,
+        regressions.join(", ")
+    );
+
+    Ok(())
+}
+
+/// The median milliseconds of packaging an app of the shape
+fn measure(shape: Shape, runs: usize) -> DefaultResult<f64> {
+    let app = Builder::new().prefix("hcdev-bench").tempdir()?;
+    shape.generate(app.path())?;
+
+    let bundle_path = app.path().join("bundle.json");
+    let hcdev = env::current_exe()?;
+    let mut times = Vec::new();
+
+    for _ in 0..runs {
+        let started = Instant::now();
+
+        let status = Command::new(&hcdev)
+            .args(&["package", "-o"])
+            .arg(&bundle_path)
+            .current_dir(app.path())
+            .stdout(Stdio::null())
+            .status()?;
+
+        ensure!(
+            status.success(),
+            "packaging the {} app failed",
+            shape.name()
+        );
+
+        times.push(started.elapsed().as_secs_f64() * 1000.0);
+        fs::remove_file(&bundle_path)?;
+    }
+
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    Ok(times[times.len() / 2])
+}
+
+/// How many percent slower the new time is than the baseline, negative if it got faster
+fn regression(baseline: f64, time: f64) -> f64 {
+    (time - baseline) / baseline * 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_apps_and_compares_to_the_baseline() {
+        let app = Builder::new().prefix("bench").tempdir().unwrap();
+
+        Shape::DeepNesting.generate(app.path()).unwrap();
+
+        assert!(app
+            .path()
+            .join("assets/level0/level1/level2/0.bin")
+            .is_file());
+        assert_eq!(regression(200.0, 250.0), 25.0);
+    }
+}
@@ -0,0 +1,161 @@
+//! Describes the builds `package` would run, for build systems like Bazel, Buck or Nix to run
+//! them on their own terms, like `cargo build --build-plan` does for crates.
+//!
+//! Every Zome is listed with the commands that build it, the files those read and the WASM they
+//! produce, all relative to the app's root directory. Nothing is built.
+
+use build_cache;
+use cli::{layout::Layout, package::BUILD_CONFIG_FILE_NAME};
+use config_files::{Build, CargoLockPolicy, Project};
+use error::DefaultResult;
+use i18n;
+use output;
+use serde_json;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use util;
+
+pub const BUILD_PLAN_FILE_NAME: &str = "build-plan.json";
+
+/// Bumped whenever the layout of the plan changes in a way its readers have to know about
+const BUILD_PLAN_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct BuildPlan {
+    pub version: u32,
+    pub zomes: Vec<ZomePlan>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ZomePlan {
+    pub name: String,
+    /// The code directory all invocations run in
+    pub cwd: PathBuf,
+    /// To be run one after the other
+    pub invocations: Vec<Invocation>,
+    pub inputs: Vec<PathBuf>,
+    /// The last one is what ends up in the bundle
+    pub outputs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Invocation {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+/// Plans the build of every Zome of the app in `root`
+pub fn plan(root: &Path, locked: bool) -> DefaultResult<BuildPlan> {
+    let code_dirs = Layout::of_app(root)?.code_dirs_with(root, BUILD_CONFIG_FILE_NAME)?;
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+    let mut zomes = Vec::new();
+
+    for code_dir in code_dirs {
+        let build = Build::from_file(code_dir.join(BUILD_CONFIG_FILE_NAME))?;
+        let build = if locked { build.locked() } else { build };
+
+        let env: BTreeMap<_, _> = util::command_env().into_iter().collect();
+        let invocations = build
+            .commands()
+            .into_iter()
+            .map(|(program, args)| Invocation {
+                program,
+                args,
+                env: env.clone(),
+            })
+            .collect();
+
+        let mut outputs = vec![code_dir.join(&build.artifact)];
+
+        if build.output() != build.artifact {
+            outputs.push(code_dir.join(build.output()));
+        }
+
+        let mut inputs = vec![code_dir.join(BUILD_CONFIG_FILE_NAME)];
+        inputs.extend(build_cache::source_files(&code_dir, &outputs));
+
+        for module in &build.modules {
+            let path = code_dir.join(&module.path);
+
+            if !inputs.contains(&path) {
+                inputs.push(path);
+            }
+        }
+
+        zomes.push(ZomePlan {
+            name: util::file_name_string(&code_dir.parent().unwrap_or(&code_dir).to_path_buf())?,
+            cwd: relative(&code_dir),
+            invocations,
+            inputs: inputs.iter().map(|path| relative(path)).collect(),
+            outputs: outputs.iter().map(|path| relative(path)).collect(),
+        });
+    }
+
+    Ok(BuildPlan {
+        version: BUILD_PLAN_VERSION,
+        zomes,
+    })
+}
+
+/// Writes the build plan of the app in the current directory
+pub fn build_plan(output: Option<PathBuf>, locked: bool) -> DefaultResult<()> {
+    let locked = locked || Project::from_dir(".")?.zomes.cargo_lock == CargoLockPolicy::Locked;
+    let plan = plan(Path::new(""), locked)?;
+    let output = output.unwrap_or_else(|| PathBuf::from(BUILD_PLAN_FILE_NAME));
+
+    fs::write(&output, serde_json::to_vec_pretty(&plan)?)?;
+
+    output::print(i18n::tr(
+        "created-build-plan",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", output)),
+            ("count", plan.zomes.len().to_string()),
+        ],
+    ));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder;
+
+    #[test]
+    fn lists_commands_inputs_and_outputs() {
+        let tmp = Builder::new().prefix("build_plan").tempdir().unwrap();
+        let code_dir = tmp.path().join("zomes/posts/code");
+
+        fs::create_dir_all(code_dir.join("src")).unwrap();
+        fs::create_dir_all(code_dir.join("target")).unwrap();
+        fs::write(code_dir.join("src/lib.rs"), "").unwrap();
+        fs::write(code_dir.join("target/code.wasm"), "").unwrap();
+        Build::with_artifact("target/code.wasm")
+            .cmd("cargo", &["build", "--release"])
+            .save_as(code_dir.join(BUILD_CONFIG_FILE_NAME))
+            .unwrap();
+
+        let plan = plan(tmp.path(), true).unwrap();
+
+        assert_eq!(plan.zomes.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&plan.zomes[0]).unwrap(),
+            json!({
+                "name": "posts",
+                "cwd": "zomes/posts/code",
+                "invocations": [{
+                    "program": "cargo",
+                    "args": ["build", "--release", "--locked"],
+                    "env": {}
+                }],
+                "inputs": ["zomes/posts/code/.build", "zomes/posts/code/src/lib.rs"],
+                "outputs": ["zomes/posts/code/target/code.wasm"]
+            })
+        );
+    }
+}
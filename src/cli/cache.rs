@@ -0,0 +1,318 @@
+use build_cache::{self, FINGERPRINT_ENV_VARS};
+use cli::{layout::Layout, package::BUILD_CONFIG_FILE_NAME};
+use colored::*;
+use config_files::{Build, CargoLockPolicy, Project};
+use error::DefaultResult;
+use hcignore::IgnoreRules;
+use ignore::WalkBuilder;
+use output;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+use util;
+
+/// Tells for every Zome whether the next package run reuses its last build, and why not
+pub fn cache_status() -> DefaultResult<()> {
+    let zomes = Project::from_dir(".")?.zomes;
+    let code_dirs =
+        Layout::from_config(&zomes)?.code_dirs_with(Path::new("."), BUILD_CONFIG_FILE_NAME)?;
+    let cargo_lock = zomes.cargo_lock;
+
+    for code_dir in code_dirs {
+        let build = packaged_build(&code_dir, cargo_lock)?;
+        let reasons = build_cache::rebuild_reasons(&build, &code_dir)?;
+
+        if reasons.is_empty() {
+            output::print(format!("{:?}: {}", code_dir, "fresh".green()));
+        } else {
+            output::print(format!("{:?}: {}", code_dir, "will rebuild".yellow()));
+
+            for reason in reasons {
+                output::print(format!("  - {}", reason));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The build of the code directory the way package runs it, which is what its fingerprint is
+/// taken of
+pub fn packaged_build(code_dir: &Path, cargo_lock: CargoLockPolicy) -> DefaultResult<Build> {
+    let build = Build::from_file(code_dir.join(BUILD_CONFIG_FILE_NAME))?;
+
+    Ok(match cargo_lock {
+        CargoLockPolicy::Use => build,
+        CargoLockPolicy::Locked => build.locked(),
+        CargoLockPolicy::Ignore => build.unlocked(),
+    })
+}
+
+/// Shows the dependency edges of a file or directory of the app: what it is built from if it's
+/// a code directory, and which build or part of the bundle it goes into otherwise
+pub fn cache_explain(path: &PathBuf) -> DefaultResult<()> {
+    let root = env::current_dir()?;
+    let relative = util::normalize(&root.join(path))
+        .strip_prefix(&root)
+        .map_err(|_| format_err!("{:?} is outside of the app", path))?
+        .to_path_buf();
+
+    ensure!(root.join(&relative).exists(), "{:?} doesn't exist", path);
+
+    let root = Path::new(".");
+
+    output::print(display(root, &root.join(&relative)));
+
+    for edge in edges(root, &root.join(&relative))? {
+        output::print(format!("  {}", edge));
+    }
+
+    Ok(())
+}
+
+fn edges(root: &Path, path: &Path) -> DefaultResult<Vec<String>> {
+    let mut rules = IgnoreRules::load(root)?;
+    let mut ancestors: Vec<&Path> = path.ancestors().take_while(|dir| *dir != root).collect();
+
+    ancestors.reverse();
+
+    // package doesn't descend into ignored directories, so the topmost ignored one counts
+    for ancestor in &ancestors {
+        match rules.explain(ancestor, ancestor.is_dir())? {
+            Some(ref rule) if !rule.whitelisted => {
+                return Ok(vec![format!(
+                    "-> nothing, {}: {} leaves it out of the bundle",
+                    display(root, &rule.file),
+                    rule.pattern
+                )]);
+            }
+            _ => (),
+        }
+    }
+
+    let code_dir = ancestors
+        .iter()
+        .find(|dir| dir.join(BUILD_CONFIG_FILE_NAME).is_file())
+        .map(|dir| dir.to_path_buf());
+
+    let code_dir = match code_dir {
+        Some(code_dir) => code_dir,
+        None => return Ok(vec![bundle_edge(root, path)]),
+    };
+
+    let build = Build::from_file(code_dir.join(BUILD_CONFIG_FILE_NAME))?;
+    let outputs = [
+        code_dir.join(&build.artifact),
+        code_dir.join(build.output()),
+    ];
+    let sources = build_cache::source_files(&code_dir, &outputs);
+    let wasm_edge = format!("-> the WASM of {} in the bundle", display(root, &code_dir));
+
+    let edges = if path == code_dir {
+        let mut edges: Vec<String> = sources
+            .iter()
+            .map(|source| format!("<- source {}", display(&code_dir, source)))
+            .collect();
+
+        edges.push(format!(
+            "<- the build described by {}",
+            BUILD_CONFIG_FILE_NAME
+        ));
+        edges.push(format!(
+            "<- the versions of {}",
+            build_cache::tools(&build).join(", ")
+        ));
+        edges.push(format!(
+            "<- the environment variables {}",
+            FINGERPRINT_ENV_VARS.join(", ")
+        ));
+        edges.push(wasm_edge);
+        edges
+    } else if path == code_dir.join(BUILD_CONFIG_FILE_NAME) {
+        vec![
+            format!(
+                "-> the build steps of {}, changing what they run rebuilds it",
+                display(root, &code_dir)
+            ),
+            wasm_edge,
+        ]
+    } else if outputs.iter().any(|output| output == path) {
+        vec![format!("{}, as the output of its build", wasm_edge)]
+    } else if sources.iter().any(|source| source.starts_with(path)) {
+        vec![
+            format!(
+                "-> the sources of {}, changing them rebuilds it",
+                display(root, &code_dir)
+            ),
+            wasm_edge,
+        ]
+    } else {
+        vec![format!(
+            "-> nothing, it's build output the fingerprint of {} leaves out",
+            display(root, &code_dir)
+        )]
+    };
+
+    Ok(edges)
+}
+
+/// Where a file or directory outside of the code directories ends up in the bundle
+fn bundle_edge(root: &Path, path: &Path) -> String {
+    let dir = path.parent().unwrap_or(root);
+
+    if path.is_file() && manifest_file(dir).as_ref().map(PathBuf::as_path) == Some(path) {
+        let owner = match display(root, dir).as_str() {
+            "." => "the app".to_string(),
+            dir => dir.to_string(),
+        };
+
+        format!(
+            "-> the manifest of {} in the bundle, read on every package run without rebuilding any WASM",
+            owner
+        )
+    } else if path.is_dir() {
+        format!("-> the {} entry of the bundle", display(root, path))
+    } else {
+        format!(
+            "-> the {} entry of the bundle, encoded on every package run without rebuilding any WASM",
+            display(root, path)
+        )
+    }
+}
+
+/// The JSON file of a directory `package` takes as its manifest
+fn manifest_file(dir: &Path) -> Option<PathBuf> {
+    WalkBuilder::new(dir)
+        .max_depth(Some(1))
+        .build()
+        .skip(1)
+        .filter_map(|e| e.ok())
+        .map(|e| e.path().to_path_buf())
+        .find(|path| path.is_file() && path.to_string_lossy().ends_with(".json"))
+}
+
+fn display(root: &Path, path: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::{fs, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn unchanged_zomes_are_not_rebuilt() {
+        let tmp = Builder::new().prefix("cache").tempdir().unwrap();
+        let home = tmp.path().join("home");
+        let cache = Builder::new().prefix("cache-dir").tempdir().unwrap();
+        let code_dir = tmp.path().join("zomes/chat/code");
+
+        fs::create_dir_all(&code_dir).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        fs::write(code_dir.join("lib.rs"), "// chat").unwrap();
+        fs::write(
+            code_dir.join(".build"),
+            r#"{
+                "steps": { "sh": ["-c", "echo built >> ../builds; printf '\\0asm\\1\\0\\0\\0' > code.wasm"] },
+                "artifact": "code.wasm"
+            }"#,
+        )
+        .unwrap();
+
+        let hcdev = |args: &[&str], rustflags: &str| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .env("HC_HOME", &home)
+                .env("HC_CACHE_DIR", cache.path())
+                .env("RUSTFLAGS", rustflags)
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        assert!(hcdev(&["package"], "").status.success());
+        assert!(hcdev(&["package"], "").status.success());
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("zomes/chat/builds")).unwrap(),
+            "built\n"
+        );
+
+        let status = hcdev(&["--color", "never", "cache", "status"], "");
+        assert!(String::from_utf8_lossy(&status.stdout).contains("fresh"));
+
+        let status = hcdev(&["--color", "never", "cache", "status"], "-C debuginfo=2");
+        assert!(String::from_utf8_lossy(&status.stdout)
+            .contains("RUSTFLAGS changed from \"\" to \"-C debuginfo=2\""));
+
+        // neither the manifest nor the formatting of .build are part of the fingerprint
+        fs::write(tmp.path().join("zomes/chat/zome.json"), "{}").unwrap();
+        fs::write(
+            code_dir.join(".build"),
+            r#"{ "artifact": "code.wasm", "steps": { "sh": ["-c", "echo built >> ../builds; printf '\\0asm\\1\\0\\0\\0' > code.wasm"] } }"#,
+        ).unwrap();
+
+        let status = hcdev(&["--color", "never", "cache", "status"], "");
+        assert!(String::from_utf8_lossy(&status.stdout).contains("fresh"));
+
+        let explain = hcdev(&["cache", "explain", "zomes/chat/zome.json"], "");
+        assert!(String::from_utf8_lossy(&explain.stdout).contains("without rebuilding any WASM"));
+
+        let explain = hcdev(&["cache", "explain", "zomes/chat/code"], "");
+        assert!(String::from_utf8_lossy(&explain.stdout).contains("<- source lib.rs"));
+
+        fs::write(code_dir.join("lib.rs"), "// chat v2").unwrap();
+
+        let status = hcdev(&["--color", "never", "cache", "status"], "");
+        assert!(String::from_utf8_lossy(&status.stdout).contains("the sources changed"));
+    }
+
+    #[test]
+    fn earlier_builds_are_restored_instead_of_rebuilt() {
+        let tmp = Builder::new().prefix("restore").tempdir().unwrap();
+        let app = tmp.path().join("app");
+        let code_dir = app.join("zomes/chat/code");
+        let builds = tmp.path().join("builds");
+
+        fs::create_dir_all(&code_dir).unwrap();
+        fs::write(app.join("app.json"), "{}").unwrap();
+        fs::write(code_dir.join("lib.rs"), "// chat").unwrap();
+        fs::write(
+            code_dir.join(".build"),
+            format!(
+                r#"{{
+                    "steps": {{ "sh": ["-c", "echo built >> {}; printf '\\0asm\\1\\0\\0\\0' > code.wasm"] }},
+                    "artifact": "code.wasm"
+                }}"#,
+                builds.display()
+            ),
+        ).unwrap();
+
+        let package = || {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(&app)
+                .env("HC_HOME", tmp.path().join("home"))
+                .args(&["--color", "never", "package"])
+                .output()
+                .unwrap()
+        };
+
+        assert!(package().status.success());
+        fs::write(code_dir.join("lib.rs"), "// chat v2").unwrap();
+        assert!(package().status.success());
+
+        // back to the sources of the first build
+        fs::write(code_dir.join("lib.rs"), "// chat").unwrap();
+
+        let restored = package();
+        assert!(String::from_utf8_lossy(&restored.stdout).contains("Restored the build of"));
+        assert_eq!(fs::read_to_string(&builds).unwrap(), "built\nbuilt\n");
+    }
+}
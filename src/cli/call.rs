@@ -0,0 +1,107 @@
+use cli::layout::Layout;
+use error::DefaultResult;
+use i18n;
+use output;
+use serde_json::{self, Value};
+use zome_api::ZomeApi;
+
+/// Checks the arguments of a zome function call against the interface declared in the Zome's
+/// code, or prints an argument skeleton with `example`
+pub fn call(zome: &str, function: &str, args: Option<String>, example: bool) -> DefaultResult<()> {
+    let layout = Layout::of_app(".")?;
+    let code_dir = layout.code_dir(&layout.zome_dir(zome));
+
+    ensure!(code_dir.is_dir(), "no Zome found at {:?}", code_dir);
+
+    let api = ZomeApi::from_code_dir(&code_dir)?;
+
+    let function = api.function(function).ok_or_else(|| {
+        let available: Vec<_> = api.functions.iter().map(|f| f.name.as_str()).collect();
+
+        format_err!(
+            "zome {} has no function {}, available are: {}",
+            zome,
+            function,
+            available.join(", ")
+        )
+    })?;
+
+    if example {
+        output::print(serde_json::to_string_pretty(&function.example_args())?);
+
+        return Ok(());
+    }
+
+    let args: Value = match args {
+        Some(args) => serde_json::from_str(&args)
+            .map_err(|err| format_err!("arguments are not valid JSON: {}", err))?,
+        None => json!({}),
+    };
+
+    let problems = function.check_args(&args);
+
+    ensure!(
+        problems.is_empty(),
+        "invalid arguments for {}/{}:\n  - {}\nrun with --example to see the expected shape",
+        zome,
+        function.name,
+        problems.join("\n  - ")
+    );
+
+    // sending the call to the conductor of `run` is left to the client of its interface
+    output::print(i18n::tr(
+        "checked-call",
+        &[
+            ("status", output::status("status-checked")),
+            ("function", format!("{}/{}", zome, function.name)),
+        ],
+    ));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::{fs, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn valid_arguments_succeed() {
+        let tmp = Builder::new().prefix("call").tempdir().unwrap();
+        let src = tmp.path().join("zomes/blog/code/src");
+
+        fs::create_dir_all(&src).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "define_zome! {
+                functions: {
+                    main (Public) {
+                        create_post: {
+                            inputs: |content: String|,
+                            outputs: |result: JsonString|,
+                            handler: handle_create_post
+                        }
+                    }
+                }
+            }",
+        ).unwrap();
+
+        let call = |args: &str| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .env("HC_HOME", tmp.path().join("home"))
+                .args(&["--color", "never", "call", "blog", "create_post", args])
+                .output()
+                .unwrap()
+        };
+
+        let output = call(r#"{ "content": "Hello" }"#);
+
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("they are valid"));
+        assert!(!call(r#"{ "title": "Hello" }"#).status.success());
+    }
+}
@@ -0,0 +1,365 @@
+//! Release notes derived from the bundles of two releases.
+//!
+//! The bundles are taken from git as they were committed at the given revisions, usually the
+//! release tags. Zomes count as changed when their WASM or their `zome.json` changed, and the
+//! commits touching a changed Zome are listed below it.
+
+use base64;
+use bundle::{self, BundleType};
+use cli::{
+    docs::ZOMES_DIR_NAME,
+    layout::Layout,
+    package::{
+        read_plain_bundle, Object, CODE_DIR_NAME, DEFAULT_BUNDLE_FILE_NAME, META_SECTION_NAME,
+        META_TREE_SECTION_NAME,
+    },
+};
+use error::DefaultResult;
+use i18n;
+use output;
+use serde_json::{self, Value};
+use std::{collections::BTreeMap, fs, path::PathBuf, process::Command};
+use util;
+
+/// What a release looks like to the changelog
+struct Release {
+    /// The manifest fields of the app, by dotted path
+    fields: BTreeMap<String, Value>,
+    zomes: BTreeMap<String, Zome>,
+    size: u64,
+}
+
+struct Zome {
+    fields: BTreeMap<String, Value>,
+    code: Option<String>,
+    wasm_size: u64,
+}
+
+impl Release {
+    fn from_bundle(bundle: &Value) -> DefaultResult<Release> {
+        let empty = Object::new();
+        let root = bundle
+            .as_object()
+            .ok_or_else(|| format_err!("the bundle is not a JSON object"))?;
+        let zome_objects = root
+            .get(ZOMES_DIR_NAME)
+            .and_then(|zomes| zomes.as_object())
+            .unwrap_or(&empty);
+
+        let mut zomes = BTreeMap::new();
+
+        for (name, zome) in zome_objects {
+            let zome = match zome.as_object() {
+                Some(zome) if name != META_SECTION_NAME => zome,
+                _ => continue,
+            };
+            let code = zome
+                .get(CODE_DIR_NAME)
+                .and_then(|code| code[CODE_DIR_NAME].as_str())
+                .map(str::to_string);
+            let wasm_size = match code {
+                Some(ref code) => base64::decode(code)?.len() as u64,
+                None => 0,
+            };
+
+            zomes.insert(
+                name.clone(),
+                Zome {
+                    fields: manifest_fields(zome),
+                    code,
+                    wasm_size,
+                },
+            );
+        }
+
+        Ok(Release {
+            fields: manifest_fields(root),
+            zomes,
+            size: serde_json::to_vec(bundle)?.len() as u64,
+        })
+    }
+
+    fn wasm_size(&self) -> u64 {
+        self.zomes.values().map(|zome| zome.wasm_size).sum()
+    }
+}
+
+/// Writes the changelog section for the changes between the bundles of two git revisions,
+/// the current bundle file standing in for `to` if it's missing
+pub fn generate_changelog(
+    from: &str,
+    to: Option<String>,
+    bundle: Option<PathBuf>,
+    output: Option<PathBuf>,
+) -> DefaultResult<()> {
+    let bundle_path = bundle.unwrap_or_else(|| PathBuf::from(DEFAULT_BUNDLE_FILE_NAME));
+
+    let base = Release::from_bundle(&bundle_at(from, &bundle_path)?)?;
+    let target = match to {
+        Some(ref to) => bundle_at(to, &bundle_path)?,
+        None => read_plain_bundle(&bundle_path)?,
+    };
+    let target = Release::from_bundle(&target)?;
+
+    let range = format!("{}..{}", from, to.as_ref().map_or("HEAD", String::as_str));
+    let layout = Layout::of_app(".")?;
+    let mut commits = BTreeMap::new();
+
+    for name in target.zomes.keys() {
+        let zome_dir = layout.zome_dir(name);
+        let log = git(&[
+            "log",
+            "--format=%h %s",
+            &range,
+            "--",
+            &zome_dir.to_string_lossy(),
+        ])?;
+
+        commits.insert(name.clone(), log.lines().map(str::to_string).collect());
+    }
+
+    let to_label = to.unwrap_or_else(|| "Unreleased".to_string());
+    let markdown = render(from, &to_label, &base, &target, &commits);
+
+    match output {
+        Some(output) => {
+            fs::write(&output, markdown)?;
+
+            output::print(i18n::tr(
+                "created-changelog",
+                &[
+                    ("status", output::status("status-created")),
+                    ("path", format!("{:?}", output)),
+                ],
+            ));
+        }
+        None => output::print(markdown.trim_end()),
+    }
+
+    Ok(())
+}
+
+/// Reads the bundle as it was committed at the given revision
+fn bundle_at(revision: &str, path: &PathBuf) -> DefaultResult<Value> {
+    // `./` makes git take the path relative to the current directory instead of the repository
+    let object = format!("{}:./{}", revision, path.to_string_lossy());
+    let (header, bundle) = bundle::from_slice(git(&["show", &object])?.as_bytes())?;
+
+    ensure!(
+        header.bundle_type == BundleType::App,
+        "{:?} at {} is not a plain app bundle",
+        path,
+        revision
+    );
+
+    Ok(bundle)
+}
+
+fn git(args: &[&str]) -> DefaultResult<String> {
+    let output = Command::new("git").args(args).output()?;
+
+    ensure!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The fields of a directory's config file, which are all of its entries that aren't files or
+/// directories, flattened to dotted paths
+fn manifest_fields(dir: &Object) -> BTreeMap<String, Value> {
+    let tree = dir
+        .get(META_SECTION_NAME)
+        .and_then(|meta| meta[META_TREE_SECTION_NAME].as_object());
+
+    let mut fields = BTreeMap::new();
+
+    for (key, value) in dir {
+        let is_entry = tree.map_or(false, |tree| tree.contains_key(key));
+
+        if key != META_SECTION_NAME && !is_entry {
+            flatten(key, value, &mut fields);
+        }
+    }
+
+    fields
+}
+
+fn flatten(path: &str, value: &Value, fields: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(obj) if !obj.is_empty() => {
+            for (key, child) in obj {
+                flatten(&format!("{}.{}", path, key), child, fields);
+            }
+        }
+        _ => {
+            fields.insert(path.to_string(), value.clone());
+        }
+    }
+}
+
+fn render(
+    from: &str,
+    to: &str,
+    base: &Release,
+    target: &Release,
+    commits: &BTreeMap<String, Vec<String>>,
+) -> String {
+    let mut out = format!("## {}\n\nChanges since {}.\n", to, from);
+
+    let mut zomes = Vec::new();
+
+    for (name, zome) in &target.zomes {
+        match base.zomes.get(name) {
+            None => zomes.push(format!(
+                "- Added `{}` ({})\n",
+                name,
+                util::format_size(zome.wasm_size)
+            )),
+            Some(old) if old.code != zome.code || old.fields != zome.fields => {
+                let mut entry = format!(
+                    "- Changed `{}`: {}\n",
+                    name,
+                    size_change(old.wasm_size, zome.wasm_size)
+                );
+
+                for line in field_changes(&old.fields, &zome.fields) {
+                    entry.push_str(&format!("  {}\n", line));
+                }
+
+                for commit in commits.get(name).into_iter().flatten() {
+                    entry.push_str(&format!("  - {}\n", commit));
+                }
+
+                zomes.push(entry);
+            }
+            Some(_) => (),
+        }
+    }
+
+    for name in base.zomes.keys() {
+        if !target.zomes.contains_key(name) {
+            zomes.push(format!("- Removed `{}`\n", name));
+        }
+    }
+
+    if !zomes.is_empty() {
+        out.push_str("\n### Zomes\n\n");
+        out.push_str(&zomes.concat());
+    }
+
+    let fields = field_changes(&base.fields, &target.fields);
+
+    if !fields.is_empty() {
+        out.push_str("\n### Manifest\n\n");
+
+        for line in fields {
+            out.push_str(&format!("{}\n", line));
+        }
+    }
+
+    out.push_str("\n### Size\n\n");
+    out.push_str(&format!("| | {} | {} | Change |\n", from, to));
+    out.push_str("|---|---:|---:|---:|\n");
+
+    for (label, old, new) in &[
+        ("Bundle", base.size, target.size),
+        ("WASM", base.wasm_size(), target.wasm_size()),
+    ] {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            label,
+            util::format_size(*old),
+            util::format_size(*new),
+            size_delta(*old, *new)
+        ));
+    }
+
+    out
+}
+
+/// Describes the differences between two sets of manifest fields as Markdown list items
+fn field_changes(base: &BTreeMap<String, Value>, target: &BTreeMap<String, Value>) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    for (path, value) in target {
+        match base.get(path) {
+            None => changes.push(format!("- Added `{}`: `{}`", path, value)),
+            Some(old) if old != value => {
+                changes.push(format!("- `{}`: `{}` → `{}`", path, old, value))
+            }
+            Some(_) => (),
+        }
+    }
+
+    for path in base.keys() {
+        if !target.contains_key(path) {
+            changes.push(format!("- Removed `{}`", path));
+        }
+    }
+
+    changes
+}
+
+fn size_change(old: u64, new: u64) -> String {
+    format!(
+        "{} → {} ({})",
+        util::format_size(old),
+        util::format_size(new),
+        size_delta(old, new)
+    )
+}
+
+fn size_delta(old: u64, new: u64) -> String {
+    if new >= old {
+        format!("+{}", util::format_size(new - old))
+    } else {
+        format!("-{}", util::format_size(old - new))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(description: &str, wasm: &[u8], zome_name: &str) -> Release {
+        Release::from_bundle(&json!({
+            "name": "chat",
+            "description": description,
+            "zomes": {
+                zome_name: {
+                    "description": "Posting",
+                    "code": { "code": base64::encode(wasm) },
+                    "__META__": { "tree": { "code": "dir" } }
+                },
+                "__META__": { "tree": { zome_name: "dir" } }
+            },
+            "__META__": { "tree": { "zomes": "dir" }, "config_file": "app.json" }
+        })).unwrap()
+    }
+
+    #[test]
+    fn describes_what_changed_between_releases() {
+        let mut commits = BTreeMap::new();
+        commits.insert("posts".to_string(), vec!["1a2b3c4 Add rooms".to_string()]);
+
+        let base = release("A chat", b"\0asm", "posts");
+        let target = release("A better chat", b"\0asm\x01", "posts");
+        let changelog = render("v0.1.0", "v0.2.0", &base, &target, &commits);
+
+        assert!(changelog.starts_with("## v0.2.0\n"));
+        assert!(changelog.contains("- Changed `posts`: 4 B → 5 B (+1 B)\n  - 1a2b3c4 Add rooms\n"));
+        assert!(changelog.contains("- `description`: `\"A chat\"` → `\"A better chat\"`"));
+        assert!(!changelog.contains("`zomes"));
+
+        let renamed = release("A chat", b"\0asm", "messages");
+        let changelog = render("v0.1.0", "v0.2.0", &base, &renamed, &BTreeMap::new());
+
+        assert!(changelog.contains("- Added `messages` (4 B)"));
+        assert!(changelog.contains("- Removed `posts`"));
+        assert!(!changelog.contains("### Manifest"));
+    }
+}
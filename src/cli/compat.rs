@@ -0,0 +1,375 @@
+//! Whether a bundle runs on a given release of the conductor.
+//!
+//! Each release of the conductor provides a set of host functions to the WASM of the Zomes and
+//! understands a set of fields in their manifests. `compat` compares what the bundle needs with
+//! the releases hcdev knows about, so planning an upgrade doesn't mean instantiating the bundle
+//! on every release to find out what breaks.
+
+use base64;
+use cli::{
+    explain::find_zomes,
+    lint::HOST_MODULE,
+    package::{self, Object, META_SECTION_NAME, META_TREE_SECTION_NAME},
+};
+use error::DefaultResult;
+use output;
+use serde_json::{self, Value};
+use std::{collections::BTreeSet, path::PathBuf};
+use wasm::Module;
+
+struct Release {
+    version: &'static str,
+    /// The host functions the release added to the ones of the release before
+    host_functions: &'static [&'static str],
+    /// The fields of a Zome's manifest the release added
+    zome_fields: &'static [&'static str],
+    /// The ways of sharing an entry type the release added
+    sharing: &'static [&'static str],
+}
+
+/// The releases hcdev knows about, oldest first
+const RELEASES: &[Release] = &[
+    Release {
+        version: "0.0.1-alpha",
+        host_functions: &[
+            "hc_init_globals",
+            "hc_debug",
+            "hc_commit_entry",
+            "hc_update_entry",
+            "hc_remove_entry",
+            "hc_get_entry",
+            "hc_entry_address",
+            "hc_query",
+            "hc_call",
+            "hc_link_entries",
+            "hc_get_links",
+        ],
+        zome_fields: &[
+            "description",
+            "config",
+            "entry_types",
+            "capabilities",
+            "code",
+        ],
+        sharing: &["public", "private"],
+    },
+    Release {
+        version: "0.0.4-alpha",
+        host_functions: &["hc_send", "hc_sleep", "hc_remove_link"],
+        zome_fields: &["bridges"],
+        sharing: &[],
+    },
+    Release {
+        version: "0.0.10-alpha2",
+        host_functions: &[
+            "hc_sign",
+            "hc_sign_one_time",
+            "hc_verify_signature",
+            "hc_property",
+            "hc_keystore_list",
+            "hc_keystore_new_random",
+            "hc_keystore_derive_seed",
+            "hc_keystore_derive_key",
+            "hc_keystore_sign",
+            "hc_keystore_get_public_key",
+        ],
+        zome_fields: &[],
+        sharing: &[],
+    },
+    Release {
+        version: "0.0.18-alpha1",
+        host_functions: &["hc_emit_signal", "hc_encrypt", "hc_decrypt"],
+        zome_fields: &[],
+        sharing: &["encrypted"],
+    },
+];
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Kind {
+    /// A function the Zome's WASM imports from the conductor
+    HostFunction,
+    /// An import from somewhere else than the conductor, which no release provides
+    ForeignImport,
+    ZomeField,
+    Sharing,
+}
+
+impl Kind {
+    fn provided_by(self, release: &Release) -> &'static [&'static str] {
+        match self {
+            Kind::HostFunction => release.host_functions,
+            Kind::ForeignImport => &[],
+            Kind::ZomeField => release.zome_fields,
+            Kind::Sharing => release.sharing,
+        }
+    }
+}
+
+/// Something the bundle needs from the conductor
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct Need {
+    zome: String,
+    kind: Kind,
+    name: String,
+}
+
+/// A need a release doesn't meet
+#[derive(Debug, PartialEq, Serialize)]
+struct Incompatibility {
+    zome: String,
+    kind: Kind,
+    name: String,
+    /// The first release that meets the need, if any does
+    since: Option<&'static str>,
+}
+
+impl Incompatibility {
+    fn describe(&self, conductor: &str) -> String {
+        let problem = match self.kind {
+            Kind::HostFunction => format!(
+                "imports {}, which conductor {} doesn't provide",
+                self.name, conductor
+            ),
+            Kind::ForeignImport => format!("imports {}, which no conductor provides", self.name),
+            Kind::ZomeField => format!(
+                "has a {} field, which conductor {} doesn't understand",
+                self.name, conductor
+            ),
+            Kind::Sharing => format!(
+                "shares entries as {}, which conductor {} doesn't support",
+                self.name, conductor
+            ),
+        };
+
+        match self.since {
+            Some(since) => format!("zome {} {} (since {})", self.zome, problem, since),
+            None => format!("zome {} {}", self.zome, problem),
+        }
+    }
+}
+
+/// Reports what keeps the bundle from running on the given release of the conductor and fails
+/// if there is anything. Without a release, tells for every known release whether the bundle
+/// runs on it.
+pub fn compat(path: &PathBuf, conductor: Option<String>, json: bool) -> DefaultResult<()> {
+    let needs = needs(&package::read_plain_bundle(path)?)?;
+
+    let conductor = match conductor {
+        Some(conductor) => conductor,
+        None => {
+            for (index, release) in RELEASES.iter().enumerate() {
+                let count = incompatibilities(&needs, index).len();
+
+                output::print(format!(
+                    "{:<16}{}",
+                    release.version,
+                    match count {
+                        0 => "compatible".to_string(),
+                        count => counted(count),
+                    }
+                ));
+            }
+
+            return Ok(());
+        }
+    };
+
+    let index = RELEASES
+        .iter()
+        .position(|release| release.version == conductor)
+        .ok_or_else(|| {
+            format_err!(
+                "conductor {} is unknown to hcdev, the known releases are: {}",
+                conductor,
+                RELEASES
+                    .iter()
+                    .map(|release| release.version)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+
+    let incompatibilities = incompatibilities(&needs, index);
+
+    if json {
+        output::print(serde_json::to_string_pretty(&incompatibilities)?);
+    } else {
+        for incompatibility in &incompatibilities {
+            output::print(incompatibility.describe(&conductor));
+        }
+    }
+
+    ensure!(
+        incompatibilities.is_empty(),
+        "{:?} doesn't run on conductor {}, found {}",
+        path,
+        conductor,
+        counted(incompatibilities.len())
+    );
+
+    if !json {
+        output::print(format!("{:?} runs on conductor {}", path, conductor));
+    }
+
+    Ok(())
+}
+
+fn counted(count: usize) -> String {
+    match count {
+        1 => "1 incompatibility".to_string(),
+        count => format!("{} incompatibilities", count),
+    }
+}
+
+/// What the Zomes of the bundle need from the conductor
+fn needs(bundle: &Value) -> DefaultResult<Vec<Need>> {
+    let app = bundle
+        .as_object()
+        .ok_or_else(|| format_err!("the bundle isn't a JSON object"))?;
+    let mut zomes = Vec::new();
+    let mut needs = Vec::new();
+
+    find_zomes(app, &mut zomes);
+
+    for (zome, manifest) in zomes {
+        let need = |kind: Kind, name: &str| Need {
+            zome: zome.clone(),
+            kind,
+            name: name.to_string(),
+        };
+
+        let code = manifest
+            .get("code")
+            .and_then(|code| code["code"].as_str())
+            .ok_or_else(|| format_err!("zome {} has no code", zome))?;
+        let module = Module::parse(&base64::decode(code)?)
+            .map_err(|err| format_err!("the code of zome {} can't be read: {}", zome, err))?;
+        let mut imports = BTreeSet::new();
+
+        for import in &module.imports {
+            imports.insert(
+                if import.module == HOST_MODULE && import.name.starts_with("hc_") {
+                    need(Kind::HostFunction, &import.name)
+                } else {
+                    need(
+                        Kind::ForeignImport,
+                        &format!("{}.{}", import.module, import.name),
+                    )
+                },
+            );
+        }
+
+        needs.extend(imports.into_iter());
+
+        for field in manifest_fields(&manifest) {
+            needs.push(need(Kind::ZomeField, &field));
+        }
+
+        let sharing: BTreeSet<_> = manifest
+            .get("entry_types")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flat_map(|entry_types| entry_types.values())
+            .filter_map(|entry_type| entry_type["sharing"].as_str())
+            .collect();
+
+        needs.extend(
+            sharing
+                .into_iter()
+                .map(|sharing| need(Kind::Sharing, sharing)),
+        );
+    }
+
+    Ok(needs)
+}
+
+/// The keys of the Zome that come from its manifest, not its files
+fn manifest_fields(zome: &Object) -> Vec<String> {
+    let tree = zome
+        .get(META_SECTION_NAME)
+        .and_then(|meta| meta[META_TREE_SECTION_NAME].as_object());
+
+    zome.keys()
+        .filter(|key| *key != META_SECTION_NAME)
+        .filter(|key| match tree {
+            Some(tree) => !tree.contains_key(*key),
+            // without a meta section only the code is known to be no field
+            None => *key != "code",
+        })
+        .cloned()
+        .collect()
+}
+
+/// The needs the release at the index doesn't meet, with the first release that does
+fn incompatibilities(needs: &[Need], index: usize) -> Vec<Incompatibility> {
+    let meets = |release: &Release, need: &Need| {
+        need.kind
+            .provided_by(release)
+            .iter()
+            .any(|provided| *provided == need.name)
+    };
+
+    needs
+        .iter()
+        .filter(|need| {
+            !RELEASES[..=index]
+                .iter()
+                .any(|release| meets(release, need))
+        })
+        .map(|need| Incompatibility {
+            zome: need.zome.clone(),
+            kind: need.kind,
+            name: need.name.clone(),
+            since: RELEASES[index..]
+                .iter()
+                .find(|release| meets(release, need))
+                .map(|release| release.version),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm::test_support;
+
+    #[test]
+    fn reports_what_older_conductors_lack() {
+        let wasm = test_support::linked_module(&["hc_commit_entry", "hc_encrypt"], &[]);
+        let bundle = json!({
+            "zomes": {
+                "posts": {
+                    "code": { "code": base64::encode(&wasm) },
+                    "bridges": [],
+                    "entry_types": { "secret": { "sharing": "encrypted" } },
+                    "README.md": "",
+                    "__META__": { "tree": { "code": "bin", "README.md": "file" } }
+                },
+                "__META__": { "tree": { "posts": "dir" } }
+            },
+            "__META__": { "tree": { "zomes": "dir" } }
+        });
+
+        let needs = needs(&bundle).unwrap();
+        let names = |index| -> Vec<String> {
+            incompatibilities(&needs, index)
+                .into_iter()
+                .map(|incompatibility| incompatibility.describe(RELEASES[index].version))
+                .collect()
+        };
+
+        assert_eq!(
+            names(0),
+            vec![
+                "zome posts imports hc_encrypt, which conductor 0.0.1-alpha doesn't provide \
+                 (since 0.0.18-alpha1)",
+                "zome posts has a bridges field, which conductor 0.0.1-alpha doesn't \
+                 understand (since 0.0.4-alpha)",
+                "zome posts shares entries as encrypted, which conductor 0.0.1-alpha doesn't \
+                 support (since 0.0.18-alpha1)",
+            ]
+        );
+        assert!(names(RELEASES.len() - 1).is_empty());
+    }
+}
@@ -0,0 +1,365 @@
+//! Shell completions, generated from the same clap definitions the arguments are parsed with.
+//!
+//! clap writes the scripts for bash, zsh, fish and PowerShell itself. Its elvish script uses
+//! syntax current elvish versions reject and it has nothing for nushell, so both are written
+//! here from the tree of subcommands and their arguments.
+
+use error::DefaultResult;
+use output;
+use std::str::FromStr;
+use structopt::clap::{self, App, ArgSettings};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+    Elvish,
+    Nushell,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Shell, String> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" => Ok(Shell::PowerShell),
+            "elvish" => Ok(Shell::Elvish),
+            "nushell" => Ok(Shell::Nushell),
+            _ => Err(format!(
+                "unknown shell: {} (expected bash, zsh, fish, powershell, elvish or nushell)",
+                s
+            )),
+        }
+    }
+}
+
+/// A command as the completions see it
+struct Spec {
+    /// The words that invoke the command, starting with the binary
+    words: Vec<String>,
+    about: String,
+    flags: Vec<Flag>,
+    positionals: Vec<Positional>,
+    subcommands: Vec<Spec>,
+}
+
+struct Flag {
+    long: Option<String>,
+    short: Option<char>,
+    help: String,
+    takes_value: bool,
+}
+
+struct Positional {
+    name: String,
+    help: String,
+    required: bool,
+    multiple: bool,
+}
+
+impl Spec {
+    fn new(app: &App, parent: &[String]) -> Spec {
+        let mut words = parent.to_vec();
+        words.push(app.get_name().to_string());
+
+        let mut flags: Vec<_> = app
+            .p
+            .flags
+            .iter()
+            .filter(|flag| !flag.b.settings.is_set(ArgSettings::Hidden))
+            .map(|flag| Flag {
+                long: flag.s.long.map(str::to_string),
+                short: flag.s.short,
+                help: one_line(flag.b.help),
+                takes_value: false,
+            })
+            .chain(
+                app.p
+                    .opts
+                    .iter()
+                    .filter(|opt| !opt.b.settings.is_set(ArgSettings::Hidden))
+                    .map(|opt| Flag {
+                        long: opt.s.long.map(str::to_string),
+                        short: opt.s.short,
+                        help: one_line(opt.b.help),
+                        takes_value: true,
+                    }),
+            )
+            .collect();
+
+        // clap only adds these while parsing
+        for (long, short, help) in &[
+            ("help", 'h', "Prints help information"),
+            ("version", 'V', "Prints version information"),
+        ] {
+            if !flags
+                .iter()
+                .any(|flag| flag.long.as_ref().map(String::as_str) == Some(long))
+            {
+                flags.push(Flag {
+                    long: Some(long.to_string()),
+                    short: Some(*short),
+                    help: help.to_string(),
+                    takes_value: false,
+                });
+            }
+        }
+
+        flags.sort_by(|a, b| a.long.cmp(&b.long));
+
+        let positionals = app
+            .p
+            .positionals
+            .values()
+            .map(|positional| Positional {
+                name: positional.b.name.to_string(),
+                help: one_line(positional.b.help),
+                required: positional.b.settings.is_set(ArgSettings::Required),
+                multiple: positional.b.settings.is_set(ArgSettings::Multiple),
+            })
+            .collect();
+
+        Spec {
+            about: one_line(app.p.meta.about),
+            subcommands: app
+                .p
+                .subcommands
+                .iter()
+                .map(|subcommand| Spec::new(subcommand, &words))
+                .collect(),
+            words,
+            flags,
+            positionals,
+        }
+    }
+
+    /// This command and all of its subcommands, depth first
+    fn all(&self) -> Vec<&Spec> {
+        let mut all = vec![self];
+
+        for subcommand in &self.subcommands {
+            all.extend(subcommand.all());
+        }
+
+        all
+    }
+}
+
+/// Prints the completion script for the given shell
+pub fn completions(mut app: App, shell: Shell) -> DefaultResult<()> {
+    let bin_name = app.get_name().to_string();
+
+    let clap_shell = match shell {
+        Shell::Bash => Some(clap::Shell::Bash),
+        Shell::Zsh => Some(clap::Shell::Zsh),
+        Shell::Fish => Some(clap::Shell::Fish),
+        Shell::PowerShell => Some(clap::Shell::PowerShell),
+        Shell::Elvish | Shell::Nushell => None,
+    };
+
+    let script = match clap_shell {
+        Some(clap_shell) => {
+            let mut script = Vec::new();
+            app.gen_completions_to(bin_name, clap_shell, &mut script);
+
+            String::from_utf8(script)?
+        }
+        None => {
+            // the global options are only copied to the subcommands on demand
+            app.p.propagate_globals();
+
+            let spec = Spec::new(&app, &[]);
+
+            match shell {
+                Shell::Elvish => elvish(&spec),
+                _ => nushell(&spec),
+            }
+        }
+    };
+
+    output::print(script.trim_end());
+
+    Ok(())
+}
+
+fn nushell(spec: &Spec) -> String {
+    let mut out = format!(
+        "# Completions for {bin}, load them with: source {bin}.nu\n",
+        bin = spec.words[0]
+    );
+
+    for command in spec.all() {
+        out.push_str(&format!("\n# {}\n", command.about));
+        out.push_str(&format!(
+            "export extern \"{}\" [\n",
+            command.words.join(" ")
+        ));
+
+        for positional in &command.positionals {
+            let name = positional.name.replace('-', "_");
+            let param = match (positional.multiple, positional.required) {
+                (true, _) => format!("...{}: string", name),
+                (false, true) => format!("{}: string", name),
+                (false, false) => format!("{}?: string", name),
+            };
+
+            out.push_str(&nushell_line(&param, &positional.help));
+        }
+
+        // plugins are run as subcommands the completions don't know about
+        if command.words.len() == 1 {
+            out.push_str(&nushell_line("...args: string", ""));
+        }
+
+        for flag in &command.flags {
+            let mut param = match (&flag.long, flag.short) {
+                (Some(long), Some(short)) => format!("--{}(-{})", long, short),
+                (Some(long), None) => format!("--{}", long),
+                (None, Some(short)) => format!("-{}", short),
+                (None, None) => continue,
+            };
+
+            if flag.takes_value {
+                param.push_str(": string");
+            }
+
+            out.push_str(&nushell_line(&param, &flag.help));
+        }
+
+        out.push_str("]\n");
+    }
+
+    out
+}
+
+fn nushell_line(param: &str, help: &str) -> String {
+    match help {
+        "" => format!("  {}\n", param),
+        _ => format!("  {:<32} # {}\n", param, help),
+    }
+}
+
+fn elvish(spec: &Spec) -> String {
+    let bin = &spec.words[0];
+    let mut cases = String::new();
+
+    for command in spec.all() {
+        cases.push_str(&format!("        &'{}'= {{\n", command.words.join(";")));
+
+        for flag in &command.flags {
+            if let Some(short) = flag.short {
+                cases.push_str(&elvish_candidate(&format!("-{}", short), &flag.help));
+            }
+
+            if let Some(ref long) = flag.long {
+                cases.push_str(&elvish_candidate(&format!("--{}", long), &flag.help));
+            }
+        }
+
+        for subcommand in &command.subcommands {
+            cases.push_str(&elvish_candidate(
+                &subcommand.words[subcommand.words.len() - 1],
+                &subcommand.about,
+            ));
+        }
+
+        cases.push_str("        }\n");
+    }
+
+    format!(
+        r#"use builtin
+use str
+
+set edit:completion:arg-completer[{bin}] = {{|@words|
+    fn spaces {{|n|
+        builtin:repeat $n ' ' | str:join ''
+    }}
+    fn cand {{|text desc|
+        edit:complex-candidate $text &display=$text' '(spaces (- 18 (wcswidth $text)))$desc
+    }}
+    var command = '{bin}'
+    for word $words[1..-1] {{
+        if (str:has-prefix $word '-') {{
+            break
+        }}
+        set command = $command';'$word
+    }}
+    var completions = [
+{cases}    ]
+    if (has-key $completions $command) {{
+        $completions[$command]
+    }}
+}}
+"#,
+        bin = bin,
+        cases = cases
+    )
+}
+
+fn elvish_candidate(text: &str, help: &str) -> String {
+    format!("            cand {} '{}'\n", text, help.replace('\'', "''"))
+}
+
+fn one_line(text: Option<&str>) -> String {
+    text.unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::clap::{Arg, SubCommand};
+
+    fn app() -> App<'static, 'static> {
+        App::new("hcdev")
+            .about("A command line for Holochain")
+            .arg(
+                Arg::with_name("offline")
+                    .long("offline")
+                    .help("Forbids all network access")
+                    .global(true),
+            )
+            .subcommand(
+                SubCommand::with_name("package")
+                    .about("Builds the app's bundle")
+                    .arg(
+                        Arg::with_name("output")
+                            .long("output")
+                            .short("o")
+                            .takes_value(true)
+                            .help("Where the bundle goes, 'bundle.json' by default"),
+                    )
+                    .arg(Arg::with_name("zome").help("Only this zome")),
+            )
+    }
+
+    #[test]
+    fn describes_every_command_and_flag() {
+        let mut app = app();
+        app.p.propagate_globals();
+        let spec = Spec::new(&app, &[]);
+
+        let nu = nushell(&spec);
+        assert!(nu.contains("export extern \"hcdev package\" [\n  zome?: string"));
+        assert!(nu.contains("  --output(-o): string"));
+        assert!(nu.contains("# Where the bundle goes"));
+        assert_eq!(
+            nu.matches("--offline").count(),
+            2,
+            "global flags are repeated"
+        );
+        assert!(nu.contains("...args: string"));
+
+        let elv = elvish(&spec);
+        assert!(elv.contains("&'hcdev;package'= {"));
+        assert!(elv.contains("cand package 'Builds the app''s bundle'"));
+        assert!(elv.contains("cand --output 'Where the bundle goes, ''bundle.json'' by default'"));
+    }
+}
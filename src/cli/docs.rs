@@ -0,0 +1,113 @@
+use cli::{layout::Layout, package::BUILD_CONFIG_FILE_NAME};
+use error::DefaultResult;
+use i18n;
+use output;
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use util;
+use zome_api::{Param, ZomeApi};
+
+pub const ZOMES_DIR_NAME: &str = "zomes";
+
+pub fn docs(output: Option<PathBuf>) -> DefaultResult<()> {
+    let layout = Layout::of_app(".")?;
+    let mut markdown = String::from("# Zome API\n");
+
+    for zome_path in layout.zome_dirs(Path::new("."))? {
+        let code_dir = layout.code_dir(&zome_path);
+
+        if !code_dir.join(BUILD_CONFIG_FILE_NAME).is_file() {
+            continue;
+        }
+
+        let zome_name = util::file_name_string(&zome_path)?;
+        let api = ZomeApi::from_code_dir(&code_dir)?;
+
+        markdown.push('\n');
+        markdown.push_str(&render_markdown(&zome_name, &api));
+    }
+
+    match output {
+        Some(output) => {
+            File::create(&output)?.write_all(markdown.as_bytes())?;
+
+            output::print(i18n::tr(
+                "created-docs",
+                &[
+                    ("status", output::status("status-created")),
+                    ("path", format!("{:?}", output)),
+                ],
+            ));
+        }
+        None => output::print(markdown.trim_end()),
+    }
+
+    Ok(())
+}
+
+/// Renders the extracted interface of a single zome as a Markdown section
+pub fn render_markdown(zome_name: &str, api: &ZomeApi) -> String {
+    let mut out = format!("## Zome `{}`\n", zome_name);
+
+    if !api.entries.is_empty() {
+        out.push_str("\n### Entry types\n\n");
+        out.push_str("| Name | Native type | Validation | Description |\n");
+        out.push_str("|------|-------------|------------|-------------|\n");
+
+        for entry in &api.entries {
+            out.push_str(&format!(
+                "| `{}` | {} | {} | {} |\n",
+                entry.name,
+                entry
+                    .native_type
+                    .as_ref()
+                    .map(|ty| format!("`{}`", ty))
+                    .unwrap_or_default(),
+                if entry.has_validation { "yes" } else { "no" },
+                entry.description
+            ));
+        }
+    }
+
+    if !api.functions.is_empty() {
+        out.push_str("\n### Functions\n");
+
+        for function in &api.functions {
+            out.push_str(&format!(
+                "\n#### `{}` ({})\n\n",
+                function.name, function.capability
+            ));
+
+            if !function.doc.is_empty() {
+                out.push_str(&format!("{}\n\n", function.doc));
+            }
+
+            out.push_str(&format!("- Inputs: {}\n", render_params(&function.inputs)));
+            out.push_str(&format!(
+                "- Outputs: {}\n",
+                render_params(&function.outputs)
+            ));
+        }
+    }
+
+    if api.entries.is_empty() && api.functions.is_empty() {
+        out.push_str("\nNo `define_zome!` declaration found.\n");
+    }
+
+    out
+}
+
+fn render_params(params: &[Param]) -> String {
+    if params.is_empty() {
+        return "none".into();
+    }
+
+    params
+        .iter()
+        .map(|param| format!("`{}: {}`", param.name, param.ty))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,73 @@
+use colored::*;
+use config_files::{Global, Project};
+use error::DefaultResult;
+use output;
+use std::process::Command;
+
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+/// Checks that the tools needed to build Zomes are installed and the settings are readable
+pub fn doctor() -> DefaultResult<()> {
+    let checks: Vec<(&str, Result<String, String>)> = vec![
+        ("cargo is installed", tool_version("cargo")),
+        ("rustup is installed", tool_version("rustup")),
+        (
+            "the WASM target is installed",
+            installed_targets().and_then(|targets| {
+                if targets.lines().any(|target| target.trim() == WASM_TARGET) {
+                    Ok(WASM_TARGET.to_string())
+                } else {
+                    Err(format!("run: rustup target add {}", WASM_TARGET))
+                }
+            }),
+        ),
+        (
+            "the global settings are valid",
+            Global::load()
+                .map(|_| String::new())
+                .map_err(|err| err.to_string()),
+        ),
+        (
+            "the project settings are valid",
+            Project::from_dir(".")
+                .map(|_| String::new())
+                .map_err(|err| err.to_string()),
+        ),
+    ];
+
+    let mut failed = 0;
+
+    for (check, result) in checks {
+        match result {
+            Ok(detail) => {
+                output::print(format!("{} {} {}", "ok".green().bold(), check, detail).trim_end())
+            }
+            Err(hint) => {
+                failed += 1;
+                output::print(format!("{} {}: {}", "failed".red().bold(), check, hint));
+            }
+        }
+    }
+
+    ensure!(failed == 0, "{} check(s) failed", failed);
+
+    Ok(())
+}
+
+fn tool_version(tool: &str) -> Result<String, String> {
+    run(tool, &["--version"]).map(|version| format!("({})", version.trim()))
+}
+
+fn installed_targets() -> Result<String, String> {
+    run("rustup", &["target", "list", "--installed"])
+}
+
+fn run(bin: &str, args: &[&str]) -> Result<String, String> {
+    match Command::new(bin).args(args).output() {
+        Ok(ref output) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(output) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(err) => Err(format!("unable to run {}: {}", bin, err)),
+    }
+}
@@ -0,0 +1,82 @@
+use cli::{keys, registry::STORE_DIR_NAME, run::CONDUCTOR_ENV_VAR};
+use config_files::{dirs, project, Global};
+use error::DefaultResult;
+use file_lock::LOCK_TIMEOUT_ENV_VAR;
+use net::OFFLINE_ENV_VAR;
+use output::{self, NO_COLOR_ENV_VAR};
+use provenance::BUILDER_ID_ENV_VAR;
+use remote_cache;
+use reproducible::SOURCE_DATE_EPOCH_ENV_VAR;
+use std::{env, path::PathBuf};
+
+const ENV_VARS: &[&str] = &[
+    dirs::HOME_ENV_VAR,
+    dirs::CACHE_DIR_ENV_VAR,
+    project::CONFIG_ENV_VAR,
+    OFFLINE_ENV_VAR,
+    LOCK_TIMEOUT_ENV_VAR,
+    NO_COLOR_ENV_VAR,
+    SOURCE_DATE_EPOCH_ENV_VAR,
+    BUILDER_ID_ENV_VAR,
+    remote_cache::WRITE_ENV_VAR,
+    CONDUCTOR_ENV_VAR,
+];
+
+/// Prints the environment variables the CLI reacts to, or with `paths` where it keeps its
+/// user-wide state
+pub fn env(paths: bool) -> DefaultResult<()> {
+    if paths {
+        print_paths(&[
+            ("config", dirs::config_dir()?),
+            ("config file", Global::file_path()?),
+            ("data", dirs::data_dir()?),
+            ("identity", keys::default_identity_path()?),
+            ("cache", dirs::cache_dir()?),
+            ("store", dirs::cache_dir()?.join(STORE_DIR_NAME)),
+        ]);
+    } else {
+        for name in ENV_VARS {
+            let value = env::var(name).unwrap_or_else(|_| "(not set)".to_string());
+
+            output::print(format!("{:<20}{}", name, value));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_paths(paths: &[(&str, PathBuf)]) {
+    for (name, path) in paths {
+        output::print(format!("{:<14}{}", name, path.display()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::process::Command;
+    use tempfile::Builder;
+
+    #[test]
+    fn paths_follow_the_override_env_vars() {
+        let tmp = Builder::new().prefix("env").tempdir().unwrap();
+        let home = tmp.path().join("home");
+        let cache = tmp.path().join("cache");
+
+        let output = Command::main_binary()
+            .unwrap()
+            .env("HC_HOME", &home)
+            .env("HC_CACHE_DIR", &cache)
+            .args(&["env", "--paths"])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(stdout.contains(&format!("{}", home.join("config.toml").display())));
+        assert!(stdout.contains(&format!("{}", home.join("identity.key").display())));
+        assert!(stdout.contains(&format!("{}", cache.join("store").display())));
+    }
+}
@@ -0,0 +1,231 @@
+//! Complete example apps, set up with `init --example <name>`. The examples aren't copies of
+//! projects checked in somewhere: only their Zome code ships with the CLI, everything around it
+//! is generated like any other app, so an example always has the dependencies, build files and
+//! settings of the CLI that sets it up, and packages and tests with it.
+
+use cli::{
+    config::config_set,
+    generate::{generate, generate_entry_type},
+    layout::Layout,
+    license::{project_license, with_license_header},
+    new::{new, APP_CONFIG_FILE_NAME},
+};
+use error::DefaultResult;
+use i18n;
+use output;
+use serde_json::{self, Value};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// The host functions all examples share, with a mock to test the Zomes natively
+const HOST_SOURCE: &str = include_str!("examples/host.rs");
+
+/// The crates the Zomes of the examples use besides the HDK
+const CRATES: &[&str] = &["serde@1.0", "serde_derive@1.0", "serde_json@1.0"];
+
+struct Example {
+    name: &'static str,
+    description: &'static str,
+    zome: &'static str,
+    entry_types: &'static [&'static str],
+    /// The `src/lib.rs` of the Zome
+    code: &'static str,
+}
+
+const EXAMPLES: &[Example] = &[
+    Example {
+        name: "todo",
+        description: "A todo list, with entries that are updated when a todo is done",
+        zome: "todos",
+        entry_types: &["todo"],
+        code: include_str!("examples/todo.rs"),
+    },
+    Example {
+        name: "chat",
+        description: "A chat with channels everyone can post messages to",
+        zome: "chat",
+        entry_types: &["channel", "message"],
+        code: include_str!("examples/chat.rs"),
+    },
+    Example {
+        name: "file-share",
+        description: "Sharing files, stored as chunks that are the same for every file",
+        zome: "files",
+        entry_types: &["file", "chunk"],
+        code: include_str!("examples/file_share.rs"),
+    },
+];
+
+/// Lists the examples `init --example` sets up
+pub fn examples() -> DefaultResult<()> {
+    let width = EXAMPLES.iter().map(|example| example.name.len()).max();
+
+    for example in EXAMPLES {
+        output::print(format!(
+            "{:width$}  {}",
+            example.name,
+            example.description,
+            width = width.unwrap_or(0)
+        ));
+    }
+
+    Ok(())
+}
+
+/// Sets up the example app of the given name in the directory, ready to be packaged and tested
+pub fn init_example(path: &PathBuf, name: &str) -> DefaultResult<()> {
+    let example = EXAMPLES
+        .iter()
+        .find(|example| example.name == name)
+        .ok_or_else(|| {
+            format_err!(
+                "there is no example {}, the examples are: {}",
+                name,
+                EXAMPLES
+                    .iter()
+                    .map(|example| example.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+
+    new(path, &None)?;
+
+    // the scaffolding works on the app in the current directory
+    env::set_current_dir(path)?;
+
+    set_description(Path::new(APP_CONFIG_FILE_NAME), example.description)?;
+
+    let layout = Layout::of_app(".")?;
+    let zome = layout.zome_dir(example.zome);
+    let crates: Vec<_> = CRATES.iter().map(|spec| spec.to_string()).collect();
+
+    generate(&zome, "rust", false, &crates)?;
+
+    for entry_type in example.entry_types {
+        generate_entry_type(&zome, entry_type, "public")?;
+    }
+
+    let code_dir = layout.code_dir(&zome);
+    let src_dir = code_dir.join("src");
+    let license = project_license()?;
+    let source = |code: &str| match license {
+        Some(ref license) => with_license_header(code, license),
+        None => code.to_string(),
+    };
+
+    fs::write(src_dir.join("lib.rs"), source(example.code))?;
+    fs::write(src_dir.join("host.rs"), source(HOST_SOURCE))?;
+
+    // the logic of the Zomes is tested natively, see host.rs
+    config_set(
+        "scripts.test",
+        &format!(
+            "cargo test --manifest-path {}/Cargo.toml",
+            code_dir.to_string_lossy()
+        ),
+        false,
+    )?;
+
+    output::print(i18n::tr(
+        "created-example",
+        &[
+            ("status", output::status("status-created")),
+            ("name", example.name.to_string()),
+            ("path", format!("{:?}", path)),
+        ],
+    ));
+
+    Ok(())
+}
+
+fn set_description(config_path: &Path, description: &str) -> DefaultResult<()> {
+    let mut config: Value = serde_json::from_str(&fs::read_to_string(config_path)?)?;
+    config["description"] = description.into();
+
+    fs::write(config_path, serde_json::to_string_pretty(&config)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EXAMPLES;
+    use assert_cmd::prelude::*;
+    use serde_json::{self, Value};
+    use std::{fs, process::Command};
+    use tempfile::Builder;
+    use zome_api::ZomeApi;
+
+    #[test]
+    fn examples_declare_the_entry_types_of_their_code() {
+        let tmp = Builder::new().prefix("examples").tempdir().unwrap();
+
+        for example in EXAMPLES {
+            let app = tmp.path().join(example.name);
+
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .args(&["init", example.name, "--example", example.name])
+                .assert()
+                .success();
+
+            let zome = app.join("zomes").join(example.zome);
+            let config: Value =
+                serde_json::from_str(&fs::read_to_string(zome.join("zome.json")).unwrap()).unwrap();
+            let mut declared: Vec<_> = config["entry_types"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            let mut defined: Vec<_> = ZomeApi::from_code_dir(zome.join("code"))
+                .unwrap()
+                .entries
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect();
+
+            declared.sort();
+            defined.sort();
+
+            assert_eq!(declared, defined, "{}", example.name);
+            assert!(zome.join("code/src/host.rs").is_file());
+            assert!(fs::read_to_string(app.join("hc.toml"))
+                .unwrap()
+                .contains("cargo test --manifest-path zomes/"));
+        }
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .args(&["init", "forum", "--example", "forum"])
+            .assert()
+            .failure();
+    }
+
+    #[test]
+    fn examples_package_and_pass_their_tests() {
+        let tmp = Builder::new().prefix("examples").tempdir().unwrap();
+
+        for example in EXAMPLES {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .args(&["init", example.name, "--example", example.name])
+                .assert()
+                .success();
+
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path().join(example.name))
+                .env("HC_HOME", tmp.path().join("home"))
+                .args(&["test"])
+                .assert()
+                .success();
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! A chat with channels. A channel is an entry of its name, so every agent arrives at the same
+//! address for it, and the messages posted to it are linked from there.
+// the macros of the HDK are only used when building for the conductor
+#[cfg_attr(target_arch = "wasm32", macro_use)]
+extern crate hdk;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+pub mod host;
+
+use crate::host::Host;
+
+pub const CHANNEL_ENTRY: &str = "channel";
+pub const MESSAGE_ENTRY: &str = "message";
+/// Tags the links from a channel to its messages
+pub const MESSAGES_TAG: &str = "messages";
+
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Channel {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Message {
+    pub channel: String,
+    /// The address of the agent who posted it
+    pub author: String,
+    pub text: String,
+}
+
+pub fn validate_channel(channel: &Channel) -> Result<(), String> {
+    let valid = !channel.name.is_empty()
+        && channel
+            .name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err("channel names are made of lowercase letters, digits and dashes".to_string())
+    }
+}
+
+pub fn validate_message(message: &Message) -> Result<(), String> {
+    if message.text.trim().is_empty() {
+        return Err("a message needs a text".to_string());
+    }
+
+    if message.text.len() > MAX_MESSAGE_LENGTH {
+        return Err(format!(
+            "messages are at most {} bytes long",
+            MAX_MESSAGE_LENGTH
+        ));
+    }
+
+    Ok(())
+}
+
+/// Posts a message to the channel, which is created on its first message
+pub fn post_message<H: Host>(host: &H, channel: &str, text: &str) -> Result<String, String> {
+    let channel = Channel {
+        name: channel.to_string(),
+    };
+    validate_channel(&channel)?;
+
+    let message = Message {
+        channel: channel.name.clone(),
+        author: host.agent(),
+        text: text.to_string(),
+    };
+    validate_message(&message)?;
+
+    let channel_address = host.commit(CHANNEL_ENTRY, to_json(&channel)?)?;
+    let address = host.commit(MESSAGE_ENTRY, to_json(&message)?)?;
+    host.link(&channel_address, &address, MESSAGES_TAG)?;
+
+    Ok(address)
+}
+
+/// The messages of the channel in the order they were linked
+pub fn get_messages<H: Host>(host: &H, channel: &str) -> Result<Vec<Message>, String> {
+    let channel = Channel {
+        name: channel.to_string(),
+    };
+    validate_channel(&channel)?;
+
+    // committing the channel again only tells its address, it is the same entry
+    let channel_address = host.commit(CHANNEL_ENTRY, to_json(&channel)?)?;
+    let mut messages = Vec::new();
+
+    for address in host.links(&channel_address, MESSAGES_TAG)? {
+        if let Some(json) = host.get(&address)? {
+            messages.push(serde_json::from_str(&json).map_err(|err| err.to_string())?);
+        }
+    }
+
+    Ok(messages)
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|err| err.to_string())
+}
+
+/// The functions the conductor calls, handing their results back as JSON
+#[cfg(target_arch = "wasm32")]
+mod zome {
+    use crate::host::Hdk;
+    use hdk::{
+        self,
+        holochain_core_types::{dna::entry_types::Sharing, json::JsonString},
+    };
+
+    fn json<T: ::serde::Serialize>(result: Result<T, String>) -> JsonString {
+        JsonString::from(::serde_json::to_string(&result).unwrap_or_default())
+    }
+
+    fn handle_post_message(channel: String, text: String) -> JsonString {
+        json(super::post_message(&Hdk, &channel, &text))
+    }
+
+    fn handle_get_messages(channel: String) -> JsonString {
+        json(super::get_messages(&Hdk, &channel))
+    }
+
+    define_zome! {
+        entries: [
+            entry!(
+                name: "channel",
+                description: "A channel, the same entry for everyone who names it",
+                sharing: Sharing::Public,
+                native_type: JsonString,
+                validation_package: || hdk::ValidationPackageDefinition::Entry,
+                validation: |channel: JsonString, _ctx: hdk::ValidationData| {
+                    ::serde_json::from_str(&String::from(channel))
+                        .map_err(|err| err.to_string())
+                        .and_then(|channel| super::validate_channel(&channel))
+                }
+            ),
+            entry!(
+                name: "message",
+                description: "A message posted to a channel",
+                sharing: Sharing::Public,
+                native_type: JsonString,
+                validation_package: || hdk::ValidationPackageDefinition::Entry,
+                validation: |message: JsonString, _ctx: hdk::ValidationData| {
+                    ::serde_json::from_str(&String::from(message))
+                        .map_err(|err| err.to_string())
+                        .and_then(|message| super::validate_message(&message))
+                }
+            )
+        ]
+
+        genesis: || { Ok(()) }
+
+        functions: {
+            main (Public) {
+                /// Posts a message to a channel
+                post_message: {
+                    inputs: |channel: String, text: String|,
+                    outputs: |result: JsonString|,
+                    handler: handle_post_message
+                }
+                /// Reads the messages of a channel
+                get_messages: {
+                    inputs: |channel: String|,
+                    outputs: |messages: JsonString|,
+                    handler: handle_get_messages
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::mock::MockHost;
+
+    #[test]
+    fn channels_keep_their_own_messages() {
+        let host = MockHost::default();
+
+        post_message(&host, "general", "hello").unwrap();
+        post_message(&host, "random", "a cat picture").unwrap();
+        post_message(&host, "general", "anyone here?").unwrap();
+
+        let texts: Vec<_> = get_messages(&host, "general")
+            .unwrap()
+            .into_iter()
+            .map(|message| message.text)
+            .collect();
+
+        assert_eq!(texts, vec!["hello", "anyone here?"]);
+        assert_eq!(get_messages(&host, "random").unwrap()[0].author, "agent");
+    }
+
+    #[test]
+    fn refuses_invalid_channels_and_messages() {
+        let host = MockHost::default();
+
+        assert!(post_message(&host, "General Chat", "hello").is_err());
+        assert!(post_message(&host, "general", "").is_err());
+        assert!(post_message(&host, "general", &"x".repeat(2001)).is_err());
+    }
+}
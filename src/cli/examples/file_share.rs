@@ -0,0 +1,243 @@
+//! Sharing files. A file is stored as chunks of its base64 encoded content, which spread over
+//! the DHT on their own, and an entry listing them in order. Chunks every file has in common,
+//! like runs of zeros, are stored only once.
+// the macros of the HDK are only used when building for the conductor
+#[cfg_attr(target_arch = "wasm32", macro_use)]
+extern crate hdk;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+pub mod host;
+
+use crate::host::Host;
+
+pub const FILE_ENTRY: &str = "file";
+pub const CHUNK_ENTRY: &str = "chunk";
+/// Tags the links from the agent to the files it shared
+pub const FILES_TAG: &str = "files";
+
+/// Characters of base64 per chunk, a multiple of 4 so every chunk decodes on its own
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct File {
+    pub name: String,
+    /// The length of the base64 encoded content
+    pub size: usize,
+    /// The addresses of the chunks, in order
+    pub chunks: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Chunk {
+    pub data: String,
+}
+
+pub fn validate_file(file: &File) -> Result<(), String> {
+    if file.name.trim().is_empty() || file.name.contains('/') {
+        return Err("a file needs a name without slashes".to_string());
+    }
+
+    if file.chunks.len() != (file.size + CHUNK_SIZE - 1) / CHUNK_SIZE {
+        return Err("the chunks don't add up to the size of the file".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn validate_chunk(chunk: &Chunk) -> Result<(), String> {
+    let is_base64 = chunk
+        .data
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/' || b == b'=');
+
+    if chunk.data.is_empty() || chunk.data.len() > CHUNK_SIZE || !is_base64 {
+        return Err(format!(
+            "chunks are 1 to {} characters of base64",
+            CHUNK_SIZE
+        ));
+    }
+
+    Ok(())
+}
+
+/// Shares a file given as base64 and returns the address of its entry
+pub fn share_file<H: Host>(host: &H, name: &str, content: &str) -> Result<String, String> {
+    let mut chunks = Vec::new();
+
+    for data in content.as_bytes().chunks(CHUNK_SIZE) {
+        let chunk = Chunk {
+            data: String::from_utf8_lossy(data).into_owned(),
+        };
+        validate_chunk(&chunk)?;
+
+        chunks.push(host.commit(CHUNK_ENTRY, to_json(&chunk)?)?);
+    }
+
+    let file = File {
+        name: name.to_string(),
+        size: content.len(),
+        chunks,
+    };
+    validate_file(&file)?;
+
+    let address = host.commit(FILE_ENTRY, to_json(&file)?)?;
+    host.link(&host.agent(), &address, FILES_TAG)?;
+
+    Ok(address)
+}
+
+/// The files the agent shared with their addresses
+pub fn list_files<H: Host>(host: &H) -> Result<Vec<(String, File)>, String> {
+    let mut files = Vec::new();
+
+    for address in host.links(&host.agent(), FILES_TAG)? {
+        if let Some(file) = get::<_, File>(host, &address)? {
+            files.push((address, file));
+        }
+    }
+
+    Ok(files)
+}
+
+/// The base64 encoded content of the file at the address, put together from its chunks
+pub fn download_file<H: Host>(host: &H, address: &str) -> Result<String, String> {
+    let file: File = get(host, address)?.ok_or_else(|| format!("no file at {}", address))?;
+    let mut content = String::with_capacity(file.size);
+
+    for chunk_address in &file.chunks {
+        let chunk: Chunk = get(host, chunk_address)?
+            .ok_or_else(|| format!("chunk {} of {} is missing", chunk_address, file.name))?;
+        content.push_str(&chunk.data);
+    }
+
+    Ok(content)
+}
+
+fn get<H: Host, T: serde::de::DeserializeOwned>(
+    host: &H,
+    address: &str,
+) -> Result<Option<T>, String> {
+    match host.get(address)? {
+        Some(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|err| err.to_string()),
+        None => Ok(None),
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|err| err.to_string())
+}
+
+/// The functions the conductor calls, handing their results back as JSON
+#[cfg(target_arch = "wasm32")]
+mod zome {
+    use crate::host::Hdk;
+    use hdk::{
+        self,
+        holochain_core_types::{dna::entry_types::Sharing, json::JsonString},
+    };
+
+    fn json<T: ::serde::Serialize>(result: Result<T, String>) -> JsonString {
+        JsonString::from(::serde_json::to_string(&result).unwrap_or_default())
+    }
+
+    fn handle_share_file(name: String, content: String) -> JsonString {
+        json(super::share_file(&Hdk, &name, &content))
+    }
+
+    fn handle_list_files() -> JsonString {
+        json(super::list_files(&Hdk))
+    }
+
+    fn handle_download_file(address: String) -> JsonString {
+        json(super::download_file(&Hdk, &address))
+    }
+
+    define_zome! {
+        entries: [
+            entry!(
+                name: "file",
+                description: "The name and size of a file and the addresses of its chunks",
+                sharing: Sharing::Public,
+                native_type: JsonString,
+                validation_package: || hdk::ValidationPackageDefinition::Entry,
+                validation: |file: JsonString, _ctx: hdk::ValidationData| {
+                    ::serde_json::from_str(&String::from(file))
+                        .map_err(|err| err.to_string())
+                        .and_then(|file| super::validate_file(&file))
+                }
+            ),
+            entry!(
+                name: "chunk",
+                description: "A piece of the base64 encoded content of files",
+                sharing: Sharing::Public,
+                native_type: JsonString,
+                validation_package: || hdk::ValidationPackageDefinition::Entry,
+                validation: |chunk: JsonString, _ctx: hdk::ValidationData| {
+                    ::serde_json::from_str(&String::from(chunk))
+                        .map_err(|err| err.to_string())
+                        .and_then(|chunk| super::validate_chunk(&chunk))
+                }
+            )
+        ]
+
+        genesis: || { Ok(()) }
+
+        functions: {
+            main (Public) {
+                /// Shares a file given as base64
+                share_file: {
+                    inputs: |name: String, content: String|,
+                    outputs: |result: JsonString|,
+                    handler: handle_share_file
+                }
+                /// Lists the files the agent shared
+                list_files: {
+                    inputs: | |,
+                    outputs: |files: JsonString|,
+                    handler: handle_list_files
+                }
+                /// Downloads a file as base64
+                download_file: {
+                    inputs: |address: String|,
+                    outputs: |content: JsonString|,
+                    handler: handle_download_file
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::mock::MockHost;
+
+    #[test]
+    fn downloads_what_was_shared() {
+        let host = MockHost::default();
+        let content = "QUJD".repeat(CHUNK_SIZE / 2 + 1);
+
+        let address = share_file(&host, "abc.txt", &content).unwrap();
+
+        assert_eq!(download_file(&host, &address).unwrap(), content);
+
+        let (_, file) = list_files(&host).unwrap().remove(0);
+
+        assert_eq!(file.chunks.len(), 3);
+        // the first two chunks are the same and stored once
+        assert_eq!(file.chunks[0], file.chunks[1]);
+    }
+
+    #[test]
+    fn refuses_what_isnt_base64() {
+        let host = MockHost::default();
+
+        assert!(share_file(&host, "notes.txt", "plain text").is_err());
+        assert!(share_file(&host, "a/b.txt", "QUJD").is_err());
+    }
+}
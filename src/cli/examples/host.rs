@@ -0,0 +1,153 @@
+//! The host functions the Zome calls: the HDK's in the conductor, a mock in the tests. Keeping
+//! the logic of the Zome behind this trait lets `cargo test` run it natively.
+
+/// What the Zome needs from the conductor, with entries as JSON and addresses as strings
+pub trait Host {
+    /// The address of the agent running the Zome
+    fn agent(&self) -> String;
+    /// Commits an entry of the given type to the source chain and returns its address
+    fn commit(&self, entry_type: &str, entry: String) -> Result<String, String>;
+    /// Replaces the entry at the address by a new version, returning the address of that
+    fn update(&self, entry_type: &str, entry: String, address: &str) -> Result<String, String>;
+    /// The latest version of the entry at the address, if there is one
+    fn get(&self, address: &str) -> Result<Option<String>, String>;
+    fn link(&self, base: &str, target: &str, tag: &str) -> Result<(), String>;
+    /// The targets of the links with the tag from the base, in the order they were made
+    fn links(&self, base: &str, tag: &str) -> Result<Vec<String>, String>;
+}
+
+/// The host the Zome has when it runs in a conductor
+pub struct Hdk;
+
+#[cfg(target_arch = "wasm32")]
+impl Host for Hdk {
+    fn agent(&self) -> String {
+        ::hdk::AGENT_ADDRESS.to_string()
+    }
+
+    fn commit(&self, entry_type: &str, entry: String) -> Result<String, String> {
+        ::hdk::commit_entry(&app_entry(entry_type, entry))
+            .map(String::from)
+            .map_err(|err| err.to_string())
+    }
+
+    fn update(&self, entry_type: &str, entry: String, address: &str) -> Result<String, String> {
+        ::hdk::update_entry(app_entry(entry_type, entry), &address.to_string().into())
+            .map(String::from)
+            .map_err(|err| err.to_string())
+    }
+
+    fn get(&self, address: &str) -> Result<Option<String>, String> {
+        use hdk::holochain_core_types::entry::Entry;
+
+        match ::hdk::get_entry(&address.to_string().into()) {
+            Ok(Some(Entry::App(_, value))) => Ok(Some(String::from(value))),
+            Ok(_) => Ok(None),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    fn link(&self, base: &str, target: &str, tag: &str) -> Result<(), String> {
+        ::hdk::link_entries(&base.to_string().into(), &target.to_string().into(), tag)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+
+    fn links(&self, base: &str, tag: &str) -> Result<Vec<String>, String> {
+        ::hdk::get_links(&base.to_string().into(), tag)
+            .map(|links| {
+                links
+                    .addresses()
+                    .iter()
+                    .cloned()
+                    .map(String::from)
+                    .collect()
+            })
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn app_entry(entry_type: &str, entry: String) -> ::hdk::holochain_core_types::entry::Entry {
+    use hdk::holochain_core_types::{entry::Entry, json::JsonString};
+
+    Entry::App(entry_type.to_string().into(), JsonString::from(entry))
+}
+
+/// Keeps entries and links in memory, like a conductor with a single agent
+#[cfg(test)]
+pub mod mock {
+    use super::Host;
+    use std::{cell::RefCell, collections::HashMap};
+
+    #[derive(Default)]
+    pub struct MockHost {
+        entries: RefCell<Vec<String>>,
+        /// The address of the next version of an updated entry
+        updates: RefCell<HashMap<String, String>>,
+        links: RefCell<Vec<(String, String, String)>>,
+    }
+
+    impl Host for MockHost {
+        fn agent(&self) -> String {
+            "agent".to_string()
+        }
+
+        /// Entries are content addressed, committing the same one twice gives the same address
+        fn commit(&self, _entry_type: &str, entry: String) -> Result<String, String> {
+            let mut entries = self.entries.borrow_mut();
+
+            let index = match entries.iter().position(|existing| *existing == entry) {
+                Some(index) => index,
+                None => {
+                    entries.push(entry);
+                    entries.len() - 1
+                }
+            };
+
+            Ok(format!("Qm{}", index + 1))
+        }
+
+        fn update(&self, entry_type: &str, entry: String, address: &str) -> Result<String, String> {
+            let updated = self.commit(entry_type, entry)?;
+            self.updates
+                .borrow_mut()
+                .insert(address.to_string(), updated.clone());
+
+            Ok(updated)
+        }
+
+        fn get(&self, address: &str) -> Result<Option<String>, String> {
+            if let Some(updated) = self.updates.borrow().get(address) {
+                return self.get(updated);
+            }
+
+            let index = address
+                .trim_start_matches("Qm")
+                .parse::<usize>()
+                .map_err(|_| format!("{} is no address", address))?;
+
+            Ok(index
+                .checked_sub(1)
+                .and_then(|index| self.entries.borrow().get(index).cloned()))
+        }
+
+        fn link(&self, base: &str, target: &str, tag: &str) -> Result<(), String> {
+            self.links
+                .borrow_mut()
+                .push((base.to_string(), target.to_string(), tag.to_string()));
+
+            Ok(())
+        }
+
+        fn links(&self, base: &str, tag: &str) -> Result<Vec<String>, String> {
+            Ok(self
+                .links
+                .borrow()
+                .iter()
+                .filter(|link| link.0 == base && link.2 == tag)
+                .map(|link| link.1.clone())
+                .collect())
+        }
+    }
+}
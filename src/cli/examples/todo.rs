@@ -0,0 +1,192 @@
+//! A todo list. Every todo is an entry the agent links to itself, completing one commits a new
+//! version of it.
+// the macros of the HDK are only used when building for the conductor
+#[cfg_attr(target_arch = "wasm32", macro_use)]
+extern crate hdk;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+pub mod host;
+
+use crate::host::Host;
+
+pub const TODO_ENTRY: &str = "todo";
+/// Tags the links from the agent to its todos
+pub const TODOS_TAG: &str = "todos";
+
+const MAX_TEXT_LENGTH: usize = 500;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Todo {
+    pub text: String,
+    pub done: bool,
+}
+
+/// What every todo has to be, checked by every agent before storing one
+pub fn validate_todo(todo: &Todo) -> Result<(), String> {
+    if todo.text.trim().is_empty() {
+        return Err("a todo needs a text".to_string());
+    }
+
+    if todo.text.len() > MAX_TEXT_LENGTH {
+        return Err(format!("todos are at most {} bytes long", MAX_TEXT_LENGTH));
+    }
+
+    Ok(())
+}
+
+/// Adds a todo to the agent's list and returns its address
+pub fn create_todo<H: Host>(host: &H, text: &str) -> Result<String, String> {
+    let todo = Todo {
+        text: text.to_string(),
+        done: false,
+    };
+    validate_todo(&todo)?;
+
+    let address = host.commit(TODO_ENTRY, to_json(&todo)?)?;
+    host.link(&host.agent(), &address, TODOS_TAG)?;
+
+    Ok(address)
+}
+
+/// Marks the todo at the address as done
+pub fn complete_todo<H: Host>(host: &H, address: &str) -> Result<String, String> {
+    let mut todo = get_todo(host, address)?.ok_or_else(|| format!("no todo at {}", address))?;
+    todo.done = true;
+
+    host.update(TODO_ENTRY, to_json(&todo)?, address)
+}
+
+/// The todos of the agent with their addresses, in the order they were created
+pub fn list_todos<H: Host>(host: &H) -> Result<Vec<(String, Todo)>, String> {
+    let mut todos = Vec::new();
+
+    for address in host.links(&host.agent(), TODOS_TAG)? {
+        if let Some(todo) = get_todo(host, &address)? {
+            todos.push((address, todo));
+        }
+    }
+
+    Ok(todos)
+}
+
+fn get_todo<H: Host>(host: &H, address: &str) -> Result<Option<Todo>, String> {
+    match host.get(address)? {
+        Some(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|err| err.to_string()),
+        None => Ok(None),
+    }
+}
+
+fn to_json(todo: &Todo) -> Result<String, String> {
+    serde_json::to_string(todo).map_err(|err| err.to_string())
+}
+
+/// The functions the conductor calls, handing their results back as JSON
+#[cfg(target_arch = "wasm32")]
+mod zome {
+    use crate::host::Hdk;
+    use hdk::{
+        self,
+        holochain_core_types::{dna::entry_types::Sharing, json::JsonString},
+    };
+
+    fn json<T: ::serde::Serialize>(result: Result<T, String>) -> JsonString {
+        JsonString::from(::serde_json::to_string(&result).unwrap_or_default())
+    }
+
+    fn handle_create_todo(text: String) -> JsonString {
+        json(super::create_todo(&Hdk, &text))
+    }
+
+    fn handle_complete_todo(address: String) -> JsonString {
+        json(super::complete_todo(&Hdk, &address))
+    }
+
+    fn handle_list_todos() -> JsonString {
+        json(super::list_todos(&Hdk))
+    }
+
+    define_zome! {
+        entries: [
+            entry!(
+                name: "todo",
+                description: "Something to do, done or not",
+                sharing: Sharing::Public,
+                native_type: JsonString,
+                validation_package: || hdk::ValidationPackageDefinition::Entry,
+                validation: |todo: JsonString, _ctx: hdk::ValidationData| {
+                    ::serde_json::from_str(&String::from(todo))
+                        .map_err(|err| err.to_string())
+                        .and_then(|todo| super::validate_todo(&todo))
+                }
+            )
+        ]
+
+        genesis: || { Ok(()) }
+
+        functions: {
+            main (Public) {
+                /// Adds a todo to the agent's list
+                create_todo: {
+                    inputs: |text: String|,
+                    outputs: |result: JsonString|,
+                    handler: handle_create_todo
+                }
+                /// Marks a todo as done
+                complete_todo: {
+                    inputs: |address: String|,
+                    outputs: |result: JsonString|,
+                    handler: handle_complete_todo
+                }
+                /// Lists the agent's todos
+                list_todos: {
+                    inputs: | |,
+                    outputs: |todos: JsonString|,
+                    handler: handle_list_todos
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::mock::MockHost;
+
+    #[test]
+    fn lists_created_todos() {
+        let host = MockHost::default();
+
+        create_todo(&host, "water the plants").unwrap();
+        create_todo(&host, "buy milk").unwrap();
+
+        let texts: Vec<_> = list_todos(&host)
+            .unwrap()
+            .into_iter()
+            .map(|(_, todo)| todo.text)
+            .collect();
+
+        assert_eq!(texts, vec!["water the plants", "buy milk"]);
+    }
+
+    #[test]
+    fn completes_todos() {
+        let host = MockHost::default();
+        let address = create_todo(&host, "water the plants").unwrap();
+
+        complete_todo(&host, &address).unwrap();
+
+        assert!(list_todos(&host).unwrap()[0].1.done);
+        assert!(complete_todo(&host, "Qm42").is_err());
+    }
+
+    #[test]
+    fn refuses_empty_todos() {
+        assert!(create_todo(&MockHost::default(), "  ").is_err());
+    }
+}
@@ -0,0 +1,601 @@
+//! A narrative of what a bundle contains and does once it is instantiated, written for reviewers
+//! who don't read Rust: what the Zomes store, who may call their functions, what they ask the
+//! conductor to do and what the DNA needs from its surroundings.
+
+use base64;
+use cli::package::{
+    self, Object, META_ATTRIBUTION_SECTION_NAME, META_BIN_ID, META_DIR_ID,
+    META_README_SECTION_NAME, META_SECTION_NAME, META_TREE_SECTION_NAME,
+};
+use error::DefaultResult;
+use output;
+use registry::ZomeInfo;
+use secrets::ENCRYPTED_PROPERTY_KEY;
+use serde_json::Value;
+use std::path::PathBuf;
+use wasm::Module;
+
+/// What the functions the HDK imports from the conductor let a Zome do
+const HOST_FUNCTIONS: &[(&str, &str)] = &[
+    (
+        "hc_commit_entry",
+        "write entries to the agent's source chain",
+    ),
+    ("hc_update_entry", "update entries it wrote before"),
+    ("hc_remove_entry", "mark entries it wrote before as deleted"),
+    ("hc_get_entry", "read entries from the shared DHT"),
+    ("hc_link_entries", "link entries to each other"),
+    ("hc_remove_link", "remove links between entries"),
+    ("hc_get_links", "follow links between entries"),
+    ("hc_query", "search the agent's own source chain"),
+    (
+        "hc_call",
+        "call functions of other Zomes, also of other DNAs through bridges",
+    ),
+    ("hc_send", "send messages directly to other agents"),
+    ("hc_sign", "sign data with the agent's key"),
+    (
+        "hc_sign_one_time",
+        "sign data with keys that are thrown away after",
+    ),
+    ("hc_verify_signature", "check signatures of other agents"),
+    ("hc_encrypt", "encrypt data"),
+    ("hc_decrypt", "decrypt data"),
+    ("hc_keystore_list", "list the keys of the agent"),
+    ("hc_keystore_new_random", "create new keys for the agent"),
+    ("hc_keystore_derive_seed", "derive new keys for the agent"),
+    ("hc_keystore_derive_key", "derive new keys for the agent"),
+    ("hc_keystore_sign", "sign data with the agent's other keys"),
+    (
+        "hc_keystore_get_public_key",
+        "read the public keys of the agent",
+    ),
+    ("hc_emit_signal", "send signals to the user interface"),
+    ("hc_property", "read the properties of the DNA"),
+    ("hc_debug", "write to the conductor's log"),
+    ("hc_sleep", "pause for a while"),
+];
+
+/// Who may call the functions of a capability with the given membrane
+fn membrane_callers(membrane: &str) -> String {
+    match membrane {
+        "public" => "anyone".to_string(),
+        "agent" => "only the agent running the app".to_string(),
+        "zome" => "only other Zomes of this DNA".to_string(),
+        "api_key" => "only callers holding an API key".to_string(),
+        other => format!("callers passing the {} membrane", other),
+    }
+}
+
+/// Prints what the bundle contains and will do in prose
+pub fn explain_bundle(path: &PathBuf) -> DefaultResult<()> {
+    let bundle = package::read_plain_bundle(path)?;
+
+    for line in narrate(&bundle)? {
+        output::print(line);
+    }
+
+    Ok(())
+}
+
+fn narrate(bundle: &Value) -> DefaultResult<Vec<String>> {
+    let app = bundle
+        .as_object()
+        .ok_or_else(|| format_err!("the bundle isn't a JSON object"))?;
+    let mut lines = Vec::new();
+
+    let mut title = app["name"].as_str().unwrap_or("This app").to_string();
+
+    if let Some(version) = app.get("version").and_then(Value::as_str) {
+        title = format!("{} {}", title, version);
+    }
+
+    lines.push(title);
+
+    if let Some(description) = text(app, "description") {
+        lines.push(description);
+    }
+
+    if let Some(authors) = app.get("authors").and_then(Value::as_array) {
+        let authors: Vec<_> = authors.iter().map(person).collect();
+
+        if !authors.is_empty() {
+            lines.push(format!("Written by {}.", authors.join(", ")));
+        }
+    }
+
+    let mut zomes = Vec::new();
+    find_zomes(app, &mut zomes);
+
+    lines.push(String::new());
+    lines.push(match zomes.len() {
+        0 => "The app has no Zomes, so it neither stores nor does anything.".to_string(),
+        count => format!(
+            "The app consists of {}: {}.",
+            counted(count, "Zome", "Zomes"),
+            zomes
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    });
+
+    narrate_properties(app.get("properties"), &mut lines);
+
+    for (name, zome) in &zomes {
+        lines.push(String::new());
+        narrate_zome(name, zome, &mut lines);
+    }
+
+    Ok(lines)
+}
+
+fn narrate_properties(properties: Option<&Value>, lines: &mut Vec<String>) {
+    let mut flattened = Vec::new();
+
+    if let Some(properties) = properties.filter(|properties| !properties.is_null()) {
+        flatten_properties(properties, "", &mut flattened);
+    }
+
+    lines.push(String::new());
+
+    if flattened.is_empty() {
+        lines.push("The DNA is instantiated without properties.".to_string());
+        return;
+    }
+
+    lines.push("The DNA is instantiated with these properties:".to_string());
+    lines.extend(
+        flattened
+            .into_iter()
+            .map(|(path, value)| format!("  - {}: {}", path, value)),
+    );
+}
+
+fn flatten_properties(value: &Value, path: &str, flattened: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(obj) if obj.len() == 1 && obj.contains_key(ENCRYPTED_PROPERTY_KEY) => {
+            flattened.push((
+                path.to_string(),
+                "encrypted, only the recipient of the bundle can read it".to_string(),
+            ));
+        }
+        Value::Object(obj) if !obj.is_empty() => {
+            for (key, value) in obj {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+
+                flatten_properties(value, &path, flattened);
+            }
+        }
+        _ => flattened.push((path.to_string(), value.to_string())),
+    }
+}
+
+fn narrate_zome(name: &str, zome: &Object, lines: &mut Vec<String>) {
+    lines.push(format!("Zome {}", name));
+
+    if let Some(description) = text(zome, "description") {
+        lines.push(format!("  {}", description));
+    }
+
+    let info = zome_info(zome);
+
+    if let Some(ref author) = info.author {
+        lines.push(format!("  Written by {}.", author));
+    }
+
+    match (&info.version, &info.license) {
+        (Some(version), Some(license)) => lines.push(format!(
+            "  Version {}, licensed under {}.",
+            version, license
+        )),
+        (Some(version), None) => lines.push(format!("  Version {}.", version)),
+        (None, Some(license)) => lines.push(format!("  Licensed under {}.", license)),
+        (None, None) => (),
+    }
+
+    if let Some(readme) = zome
+        .get(META_SECTION_NAME)
+        .and_then(|meta| meta[META_README_SECTION_NAME].as_str())
+    {
+        lines.push(format!("  Its {} comes with the bundle.", readme));
+    }
+
+    let entry_types = zome
+        .get("entry_types")
+        .and_then(Value::as_object)
+        .filter(|entry_types| !entry_types.is_empty());
+
+    match entry_types {
+        Some(entry_types) => {
+            lines.push(format!(
+                "  It stores {}:",
+                counted(entry_types.len(), "entry type", "entry types")
+            ));
+
+            for (entry_type, declaration) in entry_types {
+                lines.push(format!(
+                    "    - {}",
+                    describe_entry_type(entry_type, declaration)
+                ));
+            }
+        }
+        None => lines.push("  It stores no entries of its own.".to_string()),
+    }
+
+    let module = zome
+        .get("code")
+        .and_then(|code| code["code"].as_str())
+        .ok_or_else(|| format_err!("the bundle has no code for it"))
+        .and_then(|code| Ok(base64::decode(code)?))
+        .and_then(|wasm| Module::parse(&wasm));
+
+    let validates = |module: &Module| {
+        module
+            .exports
+            .iter()
+            .any(|export| export.contains("validate"))
+    };
+
+    match module {
+        Ok(ref module) if validates(module) => {
+            lines.push("  Its code validates entries before they are stored.".to_string())
+        }
+        Ok(_) if entry_types.is_some() => lines.push(
+            "  Its code has no validation, any entry of its types is accepted as it is."
+                .to_string(),
+        ),
+        Ok(_) => (),
+        Err(ref err) => lines.push(format!("  Its code can't be read: {}.", err)),
+    }
+
+    narrate_functions(zome.get("capabilities"), lines);
+
+    if let Ok(module) = module {
+        let abilities = host_abilities(&module);
+
+        if abilities.is_empty() {
+            lines.push("  It asks nothing of the conductor.".to_string());
+        } else {
+            lines.push("  When it runs, it asks the conductor to:".to_string());
+            lines.extend(abilities.iter().map(|ability| format!("    - {}", ability)));
+        }
+    }
+
+    let bridges = zome
+        .get("bridges")
+        .and_then(Value::as_array)
+        .filter(|bridges| !bridges.is_empty());
+
+    if let Some(bridges) = bridges {
+        lines.push(format!(
+            "  It reaches other DNAs through {}:",
+            counted(bridges.len(), "bridge", "bridges")
+        ));
+        lines.extend(
+            bridges
+                .iter()
+                .map(|bridge| format!("    - {}", describe_bridge(bridge))),
+        );
+    }
+}
+
+/// What a Zome of the bundle says about itself, packaging put the attribution of its
+/// `zome.json` in its meta section
+fn zome_info(zome: &Object) -> ZomeInfo {
+    let meta = zome.get(META_SECTION_NAME);
+    let attribution = |key: &str| {
+        meta.and_then(|meta| meta[META_ATTRIBUTION_SECTION_NAME][key].as_str())
+            .map(str::to_string)
+    };
+
+    ZomeInfo {
+        description: text(zome, "description").unwrap_or_default(),
+        author: attribution("author"),
+        version: attribution("version"),
+        license: attribution("license"),
+    }
+}
+
+fn narrate_functions(capabilities: Option<&Value>, lines: &mut Vec<String>) {
+    let functions: Vec<_> = capabilities
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|capabilities| capabilities.values())
+        .flat_map(|capability| {
+            let callers = membrane_callers(
+                capability["capability"]["membrane"]
+                    .as_str()
+                    .unwrap_or("public"),
+            );
+
+            capability["functions"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(move |function| format!("{}, which {} may call", signature(function), callers))
+        })
+        .collect();
+
+    if functions.is_empty() {
+        lines.push("  It offers no functions to call.".to_string());
+    } else {
+        lines.push(format!(
+            "  It offers {}:",
+            counted(functions.len(), "function", "functions")
+        ));
+        lines.extend(
+            functions
+                .iter()
+                .map(|function| format!("    - {}", function)),
+        );
+    }
+}
+
+fn describe_entry_type(name: &str, declaration: &Value) -> String {
+    let mut description = format!(
+        "{}, {}",
+        name,
+        match declaration["sharing"].as_str() {
+            Some("private") => "kept private on the agent's source chain",
+            Some("encrypted") => "shared encrypted on the DHT",
+            _ => "shared with everyone on the DHT",
+        }
+    );
+
+    if let Some(text) = declaration["description"]
+        .as_str()
+        .filter(|text| !text.is_empty())
+    {
+        description = format!("{} ({})", description, text);
+    }
+
+    let links: Vec<_> = declaration["links_to"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|link| {
+            link["target_type"]
+                .as_str()
+                .map(|target| match link["tag"].as_str() {
+                    Some(tag) if !tag.is_empty() => format!("{} as \"{}\"", target, tag),
+                    _ => target.to_string(),
+                })
+        })
+        .collect();
+
+    if !links.is_empty() {
+        description = format!("{}; it links to {}", description, links.join(", "));
+    }
+
+    description
+}
+
+fn describe_bridge(bridge: &Value) -> String {
+    let handle = bridge["handle"].as_str().unwrap_or("an unnamed bridge");
+    let necessity = match bridge["presence"].as_str() {
+        Some("required") => "required, the app doesn't start without it",
+        _ => "optional",
+    };
+    let functions: Vec<_> = bridge["reference"]["traits"]
+        .as_object()
+        .into_iter()
+        .flat_map(|traits| traits.values())
+        .flat_map(|declared| declared["functions"].as_array().into_iter().flatten())
+        .filter_map(|function| function.as_str().or_else(|| function["name"].as_str()))
+        .collect();
+
+    match (
+        bridge["reference"]["dna_address"].as_str(),
+        functions.is_empty(),
+    ) {
+        (Some(address), _) => format!("{} ({}), to the DNA {}", handle, necessity, address),
+        (None, false) => format!(
+            "{} ({}), to any DNA offering {}",
+            handle,
+            necessity,
+            functions.join(", ")
+        ),
+        (None, true) => format!("{} ({})", handle, necessity),
+    }
+}
+
+fn signature(function: &Value) -> String {
+    let params = |key: &str| -> Vec<String> {
+        function[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|param| {
+                format!(
+                    "{}: {}",
+                    param["name"].as_str().unwrap_or("_"),
+                    param["parameter_type"].as_str().unwrap_or("?")
+                )
+            })
+            .collect()
+    };
+
+    let outputs = params("outputs");
+    let name = function["name"].as_str().unwrap_or("an unnamed function");
+
+    if outputs.is_empty() {
+        format!("{}({})", name, params("inputs").join(", "))
+    } else {
+        format!(
+            "{}({}) returning {}",
+            name,
+            params("inputs").join(", "),
+            outputs.join(", ")
+        )
+    }
+}
+
+/// What the Zome's imports let it do, in the order of `HOST_FUNCTIONS` and without repetition
+fn host_abilities(module: &Module) -> Vec<String> {
+    let mut abilities: Vec<String> = Vec::new();
+    let mut add = |ability: String| {
+        if !abilities.contains(&ability) {
+            abilities.push(ability);
+        }
+    };
+
+    for (function, ability) in HOST_FUNCTIONS {
+        if module.imports.iter().any(|import| import.name == *function) {
+            add(ability.to_string());
+        }
+    }
+
+    for import in module.imports.iter().map(|import| &import.name) {
+        if import.starts_with("hc_")
+            && !HOST_FUNCTIONS
+                .iter()
+                .any(|(function, _)| function == import)
+        {
+            add(format!("run the host function {}", import));
+        }
+    }
+
+    abilities
+}
+
+/// The Zomes of the bundle with their manifests: the directories with code in them
+/// Collects the Zomes of the bundle directory and the directories below it, with their names
+pub fn find_zomes(dir: &Object, zomes: &mut Vec<(String, Object)>) {
+    let tree = dir
+        .get(META_SECTION_NAME)
+        .and_then(|meta| meta.get(META_TREE_SECTION_NAME))
+        .and_then(Value::as_object);
+
+    for (key, value) in dir.iter().filter(|(key, _)| *key != META_SECTION_NAME) {
+        let node_type = tree.and_then(|tree| tree.get(key)).and_then(Value::as_str);
+
+        if let Value::Object(child) = value {
+            let is_code = |(_, node): (&String, &Value)| node.as_str() == Some(META_BIN_ID);
+            let has_code = match child.get(META_SECTION_NAME) {
+                Some(meta) => meta[META_TREE_SECTION_NAME]
+                    .as_object()
+                    .map_or(false, |tree| tree.iter().any(is_code)),
+                // bundles with stripped meta sections have to be taken apart by their shape
+                None => child
+                    .get("code")
+                    .map_or(false, |code| code["code"].is_string()),
+            };
+
+            if has_code {
+                zomes.push((key.clone(), child.clone()));
+            } else if node_type.map_or(tree.is_none(), |node_type| node_type == META_DIR_ID) {
+                find_zomes(child, zomes);
+            }
+        }
+    }
+}
+
+fn text(obj: &Object, key: &str) -> Option<String> {
+    obj.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// Authors as the manifest of the app declares them, or by name and email
+fn person(author: &Value) -> String {
+    let named = match (author["name"].as_str(), author["email"].as_str()) {
+        (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+        (Some(name), None) => Some(name.to_string()),
+        _ => None,
+    };
+
+    named
+        .or_else(|| author["indentifier"].as_str().map(str::to_string))
+        .or_else(|| author.as_str().map(str::to_string))
+        .unwrap_or_else(|| author.to_string())
+}
+
+fn counted(count: usize, one: &str, many: &str) -> String {
+    format!("{} {}", count, if count == 1 { one } else { many })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm::test_support;
+
+    #[test]
+    fn tells_what_the_zomes_store_offer_and_need() {
+        let wasm = test_support::linked_module(
+            &["hc_commit_entry", "hc_call", "hc_frobnicate"],
+            &["__hdk_validate_app_entry"],
+        );
+        let bundle = json!({
+            "name": "chat",
+            "version": "0.1.0",
+            "authors": [{ "name": "Alice", "email": "alice@example.com" }],
+            "properties": {
+                "api": { "url": "https://example.com", "key": { "encrypted_property": {} } }
+            },
+            "zomes": {
+                "posts": {
+                    "code": { "code": base64::encode(&wasm) },
+                    "entry_types": {
+                        "post": {
+                            "sharing": "public",
+                            "links_to": [{ "target_type": "comment", "tag": "comments" }]
+                        }
+                    },
+                    "capabilities": {
+                        "main": {
+                            "capability": { "membrane": "public" },
+                            "functions": [{
+                                "name": "create_post",
+                                "inputs": [{ "name": "content", "parameter_type": "String" }],
+                                "outputs": [{ "name": "result", "parameter_type": "Address" }]
+                            }]
+                        }
+                    },
+                    "bridges": [{
+                        "presence": "required",
+                        "handle": "social",
+                        "reference": { "traits": { "hc_public": { "functions": ["follow"] } } }
+                    }],
+                    "__META__": {
+                        "tree": { "code": "bin", "README.md": "file" },
+                        "attribution": { "author": "Bob", "version": "0.2.0", "license": "MIT" },
+                        "readme": "README.md"
+                    }
+                },
+                "__META__": { "tree": { "posts": "dir" } }
+            },
+            "__META__": { "tree": { "zomes": "dir" } }
+        });
+
+        let report = narrate(&bundle).unwrap().join("\n");
+
+        assert!(report.starts_with("chat 0.1.0\nWritten by Alice <alice@example.com>."));
+        assert!(report.contains("The app consists of 1 Zome: posts."));
+        assert!(report.contains(
+            "Zome posts\n  Written by Bob.\n  Version 0.2.0, licensed under MIT.\n  Its README.md \
+             comes with the bundle."
+        ));
+        assert!(report.contains("  - api.url: \"https://example.com\""));
+        assert!(report.contains("  - api.key: encrypted"));
+        assert!(report.contains(
+            "post, shared with everyone on the DHT; it links to comment as \"comments\""
+        ));
+        assert!(report.contains("Its code validates entries before they are stored."));
+        assert!(report.contains(
+            "create_post(content: String) returning result: Address, which anyone may call"
+        ));
+        assert!(report.contains("    - write entries to the agent's source chain\n"));
+        assert!(report.contains("    - run the host function hc_frobnicate"));
+        assert!(report.contains(
+            "social (required, the app doesn't start without it), to any DNA offering follow"
+        ));
+    }
+}
@@ -0,0 +1,314 @@
+//! Translates between hcdev bundles and the bundle layouts of newer Holochain versions.
+//!
+//! `dna-v2` is the gzipped MessagePack `.dna` file of Holochain 0.1 and later: a manifest
+//! naming the DNA and its zomes, plus the zomes' WASM as resources. Only what both layouts know
+//! about survives the translation, which is the name, the properties, the network seed (`uuid`
+//! in `app.json`) and the code of every Zome.
+
+use base64;
+use bundle::{self, BundleType};
+use cli::package::{
+    read_plain_bundle, Object, DEFAULT_BUNDLE_FILE_NAME, META_BIN_ID, META_CONFIG_SECTION_NAME,
+    META_DIR_ID, META_SECTION_NAME, META_TREE_SECTION_NAME,
+};
+use cli::{docs::ZOMES_DIR_NAME, new::APP_CONFIG_FILE_NAME};
+use error::DefaultResult;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use i18n;
+use msgpack;
+use output;
+use reproducible;
+use serde_json::Value;
+use std::{
+    fs,
+    io::{Read, Write},
+    path::PathBuf,
+    str::FromStr,
+    time::UNIX_EPOCH,
+};
+
+pub const DEFAULT_DNA_FILE_NAME: &str = "bundle.dna";
+
+const DNA_MANIFEST_VERSION: &str = "1";
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+const CODE_KEY: &str = "code";
+const NAME_KEY: &str = "name";
+const UUID_KEY: &str = "uuid";
+const PROPERTIES_KEY: &str = "properties";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    DnaV2,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "dna-v2" => Ok(Format::DnaV2),
+            _ => Err(format!("unknown bundle format: {} (expected dna-v2)", s)),
+        }
+    }
+}
+
+/// Writes the bundle at `path` in another bundle layout
+pub fn export(path: &PathBuf, format: Format, output: Option<PathBuf>) -> DefaultResult<()> {
+    let bundle = read_plain_bundle(path)?;
+
+    let bytes = match format {
+        Format::DnaV2 => to_dna_v2(&bundle, origin_time())?,
+    };
+
+    let output = output.unwrap_or_else(|| PathBuf::from(DEFAULT_DNA_FILE_NAME));
+
+    fs::write(&output, bytes)?;
+
+    output::print(i18n::tr(
+        "created-bundle",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", output)),
+        ],
+    ));
+
+    Ok(())
+}
+
+/// Turns a bundle in another layout into a bundle hcdev can unpack
+pub fn import(path: &PathBuf, format: Format, output: Option<PathBuf>) -> DefaultResult<()> {
+    let bytes = fs::read(path)?;
+
+    let bundle = match format {
+        Format::DnaV2 => from_dna_v2(&bytes).map_err(|err| format_err!("{:?}: {}", path, err))?,
+    };
+
+    let output = output.unwrap_or_else(|| PathBuf::from(DEFAULT_BUNDLE_FILE_NAME));
+
+    bundle::write(&output, BundleType::App, &bundle)?;
+
+    output::print(i18n::tr(
+        "created-bundle",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", output)),
+        ],
+    ));
+
+    Ok(())
+}
+
+/// Microseconds since the epoch, the earliest time the DNA's network accepts data from
+fn origin_time() -> i64 {
+    reproducible::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_micros() as i64)
+        .unwrap_or_default()
+}
+
+fn to_dna_v2(bundle: &Value, origin_time: i64) -> DefaultResult<Vec<u8>> {
+    let name = bundle[NAME_KEY]
+        .as_str()
+        .ok_or_else(|| format_err!("the bundle has no name, set it in {}", APP_CONFIG_FILE_NAME))?;
+
+    let mut zomes = Vec::new();
+    let mut resources = Vec::new();
+
+    if let Some(zome_objects) = bundle[ZOMES_DIR_NAME].as_object() {
+        for (zome_name, zome) in zome_objects {
+            if zome_name == META_SECTION_NAME {
+                continue;
+            }
+
+            let code = zome[CODE_KEY][CODE_KEY]
+                .as_str()
+                .ok_or_else(|| format_err!("zome {} has no code", zome_name))?;
+            let resource_path = format!("{}.wasm", zome_name);
+
+            zomes.push(msgpack::Value::Map(vec![
+                (NAME_KEY.into(), zome_name.as_str().into()),
+                ("hash".into(), msgpack::Value::Nil),
+                ("bundled".into(), resource_path.as_str().into()),
+                ("dependencies".into(), msgpack::Value::Nil),
+            ]));
+            resources.push((
+                resource_path.as_str().into(),
+                msgpack::Value::Binary(base64::decode(code)?),
+            ));
+        }
+    }
+
+    let manifest = msgpack::Value::Map(vec![
+        ("manifest_version".into(), DNA_MANIFEST_VERSION.into()),
+        (NAME_KEY.into(), name.into()),
+        (
+            "integrity".into(),
+            msgpack::Value::Map(vec![
+                ("network_seed".into(), (&bundle[UUID_KEY]).into()),
+                (PROPERTIES_KEY.into(), (&bundle[PROPERTIES_KEY]).into()),
+                ("origin_time".into(), msgpack::Value::Int(origin_time)),
+                (ZOMES_DIR_NAME.into(), msgpack::Value::Array(zomes)),
+            ]),
+        ),
+        (
+            "coordinator".into(),
+            msgpack::Value::Map(vec![(
+                ZOMES_DIR_NAME.into(),
+                msgpack::Value::Array(Vec::new()),
+            )]),
+        ),
+    ]);
+
+    let dna = msgpack::Value::Map(vec![
+        ("manifest".into(), manifest),
+        ("resources".into(), msgpack::Value::Map(resources)),
+    ]);
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&dna.encode())?;
+
+    Ok(encoder.finish()?)
+}
+
+fn from_dna_v2(bytes: &[u8]) -> DefaultResult<Value> {
+    ensure!(bytes.starts_with(GZIP_MAGIC), "not a gzipped DNA bundle");
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+
+    let dna = msgpack::Value::decode(&decompressed)?;
+    let manifest = dna
+        .get("manifest")
+        .ok_or_else(|| format_err!("the DNA bundle has no manifest"))?;
+
+    match manifest.get("manifest_version").and_then(|v| v.as_str()) {
+        Some(DNA_MANIFEST_VERSION) => (),
+        Some(version) => bail!("DNA manifest version {} is not supported", version),
+        None => bail!("the DNA manifest has no version"),
+    }
+
+    let name = manifest
+        .get(NAME_KEY)
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| format_err!("the DNA manifest has no name"))?;
+    let integrity = manifest
+        .get("integrity")
+        .ok_or_else(|| format_err!("the DNA manifest has no integrity section"))?;
+
+    let mut zomes = Object::new();
+    let mut zomes_tree = Object::new();
+
+    // coordinator zomes are imported like any other, hcdev doesn't tell them apart
+    let sections = [Some(integrity), manifest.get("coordinator")];
+    let all_zomes = sections
+        .iter()
+        .filter_map(|section| section.and_then(|section| section.get(ZOMES_DIR_NAME)))
+        .filter_map(|zomes| zomes.as_array())
+        .flat_map(|zomes| zomes.iter());
+
+    for zome in all_zomes {
+        let zome_name = zome
+            .get(NAME_KEY)
+            .and_then(|name| name.as_str())
+            .ok_or_else(|| format_err!("a zome in the DNA manifest has no name"))?;
+
+        let resource_path = match zome.get("bundled").and_then(|path| path.as_str()) {
+            Some(path) => path,
+            None => bail!(
+                "zome {} isn't bundled with the DNA, only bundled zomes can be imported",
+                zome_name
+            ),
+        };
+
+        let code = match resource(&dna, resource_path) {
+            Some(msgpack::Value::Binary(code)) => base64::encode(code),
+            _ => bail!("the DNA bundle is missing {}", resource_path),
+        };
+
+        zomes.insert(
+            zome_name.to_string(),
+            json!({
+                CODE_KEY: { CODE_KEY: code },
+                META_SECTION_NAME: { META_TREE_SECTION_NAME: { CODE_KEY: META_BIN_ID } }
+            }),
+        );
+        zomes_tree.insert(zome_name.to_string(), META_DIR_ID.into());
+    }
+
+    zomes.insert(
+        META_SECTION_NAME.into(),
+        json!({ META_TREE_SECTION_NAME: zomes_tree }),
+    );
+
+    let mut bundle = Object::new();
+    bundle.insert(NAME_KEY.into(), name.into());
+
+    for (key, manifest_key) in &[(UUID_KEY, "network_seed"), (PROPERTIES_KEY, PROPERTIES_KEY)] {
+        if let Some(value) = integrity.get(manifest_key).filter(|value| !value.is_nil()) {
+            bundle.insert(key.to_string(), value.to_json()?);
+        }
+    }
+
+    bundle.insert(ZOMES_DIR_NAME.into(), zomes.into());
+    bundle.insert(
+        META_SECTION_NAME.into(),
+        json!({
+            META_TREE_SECTION_NAME: { ZOMES_DIR_NAME: META_DIR_ID },
+            META_CONFIG_SECTION_NAME: APP_CONFIG_FILE_NAME
+        }),
+    );
+
+    Ok(bundle.into())
+}
+
+/// Looks up a resource, ignoring a leading `./` on either side
+fn resource<'a>(dna: &'a msgpack::Value, path: &str) -> Option<&'a msgpack::Value> {
+    let path = path.trim_start_matches("./");
+
+    match dna.get("resources") {
+        Some(msgpack::Value::Map(resources)) => resources
+            .iter()
+            .find(|(key, _)| key.as_str().map(|key| key.trim_start_matches("./")) == Some(path))
+            .map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_to_dna_v2_and_back() {
+        let bundle = json!({
+            "name": "chat",
+            "description": "Lost in translation",
+            "uuid": "00000000-0000-0000-0000-000000000000",
+            "properties": { "rooms": 3 },
+            "zomes": {
+                "bubblechat": {
+                    "description": "Lost as well",
+                    "code": { "code": base64::encode(b"\0asm") }
+                },
+                "__META__": {}
+            }
+        });
+
+        let dna = to_dna_v2(&bundle, 1_600_000_000_000_000).unwrap();
+
+        assert!(dna.starts_with(GZIP_MAGIC));
+
+        let imported = from_dna_v2(&dna).unwrap();
+
+        assert_eq!(imported["name"], "chat");
+        assert_eq!(imported["uuid"], bundle["uuid"]);
+        assert_eq!(imported["properties"], bundle["properties"]);
+        assert_eq!(
+            imported["zomes"]["bubblechat"]["code"],
+            bundle["zomes"]["bubblechat"]["code"]
+        );
+        assert!(imported.get("description").is_none());
+        assert!(from_dna_v2(b"{}").is_err());
+    }
+}
@@ -1,18 +1,81 @@
 use cli::{
-    package::CODE_DIR_NAME,
+    assets::UI_DIR_NAME,
+    layout::Layout,
+    license::project_license,
+    new::{APP_CONFIG_FILE_NAME, TESTS_DIR_NAME},
+    package::CARGO_FILE_NAME,
+    prompt,
     scaffold::{self, Scaffold},
 };
+use config_files::Project;
 use error::DefaultResult;
-use serde_json;
+use hcignore::IGNORE_FILE_NAME;
+use i18n;
+use output;
+use scripts;
+use serde_json::{self, Map, Value};
 use std::{
-    fs::{self, File},
-    path::PathBuf,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
 };
 use util;
 
 pub const ZOME_CONFIG_FILE_NAME: &str = "zome.json";
 
-pub fn generate(zome_name: &PathBuf, language: &str) -> DefaultResult<()> {
+/// What `generate` asks for when it is run without arguments
+const KINDS: &[&str] = &["zome", "entry", "test", "ui"];
+
+const LANGUAGES: &[&str] = &["rust", "wat"];
+
+const DEFAULT_LANGUAGE: &str = "rust";
+
+const SHARINGS: &[&str] = &["public", "private"];
+
+const TEST_TEMPLATE: &str = "\
+// Run by `hcdev test` once the test script runs the files in tests/, e.g. with
+// hcdev config set scripts.test \"node --test tests/\"
+const test = require('node:test')
+const assert = require('node:assert')
+
+test('{name}', () => {
+  assert.ok(true)
+})
+";
+
+const UI_PAGE_TEMPLATE: &str = "\
+<!DOCTYPE html>
+<html>
+<head>
+  <meta charset=\"utf-8\">
+  <title>{title}</title>
+</head>
+<body>
+  <h1>{title}</h1>
+</body>
+</html>
+";
+
+pub fn generate(
+    zome_name: &PathBuf,
+    language: &str,
+    tiny: bool,
+    crates: &[String],
+) -> DefaultResult<()> {
+    // refuse unusable crates before anything is written
+    let dependencies = crates
+        .iter()
+        .map(|spec| scaffold::rust::dependency(spec))
+        .collect::<DefaultResult<Vec<_>>>()?;
+    let project_root = PathBuf::from(".");
+    let hook_env = [
+        ("HC_ZOME_PATH", zome_name.to_string_lossy().into_owned()),
+        ("HC_ZOME_LANGUAGE", language.to_owned()),
+    ];
+
+    scripts::run_hook(&project_root, scripts::PRE_GENERATE, &hook_env)?;
+
     if !zome_name.exists() {
         fs::create_dir_all(&zome_name)?;
     }
@@ -24,7 +87,7 @@ pub fn generate(zome_name: &PathBuf, language: &str) -> DefaultResult<()> {
 
     let file_name = util::file_name_string(&zome_name)?;
 
-    let zome_config_json = json!{
+    let zome_config_json = json! {
         {
             "description": format!("The {} App", file_name)
         }
@@ -33,26 +96,384 @@ pub fn generate(zome_name: &PathBuf, language: &str) -> DefaultResult<()> {
     let file = File::create(zome_name.join(ZOME_CONFIG_FILE_NAME))?;
     serde_json::to_writer_pretty(file, &zome_config_json)?;
 
-    let code_dir = zome_name.join(CODE_DIR_NAME);
+    let code_dir = Layout::of_app(".")?.code_dir(zome_name);
     fs::create_dir_all(&code_dir)?;
 
+    // name the code after the app it belongs to, so the Zomes of different apps can be told apart
+    let package_name = match app_name()? {
+        Some(app_name) => format!("{}-{}", app_name, file_name),
+        None => file_name.clone(),
+    };
+
     // match against all supported languages
     match language {
-        "rust" => scaffold(&scaffold::rust::RustScaffold::new(), code_dir)?,
-        _ => bail!("unsupported language: {}", language),
+        "rust" => scaffold(
+            &scaffold::rust::RustScaffold::new()
+                .tiny(tiny)
+                .license(project_license()?)
+                .package_name(Some(scaffold::rust::crate_name(&package_name)))
+                .dependencies(dependencies),
+            code_dir,
+        )?,
+        "wat" => {
+            ensure!(!tiny, "--tiny only applies to Rust zomes");
+            ensure!(crates.is_empty(), "--crate only applies to Rust zomes");
+
+            scaffold(
+                &scaffold::wat::WatScaffold::new().license(project_license()?),
+                code_dir,
+            )?
+        }
+        _ => bail!(i18n::tr(
+            "unsupported-language",
+            &[("language", language.to_owned())]
+        )),
+    }
+
+    scripts::run_hook(&project_root, scripts::POST_GENERATE, &hook_env)?;
+
+    Ok(())
+}
+
+/// The language of a Zome at the given path: the given one, else the default language of the
+/// project, else the one all the other Zomes next to it are written in, else Rust
+pub fn zome_language(zome: &Path, language: Option<String>) -> DefaultResult<String> {
+    if let Some(language) = language {
+        return Ok(language);
+    }
+
+    Ok(match detect_language(zome)? {
+        Some((language, message)) => {
+            output::print(message);
+
+            language
+        }
+        None => DEFAULT_LANGUAGE.to_string(),
+    })
+}
+
+/// The language a new Zome at the path would be written in and why, if the project or the
+/// Zomes next to it tell
+fn detect_language(zome: &Path) -> DefaultResult<Option<(String, String)>> {
+    if let Some(language) = Project::from_dir(".")?.zomes.language {
+        let message = i18n::tr("language-from-config", &[("language", language.clone())]);
+
+        return Ok(Some((language, message)));
+    }
+
+    let siblings_dir = match zome.parent() {
+        Some(parent) if parent.is_dir() => parent,
+        _ => return Ok(None),
+    };
+
+    let layout = Layout::of_app(".")?;
+    let mut languages = Vec::new();
+
+    for entry in fs::read_dir(siblings_dir)? {
+        let path = entry?.path();
+
+        if path.file_name() != zome.file_name() && path.join(ZOME_CONFIG_FILE_NAME).is_file() {
+            languages.push(written_in(&layout.code_dir(&path)));
+        }
+    }
+
+    let language = match languages.first() {
+        Some(Some(language))
+            if languages
+                .iter()
+                .all(|other| other.as_ref() == Some(language)) =>
+        {
+            language.to_string()
+        }
+        _ => return Ok(None),
+    };
+
+    let message = i18n::tr(
+        "language-from-siblings",
+        &[
+            ("language", language.clone()),
+            ("zome", util::file_name_string(&zome.to_path_buf())?),
+        ],
+    );
+
+    Ok(Some((language, message)))
+}
+
+/// The language of the Zome with the given code directory, if it was generated by this tool
+fn written_in(code_dir: &Path) -> Option<&'static str> {
+    if code_dir.join(CARGO_FILE_NAME).is_file() {
+        Some("rust")
+    } else if code_dir.join(scaffold::wat::SOURCE_FILE_NAME).is_file() {
+        Some("wat")
+    } else {
+        None
+    }
+}
+
+/// Asks what to generate and how, for `generate` without arguments in a terminal
+pub fn generate_interactive(
+    non_interactive: bool,
+    tiny: bool,
+    crates: &[String],
+) -> DefaultResult<()> {
+    ensure!(
+        prompt::is_interactive(non_interactive),
+        "missing the path of the zome to generate"
+    );
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+
+    match prompt::choose(&mut input, "What to generate", KINDS, "zome")?.as_str() {
+        "zome" => {
+            let name = prompt::ask(&mut input, "Name of the zome", "", prompt::check_name)?;
+            let path = Layout::of_app(".")?.zome_dir(&name);
+            let detected = detect_language(&path)?.map(|(language, _)| language);
+            let language = prompt::choose(
+                &mut input,
+                "Language",
+                LANGUAGES,
+                detected.as_ref().map_or(DEFAULT_LANGUAGE, String::as_str),
+            )?;
+
+            generate(&path, &language, tiny, crates)
+        }
+        "entry" => {
+            let zomes = zome_names()?;
+
+            ensure!(
+                !zomes.is_empty(),
+                "there is no zome to add an entry type to, generate one first"
+            );
+
+            let zomes: Vec<&str> = zomes.iter().map(String::as_str).collect();
+            let zome = prompt::choose(&mut input, "Zome", &zomes, zomes[0])?;
+            let name = prompt::ask(&mut input, "Name of the entry type", "", prompt::check_name)?;
+            let sharing = prompt::choose(&mut input, "Sharing", SHARINGS, "public")?;
+
+            generate_entry_type(&Layout::of_app(".")?.zome_dir(&zome), &name, &sharing)
+        }
+        "test" => generate_test(&prompt::ask(
+            &mut input,
+            "Name of the test",
+            "",
+            prompt::check_name,
+        )?),
+        _ => generate_ui_page(&prompt::ask(
+            &mut input,
+            "Name of the page",
+            "index",
+            prompt::check_name,
+        )?),
+    }
+}
+
+/// Declares a new entry type in the manifest of the Zome
+pub fn generate_entry_type(zome: &Path, name: &str, sharing: &str) -> DefaultResult<()> {
+    ensure!(
+        SHARINGS.contains(&sharing),
+        "unknown sharing {} (expected {})",
+        sharing,
+        SHARINGS.join(" or ")
+    );
+
+    let config_path = zome.join(ZOME_CONFIG_FILE_NAME);
+    let mut config: Value = serde_json::from_str(
+        &fs::read_to_string(&config_path)
+            .map_err(|err| format_err!("{:?} is no zome: {}", zome, err))?,
+    )
+    .map_err(|err| format_err!("{:?}: {}", config_path, err))?;
+
+    if !config["entry_types"].is_object() {
+        config["entry_types"] = Map::new().into();
+    }
+
+    ensure!(
+        config["entry_types"].get(name).is_none(),
+        "zome {:?} already has an entry type {}",
+        zome,
+        name
+    );
+
+    config["entry_types"][name] = json!({
+        "sharing": sharing,
+        "links_to": [],
+        "linked_from": []
+    });
+
+    fs::write(&config_path, serde_json::to_string_pretty(&config)? + "\n")?;
+
+    output::print(i18n::tr(
+        "created-entry-type",
+        &[
+            ("status", output::status("status-added")),
+            ("name", name.to_string()),
+            ("path", format!("{:?}", config_path)),
+        ],
+    ));
+
+    Ok(())
+}
+
+/// Adds a test file to the tests of the app
+pub fn generate_test(name: &str) -> DefaultResult<()> {
+    add_test(name, &TEST_TEMPLATE.replace("{name}", name))
+}
+
+/// Writes a test file with the given content to the tests of the app
+pub fn add_test(name: &str, content: &str) -> DefaultResult<()> {
+    let path = Path::new(TESTS_DIR_NAME).join(format!("{}.js", name));
+
+    ensure!(!path.exists(), "{:?} already exists", path);
+
+    fs::create_dir_all(TESTS_DIR_NAME)?;
+    fs::write(&path, content)?;
+
+    output::print(i18n::tr(
+        "created-test",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", path)),
+        ],
+    ));
+
+    if Project::from_dir(".")?.script(scripts::TEST).is_none() {
+        output::print("Run the tests with hcdev test after:");
+        output::print("  hcdev config set scripts.test \"node --test tests/\"");
+    }
+
+    Ok(())
+}
+
+/// Adds a page to the UI of the app
+pub fn generate_ui_page(name: &str) -> DefaultResult<()> {
+    let path = Path::new(UI_DIR_NAME).join(format!("{}.html", name));
+
+    ensure!(!path.exists(), "{:?} already exists", path);
+
+    let title = match app_name()? {
+        Some(app_name) if name == "index" => app_name,
+        _ => name.to_string(),
+    };
+
+    fs::create_dir_all(UI_DIR_NAME)?;
+    fs::write(&path, UI_PAGE_TEMPLATE.replace("{title}", &title))?;
+
+    output::print(i18n::tr(
+        "created-ui-page",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", path)),
+        ],
+    ));
+
+    Ok(())
+}
+
+/// Scaffolds an Electron shell for the app in the current directory at the given path, which
+/// `package` leaves out of the bundle
+pub fn generate_electron(path: &PathBuf) -> DefaultResult<()> {
+    let root = env::current_dir()?;
+    let path = util::normalize(&root.join(path));
+
+    ensure!(
+        Path::new(APP_CONFIG_FILE_NAME).is_file(),
+        "there is no {} here, the shell has to be generated in an app",
+        APP_CONFIG_FILE_NAME
+    );
+    ensure!(
+        !path.exists() || fs::read_dir(&path)?.next().is_none(),
+        "{:?} already exists and isn't empty",
+        path
+    );
+
+    let relative = path
+        .strip_prefix(&root)
+        .map_err(|_| format_err!("{:?} is outside of the app", path))?
+        .to_path_buf();
+    let app_root = relative
+        .components()
+        .map(|_| "..")
+        .collect::<Vec<_>>()
+        .join("/");
+    let app_name = match app_name()? {
+        Some(app_name) => app_name,
+        None => util::file_name_string(&root)?,
+    };
+
+    scaffold(
+        &scaffold::electron::ElectronScaffold::new(&app_name, &app_root),
+        path.clone(),
+    )?;
+
+    // the shell and its node_modules are no part of the app
+    let ignore_line = format!("{}/", relative.to_string_lossy().replace('\\', "/"));
+    let ignore_file = Path::new(IGNORE_FILE_NAME);
+    let ignored = fs::read_to_string(ignore_file)
+        .map(|content| content.lines().any(|line| line.trim() == ignore_line))
+        .unwrap_or(false);
+
+    if !ignored {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(ignore_file)?;
+        writeln!(file, "{}", ignore_line)?;
     }
 
+    output::print(i18n::tr(
+        "created-electron-shell",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", path)),
+        ],
+    ));
+
     Ok(())
 }
 
+/// The name of the app in the current directory, if there is one
+fn app_name() -> DefaultResult<Option<String>> {
+    let app_config_file = PathBuf::from(APP_CONFIG_FILE_NAME);
+
+    if !app_config_file.is_file() {
+        return Ok(None);
+    }
+
+    let app_config: Value = serde_json::from_slice(&fs::read(app_config_file)?)?;
+
+    Ok(app_config["name"].as_str().map(str::to_string))
+}
+
+/// The names of the Zomes of the app in the current directory, sorted
+fn zome_names() -> DefaultResult<Vec<String>> {
+    let mut names = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(Layout::of_app(".")?.zomes_dir()) {
+        for entry in entries {
+            let path = entry?.path();
+
+            if path.join(ZOME_CONFIG_FILE_NAME).is_file() {
+                names.push(util::file_name_string(&path)?);
+            }
+        }
+    }
+
+    names.sort();
+
+    Ok(names)
+}
+
 fn scaffold<S: Scaffold>(tooling: &S, base_path: PathBuf) -> DefaultResult<()> {
     tooling.gen(base_path)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use assert_cmd::prelude::*;
-    use std::process::Command;
+    use config_files::Build;
+    use std::{fs, process::Command};
     use tempfile::{Builder, TempDir};
 
     const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
@@ -81,5 +502,152 @@ mod tests {
             .args(&["g", "zomes/bubblechat", "rust"])
             .assert()
             .success();
+
+        let app_name = tmp.path().file_name().unwrap().to_string_lossy();
+        let app_config = fs::read_to_string(tmp.path().join("app.json")).unwrap();
+        let cargo_toml =
+            fs::read_to_string(tmp.path().join("zomes/bubblechat/code/Cargo.toml")).unwrap();
+
+        assert!(app_config.contains(&format!("\"name\": \"{}\"", app_name)));
+        assert!(cargo_toml.contains(&format!(
+            "name = \"{}-bubblechat\"",
+            scaffold::rust::crate_name(&app_name)
+        )));
+        assert!(cargo_toml.contains("name = \"code\""));
+
+        let code_dir = tmp.path().join("zomes/bubblechat/code");
+        let lib_code = fs::read_to_string(code_dir.join("src/lib.rs")).unwrap();
+        let build = Build::from_file(code_dir.join(".build")).unwrap();
+
+        assert!(lib_code.contains("struct MockHost"));
+        assert_eq!(build.tests["cargo"], vec!["test".to_string()]);
+    }
+
+    #[test]
+    fn can_generate_tiny_rust_scaffold() {
+        let tmp = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/tiny", "rust", "--tiny"])
+            .assert()
+            .success();
+
+        let code_dir = tmp.path().join("zomes/tiny/code");
+        let cargo_toml = fs::read_to_string(code_dir.join("Cargo.toml")).unwrap();
+        let lib_code = fs::read_to_string(code_dir.join("src/lib.rs")).unwrap();
+
+        assert!(cargo_toml.contains("wee_alloc"));
+        assert!(cargo_toml.contains("opt-level = \"z\""));
+        assert!(lib_code.contains("#[global_allocator]"));
+    }
+
+    #[test]
+    fn generates_an_electron_shell_left_out_of_the_bundle() {
+        let tmp = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["init", "."])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path().join("ui"))
+            .args(&["generate", "electron", "../desktop"])
+            .assert()
+            .success();
+
+        let shell_dir = tmp.path().join("desktop");
+        let main_js = fs::read_to_string(shell_dir.join("main.js")).unwrap();
+
+        assert!(main_js.contains("'../ui/index.html'"));
+        assert!(shell_dir.join("conductor-config.toml").is_file());
+        assert!(fs::read_to_string(shell_dir.join("package.json"))
+            .unwrap()
+            .contains("cd .. && hcdev package -o desktop/bundle.json"));
+        assert!(fs::read_to_string(tmp.path().join(IGNORE_FILE_NAME))
+            .unwrap()
+            .lines()
+            .any(|line| line == "desktop/"));
+    }
+
+    #[test]
+    fn generates_entry_types_tests_and_ui_pages_from_arguments() {
+        let tmp = gen_dir();
+        let hcdev = |args: &[&str]| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(&tmp.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        assert!(hcdev(&["init", "."]).status.success());
+        assert!(hcdev(&["g", "zomes/blog", "wat"]).status.success());
+        assert!(
+            hcdev(&["g", "entry", "zomes/blog", "post", "--sharing", "private"])
+                .status
+                .success()
+        );
+        assert!(!hcdev(&["g", "entry", "zomes/blog", "post"])
+            .status
+            .success());
+        assert!(hcdev(&["g", "test", "posting"]).status.success());
+        assert!(hcdev(&["g", "ui"]).status.success());
+
+        let zome_config: Value = serde_json::from_str(
+            &fs::read_to_string(tmp.path().join("zomes/blog/zome.json")).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(zome_config["entry_types"]["post"]["sharing"], "private");
+        assert!(fs::read_to_string(tmp.path().join("tests/posting.js"))
+            .unwrap()
+            .contains("test('posting'"));
+        assert!(tmp.path().join("ui/index.html").is_file());
+
+        // without a terminal to ask in, the zome still has to be given
+        let output = hcdev(&["--non-interactive", "g"]);
+
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("missing the path"));
+    }
+
+    #[test]
+    fn can_generate_wat_scaffold() {
+        let tmp = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/handwritten", "wat"])
+            .assert()
+            .success();
+
+        let code_dir = tmp.path().join("zomes/handwritten/code");
+        let build = fs::read_to_string(code_dir.join(".build")).unwrap();
+
+        assert!(fs::read_to_string(code_dir.join("code.wat"))
+            .unwrap()
+            .starts_with("(module"));
+        assert!(build.contains("wat2wasm"));
+        assert!(build.contains("\"artifact\": \"code.wasm\""));
+
+        // the next zome is written in the language of the others
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/by_hand_too"])
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("Using wat"));
+        assert!(tmp.path().join("zomes/by_hand_too/code/code.wat").is_file());
     }
 }
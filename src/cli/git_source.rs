@@ -0,0 +1,283 @@
+//! Zomes maintained in repositories of their own.
+//!
+//! Instead of a `code` directory, the Zome's `zome.json` names the repository and the revision to
+//! build: `"source": { "git": "https://...", "rev": "v1.2.0" }`, with an optional `path` to the
+//! Zome's directory inside of the repository. Every revision is cloned into a directory of its
+//! own in the cache, so once it's there, packaging works offline and builds are cached like those
+//! of local Zomes. A branch name as `rev` is resolved when it is first cloned, later commits to the
+//! branch aren't picked up.
+//!
+//! The `path` has to stay inside of the repository, and the checkout is locked while a Zome is
+//! built in it, since every app with the same source shares it.
+
+use cli::{layout::Layout, package::BUILD_CONFIG_FILE_NAME};
+use config_files::dirs;
+use error::DefaultResult;
+use file_lock::FileLock;
+use interrupt::PartialFile;
+use net;
+use serde_json::{Map, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use util;
+
+/// The key of `zome.json` declaring where the Zome's code comes from
+pub const SOURCE_KEY: &str = "source";
+pub const GIT_DIR_NAME: &str = "git";
+
+const FILE_SCHEME: &str = "file://";
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GitSource {
+    /// URL of the repository, or a path relative to the Zome's directory
+    pub git: String,
+    /// Commit, tag or branch to build
+    pub rev: String,
+    /// Directory of the Zome inside of the repository, the one containing `code`
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+}
+
+impl GitSource {
+    /// The git source declared in a Zome's manifest, if it has one
+    pub fn from_manifest(manifest: &Map<String, Value>) -> DefaultResult<Option<GitSource>> {
+        let source: GitSource = match manifest.get(SOURCE_KEY) {
+            Some(source) => ::serde_json::from_value(source.clone())
+                .map_err(|err| format_err!("invalid {}: {}", SOURCE_KEY, err))?,
+            None => return Ok(None),
+        };
+
+        // git would take them for options
+        ensure!(
+            !source.git.starts_with('-') && !source.rev.starts_with('-'),
+            "invalid {}: neither git nor rev may start with -",
+            SOURCE_KEY
+        );
+
+        Ok(Some(source))
+    }
+
+    /// The code directory of the Zome, cloned into the cache first if it isn't there yet. The
+    /// repository is expected to lay out the Zome like the app does.
+    pub fn code_dir(&self, zome_dir: &Path, layout: &Layout) -> DefaultResult<PathBuf> {
+        let url = self.resolved_url(zome_dir);
+        let checkout = dirs::cache_dir()?
+            .join(GIT_DIR_NAME)
+            .join(util::sha256_hex(format!("{}#{}", url, self.rev).as_bytes()));
+
+        let _lock = FileLock::acquire(&checkout)?;
+
+        if !checkout.is_dir() {
+            self.clone_into(&url, &checkout)?;
+        }
+
+        let zome_dir = match self.path {
+            Some(ref path) => checkout.join(path),
+            None => checkout.clone(),
+        };
+
+        // neither `..` nor a symlink in the repository may lead out of the checkout
+        let root = checkout.canonicalize()?;
+        let inside = zome_dir
+            .canonicalize()
+            .map(|dir| dir.starts_with(&root))
+            .unwrap_or(false);
+
+        ensure!(
+            inside,
+            "{} at {} has no directory {} inside of the repository",
+            self.git,
+            self.rev,
+            zome_dir
+                .strip_prefix(&checkout)
+                .unwrap_or(&zome_dir)
+                .display()
+        );
+
+        let code_dir = layout.code_dir(&zome_dir);
+
+        ensure!(
+            code_dir.join(BUILD_CONFIG_FILE_NAME).is_file(),
+            "{} at {} has no {}/{} file{}",
+            self.git,
+            self.rev,
+            layout.code_dir_name(),
+            BUILD_CONFIG_FILE_NAME,
+            self.path
+                .as_ref()
+                .map_or_else(String::new, |path| format!(" in {}", path.display()))
+        );
+
+        Ok(code_dir)
+    }
+
+    /// Local repositories are cloned from their absolute path, so the cache entry doesn't
+    /// depend on the directory packaging ran in
+    fn resolved_url(&self, zome_dir: &Path) -> String {
+        let local = Path::new(self.git.trim_start_matches(FILE_SCHEME));
+
+        if self.is_local() && local.is_relative() {
+            zome_dir
+                .join(local)
+                .canonicalize()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| self.git.clone())
+        } else {
+            self.git.clone()
+        }
+    }
+
+    fn is_local(&self) -> bool {
+        self.git.starts_with(FILE_SCHEME) || !self.git.contains(':')
+    }
+
+    fn clone_into(&self, url: &str, checkout: &Path) -> DefaultResult<()> {
+        ensure!(
+            !net::is_offline() || self.is_local(),
+            "refusing to clone {} in offline mode",
+            self.git
+        );
+
+        let parent = checkout
+            .parent()
+            .ok_or_else(|| format_err!("invalid cache directory {:?}", checkout))?;
+        fs::create_dir_all(parent)?;
+
+        // cloned next to the entry and moved into place once checked out, so an interrupted
+        // clone never looks like a finished one
+        let partial = checkout.with_extension("partial");
+        let _partial = PartialFile::new(&partial);
+
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+
+        let git = |args: Vec<String>| util::run_cmd(parent.to_path_buf(), "git".into(), args);
+        let partial_arg = partial.to_string_lossy().into_owned();
+
+        git(vec![
+            "clone".into(),
+            "--quiet".into(),
+            "--".into(),
+            url.into(),
+            partial_arg.clone(),
+        ])?;
+        // the revision in front of `--`, so it's never taken for a path
+        git(vec![
+            "-C".into(),
+            partial_arg,
+            "checkout".into(),
+            "--quiet".into(),
+            "--detach".into(),
+            self.rev.clone(),
+            "--".into(),
+        ])?;
+
+        fs::rename(&partial, checkout)?;
+
+        Ok(())
+    }
+}
+
+/// Whether the directory is inside of a checkout of a git source, which other apps share
+pub fn is_checkout(dir: &Path) -> DefaultResult<bool> {
+    Ok(dir.starts_with(dirs::cache_dir()?.join(GIT_DIR_NAME)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use std::process::Command;
+    use tempfile::Builder;
+
+    #[test]
+    fn reads_the_source_of_a_zome() {
+        let manifest = json!({
+            "description": "Posts",
+            "source": { "git": "https://example.com/posts.git", "rev": "v1.2.0" }
+        });
+
+        let source = GitSource::from_manifest(manifest.as_object().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.rev, "v1.2.0");
+        assert_eq!(source.path, None);
+        assert!(!source.is_local());
+
+        let local = json!({ "description": "Posts" });
+        assert_eq!(
+            GitSource::from_manifest(local.as_object().unwrap()).unwrap(),
+            None
+        );
+
+        let invalid = json!({ "source": { "rev": "v1" } });
+        assert!(GitSource::from_manifest(invalid.as_object().unwrap()).is_err());
+
+        let option = json!({ "source": { "git": "--upload-pack=touch pwned", "rev": "v1" } });
+        assert!(GitSource::from_manifest(option.as_object().unwrap()).is_err());
+    }
+
+    #[test]
+    fn builds_zomes_only_from_inside_of_the_repository() {
+        let tmp = Builder::new().prefix("git_source").tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        let app = tmp.path().join("app");
+        let code_dir = repo.join("posts/code");
+
+        fs::create_dir_all(&code_dir).unwrap();
+        fs::create_dir_all(app.join("zomes/posts")).unwrap();
+        fs::write(app.join("app.json"), "{}").unwrap();
+        fs::write(
+            code_dir.join(".build"),
+            r#"{
+                "steps": { "sh": ["-c", "printf '\\0asm\\1\\0\\0\\0' > code.wasm"] },
+                "artifact": "code.wasm"
+            }"#,
+        ).unwrap();
+
+        let git = |args: &[&str]| {
+            assert!(Command::new("git")
+                .current_dir(&repo)
+                .args(args)
+                .env("GIT_AUTHOR_NAME", "Test")
+                .env("GIT_AUTHOR_EMAIL", "test@example.com")
+                .env("GIT_COMMITTER_NAME", "Test")
+                .env("GIT_COMMITTER_EMAIL", "test@example.com")
+                .status()
+                .unwrap()
+                .success());
+        };
+
+        git(&["init", "--quiet"]);
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "Posts"]);
+        git(&["tag", "v1"]);
+
+        let package = |path: &str| {
+            fs::write(
+                app.join("zomes/posts/zome.json"),
+                json!({
+                    "description": "Posts",
+                    "source": { "git": "../../../repo", "rev": "v1", "path": path }
+                }).to_string(),
+            ).unwrap();
+
+            Command::main_binary()
+                .unwrap()
+                .current_dir(&app)
+                .env("HC_HOME", tmp.path().join("home"))
+                .args(&["package", "-o", "../bundle.json"])
+                .output()
+                .unwrap()
+        };
+
+        assert!(package("posts").status.success());
+
+        let output = package("../..");
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("inside of the repository"));
+    }
+}
@@ -0,0 +1,222 @@
+//! Git hooks that run checks of hcdev before a commit or a push. The installed scripts only hand
+//! over to `hooks run`, which reads the commands from the `[hooks]` settings every time, so
+//! changing them doesn't need another install:
+//!
+//! ```toml
+//! [hooks]
+//! pre_commit = ["lint", "package --check"]
+//! pre_push = ["test"]
+//! ```
+//!
+//! `package --check` reuses the builds of the Zomes that didn't change, so only the changed
+//! ones are built before a commit.
+
+use config_files::Project;
+use error::DefaultResult;
+use i18n;
+use interrupt;
+use net::{self, OFFLINE_ENV_VAR};
+use output;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const PRE_COMMIT: &str = "pre-commit";
+const PRE_PUSH: &str = "pre-push";
+
+const HOOKS: &[&str] = &[PRE_COMMIT, PRE_PUSH];
+
+/// Marks the hooks `install` wrote, only those are replaced without `--force`
+const MARKER: &str = "# installed by hcdev hooks install";
+
+const HOOK_SCRIPT: &str = "\
+#!/bin/sh
+{marker}, runs the commands of the [hooks] settings in hc.toml
+cd \"{app_dir}\" && exec hcdev hooks run {hook}
+";
+
+/// Installs the pre-commit and pre-push hooks into the git repository the app is in
+pub fn hooks_install(force: bool) -> DefaultResult<()> {
+    let hooks_dir = PathBuf::from(git(&["rev-parse", "--git-path", "hooks"])?);
+    let top_level = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?).canonicalize()?;
+    let app_dir = env::current_dir()?.canonicalize()?;
+    let app_dir = match app_dir.strip_prefix(&top_level) {
+        Ok(relative) if relative != Path::new("") => relative.to_string_lossy().replace('\\', "/"),
+        _ => ".".to_string(),
+    };
+
+    fs::create_dir_all(&hooks_dir)?;
+
+    for hook in HOOKS {
+        let path = hooks_dir.join(hook);
+
+        if path.exists() && !force {
+            ensure!(
+                fs::read_to_string(&path)?.contains(MARKER),
+                "{:?} is a hook of its own, pass --force to replace it",
+                path
+            );
+        }
+
+        fs::write(
+            &path,
+            HOOK_SCRIPT
+                .replace("{marker}", MARKER)
+                .replace("{app_dir}", &app_dir)
+                .replace("{hook}", hook),
+        )?;
+
+        make_executable(&path)?;
+
+        output::print(i18n::tr(
+            "installed-hook",
+            &[
+                ("status", output::status("status-installed")),
+                ("hook", hook.to_string()),
+                ("path", format!("{:?}", path)),
+            ],
+        ));
+    }
+
+    Ok(())
+}
+
+/// Runs the commands configured for the hook one after another, stopping at the first failure.
+/// A commit that doesn't touch the app skips the checks.
+pub fn hooks_run(hook: &str) -> DefaultResult<()> {
+    let config = Project::from_dir(".")?.hooks;
+
+    let commands = match hook {
+        PRE_COMMIT => config.pre_commit,
+        PRE_PUSH => config.pre_push,
+        _ => bail!("unknown hook {}, expected {}", hook, HOOKS.join(" or ")),
+    };
+
+    if hook == PRE_COMMIT && !has_staged_changes() {
+        return Ok(());
+    }
+
+    for command in commands {
+        output::command("hcdev", &command);
+
+        let mut process = Command::new(env::current_exe()?);
+        process.args(command.split_whitespace());
+
+        if net::is_offline() {
+            process.env(OFFLINE_ENV_VAR, "1");
+        }
+
+        ensure!(
+            interrupt::status(&mut process)?.success(),
+            "the {} hook failed at hcdev {}",
+            hook,
+            command
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether anything below the current directory is staged, or git can't tell
+fn has_staged_changes() -> bool {
+    git(&["diff", "--cached", "--name-only", "--relative"])
+        .map(|staged| !staged.is_empty())
+        .unwrap_or(true)
+}
+
+fn git(args: &[&str]) -> DefaultResult<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|err| format_err!("unable to run git: {}", err))?;
+
+    ensure!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> DefaultResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> DefaultResult<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use tempfile::Builder;
+
+    #[test]
+    fn installed_hooks_check_the_app_without_packaging_it() {
+        let tmp = Builder::new().prefix("hooks").tempdir().unwrap();
+        let app = tmp.path().join("apps/chat");
+        let hcdev = |args: &[&str]| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(&app)
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        fs::create_dir_all(&app).unwrap();
+        fs::write(
+            app.join("app.json"),
+            "{\"name\": \"chat\", \"description\": \"Chat\"}",
+        )
+        .unwrap();
+        fs::write(
+            app.join("hc.toml"),
+            "[hooks]\npre_commit = [\"package --check\"]\n",
+        )
+        .unwrap();
+
+        assert!(Command::new("git")
+            .args(&["init", "--quiet"])
+            .current_dir(tmp.path())
+            .status()
+            .unwrap()
+            .success());
+
+        assert!(hcdev(&["hooks", "install"]).status.success());
+
+        let hook = fs::read_to_string(tmp.path().join(".git/hooks/pre-commit")).unwrap();
+
+        assert!(hook.contains("cd \"apps/chat\" && exec hcdev hooks run pre-commit"));
+        assert!(hcdev(&["hooks", "install"]).status.success());
+
+        fs::write(tmp.path().join(".git/hooks/pre-push"), "#!/bin/sh\n").unwrap();
+
+        assert!(!hcdev(&["hooks", "install"]).status.success());
+        assert!(hcdev(&["hooks", "install", "--force"]).status.success());
+
+        assert!(Command::new("git")
+            .args(&["add", "app.json"])
+            .current_dir(&app)
+            .status()
+            .unwrap()
+            .success());
+
+        let output = hcdev(&["hooks", "run", "pre-commit"]);
+
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("package --check"));
+        assert!(!app.join("bundle.json").exists());
+    }
+}
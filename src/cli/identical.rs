@@ -0,0 +1,126 @@
+//! Tells whether two bundles carry the same app, for CI to skip a redeploy if nothing changed.
+//!
+//! Bundles are compared as JSON, so neither formatting nor the order of keys matter. The
+//! `__META__` sections are left out as well: they describe how to unpack a bundle and where its
+//! debug info is, not what the conductor runs.
+
+use bundle::{self, BundleType};
+use cli::package::META_SECTION_NAME;
+use delta;
+use error::DefaultResult;
+use output;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Only the first differences are listed, the count tells how many there are
+const MAX_LISTED_DIFFERENCES: usize = 10;
+
+/// Compares the bundles, listing where they differ if they aren't identical
+pub fn identical(a: &PathBuf, b: &PathBuf) -> DefaultResult<bool> {
+    let (a_type, a) = comparable(a)?;
+    let (b_type, b) = comparable(b)?;
+
+    if a_type != b_type {
+        output::print(format!("a {:?} bundle and a {:?} bundle", a_type, b_type).to_lowercase());
+
+        return Ok(false);
+    }
+
+    let differences = differences(&a, &b)?;
+
+    if differences.is_empty() {
+        output::print("identical");
+    } else {
+        for difference in differences.iter().take(MAX_LISTED_DIFFERENCES) {
+            output::print(difference);
+        }
+
+        if differences.len() > MAX_LISTED_DIFFERENCES {
+            output::print(format!(
+                "and {} more differences",
+                differences.len() - MAX_LISTED_DIFFERENCES
+            ));
+        }
+    }
+
+    Ok(differences.is_empty())
+}
+
+/// The content of the bundle without what doesn't count for the comparison
+fn comparable(path: &PathBuf) -> DefaultResult<(BundleType, Value)> {
+    let (header, mut content) = bundle::read(path)?;
+
+    ensure!(
+        header.bundle_type != BundleType::Encrypted,
+        "{:?} is encrypted, its content can't be compared",
+        path
+    );
+
+    strip_meta(&mut content);
+
+    Ok((header.bundle_type, content))
+}
+
+fn strip_meta(value: &mut Value) {
+    if let Value::Object(obj) = value {
+        obj.remove(META_SECTION_NAME);
+
+        for child in obj.values_mut() {
+            strip_meta(child);
+        }
+    }
+}
+
+/// The paths that were changed, added or removed from `a` to `b`
+fn differences(a: &Value, b: &Value) -> DefaultResult<Vec<String>> {
+    let delta = delta::delta(&delta::diff(a, b)?)?
+        .ok_or_else(|| format_err!("the bundles couldn't be compared"))?;
+
+    let mut differences: Vec<_> = delta
+        .set
+        .iter()
+        .map(|change| format!("changed: {}", change.path.join("/")))
+        .chain(
+            delta
+                .remove
+                .iter()
+                .map(|path| format!("removed: {}", path.join("/"))),
+        )
+        .collect();
+
+    differences.sort();
+
+    Ok(differences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_meta_and_key_order() {
+        let mut a = json!({
+            "name": "chat",
+            "zomes": { "posts": { "code": { "code": "AGFzbQ==" } } },
+            "__META__": { "tree": { "zomes": "dir" } }
+        });
+        let mut b = json!({
+            "zomes": {
+                "posts": { "code": { "code": "AGFzbQ==" }, "__META__": { "debug_info": {} } }
+            },
+            "name": "chat"
+        });
+        strip_meta(&mut a);
+        strip_meta(&mut b);
+
+        assert!(differences(&a, &b).unwrap().is_empty());
+
+        b["zomes"]["posts"]["code"]["code"] = json!("AGFzbQE=");
+        b.as_object_mut().unwrap().remove("name");
+
+        assert_eq!(
+            differences(&a, &b).unwrap(),
+            vec!["changed: zomes/posts/code/code", "removed: name"]
+        );
+    }
+}
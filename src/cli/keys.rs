@@ -0,0 +1,83 @@
+use config_files::dirs;
+use encryption;
+use error::DefaultResult;
+use i18n;
+use output;
+use provenance::{self, SIGNING_KEY_FILE_NAME};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+pub const IDENTITY_FILE_NAME: &str = "identity.key";
+
+/// Where keygen puts the identity if no path is given, and where unpack looks for it
+pub fn default_identity_path() -> DefaultResult<PathBuf> {
+    Ok(dirs::data_dir()?.join(IDENTITY_FILE_NAME))
+}
+
+/// Where `keygen --signing` puts the key if no path is given, and where package looks for it
+pub fn default_signing_key_path() -> DefaultResult<PathBuf> {
+    Ok(dirs::data_dir()?.join(SIGNING_KEY_FILE_NAME))
+}
+
+/// Creates an identity for receiving encrypted bundles, or with `signing` a key for signing
+/// the provenance of bundles
+pub fn keygen(path: Option<PathBuf>, signing: bool) -> DefaultResult<()> {
+    let path = match (path, signing) {
+        (Some(path), _) => path,
+        (None, false) => default_identity_path()?,
+        (None, true) => default_signing_key_path()?,
+    };
+
+    ensure!(
+        !path.exists(),
+        "{:?} already exists, refusing to overwrite a key",
+        path
+    );
+
+    let (secret, public) = if signing {
+        provenance::generate_signing_key()
+    } else {
+        encryption::generate_identity()
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    create_secret(&path)?.write_all(format!("{}\n", secret).as_bytes())?;
+
+    output::print(i18n::tr(
+        if signing {
+            "created-signing-key"
+        } else {
+            "created-identity"
+        },
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", path)),
+        ],
+    ));
+    output::print(i18n::tr("public-key", &[("key", public)]));
+
+    Ok(())
+}
+
+/// Creates the file of a key only its owner can read, before anything is written to it
+#[cfg(unix)]
+fn create_secret(path: &PathBuf) -> DefaultResult<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    Ok(OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?)
+}
+
+#[cfg(not(unix))]
+fn create_secret(path: &PathBuf) -> DefaultResult<File> {
+    Ok(OpenOptions::new().write(true).create_new(true).open(path)?)
+}
@@ -0,0 +1,204 @@
+//! Where the Zomes of an app are. By default every Zome is a directory in `zomes/` with its
+//! code in `code/`, which the `[zomes]` settings change for apps laid out differently:
+//!
+//! ```toml
+//! [zomes]
+//! dir = "dna/zomes"
+//! code_dir = "rust"
+//! ```
+//!
+//! Only the directories of the app follow the layout. Bundles keep the one the conductor
+//! expects, with the Zomes in `zomes` and their WASM in `code`, however the app is laid out.
+
+use cli::{docs::ZOMES_DIR_NAME, package::CODE_DIR_NAME};
+use config_files::{project::ZomesConfig, Project};
+use error::DefaultResult;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    zomes_dir: PathBuf,
+    code_dir: String,
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout {
+            zomes_dir: PathBuf::from(ZOMES_DIR_NAME),
+            code_dir: CODE_DIR_NAME.to_string(),
+        }
+    }
+}
+
+impl Layout {
+    pub fn from_config(config: &ZomesConfig) -> DefaultResult<Layout> {
+        let default = Layout::default();
+        let layout = Layout {
+            zomes_dir: config.dir.clone().unwrap_or(default.zomes_dir),
+            code_dir: config.code_dir.clone().unwrap_or(default.code_dir),
+        };
+
+        // both are looked up from the app's root on every machine
+        let is_inside = |path: &Path| {
+            path.components().any(|component| match component {
+                Component::Normal(_) => true,
+                _ => false,
+            }) && path.components().all(|component| match component {
+                Component::Normal(_) | Component::CurDir => true,
+                _ => false,
+            })
+        };
+
+        ensure!(
+            is_inside(&layout.zomes_dir),
+            "zomes.dir = {:?} has to be a directory inside the app",
+            layout.zomes_dir
+        );
+        ensure!(
+            is_inside(Path::new(&layout.code_dir))
+                && Path::new(&layout.code_dir).components().count() == 1,
+            "zomes.code_dir = {:?} has to be the name of a directory",
+            layout.code_dir
+        );
+
+        Ok(layout)
+    }
+
+    /// The layout of the app in the given directory
+    pub fn of_app<T: AsRef<Path>>(root: T) -> DefaultResult<Layout> {
+        Layout::from_config(&Project::from_dir(root)?.zomes)
+    }
+
+    /// The directory the Zomes are in, relative to the app's root
+    pub fn zomes_dir(&self) -> &Path {
+        &self.zomes_dir
+    }
+
+    /// The name of the directory of a Zome its code is in
+    pub fn code_dir_name(&self) -> &str {
+        &self.code_dir
+    }
+
+    /// The directory of the Zome of the given name, relative to the app's root
+    pub fn zome_dir(&self, name: &str) -> PathBuf {
+        self.zomes_dir.join(name)
+    }
+
+    /// The code directory of the Zome in the given directory
+    pub fn code_dir(&self, zome_dir: &Path) -> PathBuf {
+        zome_dir.join(&self.code_dir)
+    }
+
+    /// The directories in the Zomes directory of the app in `root`, sorted by name. It is an
+    /// error if there is no such directory, there is no app without it.
+    pub fn zome_dirs(&self, root: &Path) -> DefaultResult<Vec<PathBuf>> {
+        let zomes_dir = root.join(&self.zomes_dir);
+
+        ensure!(
+            zomes_dir.is_dir(),
+            "no \"{}\" directory found, is this a Holochain app?",
+            self.zomes_dir.display()
+        );
+
+        let mut zome_dirs: Vec<_> = fs::read_dir(&zomes_dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| path.is_dir())
+            .collect();
+
+        zome_dirs.sort();
+
+        Ok(zome_dirs)
+    }
+
+    /// The code directories of all Zomes of the app in `root` that have the given file, like
+    /// the `.build` or the `Cargo.toml`, sorted by the name of the Zome
+    pub fn code_dirs_with(&self, root: &Path, file_name: &str) -> DefaultResult<Vec<PathBuf>> {
+        Ok(self
+            .zome_dirs(root)?
+            .iter()
+            .map(|zome_dir| self.code_dir(zome_dir))
+            .filter(|code_dir| code_dir.join(file_name).is_file())
+            .collect())
+    }
+
+    /// The name a directory of the app has in the bundle: code directories are called `code`
+    /// there
+    pub fn bundle_name(&self, dir_name: &str) -> String {
+        if dir_name == self.code_dir {
+            CODE_DIR_NAME.to_string()
+        } else {
+            dir_name.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use serde_json::Value;
+    use std::{fs, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn generates_and_packages_zomes_in_the_configured_layout() {
+        let tmp = Builder::new().prefix("layout").tempdir().unwrap();
+        let home = Builder::new().prefix("layout-home").tempdir().unwrap();
+        let hcdev = |args: &[&str]| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .env("HC_HOME", home.path())
+                .args(args)
+                .assert()
+                .success();
+        };
+
+        hcdev(&["init", "."]);
+        fs::remove_dir(tmp.path().join("zomes")).unwrap();
+        fs::write(
+            tmp.path().join("hc.toml"),
+            "[zomes]\ndir = \"dna/zomes\"\ncode_dir = \"wat\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(tmp.path().join("dna")).unwrap();
+        fs::write(tmp.path().join("dna/README.md"), "# DNA\n").unwrap();
+
+        hcdev(&["generate", "dna/zomes/posts", "wat"]);
+
+        let code_dir = tmp.path().join("dna/zomes/posts/wat");
+        assert!(code_dir.join(".build").is_file());
+
+        // no wat2wasm needed, the build just takes the module as it is
+        fs::write(code_dir.join("code.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        fs::write(
+            code_dir.join(".build"),
+            r#"{ "steps": { "true": [] }, "artifact": "code.wasm" }"#,
+        )
+        .unwrap();
+
+        hcdev(&["package", "--output", "app.hcpkg"]);
+
+        let bundle: Value =
+            serde_json::from_str(&fs::read_to_string(tmp.path().join("app.hcpkg")).unwrap())
+                .unwrap();
+
+        assert!(bundle["zomes"]["posts"]["code"]["code"].is_string());
+        assert_eq!(bundle["__META__"]["tree"]["zomes"], "dir");
+        assert!(bundle["dna"]["README.md"].is_string());
+        assert!(bundle["dna"].get("zomes").is_none());
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .env("HC_HOME", home.path())
+            .args(&["cache", "status"])
+            .output()
+            .unwrap();
+
+        assert!(String::from_utf8_lossy(&output.stdout).contains("posts"));
+    }
+}
@@ -0,0 +1,118 @@
+use cli::layout::Layout;
+use config_files::Project;
+use error::DefaultResult;
+use i18n;
+use licenses;
+use output;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const SPDX_TAG: &str = "SPDX-License-Identifier:";
+
+/// Existing headers are only looked for in the first lines of a file
+const HEADER_LINES: usize = 5;
+
+const RUST_FILE_EXTENSION: &str = "rs";
+
+/// Prepends an SPDX license header to the code, unless it already has one
+pub fn with_license_header(code: &str, license: &str) -> String {
+    if code
+        .lines()
+        .take(HEADER_LINES)
+        .any(|line| line.contains(SPDX_TAG))
+    {
+        code.to_string()
+    } else {
+        format!("// {} {}\n\n{}", SPDX_TAG, license, code)
+    }
+}
+
+/// The license of the app in the current directory, as set in `hc.toml`
+pub fn project_license() -> DefaultResult<Option<String>> {
+    Ok(Project::from_dir(".")?.license)
+}
+
+/// Adds an SPDX license header to every Rust source file of the app's Zomes that lacks one
+pub fn generate_license(license: Option<String>) -> DefaultResult<()> {
+    let license = match license {
+        Some(license) => license,
+        None => project_license()?.ok_or_else(|| {
+            format_err!("no license given, pass one or set it with: config set license <SPDX id>")
+        })?,
+    };
+
+    ensure!(
+        licenses::is_known(&license),
+        "{} is not an SPDX license expression hcdev knows, see https://spdx.org/licenses/",
+        license
+    );
+
+    let layout = Layout::of_app(".")?;
+    let mut files = Vec::new();
+
+    for zome_dir in layout.zome_dirs(Path::new("."))? {
+        collect_sources(&layout.code_dir(&zome_dir).join("src"), &mut files)?;
+    }
+
+    let mut count = 0;
+
+    for path in files {
+        let code = fs::read_to_string(&path)?;
+        let licensed = with_license_header(&code, &license);
+
+        if licensed != code {
+            fs::write(&path, licensed)?;
+            count += 1;
+        }
+    }
+
+    output::print(i18n::tr(
+        "added-license-headers",
+        &[
+            ("status", output::status("status-added")),
+            ("license", license),
+            ("count", count.to_string()),
+        ],
+    ));
+
+    Ok(())
+}
+
+fn collect_sources(dir: &PathBuf, files: &mut Vec<PathBuf>) -> DefaultResult<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_sources(&path, files)?;
+        } else if path
+            .extension()
+            .map_or(false, |ext| ext == RUST_FILE_EXTENSION)
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_the_header_only_once() {
+        let licensed = with_license_header("fn main() {}\n", "MIT");
+
+        assert_eq!(
+            licensed,
+            "// SPDX-License-Identifier: MIT\n\nfn main() {}\n"
+        );
+        assert_eq!(with_license_header(&licensed, "Apache-2.0"), licensed);
+    }
+}
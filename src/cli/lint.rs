@@ -0,0 +1,549 @@
+//! Checks the manifests and `.build` files for values that only make sense on the machine they
+//! were written on: absolute paths, paths into someone's home directory and the hostname.
+//!
+//! Such values build fine for whoever committed them and break for everybody else. `--fix`
+//! rewrites paths that point into the app to paths relative to the file they're in, everything
+//! else has to be fixed by hand.
+//!
+//! `--wasm-imports` also checks the built WASM of the Zomes for imports the conductor doesn't
+//! provide, like the JavaScript glue of wasm-bindgen. Otherwise those only show up when the
+//! conductor fails to instantiate the Zome.
+
+use cli::{
+    generate::ZOME_CONFIG_FILE_NAME, layout::Layout, new::APP_CONFIG_FILE_NAME,
+    package::BUILD_CONFIG_FILE_NAME,
+};
+use config_files::Build;
+use error::DefaultResult;
+use output;
+use serde_json::{self, value::RawValue, Value};
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+use wasm::{Import, Module};
+
+/// The module the conductor provides its host functions in
+pub const HOST_MODULE: &str = "env";
+/// The modules wasm-bindgen imports its JavaScript glue from, before and after `wasm-bindgen`
+/// processed the module
+const JS_GLUE_MODULES: &[&str] = &[
+    "__wbindgen_placeholder__",
+    "__wbindgen_externref_xform__",
+    "wbg",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    AbsolutePath,
+    HomePath,
+    Hostname,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Kind::AbsolutePath => "an absolute path",
+            Kind::HomePath => "a path in a home directory",
+            Kind::Hostname => "the hostname of this machine",
+        })
+    }
+}
+
+/// A value of a manifest that is specific to one machine
+#[derive(Clone, Debug)]
+pub struct Finding {
+    /// Dotted path of the value in the file
+    pub key: String,
+    /// The keys and indices leading to the value, which may contain dots themselves
+    pub path: Vec<String>,
+    /// Whether the value is the last key of the path rather than what it holds
+    pub is_key: bool,
+    pub value: String,
+    pub kind: Kind,
+}
+
+/// What is specific to the machine the check runs on
+pub struct Host {
+    home: Option<String>,
+    hostname: Option<String>,
+}
+
+impl Host {
+    pub fn current() -> Host {
+        let hostname = env::var("HOSTNAME")
+            .or_else(|_| env::var("COMPUTERNAME"))
+            .ok()
+            .or_else(|| fs::read_to_string("/etc/hostname").ok())
+            .map(|hostname| hostname.trim().to_lowercase())
+            .filter(|hostname| !hostname.is_empty() && hostname != "localhost");
+
+        Host {
+            home: env::var("HOME")
+                .or_else(|_| env::var("USERPROFILE"))
+                .ok()
+                .filter(|home| !home.is_empty()),
+            hostname,
+        }
+    }
+
+    /// Finds the machine specific keys and strings in a JSON document, sorted by their path
+    pub fn check(&self, value: &Value) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        self.check_recurse(&mut Vec::new(), value, &mut findings);
+
+        // serde_json may keep the keys in the order of the file, the findings don't depend on it
+        findings.sort_by(|a, b| a.key.cmp(&b.key));
+
+        findings
+    }
+
+    fn check_recurse(&self, path: &mut Vec<String>, value: &Value, findings: &mut Vec<Finding>) {
+        match value {
+            Value::String(string) => findings.extend(self.check_string(path, string, false)),
+            Value::Array(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    path.push(index.to_string());
+                    self.check_recurse(path, item, findings);
+                    path.pop();
+                }
+            }
+            Value::Object(obj) => {
+                for (name, child) in obj {
+                    path.push(name.to_string());
+
+                    // the build steps are keyed by the binary to run
+                    findings.extend(self.check_string(path, name, true));
+                    self.check_recurse(path, child, findings);
+                    path.pop();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn check_string(&self, path: &[String], string: &str, is_key: bool) -> Option<Finding> {
+        let finding = |kind| Finding {
+            key: path.join("."),
+            path: path.to_vec(),
+            is_key,
+            value: string.to_string(),
+            kind,
+        };
+
+        // options like `--target-dir=/tmp/build` carry paths too
+        let path = string.splitn(2, '=').last().unwrap_or(string);
+
+        if !string.contains("://") {
+            let in_home = path.starts_with("~/")
+                || path.starts_with("$HOME")
+                || self.home.as_ref().map_or(false, |home| {
+                    path.starts_with(home.as_str()) && Path::new(path).is_absolute()
+                });
+
+            if in_home {
+                return Some(finding(Kind::HomePath));
+            }
+
+            if is_absolute(path) {
+                return Some(finding(Kind::AbsolutePath));
+            }
+        }
+
+        let hostname = self.hostname.as_ref()?;
+        let mentions_host = string
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric() && c != '-')
+            .any(|word| word == hostname);
+
+        if mentions_host {
+            Some(finding(Kind::Hostname))
+        } else {
+            None
+        }
+    }
+}
+
+/// Absolute paths on any platform, not only the current one
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let has_drive = bytes.len() > 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'/' || bytes[2] == b'\\');
+
+    (path.starts_with('/') && path.len() > 1) || path.starts_with("\\\\") || has_drive
+}
+
+/// The Zome directories of the app in the current directory
+fn zome_dirs(layout: &Layout) -> DefaultResult<Vec<PathBuf>> {
+    if !layout.zomes_dir().is_dir() {
+        return Ok(Vec::new());
+    }
+
+    layout.zome_dirs(Path::new("."))
+}
+
+/// The manifests and build files of the app in the current directory
+pub fn manifest_files() -> DefaultResult<Vec<PathBuf>> {
+    let layout = Layout::of_app(".")?;
+    let mut files = vec![PathBuf::from(APP_CONFIG_FILE_NAME)];
+
+    for zome in zome_dirs(&layout)? {
+        files.push(zome.join(ZOME_CONFIG_FILE_NAME));
+        files.push(layout.code_dir(&zome).join(BUILD_CONFIG_FILE_NAME));
+    }
+
+    Ok(files.into_iter().filter(|file| file.is_file()).collect())
+}
+
+/// The imports of a module the conductor can't satisfy: everything but its `hc_` functions
+pub fn foreign_imports(module: &Module) -> Vec<&Import> {
+    module
+        .imports
+        .iter()
+        .filter(|import| import.module != HOST_MODULE || !import.name.starts_with("hc_"))
+        .collect()
+}
+
+/// Whether the import is part of the JavaScript glue wasm-bindgen generates
+fn is_js_glue(import: &Import) -> bool {
+    JS_GLUE_MODULES.contains(&import.module.as_str())
+        || import.module.ends_with(".js")
+        || import.name.starts_with("__wbindgen")
+        || import.name.starts_with("__wbg_")
+}
+
+/// Reports the imports of the built Zomes the conductor can't satisfy and returns how many
+fn check_wasm_imports() -> DefaultResult<usize> {
+    let layout = Layout::of_app(".")?;
+    let mut found = 0;
+
+    for zome in zome_dirs(&layout)? {
+        let code_dir = layout.code_dir(&zome);
+        let build_file = code_dir.join(BUILD_CONFIG_FILE_NAME);
+
+        if !build_file.is_file() {
+            continue;
+        }
+
+        let artifact = code_dir.join(Build::from_file(&build_file)?.output());
+
+        ensure!(
+            artifact.is_file(),
+            "{:?} doesn't exist yet, build the Zome with hcdev package first",
+            artifact
+        );
+
+        let module = Module::parse(&fs::read(&artifact)?)
+            .map_err(|err| format_err!("{:?}: {}", artifact, err))?;
+
+        for import in foreign_imports(&module) {
+            found += 1;
+
+            output::error(format!(
+                "{}: imports {}.{}, which the conductor doesn't provide{}",
+                artifact.display(),
+                import.module,
+                import.name,
+                if is_js_glue(import) {
+                    " (JavaScript glue of wasm-bindgen)"
+                } else {
+                    ""
+                }
+            ));
+        }
+    }
+
+    Ok(found)
+}
+
+/// Checks all manifests and build files of the app, rewriting the paths into the app as
+/// relative ones if `fix` is set, and the imports of the built Zomes if `wasm_imports` is set.
+/// Fails if anything is left to be fixed by hand.
+pub fn lint(fix: bool, wasm_imports: bool) -> DefaultResult<()> {
+    let host = Host::current();
+    let root = env::current_dir()?;
+    let mut remaining = 0;
+
+    for file in manifest_files()? {
+        let mut content = fs::read_to_string(&file)?;
+        let value: Value =
+            serde_json::from_str(&content).map_err(|err| format_err!("{:?}: {}", file, err))?;
+        let file_dir = root.join(file.parent().unwrap_or_else(|| Path::new("")));
+        let mut edits = Vec::new();
+
+        for finding in host.check(&value) {
+            let replacement = if fix {
+                relative_replacement(&finding, &root, &file_dir)
+                    .and_then(|replacement| Some((span(&content, &finding)?, replacement)))
+            } else {
+                None
+            };
+
+            match replacement {
+                Some((span, replacement)) => {
+                    output::print(format!(
+                        "{}: {}: {} -> {}",
+                        file.display(),
+                        finding.key,
+                        finding.value,
+                        replacement
+                    ));
+
+                    edits.push((span, serde_json::to_string(&replacement)?));
+                }
+                None => {
+                    remaining += 1;
+
+                    output::error(format!(
+                        "{}: {} is {}: {}",
+                        file.display(),
+                        finding.key,
+                        finding.kind,
+                        finding.value
+                    ));
+                }
+            }
+        }
+
+        if !edits.is_empty() {
+            // replacing only the flagged strings keeps the formatting and order of the file and
+            // leaves the same string elsewhere in it alone, the last first so the spans of the
+            // others stay where they are
+            edits.sort_by_key(|&((start, _), _)| start);
+
+            for ((start, end), replacement) in edits.into_iter().rev() {
+                content.replace_range(start..end, &replacement);
+            }
+
+            fs::write(&file, content)?;
+        }
+    }
+
+    ensure!(
+        remaining == 0,
+        "{} machine specific value(s) found{}",
+        remaining,
+        if fix {
+            ", they can't be made relative to the app"
+        } else {
+            ", run lint --fix to make paths into the app relative"
+        }
+    );
+
+    if wasm_imports {
+        let foreign = check_wasm_imports()?;
+
+        ensure!(
+            foreign == 0,
+            "{} WASM import(s) found the conductor can't satisfy, drop the crates they come from",
+            foreign
+        );
+    }
+
+    Ok(())
+}
+
+/// Where the JSON string of the finding is in `content`, as byte range
+fn span(content: &str, finding: &Finding) -> Option<(usize, usize)> {
+    let mut raw: &RawValue = serde_json::from_str(content).ok()?;
+
+    for segment in &finding.path {
+        raw = if raw.get().starts_with('{') {
+            let mut obj: HashMap<String, &RawValue> = serde_json::from_str(raw.get()).ok()?;
+            obj.remove(segment)?
+        } else {
+            let items: Vec<&RawValue> = serde_json::from_str(raw.get()).ok()?;
+            *items.get(segment.parse::<usize>().ok()?)?
+        };
+    }
+
+    let start = raw.get().as_ptr() as usize - content.as_ptr() as usize;
+    let end = start + raw.get().len();
+
+    if !finding.is_key {
+        return Some((start, end));
+    }
+
+    // the key is the string in front of the colon in front of the value
+    let before = content[..start].trim_end();
+    let key = serde_json::to_string(finding.path.last()?).ok()?;
+
+    if !before.ends_with(':') {
+        return None;
+    }
+
+    let before = before[..before.len() - 1].trim_end();
+
+    if before.ends_with(&key) {
+        Some((before.len() - key.len(), before.len()))
+    } else {
+        None
+    }
+}
+
+/// The finding's value with its path made relative to `file_dir`, if it points into the app
+fn relative_replacement(finding: &Finding, root: &Path, file_dir: &Path) -> Option<String> {
+    if finding.kind == Kind::Hostname {
+        return None;
+    }
+
+    let (prefix, path) = match finding.value.find('=') {
+        Some(index) => finding.value.split_at(index + 1),
+        None => ("", finding.value.as_str()),
+    };
+    let path = match (path.starts_with("~/"), env::var("HOME")) {
+        (true, Ok(home)) => PathBuf::from(home).join(&path[2..]),
+        _ => PathBuf::from(path),
+    };
+
+    if !path.is_absolute() || !path.starts_with(root) {
+        return None;
+    }
+
+    Some(format!(
+        "{}{}",
+        prefix,
+        relative_path(&path, file_dir).to_string_lossy()
+    ))
+}
+
+/// The path leading from `base` to `path`, both absolute
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+    let mut relative: PathBuf = base[common..].iter().map(|_| "..").collect();
+    relative.extend(&path[common..]);
+
+    if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm::test_support;
+
+    #[test]
+    fn finds_machine_specific_values() {
+        let host = Host {
+            home: Some("/home/alice".to_string()),
+            hostname: Some("alice-laptop".to_string()),
+        };
+        let build = json!({
+            "steps": {
+                "/usr/local/bin/cargo": ["build", "--target-dir=/home/alice/chat/target"]
+            },
+            "artifact": "C:\\chat\\code.wasm",
+            "properties": { "server": "ws://Alice-Laptop:8888", "docs": "https://example.com/a" },
+            "description": "A chat for alice"
+        });
+
+        let findings: Vec<_> = host
+            .check(&build)
+            .into_iter()
+            .map(|finding| (finding.key, finding.kind))
+            .collect();
+
+        assert_eq!(
+            findings,
+            vec![
+                ("artifact".to_string(), Kind::AbsolutePath),
+                ("properties.server".to_string(), Kind::Hostname),
+                ("steps./usr/local/bin/cargo".to_string(), Kind::AbsolutePath),
+                ("steps./usr/local/bin/cargo.1".to_string(), Kind::HomePath),
+            ]
+        );
+
+        let finding = Finding {
+            key: "steps.cargo.1".to_string(),
+            path: vec!["steps".to_string(), "cargo".to_string(), "1".to_string()],
+            is_key: false,
+            value: "--target-dir=/home/alice/chat/target".to_string(),
+            kind: Kind::HomePath,
+        };
+        assert_eq!(
+            relative_replacement(
+                &finding,
+                Path::new("/home/alice/chat"),
+                Path::new("/home/alice/chat/zomes/posts/code")
+            ),
+            Some("--target-dir=../../../target".to_string())
+        );
+        assert_eq!(
+            relative_replacement(
+                &finding,
+                Path::new("/home/alice/other"),
+                Path::new("/home/alice/other/zomes/posts/code")
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn fixes_replace_only_the_flagged_string() {
+        let host = Host {
+            home: Some("/home/alice".to_string()),
+            hostname: None,
+        };
+        let content = r#"{
+            "steps": { "/home/alice/chat/run.sh": ["/home/alice/chat/run.sh"] },
+            "properties": { "script": "/home/alice/chat/run.sh" }
+        }"#;
+
+        let spans: Vec<_> = host
+            .check(&serde_json::from_str(content).unwrap())
+            .iter()
+            .map(|finding| {
+                let (start, end) = span(content, finding).unwrap();
+
+                assert_eq!(&content[start..end], "\"/home/alice/chat/run.sh\"");
+                start
+            })
+            .collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                content.rfind("\"/home").unwrap(),
+                content.find("\"/home").unwrap(),
+                content.find("[\"/home").unwrap() + 1,
+            ]
+        );
+    }
+
+    #[test]
+    fn only_host_functions_may_be_imported() {
+        let wasm = test_support::linked_module(
+            &[
+                "hc_commit_entry",
+                "env/memcpy",
+                "__wbindgen_placeholder__/__wbindgen_describe",
+                "./chat_bg.js/__wbg_log_1234",
+            ],
+            &["main"],
+        );
+        let module = Module::parse(&wasm).unwrap();
+
+        let foreign: Vec<_> = foreign_imports(&module)
+            .into_iter()
+            .map(|import| (import.name.as_str(), is_js_glue(import)))
+            .collect();
+
+        assert_eq!(
+            foreign,
+            vec![
+                ("memcpy", false),
+                ("__wbindgen_describe", true),
+                ("__wbg_log_1234", true),
+            ]
+        );
+    }
+}
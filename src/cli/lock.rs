@@ -0,0 +1,64 @@
+use cli::{layout::Layout, package::CARGO_FILE_NAME};
+use config_files::{CargoLockPolicy, Project};
+use error::DefaultResult;
+use std::path::Path;
+use util;
+
+/// Creates or updates the Cargo.lock of every Rust Zome
+pub fn lock_zomes() -> DefaultResult<()> {
+    let zomes = Project::from_dir(".")?.zomes;
+
+    ensure!(
+        zomes.cargo_lock != CargoLockPolicy::Ignore,
+        "the project ignores Cargo.lock files (zomes.cargo_lock = \"ignore\" in hc.toml)"
+    );
+
+    let code_dirs = Layout::from_config(&zomes)?.code_dirs_with(Path::new("."), CARGO_FILE_NAME)?;
+
+    for code_dir in code_dirs {
+        util::run_cmd(code_dir, "cargo".into(), vec!["generate-lockfile".into()])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::{fs, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn locks_every_rust_zome() {
+        let tmp = Builder::new().prefix("lock").tempdir().unwrap();
+        let code_dir = tmp.path().join("zomes/chat/code");
+
+        fs::create_dir_all(code_dir.join("src")).unwrap();
+        fs::write(
+            code_dir.join("Cargo.toml"),
+            "[package]\nname = \"chat\"\nversion = \"0.1.0\"\n\n[dependencies]\n",
+        ).unwrap();
+        fs::write(code_dir.join("src/lib.rs"), "").unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .args(&["lock", "zomes"])
+            .assert()
+            .success();
+
+        assert!(code_dir.join("Cargo.lock").is_file());
+
+        fs::write(
+            tmp.path().join("hc.toml"),
+            "[zomes]\ncargo_lock = \"ignore\"\n",
+        ).unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .args(&["lock", "zomes"])
+            .assert()
+            .failure();
+    }
+}
@@ -0,0 +1,271 @@
+//! Codesigning and notarization of the native artifacts an app is distributed with on macOS,
+//! like the launcher of a desktop build. Both commands take the artifacts of the `[bundle]`
+//! settings unless paths are given, and read how to sign from the `[macos]` settings:
+//!
+//! ```toml
+//! [bundle]
+//! artifacts = ["desktop/dist/Chat.app"]
+//!
+//! [macos]
+//! identity = "Developer ID Application: Example (TEAMID)"
+//! entitlements = "desktop/entitlements.plist"
+//! notary_profile = "chat-notary"
+//! ```
+//!
+//! The `postpackage` script can then run `hcdev macos sign && hcdev macos notarize`.
+
+use config_files::Project;
+use error::DefaultResult;
+use i18n;
+use interrupt;
+use output;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tempfile::Builder;
+
+/// What `notarytool` accepts besides app bundles, which have to be zipped first
+const SUBMITTABLE_EXTENSIONS: &[&str] = &["dmg", "pkg", "zip"];
+/// What a notarization ticket can be stapled to, zip archives can't hold one
+const STAPLEABLE_EXTENSIONS: &[&str] = &["app", "dmg", "pkg"];
+
+/// Runs the tools of Xcode, or only shows what it would run
+struct Xcode {
+    dry_run: bool,
+}
+
+impl Xcode {
+    fn new(dry_run: bool) -> DefaultResult<Xcode> {
+        ensure!(
+            dry_run || cfg!(target_os = "macos"),
+            "codesign and notarytool only exist on macOS, --dry-run shows the commands anywhere"
+        );
+
+        Ok(Xcode { dry_run })
+    }
+
+    fn run(&self, bin: &str, args: &[&str]) -> DefaultResult<()> {
+        output::command(bin, args.join(" "));
+
+        if self.dry_run {
+            return Ok(());
+        }
+
+        ensure!(
+            interrupt::status(Command::new(bin).args(args))?.success(),
+            "{} {} failed",
+            bin,
+            args[0]
+        );
+
+        Ok(())
+    }
+}
+
+/// Signs the artifacts with the hardened runtime and a secure timestamp, as notarization
+/// requires
+pub fn macos_sign(paths: Vec<PathBuf>, dry_run: bool) -> DefaultResult<()> {
+    let project = Project::from_dir(".")?;
+    let identity = project.macos.identity.clone().ok_or_else(|| {
+        format_err!(
+            "no codesigning identity, set it with e.g. hcdev config set macos.identity \
+             \"Developer ID Application: Example (TEAMID)\""
+        )
+    })?;
+    let xcode = Xcode::new(dry_run)?;
+
+    for path in artifacts(paths, &project, dry_run)? {
+        let path = path.to_string_lossy();
+        let mut args = vec![
+            "--force",
+            "--timestamp",
+            "--options",
+            "runtime",
+            "--sign",
+            &identity,
+        ];
+
+        if let Some(ref entitlements) = project.macos.entitlements {
+            args.extend(&["--entitlements", entitlements]);
+        }
+
+        args.push(&path);
+
+        xcode.run("codesign", &args)?;
+        xcode.run("codesign", &["--verify", "--strict", &path])?;
+
+        output::print(i18n::tr(
+            "signed-artifact",
+            &[
+                ("status", output::status("status-signed")),
+                ("path", path.to_string()),
+                ("identity", identity.clone()),
+            ],
+        ));
+    }
+
+    Ok(())
+}
+
+/// Submits the signed artifacts to Apple's notary service, waits for the verdict and staples
+/// the ticket to them, so Gatekeeper accepts them without a connection as well
+pub fn macos_notarize(paths: Vec<PathBuf>, dry_run: bool) -> DefaultResult<()> {
+    let project = Project::from_dir(".")?;
+    let profile = project.macos.notary_profile.clone().ok_or_else(|| {
+        format_err!(
+            "no notary credentials, store them with xcrun notarytool store-credentials and set \
+             the profile with hcdev config set macos.notary_profile <profile>"
+        )
+    })?;
+    let xcode = Xcode::new(dry_run)?;
+    let tmp = Builder::new().prefix("hcdev-notarize").tempdir()?;
+
+    for path in artifacts(paths, &project, dry_run)? {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let name = path.to_string_lossy();
+
+        let submission = if SUBMITTABLE_EXTENSIONS.contains(&extension.as_str()) {
+            path.clone()
+        } else {
+            let file_name = path
+                .file_name()
+                .ok_or_else(|| format_err!("{:?} has no file name", path))?;
+            let zip = tmp
+                .path()
+                .join(format!("{}.zip", file_name.to_string_lossy()));
+
+            xcode.run(
+                "ditto",
+                &["-c", "-k", "--keepParent", &name, &zip.to_string_lossy()],
+            )?;
+
+            zip
+        };
+
+        xcode.run(
+            "xcrun",
+            &[
+                "notarytool",
+                "submit",
+                &submission.to_string_lossy(),
+                "--keychain-profile",
+                &profile,
+                "--wait",
+            ],
+        )?;
+
+        if STAPLEABLE_EXTENSIONS.contains(&extension.as_str()) {
+            xcode.run("xcrun", &["stapler", "staple", &name])?;
+        }
+
+        output::print(i18n::tr(
+            "notarized-artifact",
+            &[
+                ("status", output::status("status-notarized")),
+                ("path", name.to_string()),
+            ],
+        ));
+    }
+
+    Ok(())
+}
+
+/// The given paths, or else the artifacts of the `[bundle]` settings
+fn artifacts(paths: Vec<PathBuf>, project: &Project, dry_run: bool) -> DefaultResult<Vec<PathBuf>> {
+    let paths = if paths.is_empty() {
+        project.bundle.artifacts.iter().map(PathBuf::from).collect()
+    } else {
+        paths
+    };
+
+    ensure!(
+        !paths.is_empty(),
+        "nothing to do, pass the artifacts or list them in the artifacts of the [bundle] settings"
+    );
+
+    for path in &paths {
+        ensure!(
+            dry_run || Path::new(path).exists(),
+            "{:?} doesn't exist, build it first",
+            path
+        );
+    }
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use serde_json::{self, Value};
+    use std::fs;
+    use util;
+
+    #[test]
+    fn postpackage_scripts_get_the_artifacts_to_sign_and_notarize() {
+        let tmp = Builder::new().prefix("macos").tempdir().unwrap();
+        let home = Builder::new().prefix("macos-home").tempdir().unwrap();
+        let hcdev = |args: &[&str]| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .env("HC_HOME", home.path())
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        fs::write(
+            tmp.path().join("app.json"),
+            "{\"name\": \"chat\", \"description\": \"Chat\"}",
+        )
+        .unwrap();
+        fs::write(tmp.path().join("Chat.dmg"), "image").unwrap();
+        fs::write(
+            tmp.path().join("hc.toml"),
+            "[scripts]\n\
+             postpackage = \"cp $HC_PACKAGE_CONTEXT context.json\"\n\
+             [bundle]\n\
+             artifacts = [\"desktop/Chat.app\", \"Chat.dmg\"]\n\
+             [macos]\n\
+             identity = \"Developer ID Application: Example (TEAMID)\"\n\
+             notary_profile = \"chat-notary\"\n",
+        )
+        .unwrap();
+
+        assert!(hcdev(&["package"]).status.success());
+
+        let context: Value =
+            serde_json::from_slice(&fs::read(tmp.path().join("context.json")).unwrap()).unwrap();
+        let bundle = fs::read(tmp.path().join("bundle.json")).unwrap();
+
+        assert_eq!(context["bundle"]["sha256"], util::sha256_hex(&bundle));
+        assert_eq!(context["artifacts"][0]["kind"], "missing");
+        assert_eq!(
+            context["artifacts"][1]["sha256"],
+            util::sha256_hex(b"image")
+        );
+
+        assert!(!hcdev(&["macos", "sign"]).status.success());
+
+        let signed =
+            String::from_utf8_lossy(&hcdev(&["macos", "sign", "--dry-run"]).stdout).into_owned();
+
+        assert!(signed.contains(
+            "codesign --force --timestamp --options runtime --sign Developer ID Application: \
+             Example (TEAMID) desktop/Chat.app"
+        ));
+
+        let notarized = String::from_utf8_lossy(&hcdev(&["macos", "notarize", "--dry-run"]).stdout)
+            .into_owned();
+
+        assert!(notarized.contains("ditto -c -k --keepParent desktop/Chat.app"));
+        assert!(notarized.contains("notarytool submit Chat.dmg --keychain-profile chat-notary"));
+        assert!(notarized.contains("stapler staple Chat.dmg"));
+    }
+}
@@ -0,0 +1,231 @@
+use cli::{
+    generate::ZOME_CONFIG_FILE_NAME,
+    layout::Layout,
+    license::{project_license, with_license_header},
+};
+use error::DefaultResult;
+use i18n;
+use output;
+use serde_json::{self, Value};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+use zome_api::ZomeApi;
+
+pub const MIGRATIONS_DIR_NAME: &str = "migrations";
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+const ENTRY_TYPES_KEY: &str = "entry_types";
+const MOD_FILE_NAME: &str = "mod.rs";
+
+/// Scaffolds the migration of an entry type to its next schema version and records the new
+/// version in the Zome's manifest
+pub fn generate_migration(zome_path: &PathBuf, entry_type: &str) -> DefaultResult<()> {
+    let config_path = zome_path.join(ZOME_CONFIG_FILE_NAME);
+    let code_dir = Layout::of_app(".")?.code_dir(zome_path);
+
+    ensure!(
+        config_path.is_file() && code_dir.is_dir(),
+        "{:?} is not a Zome, generate one first",
+        zome_path
+    );
+
+    let mut config: Value = serde_json::from_str(&fs::read_to_string(&config_path)?)?;
+
+    let from_version = config[ENTRY_TYPES_KEY][entry_type][SCHEMA_VERSION_KEY]
+        .as_u64()
+        .unwrap_or(1);
+    let to_version = from_version + 1;
+
+    let type_name = ZomeApi::from_code_dir(&code_dir)?
+        .entries
+        .into_iter()
+        .find(|entry| entry.name == entry_type)
+        .and_then(|entry| entry.native_type)
+        .unwrap_or_else(|| camel_case(entry_type));
+
+    let module_name = format!(
+        "{}_v{}_to_v{}",
+        entry_type.replace('-', "_"),
+        from_version,
+        to_version
+    );
+
+    let migrations_dir = code_dir.join("src").join(MIGRATIONS_DIR_NAME);
+    let migration_path = migrations_dir.join(format!("{}.rs", module_name));
+
+    ensure!(
+        !migration_path.exists(),
+        "{:?} already exists",
+        migration_path
+    );
+
+    let code = migration_code(entry_type, &type_name, from_version, to_version);
+    let code = match project_license()? {
+        Some(license) => with_license_header(&code, &license),
+        None => code,
+    };
+
+    fs::create_dir_all(&migrations_dir)?;
+    fs::write(&migration_path, code)?;
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(migrations_dir.join(MOD_FILE_NAME))?
+        .write_all(format!("pub mod {};\n", module_name).as_bytes())?;
+
+    set_schema_version(&mut config, entry_type, to_version)?;
+    serde_json::to_writer_pretty(File::create(&config_path)?, &config)?;
+
+    output::print(i18n::tr(
+        "created-migration",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", migration_path)),
+        ],
+    ));
+
+    Ok(())
+}
+
+fn set_schema_version(config: &mut Value, entry_type: &str, version: u64) -> DefaultResult<()> {
+    let config = config
+        .as_object_mut()
+        .ok_or_else(|| format_err!("the Zome manifest has to be a JSON object"))?;
+
+    let entry = config
+        .entry(ENTRY_TYPES_KEY)
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| {
+            format_err!(
+                "{} in the Zome manifest has to be an object",
+                ENTRY_TYPES_KEY
+            )
+        })?
+        .entry(entry_type)
+        .or_insert_with(|| json!({}));
+
+    ensure!(
+        entry.is_object(),
+        "entry type {} in the Zome manifest has to be an object",
+        entry_type
+    );
+
+    entry[SCHEMA_VERSION_KEY] = version.into();
+
+    Ok(())
+}
+
+fn migration_code(entry_type: &str, type_name: &str, from: u64, to: u64) -> String {
+    format!(
+        r#"//! Migration of `{entry_type}` entries from schema version {from} to {to}.
+//!
+//! Add `mod migrations;` to your lib.rs if it isn't there yet. The structs need `serde`,
+//! `serde_derive` and `serde_json` as dependencies of the Zome.
+
+/// The shape of `{type_name}` at schema version {from}, copy its fields here before changing it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct {type_name}V{from} {{
+}}
+
+/// The shape of `{type_name}` at schema version {to}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct {type_name}V{to} {{
+}}
+
+/// Converts an entry stored at schema version {from} into schema version {to}
+pub fn migrate(old: {type_name}V{from}) -> {type_name}V{to} {{
+    // TODO: carry the fields of the old shape over into the new one
+    let {type_name}V{from} {{}} = old;
+
+    {type_name}V{to} {{}}
+}}
+
+#[cfg(test)]
+mod tests {{
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn migrated_entries_round_trip() {{
+        let old: {type_name}V{from} = serde_json::from_str("{{}}").unwrap();
+
+        let new = migrate(old);
+        let json = serde_json::to_string(&new).unwrap();
+
+        assert_eq!(serde_json::from_str::<{type_name}V{to}>(&json).unwrap(), new);
+    }}
+}}
+"#,
+        entry_type = entry_type,
+        type_name = type_name,
+        from = from,
+        to = to
+    )
+}
+
+fn camel_case(name: &str) -> String {
+    name.split(|c: char| c == '_' || c == '-')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use cli::package::CODE_DIR_NAME;
+    use std::process::Command;
+    use tempfile::Builder;
+
+    #[test]
+    fn scaffolds_migrations_and_bumps_the_schema_version() {
+        let tmp = Builder::new().prefix("migration").tempdir().unwrap();
+        let zome_path = tmp.path().join("zomes").join("blog");
+        let config_path = zome_path.join(ZOME_CONFIG_FILE_NAME);
+
+        fs::create_dir_all(zome_path.join(CODE_DIR_NAME)).unwrap();
+        fs::write(&config_path, "{ \"description\": \"blog\" }").unwrap();
+
+        for _ in 0..2 {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .args(&["generate", "migration", "zomes/blog", "blog_post"])
+                .assert()
+                .success();
+        }
+
+        let migrations_dir = zome_path.join("code/src/migrations");
+        let config: Value =
+            serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+
+        assert_eq!(config["entry_types"]["blog_post"]["schema_version"], 3);
+        assert_eq!(
+            fs::read_to_string(migrations_dir.join("mod.rs")).unwrap(),
+            "pub mod blog_post_v1_to_v2;\npub mod blog_post_v2_to_v3;\n"
+        );
+        assert!(
+            fs::read_to_string(migrations_dir.join("blog_post_v2_to_v3.rs"))
+                .unwrap()
+                .contains("pub fn migrate(old: BlogPostV2) -> BlogPostV3")
+        );
+        assert!(
+            !fs::read_to_string(migrations_dir.join("blog_post_v1_to_v2.rs"))
+                .unwrap()
+                .contains("unimplemented!")
+        );
+    }
+}
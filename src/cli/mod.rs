@@ -1,12 +1,101 @@
 mod agent;
+mod analyze;
+mod assets;
+mod audit;
+mod bench;
+mod build_plan;
+mod cache;
+mod call;
+mod changelog;
+mod compat;
+mod completions;
+mod config;
+mod docs;
+mod doctor;
+mod env;
+mod examples;
+mod explain;
+mod export;
 mod generate;
+mod git_source;
+mod hooks;
+mod identical;
+mod keys;
+mod layout;
+mod license;
+mod lint;
+mod lock;
+mod macos;
+mod migration;
 mod new;
 mod package;
+mod plugin;
+mod prompt;
+mod prune;
+mod redact;
+mod registry;
+mod run;
 mod scaffold;
+mod scaffold_spec;
+mod stats;
+mod status;
+mod symbolize;
+mod test;
+mod tree;
+mod validation;
+mod verify;
+mod watch;
 mod web;
+mod welcome;
+mod workflow;
 
 pub use self::agent::agent;
-pub use self::generate::generate;
-pub use self::new::new;
-pub use self::package::{package, unpack};
+pub use self::analyze::analyze_wasm;
+pub use self::audit::audit_licenses;
+pub use self::bench::bench_self;
+pub use self::build_plan::build_plan;
+pub use self::cache::{cache_explain, cache_status};
+pub use self::call::call;
+pub use self::changelog::generate_changelog;
+pub use self::compat::compat;
+pub use self::completions::{completions, Shell};
+pub use self::config::{config_export, config_get, config_import, config_list, config_set};
+pub use self::docs::docs;
+pub use self::doctor::doctor;
+pub use self::env::env;
+pub use self::examples::{examples, init_example};
+pub use self::explain::explain_bundle;
+pub use self::export::{export, import, Format};
+pub use self::generate::{
+    generate, generate_electron, generate_entry_type, generate_interactive, generate_test,
+    generate_ui_page, zome_language,
+};
+pub use self::hooks::{hooks_install, hooks_run};
+pub use self::identical::identical;
+pub use self::keys::keygen;
+pub use self::license::generate_license;
+pub use self::lint::lint;
+pub use self::lock::lock_zomes;
+pub use self::macos::{macos_notarize, macos_sign};
+pub use self::migration::generate_migration;
+pub use self::new::{find_project_root, new, new_app};
+pub use self::package::{
+    apply_delta, package, unpack, PackageOptions, Profile, BUILD_CONFIG_FILE_NAME,
+    CARGO_LOCK_FILE_NAME,
+};
+pub use self::plugin::{plugin, plugins};
+pub use self::prune::prune;
+pub use self::redact::redact;
+pub use self::registry::{install, search};
+pub use self::run::run;
+pub use self::scaffold_spec::generate_from_spec;
+pub use self::stats::stats_bundle;
+pub use self::status::status;
+pub use self::symbolize::symbolize;
+pub use self::test::test;
+pub use self::tree::tree;
+pub use self::verify::verify;
+pub use self::watch::package_watch;
 pub use self::web::web;
+pub use self::welcome::welcome;
+pub use self::workflow::generate_workflow;
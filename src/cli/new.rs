@@ -1,36 +1,262 @@
-use colored::*;
+use cli::{assets::UI_DIR_NAME, generate::generate, layout::Layout};
 use config_files::App as AppConfig;
 use error::DefaultResult;
+use i18n;
+use output;
 use serde_json;
 use std::{
+    env,
     fs::{self, File},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::Command,
 };
+use util;
 
+pub const APP_CONFIG_FILE_NAME: &str = "app.json";
+
+pub const TESTS_DIR_NAME: &str = "tests";
+
+/// The directories of a new app next to its `app.json`, besides the one of the Zomes
+const APP_DIR_NAMES: &[&str] = &[TESTS_DIR_NAME, "scenarios", UI_DIR_NAME];
+
+/// Finds the root of the app the given directory belongs to, the nearest directory with an
+/// `app.json`, so commands work from anywhere inside an app
+pub fn find_project_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(APP_CONFIG_FILE_NAME).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Sets up an app in the given directory. A directory that already has other content, like a
+/// README or a git repository, is fine as long as nothing of the app is in the way: existing
+/// directories of the app are kept, only files that would be overwritten are collisions.
 pub fn new(path: &PathBuf, _from: &Option<String>) -> DefaultResult<()> {
-    if !path.exists() {
-        fs::create_dir_all(&path)?;
+    let is_empty = !path.exists() || fs::read_dir(&path)?.next().is_none();
+
+    // the settings of a monorepo the app is in may lay it out differently
+    fs::create_dir_all(&path)?;
+
+    let layout = Layout::of_app(path)?;
+    let app_dirs: Vec<_> = Some(layout.zomes_dir().to_path_buf())
+        .into_iter()
+        .chain(APP_DIR_NAMES.iter().map(PathBuf::from))
+        .collect();
+
+    let mut collisions = Vec::new();
+    let mut plan = Vec::new();
+
+    for name in &app_dirs {
+        let dir = path.join(name);
+
+        if dir.is_dir() {
+            plan.push(format!("  keep    {}/ (exists)", name.display()));
+        } else if dir.exists() {
+            collisions.push(dir);
+        } else {
+            plan.push(format!("  create  {}/", name.display()));
+        }
+    }
+
+    if path.join(APP_CONFIG_FILE_NAME).exists() {
+        collisions.push(path.join(APP_CONFIG_FILE_NAME));
     } else {
-        let zomes_dir = fs::read_dir(&path)?;
+        plan.push(format!("  create  {}", APP_CONFIG_FILE_NAME));
+    }
 
-        if zomes_dir.count() > 0 {
-            bail!("directory is not empty");
+    ensure!(
+        collisions.is_empty(),
+        i18n::tr(
+            "init-collisions",
+            &[(
+                "paths",
+                collisions
+                    .iter()
+                    .map(|collision| format!("{:?}", collision))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )]
+        )
+    );
+
+    if !is_empty {
+        output::print(format!(
+            "{:?} isn't empty, leaving its content alone and adding:",
+            path
+        ));
+
+        for line in plan {
+            output::print(line);
         }
     }
 
-    fs::create_dir_all(path.join("zomes"))?;
-    fs::create_dir_all(path.join("tests"))?;
-    fs::create_dir_all(path.join("scenarios"))?;
-    fs::create_dir_all(path.join("ui"))?;
+    for name in &app_dirs {
+        fs::create_dir_all(path.join(name))?;
+    }
+
+    let app_config = AppConfig {
+        name: util::file_name_string(&path.canonicalize()?)?,
+        ..AppConfig::default()
+    };
 
-    let app_config_file = File::create(path.join("app.json"))?;
-    serde_json::to_writer_pretty(app_config_file, &AppConfig::default())?;
+    let app_config_file = File::create(path.join(APP_CONFIG_FILE_NAME))?;
+    serde_json::to_writer_pretty(app_config_file, &app_config)?;
+
+    output::print(i18n::tr(
+        "created-project",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", path)),
+        ],
+    ));
+
+    Ok(())
+}
 
-    println!(
-        "{} new Holochain project at: {:?}",
-        "Created".green().bold(),
+/// Sets up an app in a new directory in one go: the app itself, its first Zome and, if asked
+/// for, a git repository with all of it in an initial commit
+pub fn new_app(
+    path: &PathBuf,
+    zome: Option<String>,
+    language: &str,
+    git: bool,
+) -> DefaultResult<()> {
+    ensure!(
+        !path.exists(),
+        "{:?} already exists, run init to set up an app in an existing directory",
         path
     );
 
+    new(path, &None)?;
+
+    // the scaffolding works on the app in the current directory
+    env::set_current_dir(path)?;
+
+    let layout = Layout::of_app(".")?;
+
+    if let Some(ref zome) = zome {
+        generate(&layout.zome_dir(zome), language, false, &[])?;
+    }
+
+    if git {
+        fs::write(".gitignore", "target/\n")?;
+
+        git_run(&["init", "--quiet"])?;
+        git_run(&["add", "--all"])?;
+        git_run(&["commit", "--quiet", "--message", "Initial commit"])?;
+
+        output::print(i18n::tr(
+            "created-git-repository",
+            &[("status", output::status("status-created"))],
+        ));
+    }
+
+    output::print("");
+    output::print("Next steps:");
+    output::print(format!("  cd {}", path.to_string_lossy()));
+
+    if zome.is_none() {
+        output::print(format!(
+            "  hcdev generate {}/<zome> {}",
+            layout.zomes_dir().display(),
+            language
+        ));
+    }
+
+    output::print("  hcdev package");
+    output::print("  hcdev test");
+
     Ok(())
 }
+
+fn git_run(args: &[&str]) -> DefaultResult<()> {
+    let output = Command::new("git").args(args).output()?;
+
+    ensure!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::{fs, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn initializes_next_to_existing_content() {
+        let tmp = Builder::new().prefix("init").tempdir().unwrap();
+        fs::write(tmp.path().join("README.md"), "# Chat\n").unwrap();
+        fs::create_dir_all(tmp.path().join(".git")).unwrap();
+        fs::create_dir_all(tmp.path().join("tests")).unwrap();
+        fs::write(tmp.path().join("tests/chat.js"), "").unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .args(&["init", "."])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(output.status.success());
+        assert!(stdout.contains("keep    tests/ (exists)"));
+        assert!(stdout.contains("create  app.json"));
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("README.md")).unwrap(),
+            "# Chat\n"
+        );
+        assert!(tmp.path().join("tests/chat.js").is_file());
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .args(&["init", "."])
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("app.json"));
+    }
+
+    #[test]
+    fn sets_up_app_zome_and_repository() {
+        let tmp = Builder::new().prefix("new").tempdir().unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .args(&["new", "chat", "--zome", "posts", "--git"])
+            .env("GIT_AUTHOR_NAME", "Test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "Test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .assert()
+            .success();
+
+        let app = tmp.path().join("chat");
+        assert!(app.join("app.json").is_file());
+        assert!(app.join("zomes/posts/code/Cargo.toml").is_file());
+
+        let log = Command::new("git")
+            .current_dir(&app)
+            .args(&["log", "--format=%s", "--name-only"])
+            .output()
+            .unwrap();
+        let log = String::from_utf8_lossy(&log.stdout);
+
+        assert!(log.starts_with("Initial commit"));
+        assert!(log.contains("zomes/posts/zome.json"));
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .args(&["new", "chat"])
+            .assert()
+            .failure();
+    }
+}
@@ -1,27 +1,89 @@
+use annotations::{self, Annotation, Flavor, Severity};
 use base64;
-use colored::*;
-use config_files::Build;
+use build_cache::{self, Fingerprint};
+use bundle::{self, BundleType, Encoding};
+use cli::{
+    assets::{self, UI_DIR_NAME},
+    docs::{self, ZOMES_DIR_NAME},
+    generate::ZOME_CONFIG_FILE_NAME,
+    git_source::{self, GitSource, SOURCE_KEY},
+    keys,
+    layout::Layout,
+    lint::Host,
+    new::APP_CONFIG_FILE_NAME,
+    validation,
+};
+use config_files::{
+    dirs, Build, CargoLockPolicy, Global, Project, SecretsConfig, UiConfig, UnpackLimits,
+};
+use delta;
+use ed25519_dalek::SigningKey;
+use embed;
+use encryption::{self, Envelope, ENCRYPTED_BUNDLE_KEY};
 use error::DefaultResult;
+use failure::Error;
+use file_lock::FileLock;
+use hcignore::{IgnoreRules, IGNORE_FILE_NAME};
+use i18n;
 use ignore::WalkBuilder;
+use interrupt::PartialFile;
+use json;
+use licenses;
+use msgpack;
+use output;
+use parallel;
+use provenance;
+use remote_cache::RemoteCache;
+use scripts;
+use secrets;
+use semver::Version;
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, MapAccess, Visitor},
+    ser::{Serialize, SerializeMap, Serializer},
+};
 use serde_json::{self, Map, Value};
+use snapshot::Snapshot;
 use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fmt,
     fs::{self, File},
-    io::{Read, Write},
-    path::PathBuf,
+    io::{BufRead, BufReader, Read, Write},
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
 };
+use tempfile::Builder;
 use util;
+use warnings::{self, Warnings};
+use wasm;
+use zome_api::ZomeApi;
 
 pub const CODE_DIR_NAME: &str = "code";
 
 pub const BUILD_CONFIG_FILE_NAME: &str = ".build";
 
 pub const CARGO_FILE_NAME: &str = "Cargo.toml";
-pub const IGNORE_FILE_NAME: &str = ".hcignore";
+pub const CARGO_LOCK_FILE_NAME: &str = "Cargo.lock";
 
 pub const WASM_FILE_EXTENSION: &str = "wasm";
 
 pub const DEFAULT_BUNDLE_FILE_NAME: &str = "bundle.json";
 
+const UNPACK_STAGE_PREFIX: &str = ".hcdev-unpack";
+
+const DEFAULT_UNPACK_MAX_SIZE: u64 = 1 << 30;
+const DEFAULT_UNPACK_MAX_FILE_SIZE: u64 = 256 << 20;
+const DEFAULT_UNPACK_MAX_FILES: u64 = 100_000;
+const DEFAULT_UNPACK_MAX_DEPTH: u64 = 32;
+
+/// The WASM of a Zome that was unpacked with `--flatten-zomes`, and the artifact its build
+/// copies it to
+const PREBUILT_FILE_NAME: &str = "prebuilt.wasm";
+const PREBUILT_ARTIFACT_FILE_NAME: &str = "code.wasm";
+
+/// More files than an app has, walking that many likely means package runs in the wrong place
+const MAX_PROJECT_FILES: usize = 10_000;
+
 pub const META_FILE_ID: &str = "file";
 pub const META_DIR_ID: &str = "dir";
 pub const META_BIN_ID: &str = "bin";
@@ -29,47 +91,481 @@ pub const META_BIN_ID: &str = "bin";
 pub const META_SECTION_NAME: &str = "__META__";
 pub const META_TREE_SECTION_NAME: &str = "tree";
 pub const META_CONFIG_SECTION_NAME: &str = "config_file";
+pub const META_DOCS_SECTION_NAME: &str = "docs";
+pub const META_DEBUG_INFO_SECTION_NAME: &str = "debug_info";
+pub const META_ATTRIBUTION_SECTION_NAME: &str = "attribution";
+pub const META_README_SECTION_NAME: &str = "readme";
+
+/// The keys of a `zome.json` that attribute the Zome. They are for the people using it, not for
+/// the conductor, so the bundle keeps them in the Zome's meta section.
+pub const ATTRIBUTION_KEYS: &[&str] = &["author", "version", "license"];
+
+pub const DEBUG_INFO_FILE_SUFFIX: &str = ".debug.wasm";
+
+/// Stand for the settings of a directory's config file and for all other entries in the order
+/// of the `[bundle]` settings
+pub const ORDER_MANIFEST: &str = "manifest";
+pub const ORDER_REST: &str = "*";
+
+pub const MANIFEST_ENTRY_TYPES_KEY: &str = "entry_types";
+pub const MANIFEST_CAPABILITIES_KEY: &str = "capabilities";
 
 pub type Object = Map<String, Value>;
 
+/// The user supplied settings of a package run
+#[derive(Clone, Default)]
+pub struct PackageOptions {
+    pub strip_meta: bool,
+    pub output: Option<PathBuf>,
+    pub embed_docs: bool,
+    pub deny: Vec<String>,
+    /// Base64 encoded public key the bundle gets encrypted to
+    pub recipient: Option<String>,
+    /// Previous bundle, only the differences to it get packaged
+    pub delta: Option<PathBuf>,
+    /// Requires the Cargo.lock of every Zome to be up to date
+    pub locked: bool,
+    /// Strips the bundled WASM and keeps its debug info in a file next to the bundle
+    pub debug_info: bool,
+    /// Reports errors and warnings as annotations of this CI system
+    pub annotations: Option<Flavor>,
+    pub profile: Option<Profile>,
+    /// Packages the current directory even if it doesn't look like an app
+    pub force: bool,
+    /// Runs the test steps of the Zomes before building them
+    pub with_tests: bool,
+    /// Writes a signed provenance statement next to the bundle
+    pub provenance: bool,
+    /// The key to sign the provenance with instead of the one in the data directory
+    pub signing_key: Option<PathBuf>,
+    /// Builds and checks everything without writing the bundle
+    pub check: bool,
+    /// JSON, or MessagePack or CBOR for smaller bundles that are faster to parse
+    pub format: Encoding,
+}
+
+impl PackageOptions {
+    fn is_locked(&self) -> bool {
+        self.locked || self.profile == Some(Profile::Ci)
+    }
+
+    /// The flavor that was asked for, otherwise the one of the CI system the command runs in
+    fn annotation_flavor(&self) -> Option<Flavor> {
+        let detected = self.annotations.or_else(Flavor::detect);
+
+        match self.profile {
+            Some(Profile::Ci) => detected.or(Some(Flavor::GitHub)),
+            None => detected,
+        }
+    }
+}
+
+/// A set of options for one environment that package runs in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    /// `--locked` with annotations, in the flavor of the detected CI system or else GitHub's
+    Ci,
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Profile, String> {
+        match s {
+            "ci" => Ok(Profile::Ci),
+            _ => Err(format!("unknown profile: {} (expected ci)", s)),
+        }
+    }
+}
+
 struct Packager {
-    strip_meta: bool,
+    options: PackageOptions,
+    warnings: Warnings,
+    cargo_lock: CargoLockPolicy,
+    ui: UiConfig,
+    host: Host,
+    ignore: IgnoreRules,
+    secrets: SecretsConfig,
+    /// The order of the entries in the bundle file, see `OrderedDir`
+    order: Vec<String>,
+    /// What the project builds along with the bundle, for the `postpackage` script
+    artifacts: Vec<String>,
+    /// The build cache shared with the team, if there is one
+    remote_cache: Option<RemoteCache>,
+    /// Where the Zomes of the app are
+    layout: Layout,
+    /// Signs the provenance, which is only recorded if there is a key
+    signing_key: Option<SigningKey>,
+    /// The files the bundle was made of, for the provenance
+    inputs: Vec<PathBuf>,
+    /// The versions of the tools the Zome builds run, for the provenance
+    toolchain: BTreeMap<String, String>,
+    /// Every file read for the bundle, checked to be unchanged before it is written
+    snapshot: Arc<Snapshot>,
+    /// The Zomes whose WASM failed the checks, with what is wrong with it
+    invalid_zomes: Vec<(PathBuf, Vec<String>)>,
 }
 
 impl Packager {
-    fn new(strip_meta: bool) -> Packager {
-        Packager { strip_meta }
+    fn new(
+        options: PackageOptions,
+        warnings: Warnings,
+        cargo_lock: CargoLockPolicy,
+        ignore: IgnoreRules,
+        project: Project,
+        layout: Layout,
+        signing_key: Option<SigningKey>,
+    ) -> Packager {
+        Packager {
+            options,
+            warnings,
+            cargo_lock,
+            layout,
+            ui: project.ui,
+            host: Host::current(),
+            ignore,
+            secrets: project.secrets,
+            order: project.bundle.order,
+            artifacts: project.bundle.artifacts,
+            remote_cache: RemoteCache::from_config(&project.build_cache),
+            signing_key,
+            inputs: Vec::new(),
+            toolchain: BTreeMap::new(),
+            snapshot: Arc::default(),
+            invalid_zomes: Vec::new(),
+        }
+    }
+
+    pub fn package(options: PackageOptions) -> DefaultResult<()> {
+        let output = options
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_BUNDLE_FILE_NAME));
+
+        Packager::from_options(options)?.run(&output)
+    }
+
+    /// Packages the app without writing a bundle file, so there is nothing for the
+    /// `postpackage` script to look at
+    pub fn bundle(options: PackageOptions) -> DefaultResult<Value> {
+        let mut packager = Packager::from_options(options)?;
+
+        scripts::run_hook(&PathBuf::from("."), scripts::PRE_PACKAGE, &[])?;
+
+        Ok(Value::from(packager.build()?))
     }
 
-    pub fn package(strip_meta: bool, output: Option<PathBuf>) -> DefaultResult<()> {
-        let output = output.unwrap_or_else(|| PathBuf::from(DEFAULT_BUNDLE_FILE_NAME));
+    fn from_options(options: PackageOptions) -> DefaultResult<Packager> {
+        if !options.force {
+            check_project_dir(Path::new("."))?;
+        }
+
+        let project = Project::from_dir(".")?;
+
+        let mut deny = options.deny.clone();
+        deny.extend(project.warnings.deny.iter().cloned());
+
+        let cargo_lock = if options.is_locked() {
+            CargoLockPolicy::Locked
+        } else {
+            project.zomes.cargo_lock
+        };
+
+        assets::check_config(&project.ui)?;
+
+        let layout = Layout::from_config(&project.zomes)?;
+
+        let ignore = IgnoreRules::load(Path::new("."))?;
+
+        ensure!(
+            project.secrets.properties.is_empty() || project.secrets.recipient.is_some(),
+            "there are secret properties but no recipient to encrypt them to, set recipient in \
+             the [secrets] settings"
+        );
+
+        // read the key before building, a missing one shouldn't waste a whole build
+        let signing_key = if options.provenance {
+            let path = match options.signing_key {
+                Some(ref path) => path.clone(),
+                None => keys::default_signing_key_path()?,
+            };
+
+            Some(provenance::read_signing_key(path)?)
+        } else {
+            None
+        };
 
-        Packager::new(strip_meta).run(&output)
+        Ok(Packager::new(
+            options,
+            Warnings::new(deny)?,
+            cargo_lock,
+            ignore,
+            project,
+            layout,
+            signing_key,
+        ))
     }
 
-    fn run(&self, output: &PathBuf) -> DefaultResult<()> {
-        let dir_obj_bundle = self.bundle_recurse(&PathBuf::from("."))?;
+    fn run(&mut self, output: &PathBuf) -> DefaultResult<()> {
+        let project_root = PathBuf::from(".");
+        let hook_env = [("HC_BUNDLE_PATH", output.to_string_lossy().into_owned())];
+
+        scripts::run_hook(&project_root, scripts::PRE_PACKAGE, &hook_env)?;
+
+        let dir_obj_bundle = self.build()?;
+
+        if self.options.check {
+            output::print(i18n::tr(
+                "checked-app",
+                &[("status", output::status("status-checked"))],
+            ));
+
+            return Ok(());
+        }
+
+        let bundle = Value::from(dir_obj_bundle);
+
+        let (bundle_type, bundle) = match self.options.delta {
+            Some(ref base_path) => (
+                BundleType::Delta,
+                delta::diff(&read_plain_bundle(base_path)?, &bundle)?,
+            ),
+            None => (BundleType::App, bundle),
+        };
+
+        let (bundle_type, bundle) = match self.options.recipient {
+            Some(ref recipient) => (
+                BundleType::Encrypted,
+                encryption::encrypt(serde_json::to_string(&bundle)?.as_bytes(), recipient)?,
+            ),
+            None => (bundle_type, bundle),
+        };
+
+        let ordered = OrderedDir::new(&bundle, &self.order);
+
+        match self.options.format {
+            Encoding::Json => bundle::write_serialized(&output, bundle_type, &ordered)?,
+            encoding => bundle::write_binary(&output, bundle_type, encoding, ordered.to_binary()?)?,
+        }
+
+        output::print(i18n::tr(
+            "created-bundle",
+            &[
+                ("status", output::status("status-created")),
+                ("path", format!("{:?}", output)),
+            ],
+        ));
+
+        let mut provenance_path = None;
 
-        let out_file = File::create(&output)?;
+        if let Some(ref signing_key) = self.signing_key {
+            let path = provenance::write(output, &self.inputs, &self.toolchain, signing_key)?;
 
-        serde_json::to_writer_pretty(&out_file, &Value::from(dir_obj_bundle))?;
+            output::print(i18n::tr(
+                "created-provenance",
+                &[
+                    ("status", output::status("status-created")),
+                    ("path", format!("{:?}", path)),
+                ],
+            ));
 
-        println!("{} bundle file at {:?}", "Created".green().bold(), output);
+            provenance_path = Some(path);
+        }
+
+        let digest = util::sha256_hex(&fs::read(output)?);
+
+        // only there while the script runs, it is about this run alone
+        let context = Builder::new()
+            .prefix("hcdev-package")
+            .suffix(".json")
+            .tempfile()?;
+
+        serde_json::to_writer_pretty(
+            context.as_file(),
+            &self.package_context(output, &digest, bundle_type, provenance_path)?,
+        )?;
+
+        scripts::run_hook(
+            &project_root,
+            scripts::POST_PACKAGE,
+            &[
+                hook_env[0].clone(),
+                ("HC_BUNDLE_SHA256", digest),
+                (
+                    "HC_PACKAGE_CONTEXT",
+                    context.path().to_string_lossy().into_owned(),
+                ),
+            ],
+        )?;
 
         Ok(())
     }
 
-    fn bundle_recurse(&self, path: &PathBuf) -> DefaultResult<Object> {
+    /// The bundle of the app, built and checked, before it is encoded
+    fn build(&mut self) -> DefaultResult<Object> {
+        let project_root = PathBuf::from(".");
+        let mut dir_obj_bundle = self.bundle_recurse(&project_root)?;
+
+        self.relocate_zomes(&mut dir_obj_bundle)?;
+
+        validation::report(&self.invalid_zomes)?;
+
+        if self.ui.hash_assets || !self.ui.precompress.is_empty() {
+            if let Some(Value::Object(ui)) = dir_obj_bundle.get_mut(UI_DIR_NAME) {
+                assets::prepare(ui, &self.ui)?;
+            }
+        }
+
+        self.warnings.finish()?;
+
+        self.snapshot.check()?;
+
+        Ok(dir_obj_bundle)
+    }
+
+    /// What the `postpackage` script gets to know about the bundle and the artifacts built
+    /// along with it, like native launchers to sign and notarize, with absolute paths
+    fn package_context(
+        &self,
+        output: &Path,
+        digest: &str,
+        bundle_type: BundleType,
+        provenance: Option<PathBuf>,
+    ) -> DefaultResult<Value> {
+        let root = env::current_dir()?;
+        let mut artifacts = Vec::new();
+
+        for artifact in &self.artifacts {
+            let path = root.join(artifact);
+
+            artifacts.push(if path.is_file() {
+                json!({
+                    "path": path,
+                    "kind": "file",
+                    "sha256": util::sha256_hex(&fs::read(&path)?)
+                })
+            } else if path.is_dir() {
+                json!({ "path": path, "kind": "directory" })
+            } else {
+                json!({ "path": path, "kind": "missing" })
+            });
+        }
+
+        Ok(json!({
+            "project_root": root,
+            "bundle": {
+                "path": root.join(output),
+                "type": bundle_type,
+                "format": self.options.format.name(),
+                "size": fs::metadata(output)?.len(),
+                "sha256": digest
+            },
+            "provenance": provenance.map(|path| root.join(path)),
+            "artifacts": artifacts
+        }))
+    }
+
+    /// Fetches the build of a Zome from the remote build cache into its artifact path, if the
+    /// cache has it. A cache that can't be reached or has no valid WASM under the key only warns,
+    /// the Zome is built instead.
+    fn fetch_build(&mut self, key: &str, build: &Build, code_dir: &PathBuf) -> Option<String> {
+        let cache = match self.remote_cache {
+            Some(ref cache) if cache.reads() => cache,
+            _ => return None,
+        };
+
+        let fetched = cache.fetch(key).and_then(|bytes| match bytes {
+            Some(bytes) => {
+                // a truncated upload or the error page of a proxy mustn't end up in the bundle
+                wasm::Module::parse(&bytes)
+                    .map_err(|err| format_err!("the build {} is no valid WASM: {}", key, err))?;
+
+                Ok(Some(write_artifact(build, code_dir, &bytes)?))
+            }
+            None => Ok(None),
+        });
+
+        match fetched {
+            Ok(Some(wasm)) => {
+                output::print(i18n::tr(
+                    "fetched-zome",
+                    &[
+                        ("status", output::status("status-fetched")),
+                        ("path", format!("{:?}", code_dir)),
+                    ],
+                ));
+
+                Some(wasm)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                let message = format!("unable to fetch from {}: {}", cache.url(), err);
+                self.warnings
+                    .warn(warnings::REMOTE_CACHE_UNAVAILABLE, code_dir, message);
+
+                None
+            }
+        }
+    }
+
+    /// Uploads a new build of a Zome to the remote build cache, if it takes uploads
+    fn store_build(&mut self, key: &str, build: &Build, code_dir: &PathBuf) {
+        let cache = match self.remote_cache {
+            Some(ref cache) if cache.writes() => cache,
+            _ => return,
+        };
+
+        let stored = fs::read(code_dir.join(build.output()))
+            .map_err(Error::from)
+            .and_then(|bytes| cache.store(key, &bytes));
+
+        match stored {
+            Ok(()) => output::print(i18n::tr(
+                "uploaded-zome",
+                &[
+                    ("status", output::status("status-uploaded")),
+                    ("path", format!("{:?}", code_dir)),
+                ],
+            )),
+            Err(err) => {
+                let message = format!("unable to upload to {}: {}", cache.url(), err);
+                self.warnings
+                    .warn(warnings::REMOTE_CACHE_UNAVAILABLE, code_dir, message);
+            }
+        }
+    }
+
+    /// Warns about values of a manifest or build file that only work on this machine
+    fn warn_host_specific(&mut self, file: &Path, value: &Value) {
+        for finding in self.host.check(value) {
+            self.warnings.warn(
+                warnings::HOST_SPECIFIC_VALUE,
+                file,
+                format!(
+                    "{:?}: {} is {}: {}",
+                    file, finding.key, finding.kind, finding.value
+                ),
+            );
+        }
+    }
+
+    fn bundle_recurse(&mut self, path: &PathBuf) -> DefaultResult<Object> {
+        // sorted, so the order of the bundle and its manifest don't depend on the file system
         let root_dir = WalkBuilder::new(path)
             .max_depth(Some(1))
-            .add_custom_ignore_filename(IGNORE_FILE_NAME)
+            .sort_by_file_name(|a, b| a.cmp(b))
             .build()
             .skip(1);
 
-        let root: Vec<_> = root_dir
-            .filter(|e| e.is_ok())
-            .map(|e| e.unwrap().path().to_path_buf())
-            .collect();
+        let mut root = Vec::new();
+
+        for node in root_dir.filter_map(|e| e.ok()) {
+            let node = node.path().to_path_buf();
+
+            if !self.ignore.is_ignored(&node, node.is_dir())? {
+                root.push(node);
+            }
+        }
 
         let maybe_json_file_path = root
             .iter()
@@ -77,11 +573,15 @@ impl Packager {
             .find(|e| e.to_str().unwrap().ends_with(".json"));
 
         // Scan files but discard found json file
-        let all_nodes = root.iter().filter(|node_path| {
-            maybe_json_file_path
-                .and_then(|path| Some(node_path != &path))
-                .unwrap_or(true)
-        });
+        let mut all_nodes: Vec<PathBuf> = root
+            .iter()
+            .filter(|node_path| {
+                maybe_json_file_path
+                    .and_then(|path| Some(node_path != &path))
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect();
 
         let mut meta_section = Object::new();
 
@@ -94,26 +594,104 @@ impl Packager {
                 Value::String(file_name.clone()),
             );
 
-            let json_file = fs::read_to_string(json_file_path)?;
+            let json_file = self.snapshot.read(&json_file_path)?;
+
+            if self.signing_key.is_some() {
+                self.inputs.push(json_file_path.clone());
+            }
+            let mut config: Object = json::from_slice(&json_file)
+                .map_err(|err| format_err!("{:?}: {}", json_file_path, err))?;
+
+            let has_description = config
+                .get("description")
+                .and_then(|description| description.as_str())
+                .map_or(false, |description| !description.trim().is_empty());
+
+            if !has_description {
+                self.warnings.warn(
+                    warnings::MISSING_DESCRIPTION,
+                    json_file_path,
+                    format!("manifest {:?} is missing a description", json_file_path),
+                );
+            }
+
+            self.warn_host_specific(json_file_path, &Value::Object(config.clone()));
+
+            if file_name == ZOME_CONFIG_FILE_NAME {
+                let attribution = take_attribution(&mut config)
+                    .map_err(|err| format_err!("{:?}: {}", json_file_path, err))?;
+
+                if !attribution.is_empty() {
+                    meta_section.insert(META_ATTRIBUTION_SECTION_NAME.into(), attribution.into());
+                }
+
+                let readme = all_nodes
+                    .iter()
+                    .filter(|node| node.is_file())
+                    .filter_map(|node| util::file_name_string(node).ok())
+                    .find(|name| name.to_uppercase().starts_with("README"));
+
+                if let Some(readme) = readme {
+                    meta_section.insert(META_README_SECTION_NAME.into(), readme.into());
+                }
+            }
+
+            // only the app's own manifest has secret properties
+            match self.secrets.recipient {
+                Some(ref recipient) if path == Path::new(".") => {
+                    secrets::encrypt_properties(&mut config, &self.secrets.properties, recipient)
+                        .map_err(|err| format_err!("{:?}: {}", json_file_path, err))?;
+                }
+                _ => (),
+            }
 
-            serde_json::from_str(&json_file)?
+            config
         } else {
             Object::new()
         };
 
+        // a Zome can be built from a repository of its own instead of a code directory
+        if let Some(source) = GitSource::from_manifest(&main_tree)
+            .map_err(|err| format_err!("{:?}: {}", path, err))?
+        {
+            ensure!(
+                !self.layout.code_dir(path).exists(),
+                "zome {:?} has both a {} directory and a {}",
+                path,
+                self.layout.code_dir_name(),
+                SOURCE_KEY
+            );
+
+            all_nodes.push(source.code_dir(path, &self.layout)?);
+        }
+
+        // Read and encode the files of the directory on all cores
+        let files: Vec<PathBuf> = all_nodes
+            .iter()
+            .filter(|node| node.is_file())
+            .cloned()
+            .collect();
+        let snapshot = Arc::clone(&self.snapshot);
+        let encoded = parallel::map(files.clone(), move |file| encode_file(&snapshot, &file));
+        let mut encoded_files = HashMap::new();
+
+        for (file, encoded_content) in files.iter().zip(encoded) {
+            encoded_files.insert(file, encoded_content?);
+        }
+
+        if self.signing_key.is_some() {
+            self.inputs.extend(encoded_files.keys().map(|file| file.to_path_buf()));
+        }
+
         // Let's go meta. Way meta!
         let mut meta_tree = Object::new();
 
-        for node in all_nodes {
+        for node in &all_nodes {
             let file_name = util::file_name_string(&node)?;
 
-            if node.is_file() {
+            if let Some(encoded_content) = encoded_files.remove(node) {
                 meta_tree.insert(file_name.clone(), META_FILE_ID.into());
 
-                let mut buf = Vec::new();
-                File::open(node)?.read_to_end(&mut buf)?;
-                let encoded_content = base64::encode(&buf);
-
                 main_tree.insert(file_name.clone(), encoded_content.into());
             } else if node.is_dir() {
                 if let Some(build_config) = node
@@ -122,13 +700,161 @@ impl Packager {
                     .map(|e| e.unwrap().path())
                     .find(|path| path.ends_with(BUILD_CONFIG_FILE_NAME))
                 {
+                    // the conductor finds the WASM of a Zome under code, whatever the directory
+                    let file_name = self.layout.bundle_name(&file_name);
+
                     meta_tree.insert(file_name.clone(), META_BIN_ID.into());
 
-                    let build = Build::from_file(build_config)?;
+                    let build_json: Value = json::from_slice(&fs::read(&build_config)?)
+                        .map_err(|err| format_err!("{:?}: {}", build_config, err))?;
+                    self.warn_host_specific(&build_config, &build_json);
+
+                    let build = Build::from_file(&build_config)?;
+
+                    let build = match self.cargo_lock {
+                        CargoLockPolicy::Use => build,
+                        CargoLockPolicy::Locked => build.locked(),
+                        CargoLockPolicy::Ignore => build.unlocked(),
+                    };
+
+                    // other apps with the same git source build in the same checkout
+                    let _checkout_lock = if git_source::is_checkout(&node)? {
+                        Some(FileLock::acquire(&node)?)
+                    } else {
+                        None
+                    };
+
+                    // written before the fingerprint is taken, so a changed file rebuilds
+                    embed::write_module(&build, &node, &self.snapshot)?;
+
+                    // the tests run even if the build is reused, they aren't part of its fingerprint
+                    if self.options.with_tests {
+                        build.test(&node)?;
+                    }
+
+                    let outputs = [node.join(&build.artifact), node.join(build.output())];
+                    let sources = build_cache::source_files(&node, &outputs);
+
+                    // taken before the fingerprint, so a save during the build fails the package
+                    // instead of leaving the new sources recorded as built. Only the Cargo.lock
+                    // may change, the build writes it itself.
+                    self.snapshot.add(&[build_config.clone()])?;
+                    self.snapshot.add(
+                        &sources
+                            .iter()
+                            .filter(|source| !source.ends_with(CARGO_LOCK_FILE_NAME))
+                            .cloned()
+                            .collect::<Vec<_>>(),
+                    )?;
+
+                    if self.signing_key.is_some() {
+                        self.inputs.push(build_config.clone());
+                        self.inputs.extend(sources);
+                        self.toolchain.extend(build_cache::toolchain(&build, &node));
+                    }
+
+                    // taken before building, which may add files like a Cargo.lock
+                    let fingerprint = Fingerprint::current(&build, &node)?;
+                    let rebuild_reasons = fingerprint.rebuild_reasons(&node)?;
+
+                    let wasm = if rebuild_reasons.is_empty() {
+                        output::print(i18n::tr(
+                            "fresh-zome",
+                            &[
+                                ("status", output::status("status-fresh")),
+                                ("path", format!("{:?}", node)),
+                            ],
+                        ));
+
+                        build.read_artifact(&node)?
+                    } else {
+                        let key = fingerprint.key()?;
+
+                        let wasm = match restore_build(&key, &build, &node)? {
+                            Some(wasm) => wasm,
+                            None => match self.fetch_build(&key, &build, &node) {
+                                Some(wasm) => wasm,
+                                None => {
+                                    let wasm = build.run(&node)?;
+                                    self.store_build(&key, &build, &node);
+                                    wasm
+                                }
+                            },
+                        };
+
+                        build_cache::store(&key, &fs::read(node.join(build.output()))?)?;
+                        build_cache::record(&build, &node)?;
+                        wasm
+                    };
+
+                    let artifact_size = node.join(build.output()).metadata()?.len();
+
+                    if artifact_size > warnings::LARGE_ARTIFACT_THRESHOLD {
+                        self.warnings.warn(
+                            warnings::LARGE_ARTIFACT,
+                            &node.join(build.output()),
+                            format!(
+                                "artifact of {:?} is {} KB large",
+                                path,
+                                artifact_size / 1024
+                            ),
+                        );
+                    }
+
+                    if !path.join(IGNORE_FILE_NAME).exists() {
+                        self.warnings.warn(
+                            warnings::MISSING_HCIGNORE,
+                            path,
+                            format!("zome {:?} has no {} file", path, IGNORE_FILE_NAME),
+                        );
+                    }
+
+                    // checked once the manifest has the functions the code declares
+                    let built = base64::decode(&wasm)?;
+
+                    let wasm = if self.options.debug_info {
+                        let zome_name = util::file_name_string(&path.canonicalize()?)?;
+                        let (wasm, debug_info) = self.split_debug_info(&zome_name, &wasm)?;
 
-                    let wasm = build.run(&node)?;
+                        meta_section.insert(META_DEBUG_INFO_SECTION_NAME.into(), debug_info);
+
+                        wasm
+                    } else {
+                        wasm
+                    };
 
                     main_tree.insert(file_name.clone(), json!({ "code": wasm }));
+
+                    // Keep the manifest in line with what the code actually declares
+                    let api = ZomeApi::from_code_dir(&node)?;
+
+                    if !api.is_empty() {
+                        merge_manifest(
+                            &mut main_tree,
+                            MANIFEST_ENTRY_TYPES_KEY,
+                            api.entry_types_manifest(),
+                        );
+                        merge_manifest(
+                            &mut main_tree,
+                            MANIFEST_CAPABILITIES_KEY,
+                            api.capabilities_manifest(),
+                        );
+                    }
+
+                    let problems = validation::check_zome(&built, &main_tree);
+
+                    if !problems.is_empty() {
+                        self.invalid_zomes.push((path.clone(), problems));
+                    }
+
+                    if self.options.embed_docs {
+                        let zome_name = util::file_name_string(&path.canonicalize()?)?;
+
+                        meta_section.insert(
+                            META_DOCS_SECTION_NAME.into(),
+                            docs::render_markdown(&zome_name, &api).into(),
+                        );
+                    }
                 } else {
                     meta_tree.insert(file_name.clone(), META_DIR_ID.into());
 
@@ -139,7 +865,7 @@ impl Packager {
             }
         }
 
-        if !self.strip_meta {
+        if !self.options.strip_meta {
             if !meta_tree.is_empty() {
                 meta_section.insert(META_TREE_SECTION_NAME.into(), meta_tree.into());
             }
@@ -151,125 +877,994 @@ impl Packager {
 
         Ok(main_tree)
     }
-}
 
-pub fn package(strip_meta: bool, output: Option<PathBuf>) -> DefaultResult<()> {
-    Packager::package(strip_meta, output)
-}
+    /// Moves the Zomes of an app with a Zomes directory other than `zomes` to `zomes` at the top
+    /// of the bundle, where the conductor looks for them
+    fn relocate_zomes(&self, bundle: &mut Object) -> DefaultResult<()> {
+        let zomes_dir = self.layout.zomes_dir();
+        let names: Vec<String> = zomes_dir
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
 
-pub fn unpack(path: &PathBuf, to: &PathBuf) -> DefaultResult<()> {
-    ensure!(path.is_file(), "argument \"path\" doesn't point ot a file");
+        if names == [ZOMES_DIR_NAME] {
+            return Ok(());
+        }
 
-    if !to.exists() {
-        fs::create_dir_all(&to)?;
+        ensure!(
+            !bundle.contains_key(ZOMES_DIR_NAME),
+            "the Zomes are in {:?}, but there is a {} directory as well, which is where they go \
+             in the bundle",
+            zomes_dir,
+            ZOMES_DIR_NAME
+        );
+
+        if let Some(zomes) = take_entry(bundle, &names) {
+            bundle.insert(ZOMES_DIR_NAME.into(), zomes);
+
+            if !self.options.strip_meta {
+                let meta = bundle.entry(META_SECTION_NAME).or_insert_with(|| json!({}));
+                let tree = meta.as_object_mut().map(|meta| {
+                    meta.entry(META_TREE_SECTION_NAME)
+                        .or_insert_with(|| json!({}))
+                });
+
+                if let Some(Value::Object(tree)) = tree {
+                    tree.insert(ZOMES_DIR_NAME.into(), META_DIR_ID.into());
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    ensure!(to.is_dir(), "argument \"to\" doesn't point to a directory");
+    /// Writes the unstripped WASM to `<zome>.debug.wasm` next to the bundle and returns the
+    /// stripped WASM along with the meta section that refers to the debug info file
+    fn split_debug_info(&self, zome_name: &str, wasm: &str) -> DefaultResult<(String, Value)> {
+        let output = self
+            .options
+            .output
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_BUNDLE_FILE_NAME));
+        let debug_file_name = format!("{}{}", zome_name, DEBUG_INFO_FILE_SUFFIX);
+        let debug_path = match output.parent() {
+            Some(parent) => parent.join(&debug_file_name),
+            None => PathBuf::from(&debug_file_name),
+        };
 
-    let raw_bundle_content = fs::read_to_string(&path)?;
-    let bundle_content: Object = serde_json::from_str(&raw_bundle_content)?;
+        let unstripped = base64::decode(wasm)?;
+        let stripped = wasm::strip_custom_sections(&unstripped)?;
+
+        fs::write(&debug_path, &unstripped)?;
+
+        output::print(i18n::tr(
+            "created-debug-info",
+            &[
+                ("status", output::status("status-created")),
+                ("path", format!("{:?}", debug_path)),
+            ],
+        ));
+
+        Ok((
+            base64::encode(&stripped),
+            json!({
+                "file": debug_file_name,
+                "code_sha256": util::sha256_hex(&stripped),
+                "debug_sha256": util::sha256_hex(&unstripped),
+            }),
+        ))
+    }
+}
 
-    unpack_recurse(bundle_content, &to)?;
+/// Inserts an extracted manifest section, keeping what the manifest file adds to it (like
+/// the `schema_version` of entry types)
+fn merge_manifest(tree: &mut Object, key: &str, extracted: Value) {
+    fn merge(existing: &mut Value, extracted: Value) {
+        match (existing, extracted) {
+            (Value::Object(existing), Value::Object(extracted)) => {
+                for (key, value) in extracted {
+                    match existing.get_mut(&key) {
+                        Some(existing_value) => merge(existing_value, value),
+                        None => {
+                            existing.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (existing, extracted) => *existing = extracted,
+        }
+    }
 
-    Ok(())
+    match tree.get_mut(key) {
+        Some(existing) => merge(existing, extracted),
+        None => {
+            tree.insert(key.into(), extracted);
+        }
+    }
 }
 
-fn unpack_recurse(mut obj: Object, to: &PathBuf) -> DefaultResult<()> {
-    if let Some(Value::Object(mut main_meta_obj)) = obj.remove(META_SECTION_NAME) {
-        // unpack the tree
-        if let Some(Value::Object(tree_meta_obj)) = main_meta_obj.remove(META_TREE_SECTION_NAME) {
-            for (meta_entry, meta_value) in tree_meta_obj {
-                let entry = obj
-                    .remove(&meta_entry)
-                    .ok_or_else(|| format_err!("incompatible meta section"))?;
-
-                if let Value::String(node_type) = meta_value {
-                    match node_type.as_str() {
-                        META_FILE_ID if entry.is_string() => {
-                            let base64_content = entry.as_str().unwrap().to_string();
-                            let content = base64::decode(&base64_content)?;
+/// Removes the attribution from the settings of a `zome.json`, checking that the version is a
+/// semantic version and the license an SPDX expression
+fn take_attribution(config: &mut Object) -> DefaultResult<Object> {
+    let mut attribution = Object::new();
 
-                            let mut file_path = to.join(meta_entry);
+    for key in ATTRIBUTION_KEYS {
+        match config.remove(*key) {
+            Some(Value::String(value)) => {
+                attribution.insert(key.to_string(), value.into());
+            }
+            Some(Value::Null) | None => (),
+            Some(value) => bail!("{} has to be a string, not {}", key, value),
+        }
+    }
 
-                            File::create(file_path)?.write_all(&content[..])?;
-                        }
-                        META_BIN_ID if entry.is_object() => {
-                            let base64_content = entry[&meta_entry].to_string();
-                            let content = base64::decode(&base64_content)?;
+    if let Some(version) = attribution.get("version").and_then(Value::as_str) {
+        Version::parse(version)
+            .map_err(|err| format_err!("version {} is no semantic version: {}", version, err))?;
+    }
 
-                            let mut file_path =
-                                to.join(meta_entry).with_extension(WASM_FILE_EXTENSION);
+    if let Some(license) = attribution.get("license").and_then(Value::as_str) {
+        ensure!(
+            licenses::is_known(license),
+            "{} is not an SPDX license expression hcdev knows, see https://spdx.org/licenses/",
+            license
+        );
+    }
 
-                            File::create(file_path)?.write_all(&content[..])?;
-                        }
-                        META_DIR_ID if entry.is_object() => {
-                            let directory_obj = entry.as_object().unwrap();
-                            let dir_path = to.join(meta_entry);
+    Ok(attribution)
+}
 
-                            fs::create_dir(&dir_path)?;
+/// Removes the entry at the path of names from the directory, along with the directories left
+/// with nothing else in them
+fn take_entry(dir: &mut Object, names: &[String]) -> Option<Value> {
+    let (name, rest) = names.split_first()?;
+
+    let taken = if rest.is_empty() {
+        dir.remove(name)?
+    } else {
+        let sub_dir = dir.get_mut(name)?.as_object_mut()?;
+        let taken = take_entry(sub_dir, rest)?;
+        let is_empty = sub_dir.iter().all(|(key, meta)| {
+            key == META_SECTION_NAME
+                && meta.get(META_CONFIG_SECTION_NAME).is_none()
+                && meta
+                    .get(META_TREE_SECTION_NAME)
+                    .and_then(Value::as_object)
+                    .map_or(true, |tree| tree.is_empty())
+        });
 
-                            unpack_recurse(directory_obj.clone(), &dir_path)?;
-                        }
-                        _ => bail!("incompatible meta section"),
-                    }
-                } else {
-                    bail!("incompatible meta section");
-                }
-            }
+        if !is_empty {
+            return Some(taken);
         }
 
-        // unpack the config file
-        if let Some(config_file_meta) = main_meta_obj.remove(META_CONFIG_SECTION_NAME) {
-            ensure!(
-                config_file_meta.is_string(),
-                "config file has to be a string"
-            );
+        dir.remove(name);
+        taken
+    };
 
-            if !obj.is_empty() {
-                let dna_file = File::create(to.join(config_file_meta.as_str().unwrap()))?;
-                serde_json::to_writer_pretty(dna_file, &obj)?;
-            }
+    if let Some(Value::Object(meta)) = dir.get_mut(META_SECTION_NAME) {
+        if let Some(Value::Object(tree)) = meta.get_mut(META_TREE_SECTION_NAME) {
+            tree.remove(name);
         }
     }
 
-    Ok(())
+    Some(taken)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use assert_cmd::prelude::*;
-    use dir_diff;
-    use std::process::Command;
-    use tempfile::{Builder, TempDir};
+pub fn package(options: PackageOptions) -> DefaultResult<()> {
+    annotations::init(options.annotation_flavor());
 
-    const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
+    let result = Packager::package(options);
 
-    fn gen_dir() -> TempDir {
-        Builder::new()
-            .prefix(HOLOCHAIN_TEST_PREFIX)
-            .tempdir()
-            .unwrap()
+    if let Err(ref err) = result {
+        annotations::annotate(Annotation::new(Severity::Error, "package", err.to_string()));
     }
 
-    #[test]
-    fn package_and_unpack_isolated() {
-        const DEFAULT_BUNDLE_FILE_NAME: &str = "bundle.json";
+    annotations::finish()?;
 
-        fn package(shared_file_path: &PathBuf) {
-            let temp_space = gen_dir();
-            let temp_dir_path = temp_space.path();
+    result
+}
 
-            Command::main_binary()
-                .unwrap()
-                .args(&["init", temp_dir_path.to_str().unwrap()])
-                .assert()
-                .success();
+/// Packages the app into a bundle in memory, for commands that hand it on rather than writing
+/// a bundle file
+pub fn package_in_memory(options: PackageOptions) -> DefaultResult<Value> {
+    Packager::bundle(options)
+}
 
-            let bundle_file_path = shared_file_path.join(DEFAULT_BUNDLE_FILE_NAME);
+/// Puts the WASM of an earlier build with the same key into the artifact path of the Zome, if
+/// the local build store still has it
+fn restore_build(key: &str, build: &Build, code_dir: &PathBuf) -> DefaultResult<Option<String>> {
+    let wasm = match build_cache::stored(key)? {
+        Some(wasm) => wasm,
+        None => return Ok(None),
+    };
+
+    output::print(i18n::tr(
+        "restored-zome",
+        &[
+            ("status", output::status("status-restored")),
+            ("path", format!("{:?}", code_dir)),
+        ],
+    ));
+
+    Ok(Some(write_artifact(build, code_dir, &wasm)?))
+}
 
-            Command::main_binary()
-                .unwrap()
+/// Writes WASM built elsewhere to the artifact path of the Zome and returns it encoded
+fn write_artifact(build: &Build, code_dir: &PathBuf, wasm: &[u8]) -> DefaultResult<String> {
+    let artifact = code_dir.join(build.output());
+
+    if let Some(parent) = artifact.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(&artifact, wasm)?;
+
+    build.read_artifact(code_dir)
+}
+
+fn encode_file(snapshot: &Snapshot, path: &Path) -> DefaultResult<String> {
+    Ok(base64::encode(&snapshot.read(path)?))
+}
+
+/// Refuses directories that don't look like an app. Run in the home directory, package would
+/// walk and base64 encode every file in it, which takes ages and ends up in a huge bundle.
+fn check_project_dir(dir: &Path) -> DefaultResult<()> {
+    let dir = dir.canonicalize()?;
+    let guidance = format!(
+        "run package in the directory of an app (see init), or pass --force to package {:?} anyway",
+        dir
+    );
+
+    ensure!(
+        dir.parent().is_some(),
+        "refusing to package the root directory, {}",
+        guidance
+    );
+
+    if let Ok(home) = dirs::home_dir() {
+        ensure!(
+            home.canonicalize().ok().as_ref() != Some(&dir),
+            "refusing to package the home directory, {}",
+            guidance
+        );
+    }
+
+    ensure!(
+        dir.join(APP_CONFIG_FILE_NAME).is_file(),
+        "there is no {} in {:?}, {}",
+        APP_CONFIG_FILE_NAME,
+        dir,
+        guidance
+    );
+
+    let files = WalkBuilder::new(&dir)
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map_or(false, |t| t.is_file()))
+        .take(MAX_PROJECT_FILES + 1)
+        .count();
+
+    ensure!(
+        files <= MAX_PROJECT_FILES,
+        "{:?} has more than {} files, more than an app has, {}",
+        dir,
+        MAX_PROJECT_FILES,
+        guidance
+    );
+
+    Ok(())
+}
+
+/// Unpacks the bundle into the given directory. Nothing is written to it unless the whole
+/// bundle could be unpacked. With `flatten_zomes` every Zome gets a code directory whose build
+/// copies the bundled WASM, so the unpacked app can be packaged again right away.
+pub fn unpack(
+    path: &PathBuf,
+    to: &PathBuf,
+    identity: Option<PathBuf>,
+    flatten_zomes: bool,
+) -> DefaultResult<()> {
+    ensure!(path.is_file(), "argument \"path\" doesn't point ot a file");
+
+    ensure!(
+        !to.exists() || to.is_dir(),
+        "argument \"to\" doesn't point to a directory"
+    );
+
+    // plain bundles don't need one, so a data directory that can't be found doesn't matter
+    let identity = match identity {
+        Some(identity) => Some(identity),
+        None => keys::default_identity_path()
+            .ok()
+            .filter(|path| path.is_file()),
+    };
+
+    // stage next to the target, so moving the result into place doesn't cross file systems
+    let parent = match to.parent() {
+        Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    fs::create_dir_all(&parent)?;
+
+    let mut stream = UnpackStream {
+        budget: UnpackBudget::new(&Global::load_or_default().unpack)?,
+        flatten_zomes,
+        identity,
+        error: None,
+    };
+
+    let stage = Builder::new().prefix(UNPACK_STAGE_PREFIX).tempdir_in(&parent)?;
+    let _partial = PartialFile::new(stage.path());
+
+    let mut reader = BufReader::new(File::open(&path)?);
+    let encoding = Encoding::detect(reader.fill_buf()?);
+
+    let root = StreamedDir {
+        stream: &mut stream,
+        to: stage.path().to_path_buf(),
+        depth: 0,
+        root: true,
+    };
+
+    let result = match encoding {
+        Some(Encoding::MessagePack) | Some(Encoding::Cbor) => {
+            // binary bundles are parsed whole, without base64 they are the smaller ones anyway
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes)?;
+
+            let (_, content) =
+                bundle::from_slice(&bytes).map_err(|err| format_err!("{:?}: {}", path, err))?;
+
+            root.deserialize(content)
+        }
+        _ => {
+            let mut deserializer = serde_json::Deserializer::from_reader(reader);
+
+            root.deserialize(&mut deserializer)
+                .and_then(|()| deserializer.end())
+        }
+    };
+
+    result.map_err(|err| {
+        stream
+            .error
+            .take()
+            .unwrap_or_else(|| format_err!("{:?}: {}", path, err))
+    })?;
+
+    move_into_place(stage.path(), to)
+}
+
+/// Moves the unpacked entries from the staging directory to the target, undoing the moves
+/// that already happened if one fails
+fn move_into_place(stage: &Path, to: &PathBuf) -> DefaultResult<()> {
+    let is_empty = to.is_dir() && fs::read_dir(to)?.next().is_none();
+
+    if is_empty {
+        fs::remove_dir(to)?;
+    }
+
+    if !to.exists() {
+        fs::rename(stage, to)?;
+        return Ok(());
+    }
+
+    let mut names = Vec::new();
+
+    for entry in fs::read_dir(stage)? {
+        let name = entry?.file_name();
+
+        ensure!(
+            !to.join(&name).exists(),
+            "{:?} already exists, refusing to overwrite it",
+            to.join(&name)
+        );
+
+        names.push(name);
+    }
+
+    for (index, name) in names.iter().enumerate() {
+        if let Err(err) = fs::rename(stage.join(name), to.join(name)) {
+            for moved in names[..index].iter().rev() {
+                let _ = fs::rename(to.join(moved), stage.join(moved));
+            }
+
+            bail!("unable to move {:?} into place: {}", to.join(name), err);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reconstructs the full bundle from the bundle a delta was made for and the delta
+pub fn apply_delta(base: &PathBuf, delta: &PathBuf, output: Option<PathBuf>) -> DefaultResult<()> {
+    let output = output.unwrap_or_else(|| PathBuf::from(DEFAULT_BUNDLE_FILE_NAME));
+
+    let (header, delta_bundle) = bundle::read(&delta)?;
+
+    ensure!(
+        header.bundle_type == BundleType::Delta,
+        "{:?} is not a delta bundle",
+        delta
+    );
+
+    let delta = delta::delta(&delta_bundle)?
+        .ok_or_else(|| format_err!("{:?} is missing the delta", delta))?;
+
+    let bundle = delta::apply(&read_plain_bundle(base)?, &delta)?;
+
+    bundle::write(&output, BundleType::App, &bundle)?;
+
+    output::print(i18n::tr(
+        "created-bundle",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", output)),
+        ],
+    ));
+
+    Ok(())
+}
+
+/// Reads a bundle that is neither encrypted nor a delta
+pub fn read_plain_bundle(path: &PathBuf) -> DefaultResult<Value> {
+    let (header, bundle) = bundle::read(&path)?;
+
+    match header.bundle_type {
+        BundleType::App => Ok(bundle),
+        BundleType::Delta => bail!(
+            "{:?} is a delta bundle, reconstruct the full bundle with apply-delta first",
+            path
+        ),
+        BundleType::Encrypted => bail!("{:?} is encrypted, decrypt it with unpack first", path),
+    }
+}
+
+/// Keeps track of what an unpack wrote so far, so a small bundle can't expand to fill the disk
+struct UnpackBudget {
+    max_size: u64,
+    max_file_size: u64,
+    max_files: u64,
+    max_depth: u64,
+    size: u64,
+    files: u64,
+}
+
+impl UnpackBudget {
+    fn new(limits: &UnpackLimits) -> DefaultResult<UnpackBudget> {
+        let size = |limit: &Option<String>, key: &str, default: u64| match limit {
+            Some(limit) => util::parse_size(limit)
+                .map_err(|err| format_err!("invalid unpack.{} in the global config: {}", key, err)),
+            None => Ok(default),
+        };
+
+        Ok(UnpackBudget {
+            max_size: size(&limits.max_size, "max_size", DEFAULT_UNPACK_MAX_SIZE)?,
+            max_file_size: size(
+                &limits.max_file_size,
+                "max_file_size",
+                DEFAULT_UNPACK_MAX_FILE_SIZE,
+            )?,
+            max_files: limits.max_files.unwrap_or(DEFAULT_UNPACK_MAX_FILES),
+            max_depth: limits.max_depth.unwrap_or(DEFAULT_UNPACK_MAX_DEPTH),
+            size: 0,
+            files: 0,
+        })
+    }
+
+    /// Books a file of the given size, failing if it exceeds one of the limits
+    fn add_file(&mut self, path: &Path, size: u64) -> DefaultResult<()> {
+        self.files += 1;
+        self.size += size;
+
+        ensure!(
+            size <= self.max_file_size,
+            "{:?} would take up {}, more than the unpack.max_file_size of {}",
+            path,
+            util::format_size(size),
+            util::format_size(self.max_file_size)
+        );
+        ensure!(
+            self.files <= self.max_files,
+            "the bundle contains more than the unpack.max_files of {} files",
+            self.max_files
+        );
+        ensure!(
+            self.size <= self.max_size,
+            "the bundle would take up more than the unpack.max_size of {}",
+            util::format_size(self.max_size)
+        );
+
+        Ok(())
+    }
+
+    fn enter_dir(&self, path: &Path, depth: u64) -> DefaultResult<()> {
+        ensure!(
+            depth <= self.max_depth,
+            "{:?} is nested deeper than the unpack.max_depth of {} directories",
+            path,
+            self.max_depth
+        );
+
+        Ok(())
+    }
+}
+
+/// The base64 encoded WASM of a built code directory in the bundle
+fn bin_content(entry: &Value) -> DefaultResult<&str> {
+    entry["code"]
+        .as_str()
+        .ok_or_else(|| format_err!("incompatible meta section"))
+}
+
+/// Goes through unpacking the content of a plain bundle without writing anything, failing
+/// wherever `unpack` would
+pub fn check_unpack(content: Object) -> DefaultResult<()> {
+    let mut budget = UnpackBudget::new(&Global::load_or_default().unpack)?;
+
+    unpack_recurse(content, &PathBuf::new(), 0, &mut budget, false, false)
+}
+
+/// The path an entry of the bundle is unpacked to, as long as its name can't point anywhere
+/// outside of the directory
+fn entry_path(dir: &Path, name: &str) -> DefaultResult<PathBuf> {
+    let mut components = Path::new(name).components();
+    let is_plain_name = match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => !name.contains('/') && !name.contains('\\'),
+        _ => false,
+    };
+
+    ensure!(
+        is_plain_name,
+        "the bundle entry {:?} is not a plain file name and would be unpacked outside of {:?}",
+        name,
+        dir
+    );
+
+    Ok(dir.join(name))
+}
+
+fn unpack_recurse(
+    mut obj: Object,
+    to: &PathBuf,
+    depth: u64,
+    budget: &mut UnpackBudget,
+    write: bool,
+    flatten_zomes: bool,
+) -> DefaultResult<()> {
+    if let Some(Value::Object(mut main_meta_obj)) = obj.remove(META_SECTION_NAME) {
+        // unpack the tree
+        if let Some(Value::Object(tree_meta_obj)) = main_meta_obj.remove(META_TREE_SECTION_NAME) {
+            for (meta_entry, meta_value) in tree_meta_obj {
+                let entry = obj
+                    .remove(&meta_entry)
+                    .ok_or_else(|| format_err!("incompatible meta section"))?;
+
+                if let Value::String(node_type) = meta_value {
+                    match node_type.as_str() {
+                        META_FILE_ID if entry.is_string() => {
+                            let base64_content = entry.as_str().unwrap().to_string();
+                            let content = base64::decode(&base64_content)?;
+
+                            let file_path = entry_path(to, &meta_entry)?;
+
+                            budget.add_file(&file_path, content.len() as u64)?;
+
+                            if write {
+                                File::create(file_path)?.write_all(&content[..])?;
+                            }
+                        }
+                        META_BIN_ID if entry.is_object() && flatten_zomes => {
+                            let content = base64::decode(bin_content(&entry)?)?;
+                            let code_dir = entry_path(to, &meta_entry)?;
+                            let prebuilt_path = code_dir.join(PREBUILT_FILE_NAME);
+
+                            budget.enter_dir(&code_dir, depth + 1)?;
+                            budget.add_file(&prebuilt_path, content.len() as u64)?;
+
+                            if write {
+                                fs::create_dir(&code_dir)?;
+                                File::create(prebuilt_path)?.write_all(&content[..])?;
+
+                                Build::with_artifact(PREBUILT_ARTIFACT_FILE_NAME)
+                                    .cmd("cp", &[PREBUILT_FILE_NAME, PREBUILT_ARTIFACT_FILE_NAME])
+                                    .save_as(code_dir.join(BUILD_CONFIG_FILE_NAME))?;
+                            }
+                        }
+                        META_BIN_ID if entry.is_object() => {
+                            let content = base64::decode(bin_content(&entry)?)?;
+
+                            let file_path =
+                                entry_path(to, &meta_entry)?.with_extension(WASM_FILE_EXTENSION);
+
+                            budget.add_file(&file_path, content.len() as u64)?;
+
+                            if write {
+                                File::create(file_path)?.write_all(&content[..])?;
+                            }
+                        }
+                        META_DIR_ID if entry.is_object() => {
+                            let directory_obj = entry.as_object().unwrap();
+                            let dir_path = entry_path(to, &meta_entry)?;
+
+                            budget.enter_dir(&dir_path, depth + 1)?;
+
+                            if write {
+                                fs::create_dir(&dir_path)?;
+                            }
+
+                            unpack_recurse(
+                                directory_obj.clone(),
+                                &dir_path,
+                                depth + 1,
+                                budget,
+                                write,
+                                flatten_zomes,
+                            )?;
+                        }
+                        _ => bail!("incompatible meta section"),
+                    }
+                } else {
+                    bail!("incompatible meta section");
+                }
+            }
+        }
+
+        // the attribution goes back into the zome.json it came from
+        if let Some(Value::Object(attribution)) =
+            main_meta_obj.remove(META_ATTRIBUTION_SECTION_NAME)
+        {
+            obj.extend(attribution);
+        }
+
+        // unpack the config file
+        if let Some(config_file_meta) = main_meta_obj.remove(META_CONFIG_SECTION_NAME) {
+            ensure!(
+                config_file_meta.is_string(),
+                "config file has to be a string"
+            );
+
+            if !obj.is_empty() {
+                let config_path = entry_path(to, config_file_meta.as_str().unwrap())?;
+                let config = serde_json::to_vec_pretty(&obj)?;
+
+                budget.add_file(&config_path, config.len() as u64)?;
+
+                if write {
+                    fs::write(config_path, config)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A directory of the bundle that is written with its entries in the order of the `[bundle]`
+/// settings, so the diffs of bundle files between releases only show what changed. Each
+/// pattern is the name of an entry, `manifest` for the settings of the directory's config file
+/// or `*` for the rest. Entries of the same rank are sorted by name, without an order the
+/// directory is written the way it was packaged, so unpacking it gives back the same files.
+struct OrderedDir<'a> {
+    dir: &'a Value,
+    order: &'a [String],
+}
+
+impl<'a> OrderedDir<'a> {
+    fn new(dir: &'a Value, order: &'a [String]) -> OrderedDir<'a> {
+        OrderedDir { dir, order }
+    }
+
+    fn rank(&self, key: &str, tree: Option<&Object>) -> usize {
+        let is_entry =
+            key == META_SECTION_NAME || tree.map_or(false, |tree| tree.contains_key(key));
+        let position = |pattern: &str| self.order.iter().position(|other| other == pattern);
+
+        position(key)
+            .filter(|_| is_entry)
+            .or_else(|| position(ORDER_MANIFEST).filter(|_| !is_entry))
+            .or_else(|| position(ORDER_REST))
+            .unwrap_or_else(|| self.order.len())
+    }
+
+    /// The directory, its tree and its keys in order, if it has a meta section
+    fn sorted(&self) -> Option<(&'a Object, Option<&'a Object>, Vec<&'a String>)> {
+        let dir = match self.dir {
+            Value::Object(dir) if dir.contains_key(META_SECTION_NAME) => dir,
+            _ => return None,
+        };
+
+        let tree = dir[META_SECTION_NAME]
+            .get(META_TREE_SECTION_NAME)
+            .and_then(Value::as_object);
+
+        // by name first, serde_json may keep the keys in the order they were inserted, then by
+        // rank, which is a stable sort and leaves the names of a rank sorted
+        let mut keys: Vec<&String> = dir.keys().collect();
+        keys.sort();
+        keys.sort_by_key(|key| self.rank(key, tree));
+
+        Some((dir, tree, keys))
+    }
+
+    /// Converts the directory for the binary encodings, which hold the files and WASM its tree
+    /// lists as raw bytes instead of base64
+    fn to_binary(&self) -> DefaultResult<msgpack::Value> {
+        let (dir, tree, keys) = match self.sorted() {
+            Some(sorted) => sorted,
+            None => return Ok(msgpack::Value::from(self.dir)),
+        };
+
+        let mut entries = Vec::new();
+
+        for key in keys {
+            let entry = &dir[key];
+            let binary = match tree.and_then(|tree| tree.get(key)).and_then(Value::as_str) {
+                Some(META_DIR_ID) => OrderedDir::new(entry, self.order).to_binary()?,
+                Some(META_FILE_ID) => raw_bytes(entry),
+                Some(META_BIN_ID) => match entry {
+                    Value::Object(bin) => msgpack::Value::Map(
+                        bin.iter()
+                            .map(|(name, code)| (name.as_str().into(), raw_bytes(code)))
+                            .collect(),
+                    ),
+                    _ => raw_bytes(entry),
+                },
+                _ => msgpack::Value::from(entry),
+            };
+
+            entries.push((key.as_str().into(), binary));
+        }
+
+        Ok(msgpack::Value::Map(entries))
+    }
+}
+
+/// The bytes of a base64 string, for a string that reads back the same after encoding them
+/// again, and the value as it is otherwise
+fn raw_bytes(value: &Value) -> msgpack::Value {
+    match value
+        .as_str()
+        .and_then(|encoded| base64::decode(encoded).ok().map(|bytes| (encoded, bytes)))
+    {
+        Some((encoded, bytes)) if base64::encode(&bytes) == encoded => {
+            msgpack::Value::Binary(bytes)
+        }
+        _ => msgpack::Value::from(value),
+    }
+}
+
+impl<'a> Serialize for OrderedDir<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (dir, tree, keys) = match self.sorted() {
+            Some(sorted) if !self.order.is_empty() => sorted,
+            _ => return self.dir.serialize(serializer),
+        };
+
+        let mut map = serializer.serialize_map(Some(keys.len()))?;
+
+        for key in keys {
+            match tree.and_then(|tree| tree.get(key)) {
+                Some(node_type) if node_type == META_DIR_ID => {
+                    map.serialize_entry(key, &OrderedDir::new(&dir[key], self.order))?
+                }
+                _ => map.serialize_entry(key, &dir[key])?,
+            }
+        }
+
+        map.end()
+    }
+}
+
+/// What the directories of a bundle share while it is unpacked by `StreamedDir`
+struct UnpackStream {
+    budget: UnpackBudget,
+    flatten_zomes: bool,
+    identity: Option<PathBuf>,
+    /// The error that stopped the unpack, serde passes on only its message
+    error: Option<Error>,
+}
+
+impl UnpackStream {
+    fn fail<E: de::Error>(&mut self, err: Error) -> E {
+        let message = err.to_string();
+
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+
+        E::custom(message)
+    }
+}
+
+/// Unpacks a directory of the bundle while it is parsed, instead of parsing the whole bundle
+/// into memory first. Entries the meta section lists before they come up are written right
+/// away, so only one file at a time is in memory. The packager writes the entries sorted by
+/// name, the few that sort before `__META__` are kept until it was read.
+struct StreamedDir<'a> {
+    stream: &'a mut UnpackStream,
+    to: PathBuf,
+    depth: u64,
+    /// The root also holds the header, the envelope of encrypted bundles and the app's
+    /// settings with their secret properties
+    root: bool,
+}
+
+impl<'a> StreamedDir<'a> {
+    fn unpack_encrypted(&mut self, envelope: &Envelope) -> DefaultResult<()> {
+        let identity = self.stream.identity.clone().ok_or_else(|| {
+            format_err!("bundle is encrypted, pass the identity to decrypt it with --identity")
+        })?;
+
+        let plaintext = encryption::decrypt(envelope, &encryption::read_identity(identity)?)?;
+        let mut deserializer = serde_json::Deserializer::from_slice(&plaintext);
+
+        StreamedDir {
+            stream: &mut *self.stream,
+            to: self.to.clone(),
+            depth: self.depth,
+            root: true,
+        }
+        .deserialize(&mut deserializer)?;
+
+        Ok(deserializer.end()?)
+    }
+
+    /// Unpacks the entries the meta section lists and writes the config file from the rest
+    fn unpack_rest(&mut self, mut rest: Object) -> DefaultResult<()> {
+        // without an identity the secret properties stay encrypted in the unpacked app.json
+        match self.stream.identity {
+            Some(ref identity) if self.root && secrets::has_encrypted_properties(&rest) => {
+                secrets::decrypt_properties(&mut rest, &encryption::read_identity(identity)?)?;
+            }
+            _ => (),
+        }
+
+        unpack_recurse(
+            rest,
+            &self.to,
+            self.depth,
+            &mut self.stream.budget,
+            true,
+            self.stream.flatten_zomes,
+        )
+    }
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for StreamedDir<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for StreamedDir<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a directory of a Holochain bundle")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        let mut keys = HashSet::new();
+        let mut meta: Option<Object> = None;
+        let mut tree = Object::new();
+        let mut rest = Object::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            if !keys.insert(key.clone()) {
+                return Err(de::Error::custom(format!("duplicate key {}", key)));
+            }
+
+            let result = match key.as_str() {
+                bundle::HEADER_KEY if self.root => bundle::check_version(&map.next_value()?),
+                ENCRYPTED_BUNDLE_KEY if self.root => self.unpack_encrypted(&map.next_value()?),
+                delta::DELTA_BUNDLE_KEY if self.root => Err(format_err!(
+                    "this is a delta bundle, reconstruct the full bundle with apply-delta first"
+                )),
+                META_SECTION_NAME => {
+                    let mut section: Object = map.next_value()?;
+
+                    if let Some(Value::Object(section_tree)) =
+                        section.remove(META_TREE_SECTION_NAME)
+                    {
+                        tree = section_tree;
+                    }
+
+                    meta = Some(section);
+                    Ok(())
+                }
+                _ => match tree.remove(&key) {
+                    Some(Value::String(ref node_type)) if node_type == META_DIR_ID => {
+                        let dir_path =
+                            entry_path(&self.to, &key).map_err(|err| self.stream.fail(err))?;
+
+                        self.stream
+                            .budget
+                            .enter_dir(&dir_path, self.depth + 1)
+                            .and_then(|()| Ok(fs::create_dir(&dir_path)?))
+                            .map_err(|err| self.stream.fail(err))?;
+
+                        map.next_value_seed(StreamedDir {
+                            stream: &mut *self.stream,
+                            to: dir_path,
+                            depth: self.depth + 1,
+                            root: false,
+                        })?;
+                        Ok(())
+                    }
+                    Some(node_type) => {
+                        let mut entry = Object::new();
+
+                        entry.insert(key.clone(), map.next_value()?);
+                        entry.insert(
+                            META_SECTION_NAME.into(),
+                            json!({ META_TREE_SECTION_NAME: { key: node_type } }),
+                        );
+
+                        self.unpack_rest(entry)
+                    }
+                    None => {
+                        rest.insert(key, map.next_value()?);
+                        Ok(())
+                    }
+                },
+            };
+
+            result.map_err(|err| self.stream.fail(err))?;
+        }
+
+        // what came before the meta section is unpacked now, like the config file
+        if let Some(mut section) = meta {
+            section.insert(META_TREE_SECTION_NAME.into(), tree.into());
+            rest.insert(META_SECTION_NAME.into(), section.into());
+        }
+
+        self.unpack_rest(rest).map_err(|err| self.stream.fail(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use dir_diff;
+    use std::process::Command;
+    use tempfile::{Builder, TempDir};
+
+    const HOLOCHAIN_TEST_PREFIX: &str = "org.holochain.test";
+
+    fn gen_dir() -> TempDir {
+        Builder::new()
+            .prefix(HOLOCHAIN_TEST_PREFIX)
+            .tempdir()
+            .unwrap()
+    }
+
+    #[test]
+    fn package_and_unpack_isolated() {
+        const DEFAULT_BUNDLE_FILE_NAME: &str = "bundle.json";
+
+        fn package(shared_file_path: &PathBuf) {
+            let temp_space = gen_dir();
+            let temp_dir_path = temp_space.path();
+
+            Command::main_binary()
+                .unwrap()
+                .args(&["init", temp_dir_path.to_str().unwrap()])
+                .assert()
+                .success();
+
+            let bundle_file_path = shared_file_path.join(DEFAULT_BUNDLE_FILE_NAME);
+
+            Command::main_binary()
+                .unwrap()
+                .env("HC_HOME", shared_file_path.join("home"))
                 .args(&["package", "-o", bundle_file_path.to_str().unwrap()])
                 .current_dir(&temp_dir_path)
                 .assert()
@@ -326,6 +1921,7 @@ mod tests {
 
         Command::main_binary()
             .unwrap()
+            .env("HC_HOME", root_path.join("home"))
             .args(&["package", "-o", bundle_file_path.to_str().unwrap()])
             .current_dir(&source_path)
             .assert()
@@ -349,28 +1945,741 @@ mod tests {
     }
 
     #[test]
-    fn auto_compilation() {
+    fn refuses_directories_that_are_no_app() {
         let tmp = gen_dir();
+        let home = gen_dir();
 
-        Command::main_binary()
-            .unwrap()
-            .current_dir(&tmp.path())
-            .args(&["init", "."])
-            .assert()
-            .success();
+        assert!(check_project_dir(tmp.path()).is_err());
 
         Command::main_binary()
             .unwrap()
             .current_dir(&tmp.path())
-            .args(&["g", "zomes/bubblechat", "rust"])
+            .env("HC_HOME", home.path())
+            .args(&["package", "-o", "out.json"])
+            .assert()
+            .failure();
+
+        fs::write(tmp.path().join(APP_CONFIG_FILE_NAME), "{}").unwrap();
+
+        assert!(check_project_dir(tmp.path()).is_ok());
+        assert!(check_project_dir(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn runs_package_scripts() {
+        let tmp = gen_dir();
+        let home = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["init", "."])
+            .assert()
+            .success();
+
+        fs::write(
+            tmp.path().join("hc.toml"),
+            "[scripts]\npostpackage = \"cp $HC_BUNDLE_PATH copy.json\"\n",
+        ).unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .env("HC_HOME", home.path())
+            .args(&["package", "-o", "out.json"])
+            .assert()
+            .success();
+
+        assert!(tmp.path().join("copy.json").is_file());
+    }
+
+    #[test]
+    fn runs_the_zome_tests_before_building() {
+        let tmp = gen_dir();
+        let code_dir = tmp.path().join("zomes/chat/code");
+
+        fs::create_dir_all(&code_dir).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        fs::write(
+            code_dir.join(".build"),
+            r#"{
+                "steps": { "sh": ["-c", "printf '\\0asm\\1\\0\\0\\0' > code.wasm"] },
+                "tests": { "touch": ["../tested"] },
+                "artifact": "code.wasm"
+            }"#,
+        ).unwrap();
+
+        let package = |args: &[&str]| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(&tmp.path())
+                .env("HC_HOME", tmp.path().join("home"))
+                .arg("package")
+                .args(args)
+                .assert()
+                .success();
+        };
+
+        package(&[]);
+        assert!(!tmp.path().join("zomes/chat/tested").exists());
+
+        // the build is reused, the tests still run
+        package(&["--with-tests"]);
+        assert!(tmp.path().join("zomes/chat/tested").is_file());
+    }
+
+    #[test]
+    fn zome_attribution_travels_in_the_meta_section() {
+        let tmp = gen_dir();
+        let zome_dir = tmp.path().join("zomes/posts");
+        let manifest = json!({
+            "description": "Posts",
+            "author": "Alice <alice@example.com>",
+            "version": "0.2.0",
+            "license": "MIT"
+        });
+
+        fs::create_dir_all(zome_dir.join("code")).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        fs::write(zome_dir.join("README.md"), "# Posts\n").unwrap();
+        fs::write(zome_dir.join("zome.json"), manifest.to_string()).unwrap();
+        fs::write(
+            zome_dir.join("code/.build"),
+            r#"{ "steps": { "sh": ["-c", "printf '\\0asm\\1\\0\\0\\0' > code.wasm"] }, "artifact": "code.wasm" }"#,
+        ).unwrap();
+
+        let hcdev = |args: &[&str]| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(&tmp.path())
+                .env("HC_HOME", tmp.path().join("home"))
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        assert!(hcdev(&["package"]).status.success());
+
+        let bundle = read_plain_bundle(&tmp.path().join(DEFAULT_BUNDLE_FILE_NAME)).unwrap();
+        let zome = &bundle["zomes"]["posts"];
+
+        assert_eq!(zome["description"], "Posts");
+        assert!(zome.get("license").is_none());
+        let meta = &zome[META_SECTION_NAME];
+
+        assert_eq!(meta[META_ATTRIBUTION_SECTION_NAME]["version"], "0.2.0");
+        assert_eq!(meta[META_README_SECTION_NAME], "README.md");
+
+        assert!(hcdev(&["unpack", DEFAULT_BUNDLE_FILE_NAME, "unpacked"])
+            .status
+            .success());
+
+        let unpacked: Value = serde_json::from_slice(
+            &fs::read(tmp.path().join("unpacked/zomes/posts/zome.json")).unwrap(),
+        ).unwrap();
+
+        assert_eq!(unpacked, manifest);
+
+        fs::write(
+            zome_dir.join("zome.json"),
+            json!({ "description": "Posts", "version": "two" }).to_string(),
+        ).unwrap();
+
+        let output = hcdev(&["package"]);
+
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("no semantic version"));
+    }
+
+    #[test]
+    fn refuses_files_that_change_while_packaging() {
+        let tmp = gen_dir();
+        let code_dir = tmp.path().join("zomes/chat/code");
+
+        fs::create_dir_all(&code_dir).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        fs::write(tmp.path().join("zomes/chat/zome.json"), "{}").unwrap();
+        // like an editor saving the manifest while the Zome builds
+        fs::write(
+            code_dir.join(".build"),
+            r#"{
+                "steps": { "sh": ["-c", "echo ' ' >> ../zome.json; printf '\\0asm\\1\\0\\0\\0' > code.wasm"] },
+                "artifact": "code.wasm"
+            }"#,
+        ).unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["package"])
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("zome.json"));
+
+        assert!(!tmp.path().join(DEFAULT_BUNDLE_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn refuses_zomes_whose_wasm_would_fail_in_the_conductor() {
+        let tmp = gen_dir();
+        let manifest = json!({
+            "capabilities": {
+                "main": { "capability": { "membrane": "public" }, "functions": [{ "name": "post" }] }
+            }
+        });
+
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+
+        for (zome, wasm) in &[("chat", "not wasm"), ("posts", "\\0asm\\1\\0\\0\\0")] {
+            let code_dir = tmp.path().join("zomes").join(zome).join("code");
+
+            fs::create_dir_all(&code_dir).unwrap();
+            fs::write(code_dir.join("../zome.json"), manifest.to_string()).unwrap();
+            fs::write(
+                code_dir.join(".build"),
+                json!({
+                    "steps": { "sh": ["-c", format!("printf '{}' > code.wasm", wasm)] },
+                    "artifact": "code.wasm"
+                }).to_string(),
+            ).unwrap();
+        }
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["package"])
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(!output.status.success());
+        assert!(stderr.contains("zomes/chat\":\n  - its WASM can't be parsed"));
+        assert!(stderr.contains("zomes/posts\":\n  - doesn't export post"));
+        assert!(stderr.contains("the WASM of 2 zome(s)"));
+
+        assert!(!tmp.path().join(DEFAULT_BUNDLE_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn quiet_packaging_prints_nothing() {
+        let tmp = gen_dir();
+        let home = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["--quiet", "init", "."])
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .env("HC_HOME", home.path())
+            .args(&["package", "-q", "-o", "out.json"])
+            .assert()
+            .success()
+            .stdout("")
+            .stderr("");
+
+        assert!(tmp.path().join("out.json").is_file());
+    }
+
+    #[test]
+    fn denied_warnings_fail_packaging() {
+        let tmp = gen_dir();
+        let app_path = tmp.path().join("app");
+
+        Command::main_binary()
+            .unwrap()
+            .args(&["init", app_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        fs::write(app_path.join("app.json"), "{}").unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&app_path)
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["package", "-o", "../warned.json"])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&app_path)
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["package", "--deny", "missing-description"])
+            .assert()
+            .failure();
+    }
+
+    #[test]
+    fn encrypted_bundles_need_the_identity() {
+        let tmp = gen_dir();
+        let app_path = tmp.path().join("app");
+        let identity_path = tmp.path().join("identity.key");
+        let bundle_path = tmp.path().join("bundle.json");
+
+        Command::main_binary()
+            .unwrap()
+            .args(&["init", app_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        let keygen_output = Command::main_binary()
+            .unwrap()
+            .args(&["keygen", identity_path.to_str().unwrap()])
+            .output()
+            .unwrap();
+        let public_key = String::from_utf8(keygen_output.stdout)
+            .unwrap()
+            .lines()
+            .last()
+            .unwrap()
+            .rsplit(' ')
+            .next()
+            .unwrap()
+            .to_string();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&app_path)
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["package", "-o", bundle_path.to_str().unwrap()])
+            .args(&["--encrypt", "--recipient", &public_key])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .args(&["unpack", bundle_path.to_str().unwrap()])
+            .arg(tmp.path().join("without_identity"))
+            .assert()
+            .failure();
+
+        Command::main_binary()
+            .unwrap()
+            .args(&["unpack", bundle_path.to_str().unwrap()])
+            .arg(tmp.path().join("with_identity"))
+            .args(&["--identity", identity_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        assert!(tmp.path().join("with_identity").join("app.json").is_file());
+    }
+
+    #[test]
+    fn failed_unpack_leaves_nothing_behind() {
+        let tmp = gen_dir();
+        let bundle_path = tmp.path().join("broken.json");
+        let existing_path = tmp.path().join("existing");
+
+        fs::write(
+            &bundle_path,
+            json!({
+                META_SECTION_NAME: {
+                    META_TREE_SECTION_NAME: { "a.txt": META_FILE_ID, "b.txt": META_FILE_ID }
+                },
+                "a.txt": "aGVsbG8=",
+                "b.txt": "not base64!"
+            }).to_string(),
+        ).unwrap();
+
+        fs::create_dir(&existing_path).unwrap();
+        fs::write(existing_path.join("keep.txt"), "keep").unwrap();
+
+        for target in &["fresh", "existing"] {
+            Command::main_binary()
+                .unwrap()
+                .args(&["unpack", bundle_path.to_str().unwrap()])
+                .arg(tmp.path().join(target))
+                .assert()
+                .failure();
+        }
+
+        let mut entries: Vec<_> = fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        entries.sort();
+
+        assert_eq!(entries, vec!["broken.json", "existing"]);
+        assert_eq!(
+            fs::read_dir(&existing_path).unwrap().count(),
+            1,
+            "nothing was added to the existing directory"
+        );
+    }
+
+    #[test]
+    fn streamed_unpack_writes_entries_before_and_after_the_meta_section() {
+        let tmp = gen_dir();
+        let bundle_path = tmp.path().join("bundle.json");
+        let unpacked_path = tmp.path().join("unpacked");
+
+        // serialized sorted by name, so README.md comes before the meta section
+        let bundle = json!({
+            bundle::HEADER_KEY: { "version": bundle::FORMAT_VERSION, "type": "app" },
+            "README.md": base64::encode("read me"),
+            META_SECTION_NAME: {
+                META_TREE_SECTION_NAME: { "README.md": META_FILE_ID, "zomes": META_DIR_ID },
+                META_CONFIG_SECTION_NAME: APP_CONFIG_FILE_NAME
+            },
+            "name": "chat",
+            "zomes": {
+                META_SECTION_NAME: { META_TREE_SECTION_NAME: { "a.txt": META_FILE_ID } },
+                "a.txt": base64::encode("a")
+            }
+        });
+
+        fs::write(&bundle_path, bundle.to_string()).unwrap();
+
+        unpack(&bundle_path, &unpacked_path, None, false).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(unpacked_path.join("README.md")).unwrap(),
+            "read me"
+        );
+        assert_eq!(
+            fs::read_to_string(unpacked_path.join("zomes/a.txt")).unwrap(),
+            "a"
+        );
+
+        let config: Value =
+            serde_json::from_slice(&fs::read(unpacked_path.join(APP_CONFIG_FILE_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(config, json!({ "name": "chat" }));
+
+        fs::write(&bundle_path, r#"{"name": "chat", "name": "chat"}"#).unwrap();
+
+        let err = unpack(&bundle_path, &tmp.path().join("duplicate"), None, false)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("duplicate key name"), "{}", err);
+    }
+
+    #[test]
+    fn binary_bundles_hold_files_as_raw_bytes_and_unpack_alike() {
+        let tmp = gen_dir();
+        let wasm = vec![0u8, 97, 115, 109, 1, 0, 0, 0];
+        let bundle = json!({
+            META_SECTION_NAME: {
+                META_TREE_SECTION_NAME: { "README.md": META_FILE_ID, "zomes": META_DIR_ID },
+                META_CONFIG_SECTION_NAME: APP_CONFIG_FILE_NAME
+            },
+            "README.md": base64::encode("read me"),
+            "name": "chat",
+            "zomes": {
+                META_SECTION_NAME: {
+                    META_TREE_SECTION_NAME: { "code": META_BIN_ID },
+                    META_CONFIG_SECTION_NAME: "zome.json"
+                },
+                "code": { "code": base64::encode(&wasm) },
+                "description": "posts"
+            }
+        });
+
+        let json_path = tmp.path().join("bundle.json");
+        let from_json = tmp.path().join("json");
+
+        bundle::write(&json_path, BundleType::App, &bundle).unwrap();
+        unpack(&json_path, &from_json, None, false).unwrap();
+
+        for (encoding, name) in &[(Encoding::MessagePack, "msgpack"), (Encoding::Cbor, "cbor")] {
+            let binary_path = tmp.path().join(format!("bundle.{}", name));
+            let binary = OrderedDir::new(&bundle, &[]).to_binary().unwrap();
+
+            bundle::write_binary(&binary_path, BundleType::App, *encoding, binary).unwrap();
+
+            let raw = fs::read(&binary_path).unwrap();
+            assert!(raw.len() < fs::read(&json_path).unwrap().len());
+            assert!(raw.windows(wasm.len()).any(|window| window == &wasm[..]));
+            assert_eq!(read_plain_bundle(&binary_path).unwrap(), bundle);
+
+            let unpacked_path = tmp.path().join(name);
+            unpack(&binary_path, &unpacked_path, None, false).unwrap();
+
+            assert!(!dir_diff::is_different(&unpacked_path, &from_json).unwrap());
+        }
+    }
+
+    #[test]
+    fn bundle_entries_follow_the_configured_order() {
+        let bundle = json!({
+            "name": "chat",
+            "description": "Chat",
+            META_SECTION_NAME: {
+                META_TREE_SECTION_NAME: {
+                    "ui": META_DIR_ID,
+                    "zomes": META_DIR_ID,
+                    "README": META_FILE_ID
+                }
+            },
+            "README": "",
+            "ui": { META_SECTION_NAME: { META_TREE_SECTION_NAME: {} } },
+            "zomes": {
+                META_SECTION_NAME: {
+                    META_TREE_SECTION_NAME: { "posts": META_FILE_ID, "chat": META_FILE_ID }
+                },
+                "posts": "",
+                "chat": ""
+            }
+        });
+        let keys = |order: &[&str]| {
+            let order: Vec<String> = order.iter().map(|pattern| pattern.to_string()).collect();
+            let json = serde_json::to_string_pretty(&OrderedDir::new(&bundle, &order)).unwrap();
+
+            // the entries of the app and of zomes/, told from those in the trees by indentation
+            let entries = &[
+                (2, "name"),
+                (2, "description"),
+                (2, "__META__"),
+                (2, "README"),
+                (2, "ui"),
+                (2, "zomes"),
+                (4, "chat"),
+                (4, "posts"),
+            ];
+
+            entries
+                .iter()
+                .map(|&(indent, key)| {
+                    let line = format!("\n{}\"{}\":", " ".repeat(indent), key);
+
+                    (json.find(&line).unwrap(), key)
+                })
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+                .map(|(_, key)| key)
+                .collect::<Vec<_>>()
+        };
+
+        // as packaged without an order
+        assert_eq!(
+            serde_json::to_string(&OrderedDir::new(&bundle, &[])).unwrap(),
+            bundle.to_string()
+        );
+        assert_eq!(
+            keys(&["manifest", "__META__", "*", "ui"]).join(" "),
+            "description name __META__ README zomes chat posts ui"
+        );
+    }
+
+    #[test]
+    fn unpack_stops_at_the_limits() {
+        let bundle = |depth: usize, files: usize| {
+            let mut obj = Object::new();
+            let mut tree = Object::new();
+
+            for i in 0..files {
+                obj.insert(format!("{}.txt", i), base64::encode(&[0; 100]).into());
+                tree.insert(format!("{}.txt", i), META_FILE_ID.into());
+            }
+
+            for _ in 0..depth {
+                obj.insert(META_SECTION_NAME.into(), json!({ META_TREE_SECTION_NAME: tree }));
+                let mut parent = Object::new();
+                parent.insert("d".into(), obj.into());
+                obj = parent;
+                tree = Object::new();
+                tree.insert("d".into(), META_DIR_ID.into());
+            }
+
+            obj.insert(META_SECTION_NAME.into(), json!({ META_TREE_SECTION_NAME: tree }));
+            obj
+        };
+
+        let unpack = |obj: Object, limits: &UnpackLimits| {
+            let tmp = gen_dir();
+            let mut budget = UnpackBudget::new(limits).unwrap();
+
+            unpack_recurse(obj, &tmp.path().to_path_buf(), 0, &mut budget, true, false)
+                .map_err(|err| err.to_string())
+        };
+
+        let limits = UnpackLimits {
+            max_size: Some("1KB".into()),
+            max_file_size: Some("100".into()),
+            max_files: Some(5),
+            max_depth: Some(2),
+        };
+
+        assert!(unpack(bundle(2, 5), &limits).is_ok());
+        assert!(unpack(bundle(3, 1), &limits)
+            .unwrap_err()
+            .contains("max_depth"));
+        assert!(unpack(bundle(0, 6), &limits)
+            .unwrap_err()
+            .contains("max_files"));
+
+        let mut large = bundle(0, 1);
+        large.insert("0.txt".into(), base64::encode(&[0; 101]).into());
+        assert!(unpack(large, &limits)
+            .unwrap_err()
+            .contains("max_file_size"));
+
+        let limits = UnpackLimits {
+            max_size: Some("250".into()),
+            ..limits
+        };
+        assert!(unpack(bundle(0, 3), &limits)
+            .unwrap_err()
+            .contains("max_size"));
+    }
+
+    #[test]
+    fn flattened_zomes_build_from_the_bundled_wasm() {
+        let tmp = gen_dir();
+        let wasm = b"\0asm\x01\0\0\0";
+        let obj = json!({
+            "code": { "code": base64::encode(wasm) },
+            META_SECTION_NAME: { META_TREE_SECTION_NAME: { "code": META_BIN_ID } }
+        });
+        let mut budget = UnpackBudget::new(&UnpackLimits::default()).unwrap();
+
+        unpack_recurse(
+            serde_json::from_value(obj).unwrap(),
+            &tmp.path().to_path_buf(),
+            0,
+            &mut budget,
+            true,
+            true,
+        ).unwrap();
+
+        let code_dir = tmp.path().join("code");
+        let build = Build::from_file(code_dir.join(BUILD_CONFIG_FILE_NAME)).unwrap();
+
+        assert_eq!(fs::read(code_dir.join(PREBUILT_FILE_NAME)).unwrap(), wasm);
+        assert_eq!(
+            base64::decode(&build.run(&code_dir).unwrap()).unwrap(),
+            wasm
+        );
+    }
+
+    #[test]
+    fn delta_bundles_reconstruct_the_full_bundle() {
+        let tmp = gen_dir();
+        let app_path = tmp.path().join("app");
+        let base_path = tmp.path().join("base.json");
+        let full_path = tmp.path().join("full.json");
+        let delta_path = tmp.path().join("delta.json");
+        let applied_path = tmp.path().join("applied.json");
+
+        Command::main_binary()
+            .unwrap()
+            .args(&["init", app_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&app_path)
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["package", "-o", base_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        fs::write(app_path.join("ui").join("index.html"), "<html></html>").unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&app_path)
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["package", "-o", full_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&app_path)
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["package", "-o", delta_path.to_str().unwrap()])
+            .args(&["--delta", base_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .args(&["apply-delta", base_path.to_str().unwrap()])
+            .args(&[delta_path.to_str().unwrap(), "-o", applied_path.to_str().unwrap()])
+            .assert()
+            .success();
+
+        // the same bundle, though serde_json may keep its keys in another order
+        let read = |path: &PathBuf| -> Value {
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+        };
+
+        assert_eq!(read(&applied_path), read(&full_path));
+    }
+
+    #[test]
+    fn auto_compilation() {
+        let tmp = gen_dir();
+        let home = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["init", "."])
+            .assert()
+            .success();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["g", "zomes/bubblechat", "rust"])
             .assert()
             .success();
 
         Command::main_binary()
             .unwrap()
             .current_dir(&tmp.path())
+            .env("HC_HOME", home.path())
             .args(&["package"])
             .assert()
             .success();
     }
+
+    #[test]
+    fn packages_the_whole_app_from_a_subdirectory() {
+        let tmp = gen_dir();
+        let home = gen_dir();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&tmp.path())
+            .args(&["init", "."])
+            .assert()
+            .success();
+
+        let zomes_dir = tmp.path().join("zomes");
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&zomes_dir)
+            .env("HC_HOME", home.path())
+            .args(&["package"])
+            .assert()
+            .success();
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&zomes_dir)
+            .env("HC_HOME", home.path())
+            .args(&["package", "-o", "here.json"])
+            .assert()
+            .success();
+
+        assert!(tmp.path().join(DEFAULT_BUNDLE_FILE_NAME).is_file());
+        assert!(zomes_dir.join("here.json").is_file());
+        assert!(!zomes_dir.join(DEFAULT_BUNDLE_FILE_NAME).exists());
+    }
 }
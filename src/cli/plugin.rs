@@ -0,0 +1,153 @@
+use cli::new::find_project_root;
+use config_files::dirs;
+use error::DefaultResult;
+use interrupt;
+use net::{self, OFFLINE_ENV_VAR};
+use output;
+use std::{
+    collections::BTreeMap,
+    env::{self, consts::EXE_SUFFIX},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Unknown subcommands `foo` are looked up as `hc-foo` executables on the `PATH`
+pub const PLUGIN_PREFIX: &str = "hc-";
+
+/// Runs the plugin for the given subcommand and returns its exit code
+pub fn plugin(name: &str, args: Vec<String>) -> DefaultResult<i32> {
+    let executable = find_plugins()?.remove(name).ok_or_else(|| {
+        format_err!(
+            "no such command: {}, and no {}{} executable on the PATH",
+            name,
+            PLUGIN_PREFIX,
+            name
+        )
+    })?;
+
+    let mut command = Command::new(&executable);
+    command
+        .args(args)
+        .env("HC_CLI", env::current_exe()?)
+        .env("HC_CONFIG_DIR", dirs::config_dir()?)
+        .env("HC_DATA_DIR", dirs::data_dir()?)
+        .env(dirs::CACHE_DIR_ENV_VAR, dirs::cache_dir()?);
+
+    if let Some(root) = find_project_root(&env::current_dir()?) {
+        command.env("HC_PROJECT_ROOT", root);
+    }
+
+    if net::is_offline() {
+        command.env(OFFLINE_ENV_VAR, "1");
+    }
+
+    let status = interrupt::status(&mut command)
+        .map_err(|err| format_err!("unable to run {:?}: {}", executable, err))?;
+
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Lists the plugins found on the `PATH`
+pub fn plugins() -> DefaultResult<()> {
+    for (name, executable) in find_plugins()? {
+        output::print(format!("{:<20}{}", name, executable.display()));
+    }
+
+    Ok(())
+}
+
+/// Maps plugin names to their executables, earlier `PATH` entries shadow later ones
+fn find_plugins() -> DefaultResult<BTreeMap<String, PathBuf>> {
+    let mut plugins = BTreeMap::new();
+    let path = env::var_os("PATH").unwrap_or_default();
+
+    for dir in env::split_paths(&path) {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            if !file_name.starts_with(PLUGIN_PREFIX)
+                || !file_name.ends_with(EXE_SUFFIX)
+                || !is_executable(&path)
+            {
+                continue;
+            }
+
+            let name =
+                file_name[PLUGIN_PREFIX.len()..file_name.len() - EXE_SUFFIX.len()].to_string();
+
+            plugins.entry(name).or_insert(path);
+        }
+    }
+
+    Ok(plugins)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::{env, fs, os::unix::fs::PermissionsExt, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn unknown_subcommands_run_plugins() {
+        let tmp = Builder::new().prefix("plugin").tempdir().unwrap();
+        let bin = tmp.path().join("bin");
+        let plugin = bin.join("hc-hello");
+
+        fs::create_dir(&bin).unwrap();
+        fs::write(
+            &plugin,
+            "#!/bin/sh\necho \"hello $1 from $HC_PROJECT_ROOT\"\nexit 3\n",
+        ).unwrap();
+        fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+
+        let path = env::join_paths(
+            Some(bin.clone())
+                .into_iter()
+                .chain(env::split_paths(&env::var_os("PATH").unwrap())),
+        ).unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .env("PATH", &path)
+            .args(&["hello", "world"])
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(3));
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("hello world from /"));
+
+        Command::main_binary()
+            .unwrap()
+            .env("PATH", &path)
+            .arg("goodbye")
+            .assert()
+            .failure();
+    }
+}
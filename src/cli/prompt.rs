@@ -0,0 +1,116 @@
+//! Questions on the terminal, for commands that ask for what was left out of the command line.
+//! Every question can have a default, which an empty answer picks.
+
+use atty;
+use error::DefaultResult;
+use std::io::{self, BufRead, Write};
+
+/// Whether questions can be asked: both ends are a terminal and `--non-interactive` is off
+pub fn is_interactive(non_interactive: bool) -> bool {
+    !non_interactive && atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout)
+}
+
+/// Asks until the answer passes the check, which returns what is wrong with it otherwise
+pub fn ask<R, F>(input: &mut R, question: &str, default: &str, check: F) -> DefaultResult<String>
+where
+    R: BufRead,
+    F: Fn(&str) -> Result<(), String>,
+{
+    loop {
+        if default.is_empty() {
+            print!("{}: ", question);
+        } else {
+            print!("{} [{}]: ", question, default);
+        }
+
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+
+        ensure!(
+            input.read_line(&mut answer)? > 0,
+            "no answer to \"{}\"",
+            question
+        );
+
+        let answer = match answer.trim() {
+            "" => default,
+            answer => answer,
+        };
+
+        match check(answer) {
+            Ok(()) => return Ok(answer.to_string()),
+            Err(problem) => println!("{}", problem),
+        }
+    }
+}
+
+/// Asks for one of the options, by name or by its number in the list
+pub fn choose<R: BufRead>(
+    input: &mut R,
+    question: &str,
+    options: &[&str],
+    default: &str,
+) -> DefaultResult<String> {
+    let pick = |answer: &str| {
+        answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| options.get(number.wrapping_sub(1)))
+            .or_else(|| options.iter().find(|option| **option == answer))
+            .map(|option| option.to_string())
+    };
+
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}) {}", i + 1, option);
+    }
+
+    let answer = ask(input, question, default, |answer| {
+        pick(answer)
+            .map(|_| ())
+            .ok_or_else(|| format!("Please pick one of: {}", options.join(", ")))
+    })?;
+
+    Ok(pick(&answer).unwrap_or(answer))
+}
+
+/// Names of Zomes, entry types and the like: ASCII letters, digits, `_` and `-`, starting with a
+/// letter, so they work as directory, crate and identifier names alike
+pub fn check_name(name: &str) -> Result<(), String> {
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        Err("The name has to start with a letter.".to_string())
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Err("The name may only contain letters, digits, _ and -.".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn asks_again_until_the_answer_is_valid() {
+        let mut input = Cursor::new("9\nwat\n\n1 thing\nchat\n");
+
+        assert_eq!(
+            choose(&mut input, "Language", &["rust", "wat"], "rust").unwrap(),
+            "wat"
+        );
+        assert_eq!(
+            choose(&mut input, "Language", &["rust", "wat"], "rust").unwrap(),
+            "rust"
+        );
+        assert_eq!(ask(&mut input, "Name", "", check_name).unwrap(), "chat");
+        assert!(ask(&mut input, "Name", "", check_name).is_err());
+        assert_eq!(
+            choose(&mut Cursor::new("2\n"), "Kind", &["zome", "entry"], "zome").unwrap(),
+            "entry"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use build_cache::BUILDS_DIR_NAME;
+use cli::registry::STORE_DIR_NAME;
+use config_files::dirs;
+use error::DefaultResult;
+use file_lock::{self, FileLock};
+use i18n;
+use output;
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+use util;
+
+/// The directories below the cache directory that only hold data which can be recreated
+pub const PRUNABLE_DIR_NAMES: &[&str] = &[STORE_DIR_NAME, BUILDS_DIR_NAME];
+
+struct CachedFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Deletes cached files older than `older_than`, then the least recently modified ones until
+/// all caches together fit into `max_size`, along with the lock files nobody holds
+pub fn prune(max_size: Option<u64>, older_than: Option<Duration>) -> DefaultResult<()> {
+    ensure!(
+        max_size.is_some() || older_than.is_some(),
+        "nothing to prune by, pass --max-size and/or --older-than"
+    );
+
+    let cache_dir = dirs::cache_dir()?;
+    let mut files = Vec::new();
+
+    for dir_name in PRUNABLE_DIR_NAMES {
+        collect_files(&cache_dir.join(dir_name), &mut files)?;
+    }
+
+    // oldest first, so both passes remove the least recently used files
+    files.sort_by_key(|file| file.modified);
+
+    let now = SystemTime::now();
+    let mut total_size: u64 = files.iter().map(|file| file.size).sum();
+    let mut removed_count = 0;
+    let mut reclaimed = 0;
+
+    for file in files {
+        let expired = older_than.map_or(false, |older_than| {
+            now.duration_since(file.modified)
+                .map(|age| age > older_than)
+                .unwrap_or(false)
+        });
+        let too_large = max_size.map_or(false, |max_size| total_size > max_size);
+
+        if !expired && !too_large {
+            continue;
+        }
+
+        // entries another process is working with right now are left alone
+        let _lock = match FileLock::try_acquire(&file.path)? {
+            Some(lock) => lock,
+            None => continue,
+        };
+
+        fs::remove_file(&file.path)?;
+
+        total_size -= file.size;
+        removed_count += 1;
+        reclaimed += file.size;
+    }
+
+    // lock files are recreated by the next process that locks their entry
+    removed_count += file_lock::prune_locks()?;
+
+    output::print(i18n::tr(
+        "pruned-files",
+        &[
+            ("status", output::status("status-pruned")),
+            ("count", removed_count.to_string()),
+            ("size", util::format_size(reclaimed)),
+        ],
+    ));
+
+    Ok(())
+}
+
+fn collect_files(dir: &PathBuf, files: &mut Vec<CachedFile>) -> DefaultResult<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let metadata = fs::symlink_metadata(&path)?;
+
+        if metadata.is_dir() {
+            collect_files(&path, files)?;
+        } else if !file_lock::is_partial_write(&path) {
+            files.push(CachedFile {
+                path,
+                size: metadata.len(),
+                modified: metadata.modified()?,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use std::process::Command;
+    use tempfile::Builder;
+
+    #[test]
+    fn prunes_down_to_max_size() {
+        let home = Builder::new().prefix("prune").tempdir().unwrap();
+        let store = home.path().join(".hc").join(STORE_DIR_NAME);
+
+        fs::create_dir_all(&store).unwrap();
+        fs::write(store.join("a-0.1.0.json"), vec![b'a'; 600]).unwrap();
+        fs::write(store.join("b-0.1.0.json"), vec![b'b'; 600]).unwrap();
+        fs::create_dir_all(home.path().join(".hc/locks")).unwrap();
+        fs::write(home.path().join(".hc/locks/abc.lock"), "").unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .env("HOME", home.path())
+            .args(&["prune", "--max-size", "1KB"])
+            .assert()
+            .success();
+
+        assert_eq!(fs::read_dir(&store).unwrap().count(), 1);
+        assert!(!home.path().join(".hc/locks/abc.lock").exists());
+
+        Command::main_binary()
+            .unwrap()
+            .env("HOME", home.path())
+            .args(&["prune"])
+            .assert()
+            .failure();
+    }
+}
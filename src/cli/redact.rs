@@ -0,0 +1,128 @@
+//! Makes a copy of a bundle that can be attached to a bug report without giving the code away.
+//!
+//! Every WASM module is replaced by its hash and size, so a report can still tell whether two
+//! builds are the same. `--obfuscate` also replaces every other string, config values and file
+//! contents alike, with a placeholder derived from its hash: equal values stay equal, so the
+//! structure of the manifests can still be debugged. Keys and `__META__` sections are kept.
+
+use base64;
+use bundle::{self, BundleType};
+use cli::package::META_SECTION_NAME;
+use error::DefaultResult;
+use i18n;
+use output;
+use serde_json::Value;
+use std::path::PathBuf;
+use util;
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+const REDACTED_FILE_SUFFIX: &str = ".redacted.json";
+
+/// How many hex digits of the hash identify an obfuscated value
+const OBFUSCATED_HASH_LENGTH: usize = 12;
+
+/// Writes a redacted copy of the bundle, next to it unless `output` is given
+pub fn redact(path: &PathBuf, output: Option<PathBuf>, obfuscate: bool) -> DefaultResult<()> {
+    let (header, mut content) = bundle::read(path)?;
+
+    ensure!(
+        header.bundle_type != BundleType::Encrypted,
+        "{:?} is encrypted, unpack it first and redact the bundle packaged from that",
+        path
+    );
+
+    let redacted = redact_recurse(&mut content, obfuscate);
+
+    let output = output.unwrap_or_else(|| {
+        let stem = path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+
+        path.with_file_name(format!("{}{}", stem, REDACTED_FILE_SUFFIX))
+    });
+
+    bundle::write(&output, header.bundle_type, &content)?;
+
+    output::print(i18n::tr(
+        "created-redacted-bundle",
+        &[
+            ("status", output::status("status-created")),
+            ("path", format!("{:?}", output)),
+            ("count", redacted.to_string()),
+        ],
+    ));
+
+    Ok(())
+}
+
+/// Redacts the value in place, returning the number of WASM modules that were replaced
+fn redact_recurse(value: &mut Value, obfuscate: bool) -> usize {
+    match value {
+        Value::Object(obj) => obj
+            .iter_mut()
+            .filter(|(key, _)| *key != META_SECTION_NAME)
+            .map(|(_, child)| redact_recurse(child, obfuscate))
+            .sum(),
+        Value::Array(items) => items
+            .iter_mut()
+            .map(|item| redact_recurse(item, obfuscate))
+            .sum(),
+        Value::String(string) => {
+            let decoded = base64::decode(string.as_str()).ok();
+
+            match decoded {
+                Some(ref wasm) if wasm.starts_with(WASM_MAGIC) => {
+                    *value = json!({
+                        "redacted": "wasm",
+                        "sha256": util::sha256_hex(wasm),
+                        "size": wasm.len(),
+                    });
+
+                    1
+                }
+                _ => {
+                    if obfuscate {
+                        let hash = util::sha256_hex(string.as_bytes());
+                        *string = format!("redacted-{}", &hash[..OBFUSCATED_HASH_LENGTH]);
+                    }
+
+                    0
+                }
+            }
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_wasm_by_hash_and_size() {
+        let wasm = b"\0asm\x01\0\0\0";
+        let mut content = json!({
+            "name": "chat",
+            "zomes": {
+                "posts": {
+                    "description": "Posts",
+                    "code": { "code": base64::encode(wasm) }
+                }
+            },
+            "__META__": { "config_file": "app.json" }
+        });
+
+        assert_eq!(redact_recurse(&mut content.clone(), false), 1);
+        assert_eq!(redact_recurse(&mut content, true), 1);
+
+        assert_eq!(
+            content["zomes"]["posts"]["code"]["code"],
+            json!({ "redacted": "wasm", "sha256": util::sha256_hex(wasm), "size": 8 })
+        );
+        assert_eq!(
+            content["name"],
+            format!("redacted-{}", &util::sha256_hex(b"chat")[..12])
+        );
+        assert_eq!(content["__META__"]["config_file"], "app.json");
+    }
+}
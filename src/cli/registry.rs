@@ -0,0 +1,92 @@
+use bundle;
+use config_files::dirs;
+use error::DefaultResult;
+use i18n;
+use file_lock::{self, FileLock};
+use net;
+use output;
+use registry::{self, Registry};
+use std::path::PathBuf;
+use util;
+
+pub const STORE_DIR_NAME: &str = "store";
+
+pub fn search(term: &str) -> DefaultResult<()> {
+    let registry = Registry::open()?;
+    let mut matches = registry.search(term);
+
+    matches.sort_by(|a, b| a.name.cmp(&b.name).then(b.version.cmp(&a.version)));
+
+    if matches.is_empty() {
+        output::print(i18n::tr("no-search-results", &[("term", term.to_owned())]));
+    }
+
+    for entry in matches {
+        output::print(format!(
+            "{}@{}  {}  {}",
+            entry.name, entry.version, entry.hash, entry.description
+        ));
+
+        for (name, zome) in &entry.zomes {
+            let attribution = zome.attribution();
+
+            output::print(if attribution.is_empty() {
+                format!("  {}  {}", name, zome.description)
+            } else {
+                format!("  {}  {}  ({})", name, zome.description, attribution)
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads the bundle matching `name@version` into the global store, or the given output
+pub fn install(spec: &str, output: Option<PathBuf>) -> DefaultResult<()> {
+    let (name, requirement) = registry::parse_spec(spec)?;
+    let registry = Registry::open()?;
+
+    let entry = registry
+        .resolve(&name, &requirement)
+        .ok_or_else(|| format_err!("no DNA in the registry matches {}", spec))?;
+
+    let bundle = net::fetch_accepting(&registry.bundle_location(entry), bundle::MIME_TYPE)?;
+    let hash = format!("sha256:{}", util::sha256_hex(&bundle));
+
+    ensure!(
+        hash == entry.hash,
+        "hash mismatch for {}@{}: the index says {} but the download is {}",
+        entry.name,
+        entry.version,
+        entry.hash,
+        hash
+    );
+
+    // make sure a misconfigured server didn't hand out e.g. an HTML error page
+    bundle::from_slice(&bundle).map_err(|err| {
+        format_err!("download of {}@{} failed: {}", entry.name, entry.version, err)
+    })?;
+
+    let target = match output {
+        Some(output) => output,
+        None => dirs::cache_dir()?
+            .join(STORE_DIR_NAME)
+            .join(format!("{}-{}.json", entry.name, entry.version)),
+    };
+
+    // parallel installs of the same DNA take turns instead of writing over each other
+    let _lock = FileLock::acquire(&target)?;
+
+    file_lock::write_atomic(&target, &bundle)?;
+
+    output::print(i18n::tr(
+        "installed-dna",
+        &[
+            ("status", output::status("status-installed")),
+            ("name", format!("{}@{}", entry.name, entry.version)),
+            ("path", format!("{:?}", target)),
+        ],
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,583 @@
+//! `run`: packages the app and serves it from a development conductor, so Zomes can be called
+//! over a websocket interface without copying the bundle into a conductor set up by hand.
+//!
+//! The conductor is `holochain` from the `PATH`, or the binary `HC_CONDUCTOR` points to, the
+//! same one the desktop shell of `generate electron` starts. The app is packaged in memory, but
+//! the conductor only loads DNAs from files, so the bundle is written for it, along with its
+//! configuration, into a directory of the app in the cache directory rather than into the app.
+//! Secret properties are decrypted on the way, like a conductor instantiating the DNA would.
+//! The instance keeps its data in memory, with `--persist` in the data directory, where the
+//! next run finds it again.
+//!
+//! `--port 0` leaves the port to the OS, so parallel runs on CI don't collide, and prints the
+//! one it picked as JSON. `--port-file` writes the ports to a file for test harnesses to pick
+//! up.
+//!
+//! A port that is in use is reported along with the process holding it, as far as `lsof` can
+//! tell. Every run keeps its process ID in a pidfile next to the bundle while the conductor is
+//! running, so a run that was left behind in another terminal is recognized, and can be
+//! stopped on the spot or given way to with the next free port.
+
+use bundle::{self, BundleType};
+use cli::{
+    keys,
+    package::{package_in_memory, PackageOptions},
+    prompt,
+};
+use config_files::dirs;
+use encryption;
+use error::DefaultResult;
+use file_lock;
+use i18n;
+use interrupt;
+use output;
+use secrets;
+use serde_json::{self, Value};
+use std::{
+    env, fs,
+    io::{self, ErrorKind},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    thread,
+    time::{Duration, Instant},
+};
+use toml;
+use util;
+
+pub const CONDUCTOR_ENV_VAR: &str = "HC_CONDUCTOR";
+
+const DEFAULT_CONDUCTOR: &str = "holochain";
+
+const RUN_DIR_NAME: &str = "run";
+
+const BUNDLE_FILE_NAME: &str = "bundle.json";
+
+const CONDUCTOR_CONFIG_FILE_NAME: &str = "conductor-config.toml";
+
+const PID_FILE_NAME: &str = "run.pid";
+
+/// How many ports after a busy one are tried for the next free one
+const FREE_PORT_SEARCH: u16 = 100;
+
+/// How long a stopped run gets to give up its port
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CONDUCTOR_CONFIG: &str = "\
+# Written by hcdev run on every start, see the configuration reference of holochain-rust.
+
+[[agents]]
+id = \"dev-agent\"
+name = \"Development agent\"
+public_address = \"\"
+keystore_file = \"\"
+test_agent = true
+
+[[dnas]]
+id = \"app\"
+file = {bundle}
+
+[[instances]]
+id = \"app\"
+dna = \"app\"
+agent = \"dev-agent\"
+
+[instances.storage]
+{storage}
+
+[[interfaces]]
+id = \"websocket\"
+
+[interfaces.driver]
+type = \"websocket\"
+port = {port}
+
+[[interfaces.instances]]
+id = \"app\"
+";
+
+/// Packages the app and runs a conductor with it until the conductor exits or Ctrl-C
+pub fn run(
+    port: u16,
+    port_file: Option<PathBuf>,
+    persist: bool,
+    identity: Option<PathBuf>,
+    non_interactive: bool,
+) -> DefaultResult<()> {
+    let app_dir = app_dir_name()?;
+    let run_dir = dirs::cache_dir()?.join(RUN_DIR_NAME).join(&app_dir);
+    let bundle = run_dir.join(BUNDLE_FILE_NAME);
+    let pid_file = run_dir.join(PID_FILE_NAME);
+
+    // held while packaging, so the port is still free once the conductor binds it
+    let reservation = match reserve(port) {
+        Ok(reservation) => reservation,
+        Err(ref err) if err.kind() == ErrorKind::AddrInUse => {
+            resolve_conflict(port, &pid_file, non_interactive)?
+        }
+        Err(err) => bail!("unable to use port {}: {}", port, err),
+    };
+    // the one the OS picked for port 0, or the next free one
+    let chosen_port = reservation.local_addr()?.port();
+
+    let mut content = package_in_memory(PackageOptions::default())?;
+
+    decrypt_secrets(&mut content, identity)?;
+
+    fs::create_dir_all(&run_dir)?;
+    bundle::write(&bundle, BundleType::App, &content)?;
+
+    let storage = if persist {
+        let storage_dir = dirs::data_dir()?.join(RUN_DIR_NAME).join(&app_dir);
+
+        fs::create_dir_all(&storage_dir)?;
+
+        format!("type = \"file\"\npath = {}", toml_string(&storage_dir))
+    } else {
+        "type = \"memory\"".to_string()
+    };
+
+    let config = run_dir.join(CONDUCTOR_CONFIG_FILE_NAME);
+
+    fs::write(
+        &config,
+        CONDUCTOR_CONFIG
+            .replace("{bundle}", &toml_string(&bundle))
+            .replace("{storage}", &storage)
+            .replace("{port}", &chosen_port.to_string()),
+    )?;
+
+    let conductor = env::var(CONDUCTOR_ENV_VAR)
+        .ok()
+        .filter(|conductor| !conductor.is_empty())
+        .unwrap_or_else(|| DEFAULT_CONDUCTOR.to_string());
+
+    let url = format!("ws://localhost:{}", chosen_port);
+    let ports = json!({ "websocket": { "port": chosen_port, "url": url } });
+
+    if let Some(ref port_file) = port_file {
+        // harnesses poll for it, so it must not be seen half written
+        file_lock::write_atomic(port_file, serde_json::to_string_pretty(&ports)?.as_bytes())?;
+    }
+
+    output::print(i18n::tr("serving-app", &[("url", url)]));
+
+    if port == 0 {
+        output::print(ports);
+    }
+
+    let _pid_file = PidFile::write(pid_file)?;
+
+    drop(reservation);
+
+    let status =
+        interrupt::status(Command::new(&conductor).arg("-c").arg(&config)).map_err(|err| {
+            format_err!(
+                "unable to start the conductor {}: {}, install holochain or point {} to it",
+                conductor,
+                err,
+                CONDUCTOR_ENV_VAR
+            )
+        })?;
+
+    ensure!(status.success(), "the conductor exited with {}", status);
+
+    Ok(())
+}
+
+/// Decrypts the secret properties of the app's manifest, which the conductor can't read
+/// otherwise
+fn decrypt_secrets(content: &mut Value, identity: Option<PathBuf>) -> DefaultResult<()> {
+    let manifest = match content.as_object_mut() {
+        Some(manifest) if secrets::has_encrypted_properties(manifest) => manifest,
+        _ => return Ok(()),
+    };
+
+    let identity = match identity {
+        Some(identity) => identity,
+        None => keys::default_identity_path()
+            .ok()
+            .filter(|path| path.is_file())
+            .ok_or_else(|| {
+                format_err!(
+                    "the app has secret properties, pass the identity to decrypt them with --identity"
+                )
+            })?,
+    };
+
+    secrets::decrypt_properties(manifest, &encryption::read_identity(identity)?)?;
+
+    Ok(())
+}
+
+/// Binds the port on all interfaces, as a listener on any of them keeps the conductor from
+/// binding it
+fn reserve(port: u16) -> io::Result<TcpListener> {
+    TcpListener::bind(("0.0.0.0", port))
+}
+
+/// Finds out who holds the port and, if the user can be asked, offers to stop an earlier run of
+/// the app or to take the next free port instead
+fn resolve_conflict(
+    port: u16,
+    pid_file: &Path,
+    non_interactive: bool,
+) -> DefaultResult<TcpListener> {
+    let earlier_run = PidFile::running(pid_file);
+    let mut problem = format!("port {} is in use", port);
+
+    if let Some((pid, name)) = port_owner(port) {
+        problem.push_str(&format!(" by {} (pid {})", name, pid));
+    }
+
+    if let Some(pid) = earlier_run {
+        problem.push_str(&format!(
+            ", an earlier hcdev run of this app is still running (pid {})",
+            pid
+        ));
+    }
+
+    let next_free = (port.saturating_add(1)..port.saturating_add(FREE_PORT_SEARCH))
+        .filter_map(|port| reserve(port).ok())
+        .next();
+
+    if !prompt::is_interactive(non_interactive) {
+        bail!(
+            "{}, stop it or pick another port with --port{}",
+            problem,
+            next_free
+                .as_ref()
+                .and_then(|free| free.local_addr().ok())
+                .map_or(String::new(), |free| format!(", e.g. {}", free.port()))
+        );
+    }
+
+    output::print(&problem);
+
+    let mut options = Vec::new();
+
+    if earlier_run.is_some() {
+        options.push("stop the earlier run");
+    }
+
+    if next_free.is_some() {
+        options.push("use the next free port");
+    }
+
+    options.push("abort");
+
+    let stdin = io::stdin();
+    let choice = prompt::choose(&mut stdin.lock(), "What now", &options, options[0])?;
+
+    match (choice.as_str(), earlier_run, next_free) {
+        ("stop the earlier run", Some(pid), _) => {
+            interrupt::kill(pid);
+
+            let started = Instant::now();
+
+            loop {
+                match reserve(port) {
+                    Ok(reservation) => return Ok(reservation),
+                    Err(_) if started.elapsed() < STOP_TIMEOUT => {
+                        thread::sleep(Duration::from_millis(100))
+                    }
+                    Err(err) => bail!(
+                        "port {} is still in use after stopping the earlier run: {}",
+                        port,
+                        err
+                    ),
+                }
+            }
+        }
+        ("use the next free port", _, Some(next_free)) => Ok(next_free),
+        _ => bail!("{}", problem),
+    }
+}
+
+/// The process listening on the port and its name, as far as `lsof` can tell
+fn port_owner(port: u16) -> Option<(u32, String)> {
+    let output = Command::new("lsof")
+        .args(&["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-Fpc"])
+        .output()
+        .ok()?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let field = |tag: char| {
+        listing
+            .lines()
+            .find(|line| line.starts_with(tag))
+            .map(|line| line[1..].to_string())
+    };
+
+    Some((field('p')?.parse().ok()?, field('c').unwrap_or_default()))
+}
+
+/// Holds the process ID of `hcdev` while it runs a conductor, removed when the run ends
+struct PidFile {
+    path: PathBuf,
+    _partial: interrupt::PartialFile,
+}
+
+impl PidFile {
+    fn write(path: PathBuf) -> DefaultResult<PidFile> {
+        fs::write(&path, process::id().to_string())?;
+
+        Ok(PidFile {
+            _partial: interrupt::PartialFile::new(&path),
+            path,
+        })
+    }
+
+    /// The process ID of the run the pidfile was written by, if it is still running
+    fn running(path: &Path) -> Option<u32> {
+        let pid = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+
+        if interrupt::is_running(pid) {
+            Some(pid)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The name of the directories of the app, a digest of its location so apps of the same name
+/// don't share them
+fn app_dir_name() -> DefaultResult<String> {
+    let root = env::current_dir()?;
+
+    Ok(util::sha256_hex(root.to_string_lossy().as_bytes())[..16].to_string())
+}
+
+fn toml_string(path: &Path) -> String {
+    toml::Value::String(PathBuf::from(path).to_string_lossy().into_owned()).to_string()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use assert_cmd::prelude::*;
+    use libc;
+    use serde_json::{self, Value};
+    use std::{
+        fs,
+        net::TcpListener,
+        os::unix::fs::PermissionsExt,
+        process::{Command, Stdio},
+        thread,
+        time::{Duration, Instant},
+    };
+    use tempfile::Builder;
+
+    #[test]
+    fn serves_the_packaged_app_from_a_conductor() {
+        let tmp = Builder::new().prefix("run").tempdir().unwrap();
+        let app = tmp.path().join("app");
+        let home = tmp.path().join("home");
+        let conductor = tmp.path().join("conductor");
+
+        fs::create_dir_all(&app).unwrap();
+        fs::write(app.join("app.json"), "{}").unwrap();
+
+        // stands in for holochain, keeping the configuration it was started with
+        fs::write(
+            &conductor,
+            format!(
+                "#!/bin/sh\ncp \"$2\" {:?}\n",
+                tmp.path().join("started.toml")
+            ),
+        ).unwrap();
+        fs::set_permissions(&conductor, fs::Permissions::from_mode(0o755)).unwrap();
+
+        Command::main_binary()
+            .unwrap()
+            .current_dir(&app)
+            .env("HC_HOME", &home)
+            .env("HC_CONDUCTOR", &conductor)
+            .args(&["run", "--port", "48123", "--persist"])
+            .assert()
+            .success();
+
+        let config = fs::read_to_string(tmp.path().join("started.toml")).unwrap();
+        let bundle = config
+            .lines()
+            .find(|line| line.starts_with("file = "))
+            .map(|line| line["file = ".len()..].trim_matches('"').to_string())
+            .unwrap();
+
+        assert!(config.contains("port = 48123"));
+        assert!(config.contains("type = \"file\""));
+        assert!(fs::metadata(bundle).unwrap().is_file());
+    }
+
+    #[test]
+    fn tells_the_port_the_os_picked() {
+        let tmp = Builder::new().prefix("run").tempdir().unwrap();
+        let app = tmp.path().join("app");
+        let conductor = tmp.path().join("conductor");
+        let port_file = tmp.path().join("ports.json");
+
+        fs::create_dir_all(&app).unwrap();
+        fs::write(app.join("app.json"), "{}").unwrap();
+        fs::write(
+            &conductor,
+            format!(
+                "#!/bin/sh\ncp \"$2\" {:?}\n",
+                tmp.path().join("started.toml")
+            ),
+        ).unwrap();
+        fs::set_permissions(&conductor, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(&app)
+            .env("HC_HOME", tmp.path().join("home"))
+            .env("HC_CONDUCTOR", &conductor)
+            .args(&["run", "--port", "0", "--port-file"])
+            .arg(&port_file)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        let ports: Value = serde_json::from_slice(&fs::read(&port_file).unwrap()).unwrap();
+        let port = ports["websocket"]["port"].as_u64().unwrap();
+        let config = fs::read_to_string(tmp.path().join("started.toml")).unwrap();
+
+        assert_ne!(port, 0);
+        assert!(config.contains(&format!("port = {}", port)));
+        assert!(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| serde_json::from_str::<Value>(line).ok() == Some(ports.clone())));
+    }
+
+    #[test]
+    fn tells_what_holds_the_port() {
+        let tmp = Builder::new().prefix("run").tempdir().unwrap();
+        let app = tmp.path().join("app");
+        let home = tmp.path().join("home");
+        let conductor = tmp.path().join("conductor");
+        let busy = TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let port = busy.local_addr().unwrap().port().to_string();
+
+        fs::create_dir_all(&app).unwrap();
+        fs::write(app.join("app.json"), "{}").unwrap();
+        fs::write(&conductor, "#!/bin/sh\nsleep 30\n").unwrap();
+        fs::set_permissions(&conductor, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let hcdev = || {
+            let mut hcdev = Command::main_binary().unwrap();
+
+            hcdev
+                .current_dir(&app)
+                .env("HC_HOME", &home)
+                .env("HC_CONDUCTOR", &conductor)
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped());
+            hcdev
+        };
+
+        // still serving in another terminal
+        let earlier = hcdev().args(&["run", "--port", "0"]).spawn().unwrap();
+        let started = Instant::now();
+
+        while !fs::read_dir(home.join("run"))
+            .map(|mut dirs| dirs.any(|dir| dir.unwrap().path().join("run.pid").is_file()))
+            .unwrap_or(false)
+        {
+            assert!(started.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        let output = hcdev()
+            .args(&["--non-interactive", "run", "--port", &port])
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        unsafe {
+            libc::kill(earlier.id() as libc::pid_t, libc::SIGTERM);
+        }
+
+        assert!(earlier.wait_with_output().is_ok());
+        assert!(!output.status.success());
+        assert!(stderr.contains(&format!("port {} is in use", port)));
+        assert!(stderr.contains("an earlier hcdev run of this app is still running"));
+        assert!(stderr.contains("pick another port with --port, e.g."));
+    }
+
+    #[test]
+    fn hands_the_conductor_the_secret_properties_decrypted() {
+        let tmp = Builder::new().prefix("run").tempdir().unwrap();
+        let app = tmp.path().join("app");
+        let identity = tmp.path().join("identity.key");
+        let conductor = tmp.path().join("conductor");
+        let hcdev = || {
+            let mut hcdev = Command::main_binary().unwrap();
+
+            hcdev
+                .current_dir(&app)
+                .env("HC_HOME", tmp.path().join("home"))
+                .env("HC_CONDUCTOR", &conductor);
+            hcdev
+        };
+
+        fs::create_dir_all(&app).unwrap();
+        fs::write(
+            app.join("app.json"),
+            r#"{ "properties": { "api": { "key": "hunter2" } } }"#,
+        ).unwrap();
+
+        let keygen = hcdev()
+            .args(&["keygen", identity.to_str().unwrap()])
+            .output()
+            .unwrap();
+        let recipient = String::from_utf8(keygen.stdout)
+            .unwrap()
+            .lines()
+            .last()
+            .unwrap()
+            .rsplit(' ')
+            .next()
+            .unwrap()
+            .to_string();
+
+        fs::write(
+            app.join("hc.toml"),
+            format!(
+                "[secrets]\nrecipient = \"{}\"\nproperties = [\"api.key\"]\n",
+                recipient
+            ),
+        ).unwrap();
+        // stands in for holochain, keeping the configuration it was started with
+        fs::write(
+            &conductor,
+            format!(
+                "#!/bin/sh\ncp \"$2\" {:?}\n",
+                tmp.path().join("started.toml")
+            ),
+        ).unwrap();
+        fs::set_permissions(&conductor, fs::Permissions::from_mode(0o755)).unwrap();
+
+        hcdev().args(&["run", "--port", "48124"]).assert().failure();
+        hcdev()
+            .args(&["run", "--port", "48124", "--identity"])
+            .arg(&identity)
+            .assert()
+            .success();
+
+        let config = fs::read_to_string(tmp.path().join("started.toml")).unwrap();
+        let bundle = config
+            .lines()
+            .find(|line| line.starts_with("file = "))
+            .map(|line| line["file = ".len()..].trim_matches('"').to_string())
+            .unwrap();
+        let served = fs::read_to_string(bundle).unwrap();
+
+        assert!(served.contains("hunter2"));
+        assert!(!served.contains("encrypted_property"));
+    }
+}
@@ -0,0 +1,162 @@
+use cli::{
+    assets::UI_DIR_NAME,
+    scaffold::{rust::crate_name, Scaffold},
+};
+use error::DefaultResult;
+use serde_json;
+use std::{fs, path::Path};
+
+/// The bundle the shell packages the app into and hands to the conductor
+pub const SHELL_BUNDLE_FILE_NAME: &str = "bundle.json";
+
+const CONDUCTOR_CONFIG_FILE_NAME: &str = "conductor-config.toml";
+
+const MAIN_JS: &str = "\
+// Starts a conductor with the app's DNA, then shows the app's UI in a window.
+const { app, BrowserWindow } = require('electron')
+const { spawn } = require('child_process')
+const path = require('path')
+
+// the conductor binary of holochain-rust, HC_CONDUCTOR points to another one
+const CONDUCTOR_BIN = process.env.HC_CONDUCTOR || 'holochain'
+
+let conductor
+
+function startConductor () {
+  conductor = spawn(CONDUCTOR_BIN, ['-c', path.join(__dirname, '{config}')], {
+    cwd: __dirname,
+    stdio: 'inherit'
+  })
+
+  conductor.on('exit', code => {
+    if (code) {
+      console.error(`the conductor exited with ${code}`)
+      app.quit()
+    }
+  })
+}
+
+function createWindow () {
+  const window = new BrowserWindow({ width: 1200, height: 800 })
+
+  window.loadFile(path.join(__dirname, '{ui_index}'))
+}
+
+app.whenReady().then(() => {
+  startConductor()
+  createWindow()
+})
+
+app.on('window-all-closed', () => app.quit())
+
+app.on('quit', () => {
+  if (conductor) {
+    conductor.kill()
+  }
+})
+";
+
+const CONDUCTOR_CONFIG: &str = "\
+# The conductor the desktop shell starts, see the configuration reference of holochain-rust.
+# The test agent gets fresh keys on every start and the memory storage forgets everything on
+# quit, switch to a keystore and file storage to keep data between runs.
+
+[[agents]]
+id = \"desktop-agent\"
+name = \"Desktop agent\"
+public_address = \"\"
+keystore_file = \"\"
+test_agent = true
+
+[[dnas]]
+id = \"app\"
+file = \"{bundle}\"
+
+[[instances]]
+id = \"app\"
+dna = \"app\"
+agent = \"desktop-agent\"
+
+[instances.storage]
+type = \"memory\"
+
+# the UI connects to ws://localhost:8888 to call zome functions
+[[interfaces]]
+id = \"websocket\"
+
+[interfaces.driver]
+type = \"websocket\"
+port = 8888
+
+[[interfaces.instances]]
+id = \"app\"
+";
+
+const GITIGNORE: &str = "node_modules/\n{bundle}\n";
+
+/// A desktop prototype of the app: an Electron shell that packages the app, starts a conductor
+/// with it and shows the UI
+pub struct ElectronScaffold {
+    app_name: String,
+    app_root: String,
+}
+
+impl ElectronScaffold {
+    /// `app_root` is the path from the shell's directory to the root of the app
+    pub fn new(app_name: &str, app_root: &str) -> ElectronScaffold {
+        ElectronScaffold {
+            app_name: app_name.to_string(),
+            app_root: app_root.to_string(),
+        }
+    }
+}
+
+impl Scaffold for ElectronScaffold {
+    fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        let base_path = base_path.as_ref();
+        fs::create_dir_all(base_path)?;
+
+        let shell_dir = base_path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+
+        let package_json = json!({
+            "name": format!("{}-desktop", crate_name(&self.app_name)),
+            "version": "0.1.0",
+            "private": true,
+            "main": "main.js",
+            "scripts": {
+                "prestart": format!(
+                    "cd {} && hcdev package -o {}/{}",
+                    self.app_root, shell_dir, SHELL_BUNDLE_FILE_NAME
+                ),
+                "start": "electron ."
+            },
+            "devDependencies": { "electron": "^33.0.0" }
+        });
+
+        fs::write(
+            base_path.join("package.json"),
+            serde_json::to_string_pretty(&package_json)? + "\n",
+        )?;
+
+        let ui_index = format!("{}/{}/index.html", self.app_root, UI_DIR_NAME);
+
+        fs::write(
+            base_path.join("main.js"),
+            MAIN_JS
+                .replace("{config}", CONDUCTOR_CONFIG_FILE_NAME)
+                .replace("{ui_index}", &ui_index),
+        )?;
+        fs::write(
+            base_path.join(CONDUCTOR_CONFIG_FILE_NAME),
+            CONDUCTOR_CONFIG.replace("{bundle}", SHELL_BUNDLE_FILE_NAME),
+        )?;
+        fs::write(
+            base_path.join(".gitignore"),
+            GITIGNORE.replace("{bundle}", SHELL_BUNDLE_FILE_NAME),
+        )?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
+pub mod electron;
 pub mod rust;
+pub mod wat;
 
 use error::DefaultResult;
 use std::path::Path;
@@ -1,4 +1,4 @@
-use cli::{package, scaffold::Scaffold};
+use cli::{license::with_license_header, package, scaffold::Scaffold};
 use config_files::Build;
 use error::DefaultResult;
 use std::{
@@ -8,8 +8,189 @@ use std::{
 };
 use util;
 
+pub const README_FILE_NAME: &str = "README.md";
+
+const LIB_FILE_PATH: &str = "src/lib.rs";
+
+const LIB_TEMPLATE: &str = "\
+// Keep the logic of the Zome in plain functions that get the host functions they need as a
+// `Host`. The tests below then run natively with `cargo test`, against a mock instead of a
+// conductor, and `hcdev package --with-tests` runs them before every build.
+extern crate hdk;
+
+/// The host functions the Zome calls: the HDK's in the conductor, a mock in the tests
+pub trait Host {
+    fn debug(&self, message: &str);
+}
+
+/// The host the Zome has when it runs in a conductor
+pub struct Hdk;
+
+#[cfg(target_arch = \"wasm32\")]
+impl Host for Hdk {
+    fn debug(&self, message: &str) {
+        let _ = hdk::debug(message);
+    }
+}
+
+/// A pure function, which needs no host to be tested
+pub fn greeting(name: &str) -> String {
+    format!(\"Hello, {}!\", name)
+}
+
+/// Greets someone and logs it through the host
+pub fn greet<H: Host>(host: &H, name: &str) -> String {
+    let greeting = greeting(name);
+    host.debug(&greeting);
+    greeting
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Records the calls of the Zome instead of making them
+    #[derive(Default)]
+    struct MockHost {
+        debugged: RefCell<Vec<String>>,
+    }
+
+    impl Host for MockHost {
+        fn debug(&self, message: &str) {
+            self.debugged.borrow_mut().push(message.to_string());
+        }
+    }
+
+    #[test]
+    fn greets_by_name() {
+        assert_eq!(greeting(\"Alice\"), \"Hello, Alice!\");
+    }
+
+    #[test]
+    fn logs_the_greeting() {
+        let host = MockHost::default();
+
+        greet(&host, \"Bob\");
+
+        assert_eq!(*host.debugged.borrow(), vec![\"Hello, Bob!\".to_string()]);
+    }
+}
+";
+
+const TINY_ALLOCATOR: &str = "\
+// wee_alloc trades allocation speed for a much smaller allocator in the WASM binary
+extern crate wee_alloc;
+
+#[global_allocator]
+static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+
+";
+
+const TINY_PROFILE: &str = "
+[profile.release]
+opt-level = \"z\"
+lto = true
+codegen-units = 1
+panic = \"abort\"
+";
+
+const TINY_README: &str = "\
+# Size optimized Zome
+
+This Zome was generated with `hcdev generate --tiny` and is tuned for a small WASM binary
+rather than for speed:
+
+- `wee_alloc` replaces the default allocator in `src/lib.rs`. According to its authors it
+  compiles to about 1 KB, compared to roughly 10 KB for the default allocator.
+- The release profile in `Cargo.toml` optimizes for size (`opt-level = \"z\"`), enables
+  link time optimization, builds a single codegen unit and aborts on panics instead of
+  unwinding, which drops the unwinding machinery and most of the panic formatting code.
+
+To measure the difference for this Zome, compare the size of
+`target/wasm32-unknown-unknown/release/code.wasm` after `hcdev package` with and without the
+`[profile.release]` section and the allocator.
+";
+
+/// Crates that only work with the JavaScript glue wasm-bindgen generates, which a conductor has
+/// no way to provide, and what they use it for
+const JS_GLUE_CRATES: &[(&str, &str)] = &[
+    ("wasm-bindgen", "generates bindings to JavaScript"),
+    (
+        "wasm-bindgen-futures",
+        "runs futures on JavaScript promises",
+    ),
+    ("js-sys", "binds the built-in objects of JavaScript"),
+    ("web-sys", "binds the Web APIs of browsers"),
+    ("gloo", "wraps the Web APIs of browsers"),
+    ("stdweb", "binds JavaScript and the Web APIs of browsers"),
+    (
+        "console_error_panic_hook",
+        "logs panics to the console of browsers",
+    ),
+    ("wasm-logger", "logs to the console of browsers"),
+];
+
+/// Parses a dependency given as `<crate>@<version>`, refusing crates that need JavaScript glue
+pub fn dependency(spec: &str) -> DefaultResult<(String, String)> {
+    let (name, version) = match spec.find('@') {
+        Some(at) => (&spec[..at], &spec[at + 1..]),
+        None => bail!("give the version of {} as {}@<version>", spec, spec),
+    };
+
+    ensure!(
+        !name.is_empty() && !version.is_empty(),
+        "{} is no dependency, expected <crate>@<version>",
+        spec
+    );
+
+    // crates.io doesn't tell - and _ apart in names
+    let normalized = name.replace('_', "-");
+    let js_glue = JS_GLUE_CRATES.iter().find(|(js_crate, _)| {
+        let js_crate = js_crate.replace('_', "-");
+        normalized == js_crate || normalized.starts_with(&format!("{}-", js_crate))
+    });
+
+    if let Some((_, purpose)) = js_glue {
+        bail!(
+            "{} can't be used in a Zome: it {} with JavaScript glue, which no conductor \
+             provides",
+            name,
+            purpose
+        );
+    }
+
+    Ok((name.to_string(), version.to_string()))
+}
+
+/// Turns a name into one cargo accepts as package name, e.g. "My Chat" into "my-chat"
+pub fn crate_name(name: &str) -> String {
+    let mut crate_name = String::new();
+
+    for c in name.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            crate_name.push(c);
+        } else if !crate_name.is_empty() && !crate_name.ends_with('-') {
+            crate_name.push('-');
+        }
+    }
+
+    let crate_name = crate_name.trim_end_matches('-');
+
+    match crate_name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() => crate_name.to_string(),
+        _ => format!("zome-{}", crate_name)
+            .trim_end_matches('-')
+            .to_string(),
+    }
+}
+
 pub struct RustScaffold {
     build_template: Build,
+    tiny: bool,
+    license: Option<String>,
+    package_name: Option<String>,
+    dependencies: Vec<(String, String)>,
 }
 
 impl RustScaffold {
@@ -19,26 +200,58 @@ impl RustScaffold {
                 .cmd(
                     "cargo",
                     &["build", "--release", "--target=wasm32-unknown-unknown"],
-                ),
+                )
+                .test_cmd("cargo", &["test"]),
+            tiny: false,
+            license: None,
+            package_name: None,
+            dependencies: Vec::new(),
         }
     }
+
+    /// Sets the name of the Cargo package, instead of the name of the code directory
+    pub fn package_name(mut self, package_name: Option<String>) -> RustScaffold {
+        self.package_name = package_name;
+        self
+    }
+
+    /// Sets whether the Zome should be set up for a minimal binary size
+    pub fn tiny(mut self, tiny: bool) -> RustScaffold {
+        self.tiny = tiny;
+        self
+    }
+
+    /// Sets the SPDX license expression to put in a header of the generated sources
+    pub fn license(mut self, license: Option<String>) -> RustScaffold {
+        self.license = license;
+        self
+    }
+
+    /// Sets further crates the Zome depends on besides the HDK, as names and versions
+    pub fn dependencies(mut self, dependencies: Vec<(String, String)>) -> RustScaffold {
+        self.dependencies = dependencies;
+        self
+    }
 }
 
 impl Scaffold for RustScaffold {
     fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
         fs::create_dir_all(&base_path)?;
 
+        let mut init_args = vec![
+            "init".to_owned(),
+            "--lib".to_owned(),
+            "--vcs".to_owned(),
+            "none".to_owned(),
+        ];
+
+        if let Some(ref package_name) = self.package_name {
+            init_args.push("--name".to_owned());
+            init_args.push(package_name.clone());
+        }
+
         // use cargo to initialise a library Rust crate without any version control
-        util::run_cmd(
-            base_path.as_ref().to_path_buf(),
-            "cargo".into(),
-            vec![
-                "init".to_owned(),
-                "--lib".to_owned(),
-                "--vcs".to_owned(),
-                "none".to_owned(),
-            ],
-        )?;
+        util::run_cmd(base_path.as_ref().to_path_buf(), "cargo".into(), init_args)?;
 
         // add hdk-rust dependency by default
         let cargo_file_path = base_path.as_ref().join(package::CARGO_FILE_NAME);
@@ -54,11 +267,41 @@ impl Scaffold for RustScaffold {
 
         cargo_file.write_all(hdk_dep.as_bytes())?;
 
-        // add WASM friendly lib configuration properties
-        let lib_config: &str = "\n\n[lib]\npath = \"src/lib.rs\"\ncrate-type = [\"cdylib\"]\n";
+        if self.tiny {
+            cargo_file.write_all(b"\nwee_alloc = \"0.4\"")?;
+        }
+
+        for (name, version) in &self.dependencies {
+            cargo_file.write_all(format!("\n{} = \"{}\"", name, version).as_bytes())?;
+        }
+
+        // add WASM friendly lib configuration properties, the library name keeps the artifact
+        // at code.wasm whatever the package is called
+        let lib_config: &str =
+            "\n\n[lib]\nname = \"code\"\npath = \"src/lib.rs\"\ncrate-type = [\"cdylib\"]\n";
 
         cargo_file.write_all(lib_config.as_bytes())?;
 
+        // replace the example of cargo init with one of testing a Zome
+        fs::write(base_path.as_ref().join(LIB_FILE_PATH), LIB_TEMPLATE)?;
+
+        if self.tiny {
+            cargo_file.write_all(TINY_PROFILE.as_bytes())?;
+
+            let lib_file_path = base_path.as_ref().join(LIB_FILE_PATH);
+            let lib_code = fs::read_to_string(&lib_file_path)?;
+
+            fs::write(&lib_file_path, format!("{}{}", TINY_ALLOCATOR, lib_code))?;
+            fs::write(base_path.as_ref().join(README_FILE_NAME), TINY_README)?;
+        }
+
+        if let Some(ref license) = self.license {
+            let lib_file_path = base_path.as_ref().join(LIB_FILE_PATH);
+            let lib_code = fs::read_to_string(&lib_file_path)?;
+
+            fs::write(&lib_file_path, with_license_header(&lib_code, license))?;
+        }
+
         // create and fill in a build file appropriate for Rust
         let build_file_path = base_path.as_ref().join(package::BUILD_CONFIG_FILE_NAME);
 
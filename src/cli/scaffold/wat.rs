@@ -0,0 +1,62 @@
+use cli::{license::SPDX_TAG, package, scaffold::Scaffold};
+use config_files::Build;
+use error::DefaultResult;
+use std::{fs, path::Path};
+
+pub const SOURCE_FILE_NAME: &str = "code.wat";
+const ARTIFACT_FILE_NAME: &str = "code.wasm";
+
+const SOURCE: &str = "\
+(module
+  ;; the conductor reads arguments and results of zome functions from the module's memory
+  (memory (export \"memory\") 1)
+
+  ;; a zome function gets the address of its JSON argument in memory and returns the address
+  ;; of its JSON result, 0 being the empty result
+  (func (export \"hello\") (param $input i32) (result i32)
+    i32.const 0)
+)
+";
+
+/// A Zome written by hand in the WebAssembly text format, for full control over the WASM the
+/// conductor gets, e.g. to learn what it expects or to test how it handles unusual modules
+pub struct WatScaffold {
+    build_template: Build,
+    license: Option<String>,
+}
+
+impl WatScaffold {
+    pub fn new() -> WatScaffold {
+        WatScaffold {
+            build_template: Build::with_artifact(ARTIFACT_FILE_NAME)
+                .cmd("wat2wasm", &[SOURCE_FILE_NAME, "-o", ARTIFACT_FILE_NAME]),
+            license: None,
+        }
+    }
+
+    /// Sets the SPDX license expression to put in a header of the generated source
+    pub fn license(mut self, license: Option<String>) -> WatScaffold {
+        self.license = license;
+        self
+    }
+}
+
+impl Scaffold for WatScaffold {
+    fn gen<P: AsRef<Path>>(&self, base_path: P) -> DefaultResult<()> {
+        fs::create_dir_all(&base_path)?;
+
+        let source = match self.license {
+            Some(ref license) => format!(";; {} {}\n\n{}", SPDX_TAG, license, SOURCE),
+            None => SOURCE.to_string(),
+        };
+
+        fs::write(base_path.as_ref().join(SOURCE_FILE_NAME), source)?;
+
+        // create and fill in a build file running wabt's assembler
+        let build_file_path = base_path.as_ref().join(package::BUILD_CONFIG_FILE_NAME);
+
+        self.build_template.save_as(build_file_path)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+//! Sets an app up from `hc-scaffold.yaml`, a declaration of its Zomes, entry types and links.
+//!
+//! Zomes that don't exist yet are generated, the entry types of all of them are brought in line
+//! with the spec. Running it again after editing the spec evolves the app, so a change to its
+//! skeleton can be reviewed as a diff of the spec. Nothing that isn't in the spec is removed:
+//! entry types can hold data, dropping one is left to a deliberate edit of the manifest.
+
+use cli::{
+    generate::{generate, ZOME_CONFIG_FILE_NAME},
+    layout::Layout,
+};
+use error::DefaultResult;
+use i18n;
+use output;
+use serde_json::{self, Map, Value};
+use serde_yaml;
+use std::{collections::BTreeMap, fs};
+
+pub const SPEC_FILE_NAME: &str = "hc-scaffold.yaml";
+
+const ENTRY_TYPES_KEY: &str = "entry_types";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    #[serde(default)]
+    zomes: BTreeMap<String, ZomeSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ZomeSpec {
+    #[serde(default = "default_language")]
+    language: String,
+    #[serde(default)]
+    tiny: bool,
+    /// Further crates as `<crate>@<version>`
+    #[serde(default)]
+    crates: Vec<String>,
+    description: Option<String>,
+    #[serde(default)]
+    entry_types: BTreeMap<String, EntryTypeSpec>,
+    /// Declared once here, they end up in the `links_to` of the base and the `linked_from` of
+    /// the target entry type
+    #[serde(default)]
+    links: Vec<LinkSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EntryTypeSpec {
+    description: Option<String>,
+    #[serde(default = "default_sharing")]
+    sharing: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LinkSpec {
+    from: String,
+    to: String,
+    tag: String,
+}
+
+fn default_language() -> String {
+    "rust".to_string()
+}
+
+fn default_sharing() -> String {
+    "public".to_string()
+}
+
+/// Generates the missing Zomes of the spec in the current directory and updates the entry types
+/// of every Zome in it
+pub fn generate_from_spec() -> DefaultResult<()> {
+    let spec = fs::read_to_string(SPEC_FILE_NAME)
+        .map_err(|err| format_err!("unable to read {}: {}", SPEC_FILE_NAME, err))?;
+    let spec: Spec =
+        serde_yaml::from_str(&spec).map_err(|err| format_err!("{}: {}", SPEC_FILE_NAME, err))?;
+
+    let layout = Layout::of_app(".")?;
+
+    for (name, zome) in &spec.zomes {
+        let zome_path = layout.zome_dir(name);
+        let config_path = zome_path.join(ZOME_CONFIG_FILE_NAME);
+
+        if !config_path.is_file() {
+            generate(&zome_path, &zome.language, zome.tiny, &zome.crates)?;
+        }
+
+        let mut config: Value = serde_json::from_str(&fs::read_to_string(&config_path)?)
+            .map_err(|err| format_err!("{:?}: {}", config_path, err))?;
+
+        apply(name, zome, &mut config)?;
+
+        fs::write(&config_path, serde_json::to_string_pretty(&config)? + "\n")?;
+
+        output::print(i18n::tr(
+            "scaffolded-zome",
+            &[
+                ("status", output::status("status-scaffolded")),
+                ("path", format!("{:?}", zome_path)),
+                ("count", zome.entry_types.len().to_string()),
+            ],
+        ));
+    }
+
+    Ok(())
+}
+
+/// Brings the manifest of a Zome in line with its spec, keeping what the spec doesn't describe,
+/// like the schema versions of entry types
+fn apply(name: &str, zome: &ZomeSpec, config: &mut Value) -> DefaultResult<()> {
+    ensure!(config.is_object(), "the manifest of zome {} isn't an object", name);
+
+    for link in &zome.links {
+        for end in &[&link.from, &link.to] {
+            ensure!(
+                zome.entry_types.contains_key(*end),
+                "link {} of zome {} refers to {}, which is no entry type of the zome",
+                link.tag,
+                name,
+                end
+            );
+        }
+    }
+
+    if let Some(ref description) = zome.description {
+        config["description"] = description.as_str().into();
+    }
+
+    if !config[ENTRY_TYPES_KEY].is_object() {
+        config[ENTRY_TYPES_KEY] = Map::new().into();
+    }
+
+    for (entry_type, spec) in &zome.entry_types {
+        let links_to: Vec<Value> = zome
+            .links
+            .iter()
+            .filter(|link| link.from == *entry_type)
+            .map(|link| json!({ "target_type": link.to, "tag": link.tag }))
+            .collect();
+        let linked_from: Vec<Value> = zome
+            .links
+            .iter()
+            .filter(|link| link.to == *entry_type)
+            .map(|link| json!({ "base_type": link.from, "tag": link.tag }))
+            .collect();
+
+        let declared = &mut config[ENTRY_TYPES_KEY][entry_type];
+
+        if !declared.is_object() {
+            *declared = Map::new().into();
+        }
+
+        if let Some(ref description) = spec.description {
+            declared["description"] = description.as_str().into();
+        }
+
+        declared["sharing"] = spec.sharing.as_str().into();
+        declared["links_to"] = links_to.into();
+        declared["linked_from"] = linked_from.into();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declares_links_on_both_ends() {
+        let spec: Spec = serde_yaml::from_str(
+            "
+zomes:
+  blog:
+    description: Posts and their comments
+    entry_types:
+      post:
+        description: A blog post
+      comment:
+        sharing: private
+    links:
+      - from: post
+        to: comment
+        tag: comments
+",
+        ).unwrap();
+        let mut config = json!({
+            "description": "The blog App",
+            "entry_types": { "post": { "schema_version": 2 } }
+        });
+
+        apply("blog", &spec.zomes["blog"], &mut config).unwrap();
+
+        assert_eq!(
+            config,
+            json!({
+                "description": "Posts and their comments",
+                "entry_types": {
+                    "post": {
+                        "schema_version": 2,
+                        "description": "A blog post",
+                        "sharing": "public",
+                        "links_to": [{ "target_type": "comment", "tag": "comments" }],
+                        "linked_from": []
+                    },
+                    "comment": {
+                        "sharing": "private",
+                        "links_to": [],
+                        "linked_from": [{ "base_type": "post", "tag": "comments" }]
+                    }
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use base64;
+use cli::package::{
+    self, Object, META_BIN_ID, META_DIR_ID, META_FILE_ID, META_SECTION_NAME, META_TREE_SECTION_NAME,
+};
+use error::DefaultResult;
+use flate2::{write::GzEncoder, Compression};
+use output;
+use serde_json::{self, Value};
+use std::{io::Write, path::PathBuf};
+use util;
+
+const TOP_ENTRIES: usize = 10;
+const BAR_WIDTH: f64 = 30.0;
+const LABEL_WIDTH: usize = 44;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Wasm,
+    Asset,
+    Config,
+    Meta,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Wasm => "WASM",
+            Kind::Asset => "Assets",
+            Kind::Config => "Config",
+            Kind::Meta => "Meta",
+        }
+    }
+}
+
+struct Entry {
+    path: String,
+    kind: Kind,
+    /// What the entry takes up in the bundle JSON
+    size: u64,
+    /// The decoded size of base64 encoded entries
+    raw_size: Option<u64>,
+}
+
+/// Prints how the size of a bundle is distributed over its Zomes, assets and configuration
+pub fn stats_bundle(path: &PathBuf) -> DefaultResult<()> {
+    let bundle = package::read_plain_bundle(path)?;
+    let serialized = serde_json::to_vec(&bundle)?;
+    let total = serialized.len() as u64;
+
+    let mut entries = Vec::new();
+
+    if let Value::Object(obj) = bundle {
+        collect_entries(&obj, "", &mut entries)?;
+    }
+
+    entries.sort_by(|a, b| b.size.cmp(&a.size));
+
+    output::print(format!("{:?}: {}", path, util::format_size(total)));
+
+    for kind in &[Kind::Wasm, Kind::Asset, Kind::Config, Kind::Meta] {
+        let children: Vec<_> = entries.iter().filter(|entry| entry.kind == *kind).collect();
+        let size = children.iter().map(|entry| entry.size).sum();
+
+        output::print("");
+        output::print(line(kind.label(), size, total, 2));
+
+        for child in children.iter().take(TOP_ENTRIES) {
+            output::print(line(&describe(child), child.size, total, 4));
+        }
+
+        if children.len() > TOP_ENTRIES {
+            output::print(format!("    ... and {} more", children.len() - TOP_ENTRIES));
+        }
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&serialized)?;
+    let compressed = encoder.finish()?.len() as u64;
+
+    output::print("");
+    output::print(format!(
+        "Compression potential: {} with gzip, {:.1}% smaller",
+        util::format_size(compressed),
+        percent(total - compressed.min(total), total)
+    ));
+
+    output::print("");
+    output::print(format!("Top {} largest entries:", TOP_ENTRIES));
+
+    for (index, entry) in entries.iter().take(TOP_ENTRIES).enumerate() {
+        output::print(format!(
+            "  {:>2}. {:>10}  {}",
+            index + 1,
+            util::format_size(entry.size),
+            describe(entry)
+        ));
+    }
+
+    Ok(())
+}
+
+fn collect_entries(obj: &Object, prefix: &str, entries: &mut Vec<Entry>) -> DefaultResult<()> {
+    let meta = obj.get(META_SECTION_NAME);
+    let tree = meta
+        .and_then(|meta| meta.get(META_TREE_SECTION_NAME))
+        .and_then(|tree| tree.as_object());
+
+    if let Some(meta) = meta {
+        entries.push(Entry {
+            path: join(prefix, META_SECTION_NAME),
+            kind: Kind::Meta,
+            size: json_size(meta)?,
+            raw_size: None,
+        });
+    }
+
+    for (key, value) in obj.iter().filter(|(key, _)| *key != META_SECTION_NAME) {
+        let path = join(prefix, key);
+        let node_type = match tree {
+            Some(tree) => tree.get(key).and_then(|node_type| node_type.as_str()),
+            // bundles with stripped meta sections have to be taken apart by their shape
+            None if value.get("code").map_or(false, |code| code.is_string()) => Some(META_BIN_ID),
+            None if value.is_object() => Some(META_DIR_ID),
+            None => None,
+        };
+
+        match (node_type, value) {
+            (Some(META_DIR_ID), Value::Object(dir)) => collect_entries(dir, &path, entries)?,
+            (Some(META_BIN_ID), _) => entries.push(Entry {
+                path,
+                kind: Kind::Wasm,
+                size: json_size(value)?,
+                raw_size: value["code"].as_str().map(decoded_size),
+            }),
+            (Some(META_FILE_ID), _) => entries.push(Entry {
+                path,
+                kind: Kind::Asset,
+                size: json_size(value)?,
+                raw_size: value.as_str().map(decoded_size),
+            }),
+            _ => entries.push(Entry {
+                path,
+                kind: Kind::Config,
+                size: json_size(value)?,
+                raw_size: None,
+            }),
+        }
+    }
+
+    Ok(())
+}
+
+fn line(label: &str, size: u64, total: u64, indent: usize) -> String {
+    let share = percent(size, total);
+    let bar = "█".repeat((share / 100.0 * BAR_WIDTH).round() as usize);
+
+    format!(
+        "{:indent$}{:<width$} {:>10} {:>6.1}%  {}",
+        "",
+        label,
+        util::format_size(size),
+        share,
+        bar,
+        indent = indent,
+        width = LABEL_WIDTH - indent
+    )
+}
+
+fn describe(entry: &Entry) -> String {
+    match entry.raw_size {
+        Some(raw_size) => format!("{} ({} decoded)", entry.path, util::format_size(raw_size)),
+        None => entry.path.clone(),
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}/{}", prefix, key)
+    }
+}
+
+fn json_size(value: &Value) -> DefaultResult<u64> {
+    Ok(serde_json::to_vec(value)?.len() as u64)
+}
+
+fn decoded_size(encoded: &str) -> u64 {
+    base64::decode(encoded)
+        .map(|bytes| bytes.len() as u64)
+        .unwrap_or(0)
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_bundle_entries() {
+        let bundle = json!({
+            "name": "app",
+            "__META__": { "tree": { "zomes": "dir", "ui": "dir" } },
+            "zomes": {
+                "__META__": { "tree": { "chat": "dir" } },
+                "chat": {
+                    "description": "chat",
+                    "__META__": { "tree": { "code": "bin" } },
+                    "code": { "code": "AAAA" }
+                }
+            },
+            "ui": {
+                "__META__": { "tree": { "index.html": "file" } },
+                "index.html": "PGh0bWw+"
+            }
+        });
+
+        let mut entries = Vec::new();
+        collect_entries(bundle.as_object().unwrap(), "", &mut entries).unwrap();
+
+        let find = |path: &str| entries.iter().find(|entry| entry.path == path).unwrap();
+
+        assert!(find("zomes/chat/code").kind == Kind::Wasm);
+        assert_eq!(find("zomes/chat/code").raw_size, Some(3));
+        assert!(find("ui/index.html").kind == Kind::Asset);
+        assert!(find("zomes/chat/description").kind == Kind::Config);
+        assert!(find("name").kind == Kind::Config);
+        assert!(find("zomes/__META__").kind == Kind::Meta);
+    }
+}
@@ -0,0 +1,365 @@
+//! An overview of the app like `git status` gives one of the repository: its Zomes and whether
+//! their builds are fresh, the state of the manifests, the bundle and the toolchain. With
+//! `--files` it shows what `package` puts into the bundle, with `--ignored` what it leaves out
+//! and the `.hcignore` rule responsible for it.
+
+use build_cache;
+use cli::{
+    cache::packaged_build,
+    lint::{manifest_files, Host},
+    new::APP_CONFIG_FILE_NAME,
+    package::{BUILD_CONFIG_FILE_NAME, DEFAULT_BUNDLE_FILE_NAME},
+};
+use colored::*;
+use config_files::Project;
+use error::DefaultResult;
+use hcignore::{IgnoreRules, Rule};
+use ignore::WalkBuilder;
+use output;
+use serde_json::{self, Value};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use warnings::{HOST_SPECIFIC_VALUE, MISSING_DESCRIPTION};
+
+#[derive(Debug, PartialEq)]
+enum Entry {
+    File(PathBuf),
+    /// A code directory, only its built artifact goes into the bundle
+    Zome(PathBuf),
+    Ignored(PathBuf, Rule),
+}
+
+/// Sums up the state of the app in the current directory, or lists the files that get packaged
+/// or the ignored ones
+pub fn status(files: bool, ignored: bool) -> DefaultResult<()> {
+    let root = Path::new(".");
+    let mut rules = IgnoreRules::load(root)?;
+    let mut entries = Vec::new();
+
+    walk(root, &mut rules, &mut entries)?;
+
+    if !files && !ignored {
+        return overview(root, &entries);
+    }
+
+    for entry in entries {
+        match (entry, ignored) {
+            (Entry::File(path), false) => output::print(display(root, &path)),
+            (Entry::Zome(path), false) => output::print(format!(
+                "{}/ (built from {})",
+                display(root, &path),
+                BUILD_CONFIG_FILE_NAME
+            )),
+            (Entry::Ignored(path, rule), true) => output::print(format!(
+                "{}  ({}: {})",
+                display(root, &path),
+                display(root, &rule.file),
+                rule.pattern
+            )),
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn overview(root: &Path, entries: &[Entry]) -> DefaultResult<()> {
+    let cargo_lock = Project::from_dir(root)?.zomes.cargo_lock;
+    let app_name = fs::read(root.join(APP_CONFIG_FILE_NAME))
+        .ok()
+        .and_then(|json| serde_json::from_slice::<Value>(&json).ok())
+        .and_then(|app_config| app_config["name"].as_str().map(str::to_string));
+
+    section("App", app_name.unwrap_or_else(|| "(unnamed)".to_string()));
+
+    // everything the bundle is made of, to tell whether it is older than any of it
+    let mut sources = Vec::new();
+    let mut toolchain = BTreeMap::new();
+    let mut zomes = 0;
+
+    output::print("Zomes:");
+
+    for entry in entries {
+        let code_dir = match entry {
+            Entry::File(path) => {
+                sources.push(path.clone());
+                continue;
+            }
+            Entry::Zome(code_dir) => code_dir,
+            Entry::Ignored(..) => continue,
+        };
+
+        zomes += 1;
+        sources.push(code_dir.join(BUILD_CONFIG_FILE_NAME));
+
+        let build = match packaged_build(code_dir, cargo_lock) {
+            Ok(build) => build,
+            Err(err) => {
+                output::print(format!(
+                    "  {}  {}: {}",
+                    display(root, code_dir),
+                    "invalid".red(),
+                    err
+                ));
+                continue;
+            }
+        };
+
+        let outputs = [
+            code_dir.join(&build.artifact),
+            code_dir.join(build.output()),
+        ];
+        let reasons = build_cache::rebuild_reasons(&build, code_dir)?;
+
+        let state = match reasons.first() {
+            None => "fresh".green().to_string(),
+            Some(reason) => format!("{}: {}", "will rebuild".yellow(), reason),
+        };
+
+        output::print(format!("  {}  {}", display(root, code_dir), state));
+
+        sources.extend(build_cache::source_files(code_dir, &outputs));
+        toolchain.extend(build_cache::toolchain(&build, code_dir));
+    }
+
+    if zomes == 0 {
+        output::print("  none, add one with hcdev generate");
+    }
+
+    let mut invalid = Vec::new();
+    let mut warnings = Vec::new();
+    let host = Host::current();
+
+    for file in manifest_files()? {
+        let value: Value = match serde_json::from_slice(&fs::read(&file)?) {
+            Ok(value) => value,
+            Err(err) => {
+                invalid.push(format!("{}: {}", display(root, &file), err));
+                continue;
+            }
+        };
+
+        let has_description = value["description"]
+            .as_str()
+            .map_or(false, |description| !description.trim().is_empty());
+
+        if !has_description && !file.ends_with(BUILD_CONFIG_FILE_NAME) {
+            warnings.push(format!(
+                "warning[{}]: {} is missing a description",
+                MISSING_DESCRIPTION,
+                display(root, &file)
+            ));
+        }
+
+        for finding in host.check(&value) {
+            warnings.push(format!(
+                "warning[{}]: {}: {} is {}",
+                HOST_SPECIFIC_VALUE,
+                display(root, &file),
+                finding.key,
+                finding.kind
+            ));
+        }
+    }
+
+    if invalid.is_empty() {
+        section("Manifests", "valid".green().to_string());
+    } else {
+        section("Manifests", "invalid".red().to_string());
+
+        for problem in invalid {
+            output::print(format!("  {}", problem));
+        }
+    }
+
+    section("Bundle", bundle_state(root, &sources)?);
+
+    if warnings.is_empty() {
+        section("Warnings", "none".green().to_string());
+    } else {
+        section("Warnings", warnings.len().to_string().yellow().to_string());
+
+        for warning in warnings {
+            output::print(format!("  {}", warning));
+        }
+    }
+
+    let missing: Vec<&str> = toolchain
+        .iter()
+        .filter(|(_, version)| *version == "unavailable")
+        .map(|(tool, _)| tool.as_str())
+        .collect();
+
+    if missing.is_empty() {
+        let tools: Vec<&str> = toolchain.keys().map(String::as_str).collect();
+
+        section(
+            "Toolchain",
+            format!("{} {}", "ok".green(), tools.join(", ")).trim_end(),
+        );
+    } else {
+        section(
+            "Toolchain",
+            format!("{} {}", "missing".red(), missing.join(", ")),
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether the bundle is older than any of the files it is made of
+fn bundle_state(root: &Path, sources: &[PathBuf]) -> DefaultResult<String> {
+    let bundle = root.join(DEFAULT_BUNDLE_FILE_NAME);
+
+    let packaged = match fs::metadata(&bundle) {
+        Ok(metadata) => metadata.modified()?,
+        Err(_) => {
+            return Ok(format!(
+                "{}, run hcdev package",
+                "not packaged yet".yellow()
+            ))
+        }
+    };
+
+    let mut newest: Option<(SystemTime, &PathBuf)> = None;
+
+    for source in sources {
+        if let Ok(modified) = fs::metadata(source).and_then(|metadata| metadata.modified()) {
+            if modified > packaged && newest.map_or(true, |(time, _)| modified > time) {
+                newest = Some((modified, source));
+            }
+        }
+    }
+
+    Ok(match newest {
+        Some((_, source)) => format!(
+            "{}, {} changed after {} was packaged",
+            "stale".yellow(),
+            display(root, source),
+            DEFAULT_BUNDLE_FILE_NAME
+        ),
+        None => format!("{} ({})", "up to date".green(), DEFAULT_BUNDLE_FILE_NAME),
+    })
+}
+
+fn section<S: AsRef<str>>(label: &str, state: S) {
+    output::print(format!("{:<11}{}", format!("{}:", label), state.as_ref()));
+}
+
+fn display(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Walks the directory like `package` does, without descending into ignored directories
+fn walk(dir: &Path, rules: &mut IgnoreRules, entries: &mut Vec<Entry>) -> DefaultResult<()> {
+    let mut nodes: Vec<_> = WalkBuilder::new(dir)
+        .max_depth(Some(1))
+        .build()
+        .skip(1)
+        .filter_map(|e| e.ok())
+        .map(|e| e.path().to_path_buf())
+        .collect();
+
+    nodes.sort();
+
+    for node in nodes {
+        let is_dir = node.is_dir();
+
+        match rules.explain(&node, is_dir)? {
+            Some(ref rule) if !rule.whitelisted => entries.push(Entry::Ignored(node, rule.clone())),
+            _ if is_dir && node.join(BUILD_CONFIG_FILE_NAME).is_file() => {
+                entries.push(Entry::Zome(node))
+            }
+            _ if is_dir => walk(&node, rules, entries)?,
+            _ => entries.push(Entry::File(node)),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hcignore::IGNORE_FILE_NAME;
+    use std::{
+        fs::{self, File},
+        time::Duration,
+    };
+    use tempfile::Builder;
+
+    #[test]
+    fn names_the_rule_that_excluded_a_path() {
+        let tmp = Builder::new().prefix("status").tempdir().unwrap();
+        let root = tmp.path();
+        let zome = root.join("zomes/posts");
+
+        fs::create_dir_all(zome.join("code")).unwrap();
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(root.join(IGNORE_FILE_NAME), "notes/\n*.log\n").unwrap();
+        fs::write(zome.join(IGNORE_FILE_NAME), "!keep.log\n").unwrap();
+        fs::write(root.join("app.json"), "{}").unwrap();
+        fs::write(root.join("notes/todo.txt"), "").unwrap();
+        fs::write(zome.join("keep.log"), "").unwrap();
+        fs::write(zome.join("build.log"), "").unwrap();
+        fs::write(zome.join("code").join(BUILD_CONFIG_FILE_NAME), "{}").unwrap();
+
+        let mut rules = IgnoreRules::load(root).unwrap();
+        let mut entries = Vec::new();
+        walk(root, &mut rules, &mut entries).unwrap();
+
+        let rule = |pattern: &str, file: &Path| Rule {
+            file: file.join(IGNORE_FILE_NAME),
+            pattern: pattern.to_string(),
+            whitelisted: false,
+        };
+
+        assert_eq!(
+            entries,
+            vec![
+                Entry::File(root.join("app.json")),
+                Entry::Ignored(root.join("notes"), rule("notes/", root)),
+                Entry::Ignored(zome.join("build.log"), rule("*.log", root)),
+                Entry::Zome(zome.join("code")),
+                Entry::File(zome.join("keep.log")),
+            ]
+        );
+    }
+
+    #[test]
+    fn tells_a_bundle_older_than_its_sources() {
+        let tmp = Builder::new().prefix("status").tempdir().unwrap();
+        let root = tmp.path();
+        let source = root.join("app.json");
+
+        fs::write(&source, "{}").unwrap();
+
+        assert!(bundle_state(root, &[source.clone()])
+            .unwrap()
+            .contains("not packaged yet"));
+
+        fs::write(root.join(DEFAULT_BUNDLE_FILE_NAME), "{}").unwrap();
+
+        assert!(bundle_state(root, &[source.clone()])
+            .unwrap()
+            .contains("up to date"));
+
+        File::options()
+            .write(true)
+            .open(&source)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+
+        assert!(bundle_state(root, &[source])
+            .unwrap()
+            .contains("app.json changed after bundle.json was packaged"));
+    }
+}
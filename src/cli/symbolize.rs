@@ -0,0 +1,156 @@
+use dwarf::{LineTable, Location};
+use error::DefaultResult;
+use output;
+use rustc_demangle::demangle;
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+use wasm::{self, Module};
+
+const DEBUG_LINE_SECTION_NAME: &str = ".debug_line";
+
+/// How the runtimes write function indices in their backtraces: wasmtime's
+/// `<wasm function 42>`, V8's `wasm-function[42]`, wasmer's `<module>[42]` and `func[42]`
+const FUNCTION_INDEX_MARKERS: &[&str] = &["wasm function ", "wasm-function[", ">[", "func["];
+
+struct Symbolizer {
+    debug: Module,
+    lines: Option<LineTable>,
+    /// Where the code section starts in the stripped module the conductor ran
+    code_offset: u64,
+}
+
+impl Symbolizer {
+    fn new(debug_bytes: &[u8]) -> DefaultResult<Symbolizer> {
+        let debug = Module::parse(debug_bytes)?;
+        let stripped = Module::parse(&wasm::strip_custom_sections(debug_bytes)?)?;
+
+        let lines = match wasm::custom_section(debug_bytes, DEBUG_LINE_SECTION_NAME)? {
+            Some(debug_line) => Some(LineTable::parse(debug_line)?),
+            None => None,
+        };
+
+        Ok(Symbolizer {
+            debug,
+            lines,
+            code_offset: stripped
+                .code_offset()
+                .ok_or_else(|| format_err!("the module has no code section"))?,
+        })
+    }
+
+    /// Describes the frame of a backtrace line, if it has one
+    fn frame(&self, line: &str) -> Option<String> {
+        let code_offset = module_offset(line)
+            .and_then(|offset| offset.checked_sub(self.code_offset))
+            .filter(|offset| self.debug.function_at(*offset).is_some());
+
+        let function = match function_index(line) {
+            Some(index) => self
+                .debug
+                .functions
+                .iter()
+                .find(|function| function.index == index),
+            None => code_offset.and_then(|offset| self.debug.function_at(offset)),
+        }?;
+
+        let name = match function.name {
+            Some(ref name) => format!("{:#}", demangle(name)),
+            None => format!("function {}", function.index),
+        };
+
+        let location = code_offset
+            .and_then(|offset| self.lines.as_ref().and_then(|lines| lines.locate(offset)));
+
+        Some(match location {
+            Some(Location { file, line, column }) => {
+                format!("{} at {}:{}:{}", name, file, line, column)
+            }
+            None => name,
+        })
+    }
+}
+
+/// Translates the WASM function indices and offsets in a trap log into Rust function names
+/// and source locations, using the debug info file `package --debug-info` wrote
+pub fn symbolize(debug_file: &PathBuf, log: Option<PathBuf>) -> DefaultResult<()> {
+    let symbolizer = Symbolizer::new(&fs::read(debug_file)?)?;
+
+    if !symbolizer.debug.has_names() {
+        output::print_err(format!(
+            "{:?} contains no function names, was it built with debug info?",
+            debug_file
+        ));
+    }
+
+    let log = match log {
+        Some(path) => fs::read_to_string(path)?,
+        None => {
+            let mut log = String::new();
+            io::stdin().read_to_string(&mut log)?;
+            log
+        }
+    };
+
+    for line in log.lines() {
+        output::print(line);
+
+        if let Some(frame) = symbolizer.frame(line) {
+            let indent = line.len() - line.trim_start().len();
+            output::print(format!("{}  -> {}", &line[..indent], frame));
+        }
+    }
+
+    Ok(())
+}
+
+fn function_index(line: &str) -> Option<u32> {
+    FUNCTION_INDEX_MARKERS.iter().find_map(|marker| {
+        let start = line.find(marker)? + marker.len();
+        let digits: String = line[start..]
+            .chars()
+            .take_while(|c| c.is_ascii_digit())
+            .collect();
+
+        digits.parse().ok()
+    })
+}
+
+/// The first hexadecimal number of the line, which all runtimes use for module offsets
+fn module_offset(line: &str) -> Option<u64> {
+    let start = line.find("0x")? + 2;
+    let digits: String = line[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_hexdigit())
+        .collect();
+
+    u64::from_str_radix(&digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm::test_support;
+
+    #[test]
+    fn finds_the_functions_of_backtrace_lines() {
+        let bytes = test_support::module(&[("_ZN4chat4post17h0123456789abcdefE", 10)]);
+        let symbolizer = Symbolizer::new(&bytes).unwrap();
+        let function_offset = symbolizer.code_offset + symbolizer.debug.functions[0].offset;
+
+        assert_eq!(
+            symbolizer.frame("    0: 0x99 - <unknown>!<wasm function 1>"),
+            Some("chat::post".to_string())
+        );
+        assert_eq!(
+            symbolizer.frame(&format!(
+                "    at <unnamed> (<module>:0x{:x})",
+                function_offset + 2
+            )),
+            Some("chat::post".to_string())
+        );
+        assert_eq!(symbolizer.frame("RuntimeError: unreachable"), None);
+    }
+}
@@ -0,0 +1,504 @@
+use cli::new::APP_CONFIG_FILE_NAME;
+use colored::*;
+use config_files::{dirs, Global, Project};
+use error::DefaultResult;
+use i18n;
+use interrupt;
+use net::{self, OFFLINE_ENV_VAR};
+use output;
+use scripts;
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+use tempfile::{Builder, TempDir};
+
+/// Directories that never contain apps but can be huge
+const SKIPPED_DIR_NAMES: &[&str] = &["target", "node_modules"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Outcome {
+    Passed,
+    Failed,
+    BuildFailed,
+    NoTests,
+    Cancelled,
+}
+
+impl Outcome {
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Passed => "passed",
+            Outcome::Failed => "FAILED",
+            Outcome::BuildFailed => "build failed",
+            Outcome::NoTests => "no tests",
+            Outcome::Cancelled => "cancelled",
+        }
+    }
+
+    fn is_failure(self) -> bool {
+        self == Outcome::Failed || self == Outcome::BuildFailed
+    }
+}
+
+struct Report {
+    name: String,
+    outcome: Outcome,
+    duration: Duration,
+}
+
+impl Report {
+    fn new(name: String, outcome: Outcome, duration: Duration) -> Report {
+        Report {
+            name,
+            outcome,
+            duration,
+        }
+    }
+}
+
+/// A home of its own for a test process, so its settings, keys and data end up there instead of
+/// in the developer's, and suites running at once don't see each other's state. It starts with
+/// the global settings, like the registry, and shares the cache, which only holds what can be
+/// downloaded or built again.
+struct Sandbox {
+    home: TempDir,
+    cache_dir: PathBuf,
+}
+
+impl Sandbox {
+    fn new() -> DefaultResult<Sandbox> {
+        let home = Builder::new().prefix("hcdev-test").tempdir()?;
+        let settings = Global::file_path()?;
+
+        // with HC_HOME set, the settings are right in it
+        if let (true, Some(file_name)) = (settings.is_file(), settings.file_name()) {
+            fs::copy(&settings, home.path().join(file_name))?;
+        }
+
+        Ok(Sandbox {
+            home,
+            cache_dir: dirs::cache_dir()?,
+        })
+    }
+
+    fn apply(&self, command: &mut Command) {
+        command
+            .env(dirs::HOME_ENV_VAR, self.home.path())
+            .env(dirs::CACHE_DIR_ENV_VAR, &self.cache_dir);
+    }
+
+    /// Keeps the home instead of deleting it with the sandbox and tells where it is
+    fn keep(self, name: &str) {
+        let path = self.home.into_path();
+
+        output::print_err(i18n::tr(
+            "kept-test-home",
+            &[("name", name.to_string()), ("path", format!("{:?}", path))],
+        ));
+    }
+}
+
+struct Suite {
+    name: String,
+    root: PathBuf,
+    script: String,
+}
+
+/// Builds the current app and runs its `test` script. With `workspace`, every app below the
+/// current directory is built and their test suites run in parallel.
+pub fn test(workspace: bool, fail_fast: bool) -> DefaultResult<()> {
+    if workspace {
+        return test_workspace(fail_fast);
+    }
+
+    let root = PathBuf::from(".");
+
+    ensure!(
+        root.join(APP_CONFIG_FILE_NAME).is_file(),
+        "not inside a Holochain app, use --workspace to test all apps below the current directory"
+    );
+    ensure!(build(&root)?, "unable to build the app");
+
+    let script = test_script(&root)?.ok_or_else(|| {
+        format_err!("the app has no tests, add them with: config set scripts.test <command>")
+    })?;
+
+    output::command(scripts::TEST, &script);
+
+    let sandbox = Sandbox::new()?;
+    let mut command = scripts::script_command(&root, scripts::TEST, &script)?;
+    sandbox.apply(&mut command);
+
+    if !interrupt::status(&mut command)?.success() {
+        sandbox.keep(&display_name(&root));
+
+        bail!(i18n::tr(
+            "script-failed",
+            &[("name", scripts::TEST.to_owned())]
+        ));
+    }
+
+    Ok(())
+}
+
+fn test_workspace(fail_fast: bool) -> DefaultResult<()> {
+    let mut projects = Vec::new();
+    find_projects(Path::new("."), &mut projects)?;
+
+    ensure!(
+        !projects.is_empty(),
+        "no Holochain apps found below the current directory"
+    );
+
+    let mut reports = Vec::new();
+    let mut suites = Vec::new();
+
+    // builds run one after another, so they share cargo's caches instead of fighting over
+    // their locks, and only the test suites run in parallel
+    for root in projects {
+        let name = display_name(&root);
+
+        if fail_fast
+            && reports
+                .iter()
+                .any(|report: &Report| report.outcome.is_failure())
+        {
+            reports.push(Report::new(name, Outcome::Cancelled, Duration::default()));
+            continue;
+        }
+
+        output::print(format!("{} {}", "Building".green().bold(), name));
+
+        let started = Instant::now();
+
+        if !build(&root)? {
+            reports.push(Report::new(name, Outcome::BuildFailed, started.elapsed()));
+            continue;
+        }
+
+        match test_script(&root)? {
+            Some(script) => suites.push(Suite { name, root, script }),
+            None => reports.push(Report::new(name, Outcome::NoTests, Duration::default())),
+        }
+    }
+
+    if fail_fast && reports.iter().any(|report| report.outcome.is_failure()) {
+        for suite in suites {
+            reports.push(Report::new(
+                suite.name,
+                Outcome::Cancelled,
+                Duration::default(),
+            ));
+        }
+    } else {
+        reports.extend(run_suites(suites, fail_fast)?);
+    }
+
+    reports.sort_by(|a, b| a.name.cmp(&b.name));
+
+    print_summary(&reports);
+
+    let failed = reports
+        .iter()
+        .filter(|report| report.outcome.is_failure())
+        .count();
+
+    ensure!(
+        failed == 0,
+        i18n::tr(
+            "projects-failed",
+            &[
+                ("count", failed.to_string()),
+                ("total", reports.len().to_string()),
+            ],
+        )
+    );
+
+    Ok(())
+}
+
+/// Runs all test suites at once, prefixing every line of their output with the app's name
+fn run_suites(suites: Vec<Suite>, fail_fast: bool) -> DefaultResult<Vec<Report>> {
+    let width = suites
+        .iter()
+        .map(|suite| suite.name.len())
+        .max()
+        .unwrap_or(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut running = Vec::new();
+    let mut sandboxes = HashMap::new();
+
+    for suite in suites {
+        output::command(scripts::TEST, format!("{} ({})", suite.script, suite.name));
+
+        let sandbox = Sandbox::new()?;
+        let mut command = scripts::script_command(&suite.root, scripts::TEST, &suite.script)?;
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        sandbox.apply(&mut command);
+        sandboxes.insert(suite.name.clone(), sandbox);
+
+        let mut child = interrupt::spawn(&mut command)?;
+        let prefix = format!("{:<width$} |", suite.name, width = width)
+            .cyan()
+            .to_string();
+
+        let forwarders = vec![
+            forward(child.stdout.take(), prefix.clone(), output::print::<String>),
+            forward(child.stderr.take(), prefix, output::print_err::<String>),
+        ];
+
+        running.push((suite.name.clone(), child.id()));
+
+        let sender = sender.clone();
+        let name = suite.name;
+        let started = Instant::now();
+
+        thread::spawn(move || {
+            let passed = interrupt::wait(child)
+                .map(|status| status.success())
+                .unwrap_or(false);
+
+            for forwarder in forwarders {
+                let _ = forwarder.join();
+            }
+
+            let _ = sender.send((name, passed, started.elapsed()));
+        });
+    }
+
+    drop(sender);
+
+    let mut reports: Vec<Report> = Vec::new();
+    let mut cancelled = false;
+
+    for (name, passed, duration) in receiver {
+        let outcome = match (passed, cancelled) {
+            (true, _) => Outcome::Passed,
+            (false, true) => Outcome::Cancelled,
+            (false, false) => Outcome::Failed,
+        };
+
+        if outcome == Outcome::Failed && fail_fast {
+            cancelled = true;
+
+            for (other, id) in &running {
+                let finished = *other == name || reports.iter().any(|report| report.name == *other);
+
+                if !finished {
+                    interrupt::kill(*id);
+                }
+            }
+        }
+
+        // the homes of passed and cancelled suites are deleted with their sandbox
+        if let Some(sandbox) = sandboxes.remove(&name) {
+            if outcome == Outcome::Failed {
+                sandbox.keep(&name);
+            }
+        }
+
+        reports.push(Report::new(name, outcome, duration));
+    }
+
+    Ok(reports)
+}
+
+fn forward<R: Read + Send + 'static>(
+    stream: Option<R>,
+    prefix: String,
+    print: fn(String),
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        if let Some(stream) = stream {
+            for line in BufReader::new(stream).lines() {
+                match line {
+                    Ok(line) => print(format!("{} {}", prefix, line)),
+                    Err(_) => break,
+                }
+            }
+        }
+    })
+}
+
+fn print_summary(reports: &[Report]) {
+    let width = reports
+        .iter()
+        .map(|report| report.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("App".len());
+
+    output::print("");
+    output::print(format!(
+        "{:<width$}  {:<12}  {}",
+        "App",
+        "Result",
+        "Time",
+        width = width
+    ));
+
+    for report in reports {
+        let label = format!("{:<12}", report.outcome.label());
+        let label = match report.outcome {
+            Outcome::Passed => label.green(),
+            Outcome::Failed | Outcome::BuildFailed => label.red().bold(),
+            Outcome::NoTests | Outcome::Cancelled => label.yellow(),
+        };
+
+        output::print(format!(
+            "{:<width$}  {}  {:.1}s",
+            report.name,
+            label,
+            duration_secs(report.duration),
+            width = width
+        ));
+    }
+}
+
+/// Packages the app in a separate process, as packaging works on the current directory
+fn build(root: &PathBuf) -> DefaultResult<bool> {
+    let mut command = Command::new(env::current_exe()?);
+    command.arg("package").current_dir(root);
+
+    if net::is_offline() {
+        command.env(OFFLINE_ENV_VAR, "1");
+    }
+
+    Ok(interrupt::status(&mut command)?.success())
+}
+
+fn test_script(root: &PathBuf) -> DefaultResult<Option<String>> {
+    Ok(Project::from_dir(root)?.script(scripts::TEST).cloned())
+}
+
+/// Collects the apps below the given directory, apps aren't expected to be nested
+fn find_projects(dir: &Path, projects: &mut Vec<PathBuf>) -> DefaultResult<()> {
+    if dir.join(APP_CONFIG_FILE_NAME).is_file() {
+        projects.push(dir.to_path_buf());
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        if path.is_dir() && !name.starts_with('.') && !SKIPPED_DIR_NAMES.contains(&name.as_str()) {
+            find_projects(&path, projects)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn display_name(root: &Path) -> String {
+    match root.strip_prefix(".") {
+        Ok(relative) if relative != Path::new("") => relative.display().to_string(),
+        _ => ".".to_string(),
+    }
+}
+
+fn duration_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_millis()) / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::{fs, path::PathBuf, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn runs_the_tests_of_every_app_in_the_workspace() {
+        let tmp = Builder::new().prefix("workspace").tempdir().unwrap();
+
+        for (name, script) in &[("chat", "echo chatting"), ("files", "exit 1")] {
+            let app = tmp.path().join("dnas").join(name);
+
+            fs::create_dir_all(&app).unwrap();
+            fs::write(app.join("app.json"), "{}").unwrap();
+            fs::write(
+                app.join("hc.toml"),
+                format!("[scripts]\ntest = \"{}\"\n", script),
+            )
+            .unwrap();
+        }
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .env("HC_HOME", tmp.path().join("home"))
+            .args(&["--color", "never", "test", "--workspace"])
+            .output()
+            .unwrap();
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(!output.status.success());
+        assert!(stdout.contains("dnas/chat  | chatting"));
+        assert!(stdout.contains("dnas/chat   passed"));
+        assert!(stdout.contains("dnas/files  FAILED"));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("1 of 2"));
+    }
+
+    #[test]
+    fn tests_run_in_a_home_of_their_own() {
+        let tmp = Builder::new().prefix("sandbox").tempdir().unwrap();
+        let home = tmp.path().join("home");
+
+        fs::create_dir_all(&home).unwrap();
+        fs::write(home.join("config.toml"), "").unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+
+        let test = |script: &str| {
+            fs::write(
+                tmp.path().join("hc.toml"),
+                format!("[scripts]\ntest = '{}'\n", script),
+            )
+            .unwrap();
+
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .env("HC_HOME", &home)
+                .args(&["test"])
+                .output()
+                .unwrap()
+        };
+
+        // the settings come along, the developer's home stays out of reach
+        let output = test(&format!(
+            r#"test -f "$HC_HOME/config.toml" && test "$HC_HOME" != "{}""#,
+            home.display()
+        ));
+        assert!(output.status.success());
+
+        let output = test(r#"touch "$HC_HOME/agent"; exit 1"#);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let kept = stderr
+            .lines()
+            .find(|line| line.contains("kept the home"))
+            .and_then(|line| line.split('"').nth(1))
+            .map(PathBuf::from)
+            .unwrap();
+
+        assert!(!output.status.success());
+        assert!(kept.join("agent").is_file());
+        assert!(!home.join("agent").exists());
+
+        fs::remove_dir_all(kept).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+use cli::new::APP_CONFIG_FILE_NAME;
+use config_files::{Lock, Project};
+use error::DefaultResult;
+use output;
+use registry::Registry;
+use resolver;
+use serde_json::{self, Value};
+use std::{fs, path::PathBuf};
+use util;
+
+/// Resolves the app's DNA dependencies, updates `hc.lock` and prints the resulting graph
+pub fn tree(update: bool) -> DefaultResult<()> {
+    let project_root = PathBuf::from(".");
+    let project = Project::from_dir(&project_root)?;
+    let lock = Lock::from_dir(&project_root)?;
+
+    // the lockfile alone is enough as long as it still satisfies hc.toml
+    let from_lock = if update {
+        None
+    } else {
+        resolver::resolve(&project.dependencies, &lock.dna, &lock.dna).ok()
+    };
+
+    let resolution = match from_lock {
+        Some(resolution) => resolution,
+        None => {
+            let registry = Registry::open()?;
+            let pinned = if update { Vec::new() } else { lock.dna.clone() };
+
+            resolver::resolve(&project.dependencies, &registry.index.dnas, &pinned)?
+        }
+    };
+
+    let new_lock = Lock {
+        dna: resolution.dnas.values().cloned().collect(),
+    };
+
+    if new_lock != lock {
+        new_lock.save_in(&project_root)?;
+    }
+
+    output::print(resolution.tree(&app_name(&project_root)?).trim_end());
+
+    Ok(())
+}
+
+fn app_name(project_root: &PathBuf) -> DefaultResult<String> {
+    let app_config = project_root.join(APP_CONFIG_FILE_NAME);
+
+    let name = if app_config.is_file() {
+        serde_json::from_str::<Value>(&fs::read_to_string(&app_config)?)?
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map(|name| name.to_string())
+    } else {
+        None
+    };
+
+    match name {
+        Some(name) => Ok(name),
+        None => util::file_name_string(&project_root.canonicalize()?),
+    }
+}
@@ -0,0 +1,149 @@
+//! Checks the WASM of every Zome right after it is built, before it goes into the bundle.
+//!
+//! A module that doesn't parse, misses a function its manifest declares, imports something the
+//! conductor doesn't provide or asks for more memory than is sane builds and packages fine, and
+//! only fails once the conductor instantiates or calls the Zome. Packaging reports all of that
+//! for every Zome at once instead.
+
+use cli::{
+    lint::foreign_imports,
+    package::{Object, MANIFEST_CAPABILITIES_KEY},
+};
+use error::DefaultResult;
+use output;
+use serde_json::Value;
+use std::path::PathBuf;
+use wasm::Module;
+
+/// The conductor allocates the initial memory for every instance of a Zome, a module asking for
+/// more than these 64 MiB is more likely misconfigured than in need of it
+pub const MAX_INITIAL_PAGES: u32 = 1024;
+
+/// What keeps the WASM from being instantiated and called the way the manifest of the Zome
+/// declares, nothing if it is fine
+pub fn check_zome(wasm: &[u8], manifest: &Object) -> Vec<String> {
+    let module = match Module::parse(wasm) {
+        Ok(module) => module,
+        Err(err) => return vec![format!("its WASM can't be parsed: {}", err)],
+    };
+    let mut problems = Vec::new();
+
+    for import in foreign_imports(&module) {
+        problems.push(format!(
+            "imports {}.{}, which the conductor doesn't provide",
+            import.module, import.name
+        ));
+    }
+
+    for function in declared_functions(manifest) {
+        if !module.exports.contains(&function) {
+            problems.push(format!(
+                "doesn't export {}, which its manifest declares",
+                function
+            ));
+        }
+    }
+
+    if module.memories.len() > 1 {
+        problems.push(format!(
+            "has {} memories, the conductor supports only one",
+            module.memories.len()
+        ));
+    }
+
+    for memory in &module.memories {
+        if memory.initial > MAX_INITIAL_PAGES {
+            problems.push(format!(
+                "starts with {} MiB of memory, more than the {} MiB allowed",
+                u64::from(memory.initial) * 64 / 1024,
+                MAX_INITIAL_PAGES * 64 / 1024
+            ));
+        }
+
+        match memory.maximum {
+            Some(maximum) if maximum < memory.initial => problems.push(format!(
+                "limits its memory to {} pages, fewer than the {} it starts with",
+                maximum, memory.initial
+            )),
+            _ => (),
+        }
+    }
+
+    problems
+}
+
+/// The names of the functions in the capabilities of a Zome's manifest
+fn declared_functions(manifest: &Object) -> Vec<String> {
+    manifest
+        .get(MANIFEST_CAPABILITIES_KEY)
+        .and_then(Value::as_object)
+        .into_iter()
+        .flat_map(|capabilities| capabilities.values())
+        .flat_map(|capability| capability["functions"].as_array().into_iter().flatten())
+        .filter_map(|function| function["name"].as_str())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Prints the problems of every Zome that failed the checks and fails if there is any
+pub fn report(invalid: &[(PathBuf, Vec<String>)]) -> DefaultResult<()> {
+    for (zome, problems) in invalid {
+        output::error(format!("zome {:?}:", zome));
+
+        for problem in problems {
+            output::error(format!("  - {}", problem));
+        }
+    }
+
+    ensure!(
+        invalid.is_empty(),
+        "the WASM of {} zome(s) would fail in the conductor, fix the problems listed above",
+        invalid.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use wasm::test_support;
+
+    #[test]
+    fn finds_what_would_fail_in_the_conductor() {
+        let manifest: Object = serde_json::from_value(json!({
+            "capabilities": {
+                "main": {
+                    "capability": { "membrane": "public" },
+                    "functions": [{ "name": "create_post" }, { "name": "get_post" }]
+                }
+            }
+        }))
+        .unwrap();
+
+        let valid = test_support::linked_module_with_memory(
+            &["hc_commit_entry"],
+            &["create_post", "get_post"],
+            &[(17, None)],
+        );
+        assert!(check_zome(&valid, &manifest).is_empty());
+
+        let invalid = test_support::linked_module_with_memory(
+            &["hc_commit_entry", "env/memcpy"],
+            &["create_post"],
+            &[(2048, Some(16))],
+        );
+        assert_eq!(
+            check_zome(&invalid, &manifest),
+            vec![
+                "imports env.memcpy, which the conductor doesn't provide",
+                "doesn't export get_post, which its manifest declares",
+                "starts with 128 MiB of memory, more than the 64 MiB allowed",
+                "limits its memory to 16 pages, fewer than the 2048 it starts with",
+            ]
+        );
+
+        assert_eq!(check_zome(b"wasm", &manifest).len(), 1);
+    }
+}
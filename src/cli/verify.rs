@@ -0,0 +1,386 @@
+//! Checks a bundle without unpacking or installing it, for CI to gate releases on.
+//!
+//! Every class of problem exits with a code of its own, so a pipeline can tell a corrupted
+//! download from a bundle that was packaged wrong. All checks run even if one fails, the exit
+//! code is the one of the first failure.
+
+use base64;
+use bundle::{self, BundleType};
+use cli::{
+    keys,
+    package::{
+        self, Object, META_BIN_ID, META_CONFIG_SECTION_NAME, META_DIR_ID, META_FILE_ID,
+        META_SECTION_NAME, META_TREE_SECTION_NAME,
+    },
+};
+use config_files::App as AppConfig;
+use delta;
+use encryption;
+use error::DefaultResult;
+use json;
+use output;
+use provenance;
+use serde_json::{self, Value};
+use std::{fs, path::PathBuf};
+use util;
+
+/// The prefix of the checksums the registry publishes
+const SHA256_PREFIX: &str = "sha256:";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Check {
+    /// The header, the JSON and the tree of files and directories
+    Structure,
+    /// The digest of the file against the one that was published
+    Checksum,
+    /// The authentication tag of an encrypted bundle
+    Authenticity,
+    /// The app's `app.json`
+    Manifest,
+    /// Unpacking stays inside of the target directory and within the unpack limits
+    Unpack,
+    /// The signed provenance next to the bundle is about this bundle
+    Provenance,
+}
+
+impl Check {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Check::Structure => 2,
+            Check::Checksum => 3,
+            Check::Authenticity => 4,
+            Check::Manifest => 5,
+            Check::Unpack => 6,
+            Check::Provenance => 7,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Check::Structure => "structure",
+            Check::Checksum => "checksum",
+            Check::Authenticity => "authenticity",
+            Check::Manifest => "manifest",
+            Check::Unpack => "unpack",
+            Check::Provenance => "provenance",
+        }
+    }
+}
+
+/// The outcome of all checks, in the order they ran
+struct Report {
+    failed: Option<Check>,
+}
+
+impl Report {
+    fn passed(&self, check: Check) {
+        output::print(format!("{:<14} ok", check.name()));
+    }
+
+    fn skipped(&self, check: Check, reason: &str) {
+        output::print(format!("{:<14} skipped, {}", check.name(), reason));
+    }
+
+    fn failed<E: ::std::fmt::Display>(&mut self, check: Check, err: E) {
+        output::error(format!("{:<14} failed: {}", check.name(), err));
+
+        self.failed = self.failed.or(Some(check));
+    }
+
+    fn record<T>(&mut self, check: Check, result: DefaultResult<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.passed(check);
+                Some(value)
+            }
+            Err(err) => {
+                self.failed(check, err);
+                None
+            }
+        }
+    }
+}
+
+/// Verifies the bundle, returning the exit code of the first check that failed or 0
+pub fn verify(
+    path: &PathBuf,
+    checksum: Option<String>,
+    identity: Option<PathBuf>,
+    provenance: Option<String>,
+) -> DefaultResult<i32> {
+    let bytes = fs::read(path).map_err(|err| format_err!("{:?}: {}", path, err))?;
+    let mut report = Report { failed: None };
+
+    match checksum {
+        Some(checksum) => {
+            let result = check_checksum(&bytes, &checksum);
+            report.record(Check::Checksum, result);
+        }
+        None => report.skipped(Check::Checksum, "pass the published one with --checksum"),
+    }
+
+    let content = match bundle::from_slice(&bytes) {
+        Ok((header, content)) => match header.bundle_type {
+            BundleType::App => {
+                report.skipped(Check::Authenticity, "the bundle isn't encrypted");
+
+                Some(content)
+            }
+            BundleType::Delta => {
+                let result = delta::delta(&content).and_then(|delta| {
+                    delta
+                        .map(|_| ())
+                        .ok_or_else(|| format_err!("the delta bundle is missing the delta"))
+                });
+                report.record(Check::Structure, result);
+                report.skipped(Check::Authenticity, "the bundle isn't encrypted");
+                report.skipped(Check::Manifest, "a delta has no manifest of its own");
+                report.skipped(Check::Unpack, "a delta can't be unpacked");
+
+                None
+            }
+            BundleType::Encrypted => {
+                let content = decrypt(&content, identity, &mut report);
+
+                if content.is_none() {
+                    report.skipped(Check::Manifest, "the content couldn't be read");
+                    report.skipped(Check::Unpack, "the content couldn't be read");
+                }
+
+                content
+            }
+        },
+        Err(err) => {
+            report.failed(Check::Structure, err);
+
+            None
+        }
+    };
+
+    if let Some(content) = content {
+        let content = match content {
+            Value::Object(obj) => Ok(obj),
+            _ => Err(format_err!("the bundle content is not a JSON object")),
+        };
+        let content = report.record(
+            Check::Structure,
+            content.and_then(|obj| check_tree(&obj, "").map(|_| obj)),
+        );
+
+        match content {
+            Some(content) => {
+                let result = check_manifest(&content);
+                report.record(Check::Manifest, result);
+
+                let result = package::check_unpack(content);
+                report.record(Check::Unpack, result);
+            }
+            None => {
+                report.skipped(Check::Manifest, "the structure is invalid");
+                report.skipped(Check::Unpack, "the structure is invalid");
+            }
+        }
+    }
+
+    match provenance {
+        Some(public_key) => {
+            let provenance_path = provenance::provenance_path(path);
+            let result = fs::read(&provenance_path)
+                .map_err(|err| format_err!("{:?}: {}", provenance_path, err))
+                .and_then(|envelope| provenance::verify(&bytes, &envelope, &public_key));
+            report.record(Check::Provenance, result);
+        }
+        None => report.skipped(
+            Check::Provenance,
+            "pass the builder's public key with --provenance",
+        ),
+    }
+
+    Ok(report.failed.map_or(0, Check::exit_code))
+}
+
+fn check_checksum(bytes: &[u8], checksum: &str) -> DefaultResult<()> {
+    let expected = checksum.trim_start_matches(SHA256_PREFIX).to_lowercase();
+    let actual = util::sha256_hex(bytes);
+
+    ensure!(
+        actual == expected,
+        "the bundle's digest is {}{}, expected {}{}",
+        SHA256_PREFIX,
+        actual,
+        SHA256_PREFIX,
+        expected
+    );
+
+    Ok(())
+}
+
+/// Decrypts an encrypted bundle, which also proves it wasn't tampered with
+fn decrypt(content: &Value, identity: Option<PathBuf>, report: &mut Report) -> Option<Value> {
+    let envelope = match encryption::envelope(content) {
+        Ok(Some(envelope)) => envelope,
+        Ok(None) => {
+            report.failed(
+                Check::Structure,
+                "the encrypted bundle is missing its envelope",
+            );
+            return None;
+        }
+        Err(err) => {
+            report.failed(Check::Structure, err);
+            return None;
+        }
+    };
+
+    let identity = match identity {
+        Some(identity) => Some(identity),
+        None => keys::default_identity_path()
+            .ok()
+            .filter(|path| path.is_file()),
+    };
+
+    let identity = match identity {
+        Some(identity) => identity,
+        None => {
+            report.skipped(
+                Check::Authenticity,
+                "pass the identity to decrypt the bundle with --identity",
+            );
+            return None;
+        }
+    };
+
+    let plaintext = encryption::read_identity(identity)
+        .and_then(|identity| encryption::decrypt(&envelope, &identity));
+    let plaintext = report.record(Check::Authenticity, plaintext)?;
+
+    match json::from_slice(&plaintext) {
+        Ok(content) => Some(content),
+        Err(err) => {
+            report.failed(Check::Structure, err);
+            None
+        }
+    }
+}
+
+/// Checks that every entry of the meta tree exists with the kind of content it's listed as
+fn check_tree(obj: &Object, path: &str) -> DefaultResult<()> {
+    let meta = match obj.get(META_SECTION_NAME) {
+        Some(Value::Object(meta)) => meta,
+        Some(_) => bail!("{}{} is not an object", path, META_SECTION_NAME),
+        None => return Ok(()),
+    };
+
+    if let Some(config_file) = meta.get(META_CONFIG_SECTION_NAME) {
+        ensure!(
+            config_file.is_string(),
+            "the config file name of {:?} is not a string",
+            path
+        );
+    }
+
+    let tree = match meta.get(META_TREE_SECTION_NAME) {
+        Some(Value::Object(tree)) => tree,
+        Some(_) => bail!("the tree of {:?} is not an object", path),
+        None => return Ok(()),
+    };
+
+    for (name, kind) in tree {
+        let entry_path = format!("{}{}", path, name);
+        let entry = obj
+            .get(name)
+            .ok_or_else(|| format_err!("{} is listed in the tree but missing", entry_path))?;
+
+        match (kind.as_str(), entry) {
+            (Some(META_FILE_ID), Value::String(content)) => {
+                base64::decode(content)
+                    .map_err(|err| format_err!("{} is not valid base64: {}", entry_path, err))?;
+            }
+            (Some(META_BIN_ID), Value::Object(zome)) => {
+                let code = zome
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format_err!("{} is missing its WASM code", entry_path))?;
+
+                base64::decode(code)
+                    .map_err(|err| format_err!("{} is not valid base64: {}", entry_path, err))?;
+            }
+            (Some(META_DIR_ID), Value::Object(dir)) => {
+                check_tree(dir, &format!("{}/", entry_path))?;
+            }
+            _ => bail!("{} is not the {} the tree lists it as", entry_path, kind),
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the app's manifest, the fields of the root directory that aren't entries
+fn check_manifest(content: &Object) -> DefaultResult<()> {
+    let tree = content
+        .get(META_SECTION_NAME)
+        .and_then(|meta| meta[META_TREE_SECTION_NAME].as_object());
+
+    let fields: Object = content
+        .iter()
+        .filter(|(key, _)| {
+            *key != META_SECTION_NAME && !tree.map_or(false, |tree| tree.contains_key(*key))
+        })
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+
+    serde_json::from_value::<AppConfig>(Value::Object(fields))
+        .map_err(|err| format_err!("the app manifest is invalid: {}", err))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(zome: Value) -> Object {
+        let mut app = serde_json::to_value(AppConfig::default()).unwrap();
+        app["zomes"] = json!({
+            "posts": zome,
+            "__META__": { "tree": { "posts": "dir" } }
+        });
+        app["__META__"] = json!({ "tree": { "zomes": "dir" }, "config_file": "app.json" });
+
+        app.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn tells_the_failures_apart() {
+        let valid = app(json!({
+            "code": { "code": base64::encode(b"\0asm") },
+            "__META__": { "tree": { "code": "bin" } }
+        }));
+        assert!(check_tree(&valid, "").is_ok());
+        assert!(check_manifest(&valid).is_ok());
+
+        let missing = app(json!({ "__META__": { "tree": { "code": "bin" } } }));
+        assert_eq!(
+            check_tree(&missing, "").unwrap_err().to_string(),
+            "zomes/posts/code is listed in the tree but missing"
+        );
+
+        let mut unnamed = valid.clone();
+        unnamed.remove("name");
+        assert!(check_manifest(&unnamed).is_err());
+
+        let escaping = app(json!({
+            "../../.bashrc": base64::encode(b"echo"),
+            "__META__": { "tree": { "../../.bashrc": "file" } }
+        }));
+        assert!(check_tree(&escaping, "").is_ok());
+        assert!(package::check_unpack(escaping)
+            .unwrap_err()
+            .to_string()
+            .contains("not a plain file name"));
+
+        assert!(check_checksum(b"{}", &format!("sha256:{}", util::sha256_hex(b"{}"))).is_ok());
+        assert!(check_checksum(b"{}", "sha256:00").is_err());
+        assert_eq!(Check::Unpack.exit_code(), 6);
+    }
+}
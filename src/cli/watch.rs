@@ -0,0 +1,188 @@
+//! `package --watch`: packages the app again whenever one of its files changes, until Ctrl-C.
+//!
+//! The files are polled, which needs nothing from the platform and costs little for the size
+//! of an app. What `.hcignore` leaves out of the bundle isn't watched, neither are `.git` and
+//! the `target` directories of the builds. Each run builds only the Zomes whose sources
+//! changed, the build cache reuses all the others.
+
+use cli::package::{package, PackageOptions};
+use error::DefaultResult;
+use hcignore::IgnoreRules;
+use i18n;
+use output;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Directories that are never bundled and change with every build
+const SKIPPED_DIR_NAMES: &[&str] = &[".git", "target"];
+
+/// How many of the changed files are named before the rest is only counted
+const NAMED_CHANGES: usize = 3;
+
+/// The size and modification time of every watched file
+type Stamps = BTreeMap<PathBuf, (u64, Option<SystemTime>)>;
+
+/// Packages the app with the options, then again after every change to its files. A failed
+/// run is reported and waits for the next change like a successful one.
+pub fn package_watch(options: PackageOptions) -> DefaultResult<()> {
+    let mut retried = false;
+
+    loop {
+        let before = scan(Path::new("."))?;
+        let result = package(options.clone());
+        let after = scan(Path::new("."))?;
+
+        if let Err(err) = result {
+            output::error(err);
+
+            // a file saved while packaging fails the run, the save doesn't come again though
+            if before != after && !retried {
+                retried = true;
+                continue;
+            }
+        }
+
+        retried = false;
+
+        output::print(i18n::tr("watching-app", &[]));
+
+        let changed = wait_for_change(&after)?;
+
+        output::print(i18n::tr("changed-files", &[("files", describe(&changed))]));
+    }
+}
+
+/// Waits until a file was added, removed or modified and returns which ones
+fn wait_for_change(last: &Stamps) -> DefaultResult<Vec<PathBuf>> {
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        if changed_files(last, &scan(Path::new("."))?).is_empty() {
+            continue;
+        }
+
+        // an editor saving several files is given the time to save all of them
+        thread::sleep(POLL_INTERVAL);
+
+        return Ok(changed_files(last, &scan(Path::new("."))?));
+    }
+}
+
+fn scan(root: &Path) -> DefaultResult<Stamps> {
+    // read on every scan, so changes to the .hcignore files apply right away
+    let mut ignore = IgnoreRules::load(root)?;
+    let mut stamps = Stamps::new();
+
+    scan_dir(root, &mut ignore, &mut stamps)?;
+
+    Ok(stamps)
+}
+
+fn scan_dir(dir: &Path, ignore: &mut IgnoreRules, stamps: &mut Stamps) -> DefaultResult<()> {
+    // whatever disappears while it is scanned shows up as removed
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let is_dir = path.is_dir();
+        let skipped = is_dir
+            && SKIPPED_DIR_NAMES
+                .iter()
+                .any(|name| entry.file_name() == *name);
+
+        if skipped || ignore.is_ignored(&path, is_dir)? {
+            continue;
+        }
+
+        if is_dir {
+            scan_dir(&path, ignore, stamps)?;
+        } else if let Ok(metadata) = entry.metadata() {
+            stamps.insert(path, (metadata.len(), metadata.modified().ok()));
+        }
+    }
+
+    Ok(())
+}
+
+fn changed_files(last: &Stamps, current: &Stamps) -> Vec<PathBuf> {
+    let modified = current
+        .iter()
+        .filter(|(path, stamp)| last.get(*path) != Some(stamp))
+        .map(|(path, _)| path);
+    let removed = last.keys().filter(|path| !current.contains_key(*path));
+
+    modified.chain(removed).cloned().collect()
+}
+
+fn describe(changed: &[PathBuf]) -> String {
+    let mut names: Vec<_> = changed
+        .iter()
+        .take(NAMED_CHANGES)
+        .map(|path| path.strip_prefix(".").unwrap_or(path).display().to_string())
+        .collect();
+
+    if changed.len() > NAMED_CHANGES {
+        names.push(format!("{} more", changed.len() - NAMED_CHANGES));
+    }
+
+    names.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder;
+
+    #[test]
+    fn notices_changes_to_the_files_that_are_bundled() {
+        let tmp = Builder::new().prefix("watch").tempdir().unwrap();
+        let root = tmp.path();
+
+        fs::create_dir_all(root.join("zomes/posts/code/target")).unwrap();
+        fs::create_dir_all(root.join("ui")).unwrap();
+        fs::write(root.join("app.json"), "{}").unwrap();
+        fs::write(root.join(".hcignore"), "*.log\n").unwrap();
+        fs::write(root.join("zomes/posts/code/lib.rs"), "// posts").unwrap();
+
+        let last = scan(root).unwrap();
+
+        // builds and ignored files come and go without a new package run
+        fs::write(root.join("zomes/posts/code/target/code.wasm"), "wasm").unwrap();
+        fs::write(root.join("ui/debug.log"), "log").unwrap();
+        assert!(changed_files(&last, &scan(root).unwrap()).is_empty());
+
+        fs::write(root.join("zomes/posts/code/lib.rs"), "// posts v2").unwrap();
+        fs::write(root.join("ui/index.html"), "<html></html>").unwrap();
+        fs::remove_file(root.join("app.json")).unwrap();
+
+        let changed = changed_files(&last, &scan(root).unwrap());
+
+        assert_eq!(
+            changed,
+            vec![
+                root.join("ui/index.html"),
+                root.join("zomes/posts/code/lib.rs"),
+                root.join("app.json"),
+            ]
+        );
+        assert_eq!(
+            describe(&[changed.clone(), changed].concat()),
+            format!(
+                "{}, {}, {}, 3 more",
+                root.join("ui/index.html").display(),
+                root.join("zomes/posts/code/lib.rs").display(),
+                root.join("app.json").display()
+            )
+        );
+    }
+}
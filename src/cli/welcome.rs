@@ -0,0 +1,99 @@
+use atty;
+use cli::{doctor, new};
+use error::DefaultResult;
+use output;
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+pub const DOCS_URL: &str = "https://github.com/holochain/holochain-cmd#readme";
+
+/// Greets newcomers who run `hcdev` without a command in an empty directory with a menu of
+/// the usual first steps. Returns false if the menu isn't shown, e.g. because the input
+/// doesn't come from a terminal.
+pub fn welcome(non_interactive: bool) -> DefaultResult<bool> {
+    let is_terminal = atty::is(atty::Stream::Stdin) && atty::is(atty::Stream::Stdout);
+    let is_empty_dir = fs::read_dir(".")?.next().is_none();
+
+    if non_interactive || !is_terminal || !is_empty_dir {
+        return Ok(false);
+    }
+
+    output::print("Welcome to hcdev! This directory is empty, what would you like to do?");
+    output::print("");
+    output::print("  1) Create a new Holochain app here");
+    output::print("  2) Open the documentation");
+    output::print("  3) Check that your setup can build Zomes");
+    output::print("  4) Show all commands");
+    output::print("  q) Quit");
+    output::print("");
+
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut choice = String::new();
+
+        if io::stdin().lock().read_line(&mut choice)? == 0 {
+            return Ok(true);
+        }
+
+        match choice.trim() {
+            "1" => new(&PathBuf::from("."), &None)?,
+            "2" => open_docs(),
+            "3" => doctor()?,
+            "4" => return Ok(false),
+            "q" | "" => (),
+            _ => {
+                output::print("Please pick one of the options above.");
+                continue;
+            }
+        }
+
+        return Ok(true);
+    }
+}
+
+fn open_docs() {
+    output::print(format!("The documentation lives at {}", DOCS_URL));
+
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+
+    // not having a browser at hand is fine, the link was printed already
+    let _ = Command::new(opener)
+        .arg(DOCS_URL)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::process::Command;
+    use tempfile::Builder;
+
+    #[test]
+    fn scripts_still_get_the_usage() {
+        let tmp = Builder::new().prefix("welcome").tempdir().unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .arg("--non-interactive")
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("USAGE"));
+    }
+}
@@ -0,0 +1,393 @@
+use cli::{
+    generate::{self, ZOME_CONFIG_FILE_NAME},
+    layout::Layout,
+    license::{project_license, with_license_header},
+    package::CARGO_FILE_NAME,
+    prompt,
+};
+use error::DefaultResult;
+use i18n;
+use output;
+use serde_json::{self, Value};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+const BRIDGES_KEY: &str = "bridges";
+const LIB_FILE_PATH: &str = "src/lib.rs";
+
+/// The trait holochain-rust gives the functions of a Zome's public membrane
+const PUBLIC_TRAIT: &str = "hc_public";
+
+/// A Zome a workflow calls, in this DNA or in another one through a bridge
+struct Step {
+    bridge: Option<String>,
+    zome: String,
+}
+
+impl Step {
+    /// Parses `<zome>` for a Zome of the app and `<bridge handle>/<zome>` for one of another DNA
+    fn parse(step: &str) -> DefaultResult<Step> {
+        let (bridge, zome) = match step.find('/') {
+            Some(slash) => (Some(&step[..slash]), &step[slash + 1..]),
+            None => (None, step),
+        };
+
+        for name in bridge.iter().chain(Some(&zome)) {
+            prompt::check_name(name).map_err(|problem| format_err!("{}: {}", step, problem))?;
+        }
+
+        Ok(Step {
+            bridge: bridge.map(str::to_string),
+            zome: zome.to_string(),
+        })
+    }
+}
+
+/// Scaffolds a workflow that calls functions of several Zomes one after another: the code of
+/// the calls in the first Zome, the bridges to other DNAs in its manifest and a test of the
+/// whole scenario
+pub fn generate_workflow(name: &str, zomes: &[String]) -> DefaultResult<()> {
+    prompt::check_name(name).map_err(|problem| format_err!("{}", problem))?;
+
+    ensure!(
+        zomes.len() >= 2,
+        "a workflow calls at least two zomes, the first of which runs it"
+    );
+
+    let steps = zomes
+        .iter()
+        .map(|zome| Step::parse(zome))
+        .collect::<DefaultResult<Vec<_>>>()?;
+
+    ensure!(
+        steps[0].bridge.is_none(),
+        "the workflow has to run in a zome of this app, not in {}",
+        zomes[0]
+    );
+
+    let layout = Layout::of_app(".")?;
+
+    for step in steps.iter().filter(|step| step.bridge.is_none()) {
+        ensure!(
+            layout
+                .zome_dir(&step.zome)
+                .join(ZOME_CONFIG_FILE_NAME)
+                .is_file(),
+            "the app has no zome {}, generate it first",
+            step.zome
+        );
+    }
+
+    let zome_path = layout.zome_dir(&steps[0].zome);
+    let code_dir = layout.code_dir(&zome_path);
+
+    ensure!(
+        code_dir.join(CARGO_FILE_NAME).is_file(),
+        "workflows are generated in Rust, {:?} is no Rust zome",
+        zome_path
+    );
+
+    let module_name = name.replace('-', "_");
+    let workflow_path = code_dir.join("src").join(format!("{}.rs", module_name));
+
+    ensure!(
+        !workflow_path.exists(),
+        "{:?} already exists",
+        workflow_path
+    );
+
+    let code = workflow_code(&module_name, &steps);
+    let code = match project_license()? {
+        Some(license) => with_license_header(&code, &license),
+        None => code,
+    };
+
+    fs::write(&workflow_path, code)?;
+
+    OpenOptions::new()
+        .append(true)
+        .open(code_dir.join(LIB_FILE_PATH))?
+        .write_all(format!("\npub mod {};\n", module_name).as_bytes())?;
+
+    output::print(i18n::tr(
+        "created-workflow",
+        &[
+            ("status", output::status("status-created")),
+            ("name", name.to_string()),
+            ("path", format!("{:?}", workflow_path)),
+        ],
+    ));
+
+    add_bridges(&zome_path.join(ZOME_CONFIG_FILE_NAME), &module_name, &steps)?;
+
+    generate::add_test(name, &scenario_test(name, &module_name, &steps))
+}
+
+/// Requires a bridge in the Zome's manifest for every other DNA the workflow calls
+fn add_bridges(config_path: &Path, function: &str, steps: &[Step]) -> DefaultResult<()> {
+    let mut config: Value = serde_json::from_str(&fs::read_to_string(config_path)?)?;
+
+    ensure!(
+        config.is_object(),
+        "{:?}: the Zome manifest has to be a JSON object",
+        config_path
+    );
+
+    if !config[BRIDGES_KEY].is_array() {
+        config[BRIDGES_KEY] = json!([]);
+    }
+
+    let mut added = Vec::new();
+
+    for handle in steps.iter().filter_map(|step| step.bridge.as_ref()) {
+        let bridges = config[BRIDGES_KEY].as_array_mut().unwrap();
+
+        if bridges
+            .iter()
+            .any(|bridge| bridge["handle"] == handle.as_str())
+        {
+            continue;
+        }
+
+        bridges.push(json!({
+            "presence": "required",
+            "handle": handle,
+            "reference": {
+                "traits": { PUBLIC_TRAIT: { "functions": [function] } }
+            }
+        }));
+        added.push(handle.clone());
+    }
+
+    if added.is_empty() {
+        return Ok(());
+    }
+
+    serde_json::to_writer_pretty(File::create(config_path)?, &config)?;
+
+    for handle in added {
+        output::print(i18n::tr(
+            "added-bridge",
+            &[
+                ("status", output::status("status-added")),
+                ("handle", handle),
+                ("path", format!("{:?}", config_path)),
+            ],
+        ));
+    }
+
+    Ok(())
+}
+
+fn workflow_code(module_name: &str, steps: &[Step]) -> String {
+    let step_list = steps
+        .iter()
+        .map(|step| {
+            format!(
+                "    Step {{\n        bridge: {},\n        zome: \"{}\",\n        function: \"{}\",\n    }},\n",
+                step.bridge
+                    .as_ref()
+                    .map_or("None".to_string(), |bridge| format!("Some(\"{}\")", bridge)),
+                step.zome,
+                module_name
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"//! The `{name}` workflow.
+//!
+//! It calls the steps below one after another, each with what the step before returned, and
+//! stops at the first failing one. Rename their functions to the ones each Zome offers for its
+//! part, and declare the handler below in the functions of `define_zome!`. Steps with a bridge
+//! call a Zome of another DNA through the bridge of that handle, which this Zome's manifest
+//! requires.
+
+/// A zome function the workflow calls
+pub struct Step {{
+    pub bridge: Option<&'static str>,
+    pub zome: &'static str,
+    pub function: &'static str,
+}}
+
+pub const STEPS: &[Step] = &[
+{steps}];
+
+/// Makes the calls to other Zomes: the HDK in the conductor, a mock in the tests
+pub trait Caller {{
+    fn call(&self, step: &Step, args: &str) -> Result<String, String>;
+}}
+
+#[cfg(target_arch = "wasm32")]
+impl Caller for super::Hdk {{
+    fn call(&self, step: &Step, args: &str) -> Result<String, String> {{
+        use hdk::holochain_core_types::{{cas::content::Address, json::JsonString}};
+
+        hdk::call(
+            step.bridge.unwrap_or(hdk::THIS_INSTANCE),
+            step.zome,
+            Address::from(hdk::PUBLIC_TOKEN.to_string()),
+            step.function,
+            JsonString::from(args.to_string()),
+        )
+        .map(String::from)
+        .map_err(|err| err.to_string())
+    }}
+}}
+
+/// Runs the steps of the workflow, returning what the last one returned
+pub fn {name}<C: Caller>(caller: &C, input: &str) -> Result<String, String> {{
+    STEPS.iter().try_fold(input.to_string(), |args, step| {{
+        caller
+            .call(step, &args)
+            .map_err(|err| format!("{{}}/{{}} failed: {{}}", step.zome, step.function, err))
+    }})
+}}
+
+/// The zome function of the workflow
+#[cfg(target_arch = "wasm32")]
+pub fn handle_{name}(input: String) -> Result<String, String> {{
+    {name}(&super::Hdk, &input)
+}}
+
+#[cfg(test)]
+mod tests {{
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Answers every call with the zome that was called, recording the arguments
+    #[derive(Default)]
+    struct MockCaller {{
+        calls: RefCell<Vec<String>>,
+    }}
+
+    impl Caller for MockCaller {{
+        fn call(&self, step: &Step, args: &str) -> Result<String, String> {{
+            self.calls.borrow_mut().push(args.to_string());
+
+            Ok(step.zome.to_string())
+        }}
+    }}
+
+    #[test]
+    fn calls_every_step_with_the_result_of_the_one_before() {{
+        let caller = MockCaller::default();
+
+        assert_eq!({name}(&caller, "input"), Ok(STEPS[STEPS.len() - 1].zome.to_string()));
+        assert_eq!(caller.calls.borrow()[0], "input");
+        assert_eq!(caller.calls.borrow().len(), STEPS.len());
+    }}
+}}
+"#,
+        name = module_name,
+        steps = step_list
+    )
+}
+
+fn scenario_test(name: &str, function: &str, steps: &[Step]) -> String {
+    let checks = steps
+        .iter()
+        .map(|step| match step.bridge {
+            Some(ref bridge) => format!(
+                "  // {} through the bridge {}: check what the workflow left there\n",
+                step.zome, bridge
+            ),
+            None => format!("  // {}: check what the workflow left there\n", step.zome),
+        })
+        .collect::<String>();
+
+    format!(
+        "\
+// The {name} workflow from start to end, against a conductor running the app.
+// Run by `hcdev test` once the test script runs the files in tests/, e.g. with
+// hcdev config set scripts.test \"node --test tests/\"
+const test = require('node:test')
+const assert = require('node:assert')
+
+test('{name}', async () => {{
+  // start the workflow, e.g. with @holochain/hc-web-client:
+  // const result = await callZome('app', '{zome}', 'handle_{function}')({{ input: '...' }})
+{checks}  assert.ok(true)
+}})
+",
+        name = name,
+        zome = steps[0].zome,
+        function = function,
+        checks = checks
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_cmd::prelude::*;
+    use std::process::Command;
+    use tempfile::Builder;
+
+    #[test]
+    fn scaffolds_the_calls_bridges_and_scenario_of_a_workflow() {
+        let tmp = Builder::new().prefix("workflow").tempdir().unwrap();
+        let code_dir = tmp.path().join("zomes/posts/code");
+
+        for zome in &["posts", "profiles"] {
+            fs::create_dir_all(tmp.path().join("zomes").join(zome)).unwrap();
+            fs::write(
+                tmp.path()
+                    .join("zomes")
+                    .join(zome)
+                    .join(ZOME_CONFIG_FILE_NAME),
+                "{ \"description\": \"zome\" }",
+            )
+            .unwrap();
+        }
+
+        fs::create_dir_all(code_dir.join("src")).unwrap();
+        fs::write(code_dir.join(CARGO_FILE_NAME), "[package]\n").unwrap();
+        fs::write(code_dir.join(LIB_FILE_PATH), "pub struct Hdk;\n").unwrap();
+
+        let generate = |args: &[&str]| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .args(&["generate", "workflow"])
+                .args(args)
+                .output()
+                .unwrap()
+        };
+
+        let name = "create-profile-and-post";
+
+        assert!(!generate(&[name, "profiles", "posts"]).status.success());
+        assert!(!generate(&[name, "posts", "comments"]).status.success());
+        assert!(
+            generate(&[name, "posts", "profiles", "social/follows", "social/feeds"])
+                .status
+                .success()
+        );
+        assert!(!generate(&[name, "posts", "profiles"]).status.success());
+
+        let workflow = fs::read_to_string(code_dir.join("src/create_profile_and_post.rs")).unwrap();
+
+        assert!(workflow.contains("bridge: Some(\"social\"),\n        zome: \"feeds\""));
+        assert!(workflow.contains("pub fn handle_create_profile_and_post"));
+        assert!(fs::read_to_string(code_dir.join(LIB_FILE_PATH))
+            .unwrap()
+            .ends_with("\npub mod create_profile_and_post;\n"));
+
+        let config: Value = serde_json::from_str(
+            &fs::read_to_string(tmp.path().join("zomes/posts/zome.json")).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(config["bridges"].as_array().unwrap().len(), 1);
+        assert_eq!(config["bridges"][0]["handle"], "social");
+        assert!(
+            fs::read_to_string(tmp.path().join("tests/create-profile-and-post.js"))
+                .unwrap()
+                .contains("'handle_create_profile_and_post'")
+        );
+    }
+}
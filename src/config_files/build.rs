@@ -1,28 +1,93 @@
 use base64;
+use cli::CARGO_LOCK_FILE_NAME;
 use error::DefaultResult;
+use interrupt::PartialFile;
+use json;
 use serde_json;
 use std::{
     collections::HashMap,
-    fs::File,
+    fs::{self, File},
     io::Read,
     path::{Path, PathBuf},
 };
 use util;
 
+/// Name the artifact gets in a merged module, for the other modules to import from
+const MAIN_MODULE_NAME: &str = "main";
+
+const LINKED_ARTIFACT_EXTENSION: &str = "linked.wasm";
+
+/// What the copy of a code directory the steps of an unlocked build run in is named after it
+const UNLOCKED_COPY_SUFFIX: &str = "-unlocked";
+
+const TARGET_DIR_NAME: &str = "target";
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Build {
     pub steps: HashMap<String, Vec<String>>,
     pub artifact: PathBuf,
+    /// Further WASM modules the artifact is combined with, e.g. C libraries compiled to WASM
+    /// on their own
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<WasmModule>,
+    /// The tool that combines the artifact with the `modules`
+    #[serde(default, skip_serializing_if = "LinkTool::is_default")]
+    pub link: LinkTool,
+    /// Steps that test the code natively before it's built, run by `package --with-tests`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tests: HashMap<String, Vec<String>>,
+    /// Files the code gets the content of through a generated module, like JSON schemas, SQL
+    /// or templates, relative to the code directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub embed: Vec<PathBuf>,
+    /// Whether the steps run in a copy of the code directory without its Cargo.lock
+    #[serde(skip)]
+    unlocked: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct WasmModule {
+    /// The name the other modules import this one's functions from
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LinkTool {
+    /// Binaryen's `wasm-merge`, which combines finished modules and resolves the imports
+    /// between them
+    WasmMerge,
+    /// LLVM's `wasm-ld`, which links object files, e.g. a Rust `staticlib` and C objects
+    WasmLd,
+}
+
+impl Default for LinkTool {
+    fn default() -> LinkTool {
+        LinkTool::WasmMerge
+    }
+}
+
+impl LinkTool {
+    fn is_default(&self) -> bool {
+        *self == LinkTool::default()
+    }
+
+    pub fn bin(self) -> &'static str {
+        match self {
+            LinkTool::WasmMerge => "wasm-merge",
+            LinkTool::WasmLd => "wasm-ld",
+        }
+    }
 }
 
 impl Build {
     /// Creates a Build struct from a .build JSON file and returns it
     pub fn from_file<T: AsRef<Path>>(path: T) -> DefaultResult<Build> {
-        let file = File::open(path)?;
-
-        let build = serde_json::from_reader(&file)?;
+        let path = path.as_ref();
 
-        Ok(build)
+        // a step listed twice would otherwise only run once
+        json::from_slice(&fs::read(path)?).map_err(|err| format_err!("{:?}: {}", path, err))
     }
 
     pub fn save_as<T: AsRef<Path>>(&self, path: T) -> DefaultResult<()> {
@@ -35,11 +100,120 @@ impl Build {
 
     /// Starts the build using the supplied build steps and returns the contents of the artifact
     pub fn run(&self, base_path: &PathBuf) -> DefaultResult<String> {
-        for (bin, args) in &self.steps {
-            util::run_cmd(base_path.to_path_buf(), bin.to_string(), args.clone())?;
+        self.in_work_dir(base_path, |work_dir| {
+            for (bin, args) in self.commands() {
+                util::run_cmd(work_dir.to_path_buf(), bin, args)?;
+            }
+
+            for file in &[&self.artifact, &self.output()] {
+                copy_back(&work_dir.join(file), &base_path.join(file))?;
+            }
+
+            Ok(())
+        })?;
+
+        self.read_artifact(base_path)
+    }
+
+    /// Runs the test steps, sorted by their binary like the build steps
+    pub fn test(&self, base_path: &PathBuf) -> DefaultResult<()> {
+        let mut commands: Vec<_> = self.tests.iter().collect();
+        commands.sort();
+
+        self.in_work_dir(base_path, |work_dir| {
+            for (bin, args) in commands {
+                util::run_cmd(work_dir.to_path_buf(), bin.clone(), args.clone())?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Runs the steps in the code directory, or in an unlocked copy of it
+    fn in_work_dir<F>(&self, base_path: &PathBuf, steps: F) -> DefaultResult<()>
+    where
+        F: FnOnce(&Path) -> DefaultResult<()>,
+    {
+        if !self.unlocked {
+            return steps(base_path);
         }
 
-        let artifact_path = base_path.join(&self.artifact);
+        // next to the code directory, so relative path dependencies still resolve, and under
+        // the same name every time, so the paths compiled into the WASM don't change
+        let name = util::file_name_string(base_path)?;
+        let copy = base_path.with_file_name(format!(".{}{}", name, UNLOCKED_COPY_SUFFIX));
+
+        if copy.exists() {
+            fs::remove_dir_all(&copy)?;
+        }
+
+        let partial = PartialFile::new(&copy);
+        let result = copy_unlocked(base_path, &copy).and_then(|_| steps(&copy));
+
+        fs::remove_dir_all(&copy)?;
+        drop(partial);
+
+        result
+    }
+
+    /// The commands a build runs in the code directory, in order: the build steps sorted by
+    /// their binary, then the link step if there are modules
+    pub fn commands(&self) -> Vec<(String, Vec<String>)> {
+        let mut commands: Vec<_> = self
+            .steps
+            .iter()
+            .map(|(bin, args)| (bin.clone(), args.clone()))
+            .collect();
+        commands.sort();
+
+        if !self.modules.is_empty() {
+            commands.push((self.link.bin().to_string(), self.link_args()));
+        }
+
+        commands
+    }
+
+    /// The module that ends up in the bundle, relative to the code directory: the artifact, or
+    /// the result of linking it with the other modules
+    pub fn output(&self) -> PathBuf {
+        if self.modules.is_empty() {
+            self.artifact.clone()
+        } else {
+            self.artifact.with_extension(LINKED_ARTIFACT_EXTENSION)
+        }
+    }
+
+    fn link_args(&self) -> Vec<String> {
+        let path = |path: &PathBuf| path.to_string_lossy().into_owned();
+        let mut args = Vec::new();
+
+        match self.link {
+            LinkTool::WasmMerge => {
+                args.push(path(&self.artifact));
+                args.push(MAIN_MODULE_NAME.to_string());
+
+                for module in &self.modules {
+                    args.push(path(&module.path));
+                    args.push(module.name.clone());
+                }
+            }
+            LinkTool::WasmLd => {
+                args.push("--no-entry".to_string());
+                args.push("--export-dynamic".to_string());
+                args.push(path(&self.artifact));
+                args.extend(self.modules.iter().map(|module| path(&module.path)));
+            }
+        }
+
+        args.push("-o".to_string());
+        args.push(path(&self.output()));
+
+        args
+    }
+
+    /// Returns the contents of an already built artifact
+    pub fn read_artifact(&self, base_path: &PathBuf) -> DefaultResult<String> {
+        let artifact_path = base_path.join(self.output());
 
         if artifact_path.exists() && artifact_path.is_file() {
             let mut wasm_buf = Vec::new();
@@ -51,12 +225,36 @@ impl Build {
         }
     }
 
+    /// Makes the cargo steps fail instead of updating an out of date Cargo.lock
+    pub fn locked(mut self) -> Build {
+        for (bin, args) in self.steps.iter_mut().chain(self.tests.iter_mut()) {
+            if bin == "cargo" && !args.iter().any(|arg| arg == "--locked") {
+                args.push("--locked".into());
+            }
+        }
+
+        self
+    }
+
+    /// Has the steps resolve the dependencies anew, without the Cargo.lock of the code directory
+    /// and without writing one there. They run in a copy of the code directory that shares its
+    /// `target` directory.
+    pub fn unlocked(mut self) -> Build {
+        self.unlocked = true;
+        self
+    }
+
     pub fn with_artifact<P: Into<PathBuf>>(artifact: P) -> Build {
         let path: PathBuf = artifact.into();
 
         Build {
             steps: HashMap::new(),
             artifact: path,
+            modules: Vec::new(),
+            link: LinkTool::default(),
+            tests: HashMap::new(),
+            embed: Vec::new(),
+            unlocked: false,
         }
     }
 
@@ -73,4 +271,139 @@ impl Build {
         self.steps.insert(cmd, args);
         self
     }
+
+    pub fn test_cmd<S: Into<String> + Clone>(mut self, cmd: S, args: &[S]) -> Build {
+        let args = args.iter().map(|arg| arg.clone().into()).collect();
+
+        self.tests.insert(cmd.into(), args);
+        self
+    }
+}
+
+/// Copies the code directory without its Cargo.lock, linking its `target` directory where the
+/// platform allows, so the unlocked build doesn't compile everything from scratch
+fn copy_unlocked(code_dir: &Path, copy: &Path) -> DefaultResult<()> {
+    fs::create_dir_all(copy)?;
+
+    for entry in fs::read_dir(code_dir)? {
+        let path = entry?.path();
+        let name = util::file_name_string(&path)?;
+
+        if name == CARGO_LOCK_FILE_NAME {
+            continue;
+        } else if name == TARGET_DIR_NAME && link_dir(&path, &copy.join(&name))? {
+            continue;
+        } else if path.is_dir() {
+            copy_unlocked(&path, &copy.join(&name))?;
+        } else {
+            fs::copy(&path, copy.join(&name))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link_dir(dir: &Path, link: &Path) -> DefaultResult<bool> {
+    ::std::os::unix::fs::symlink(dir.canonicalize()?, link)?;
+
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn link_dir(_dir: &Path, _link: &Path) -> DefaultResult<bool> {
+    Ok(false)
+}
+
+/// Copies what an unlocked build produced into the code directory, unless it was written there
+/// through the linked `target` already
+fn copy_back(built: &Path, original: &Path) -> DefaultResult<()> {
+    if !built.exists() || original.canonicalize().ok() == Some(built.canonicalize()?) {
+        return Ok(());
+    }
+
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::copy(built, original)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder;
+
+    #[test]
+    fn refuses_build_files_with_duplicate_keys() {
+        let tmp = Builder::new().prefix("build").tempdir().unwrap();
+        let path = tmp.path().join(".build");
+
+        fs::write(
+            &path,
+            r#"{ "steps": { "cargo": ["build"], "cargo": ["test"] }, "artifact": "code.wasm" }"#,
+        ).unwrap();
+
+        let err = Build::from_file(&path).err().unwrap().to_string();
+        assert!(err.contains("duplicate key steps.cargo"), "{}", err);
+    }
+
+    #[test]
+    fn links_the_modules_into_one() {
+        let build: Build = serde_json::from_value(json!({
+            "steps": { "cargo": ["build"] },
+            "artifact": "target/code.wasm",
+            "modules": [{ "name": "crypto", "path": "c/crypto.wasm" }]
+        })).unwrap();
+
+        assert_eq!(build.output(), PathBuf::from("target/code.linked.wasm"));
+        assert_eq!(
+            build.link_args(),
+            vec![
+                "target/code.wasm",
+                "main",
+                "c/crypto.wasm",
+                "crypto",
+                "-o",
+                "target/code.linked.wasm"
+            ]
+        );
+
+        let plain = Build::with_artifact("target/code.wasm");
+        assert_eq!(plain.output(), PathBuf::from("target/code.wasm"));
+        assert_eq!(
+            serde_json::to_value(&plain).unwrap(),
+            json!({ "steps": {}, "artifact": "target/code.wasm" })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unlocked_builds_leave_the_cargo_lock_alone() {
+        let tmp = Builder::new().prefix("unlocked").tempdir().unwrap();
+        let code_dir = tmp.path().join("code");
+
+        fs::create_dir_all(code_dir.join("target")).unwrap();
+        fs::write(code_dir.join(CARGO_LOCK_FILE_NAME), "# pinned").unwrap();
+
+        let build = Build::with_artifact("code.wasm")
+            .cmd(
+                "sh",
+                &[
+                    "-c",
+                    "test ! -e Cargo.lock && echo resolved > Cargo.lock && \
+                     touch target/cached && printf wasm > code.wasm",
+                ],
+            ).unlocked();
+
+        assert_eq!(build.run(&code_dir).unwrap(), base64::encode("wasm"));
+        assert_eq!(
+            fs::read_to_string(code_dir.join(CARGO_LOCK_FILE_NAME)).unwrap(),
+            "# pinned"
+        );
+        assert!(code_dir.join("target/cached").exists());
+        assert!(!tmp.path().join(".code-unlocked").exists());
+    }
 }
@@ -0,0 +1,129 @@
+//! Where the CLI keeps its user-wide state.
+//!
+//! By default the platform's conventions are followed: the XDG base directories on Linux and
+//! other Unixes, `~/Library` on macOS and `%APPDATA%`/`%LOCALAPPDATA%` on Windows.
+//! `HC_HOME` puts everything into a single directory instead, and `HC_CACHE_DIR` moves only
+//! the caches. Users of older versions keep their `~/.hc` until they move it.
+
+use error::DefaultResult;
+use std::{env, ffi::OsString, path::PathBuf};
+
+pub const HOME_ENV_VAR: &str = "HC_HOME";
+pub const CACHE_DIR_ENV_VAR: &str = "HC_CACHE_DIR";
+
+pub const LEGACY_DIR_NAME: &str = ".hc";
+
+const APP_DIR_NAME: &str = "holochain";
+
+/// The directory holding the user-wide settings
+pub fn config_dir() -> DefaultResult<PathBuf> {
+    if let Some(home) = hc_home()? {
+        return Ok(home);
+    }
+
+    platform_dir(
+        "XDG_CONFIG_HOME",
+        &[".config"],
+        &["Library", "Application Support"],
+        "APPDATA",
+    )
+}
+
+/// The directory holding user-wide data that can't be recreated, like identities
+pub fn data_dir() -> DefaultResult<PathBuf> {
+    if let Some(home) = hc_home()? {
+        return Ok(home);
+    }
+
+    platform_dir(
+        "XDG_DATA_HOME",
+        &[".local", "share"],
+        &["Library", "Application Support"],
+        "APPDATA",
+    )
+}
+
+/// The directory holding downloads and other files that are safe to delete
+pub fn cache_dir() -> DefaultResult<PathBuf> {
+    if let Some(cache_dir) = non_empty_var(CACHE_DIR_ENV_VAR) {
+        return Ok(PathBuf::from(cache_dir));
+    }
+
+    if let Some(home) = hc_home()? {
+        return Ok(home);
+    }
+
+    platform_dir(
+        "XDG_CACHE_HOME",
+        &[".cache"],
+        &["Library", "Caches"],
+        "LOCALAPPDATA",
+    )
+}
+
+/// `HC_HOME`, or the `~/.hc` of older versions if it is still around
+fn hc_home() -> DefaultResult<Option<PathBuf>> {
+    if let Some(home) = non_empty_var(HOME_ENV_VAR) {
+        return Ok(Some(PathBuf::from(home)));
+    }
+
+    let legacy = home_dir()?.join(LEGACY_DIR_NAME);
+
+    Ok(if legacy.is_dir() { Some(legacy) } else { None })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_dir(
+    xdg_var: &str,
+    xdg_default: &[&str],
+    _macos: &[&str],
+    _windows_var: &str,
+) -> DefaultResult<PathBuf> {
+    let base = match non_empty_var(xdg_var) {
+        // relative paths are invalid according to the spec and have to be ignored
+        Some(base) if PathBuf::from(&base).is_absolute() => PathBuf::from(base),
+        _ => xdg_default
+            .iter()
+            .fold(home_dir()?, |path, name| path.join(name)),
+    };
+
+    Ok(base.join(APP_DIR_NAME))
+}
+
+#[cfg(target_os = "macos")]
+fn platform_dir(
+    _xdg_var: &str,
+    _xdg_default: &[&str],
+    macos: &[&str],
+    _windows_var: &str,
+) -> DefaultResult<PathBuf> {
+    Ok(macos
+        .iter()
+        .fold(home_dir()?, |path, name| path.join(name))
+        .join(APP_DIR_NAME))
+}
+
+#[cfg(windows)]
+fn platform_dir(
+    _xdg_var: &str,
+    _xdg_default: &[&str],
+    _macos: &[&str],
+    windows_var: &str,
+) -> DefaultResult<PathBuf> {
+    let base = non_empty_var(windows_var)
+        .ok_or_else(|| format_err!("unable to determine the {} directory", windows_var))?;
+
+    Ok(PathBuf::from(base).join(APP_DIR_NAME))
+}
+
+/// The user's home directory
+pub fn home_dir() -> DefaultResult<PathBuf> {
+    non_empty_var("HOME")
+        .or_else(|| non_empty_var("USERPROFILE"))
+        .map(PathBuf::from)
+        .ok_or_else(|| format_err!("unable to determine the home directory"))
+}
+
+fn non_empty_var(name: &str) -> Option<OsString> {
+    env::var_os(name).filter(|value| !value.is_empty())
+}
@@ -0,0 +1,86 @@
+use colored::*;
+use config_files::dirs;
+use error::DefaultResult;
+use output;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use toml;
+
+pub const GLOBAL_CONFIG_FILE_NAME: &str = "config.toml";
+
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// User-wide settings, read from `config.toml` in the config directory
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Global {
+    /// Where `init` should take project templates from
+    pub template_source: Option<String>,
+    /// One of "auto", "always" or "never"
+    pub color: Option<String>,
+    /// Location of the registry index, either a URL or a file path
+    pub registry_url: Option<String>,
+    /// Base64 encoded ed25519 key the registry index has to be signed with
+    pub registry_public_key: Option<String>,
+    pub docker_image: Option<String>,
+    /// Forbids all network access, like passing --offline to every command
+    pub offline: Option<bool>,
+    #[serde(default)]
+    pub unpack: UnpackLimits,
+    /// Commands standing for others along with some arguments, by their name
+    #[serde(default)]
+    pub alias: BTreeMap<String, String>,
+}
+
+/// Limits for unpacking bundles, the defaults apply to the ones that aren't set
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct UnpackLimits {
+    /// Total size of all unpacked files, with a unit like "1GB"
+    pub max_size: Option<String>,
+    /// Size of a single unpacked file, with a unit like "256MB"
+    pub max_file_size: Option<String>,
+    pub max_files: Option<u64>,
+    /// How deep directories may be nested
+    pub max_depth: Option<u64>,
+}
+
+impl Global {
+    pub fn load() -> DefaultResult<Global> {
+        Global::from_file(Global::file_path()?)
+    }
+
+    /// The settings for what works without them, the defaults with a warning if they can't be
+    /// read, so a broken `config.toml` doesn't keep `config` from fixing it
+    pub fn load_or_default() -> Global {
+        Global::load().unwrap_or_else(|err| {
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                output::print_err(format!(
+                    "{}: {}, using the default global settings",
+                    "warning".yellow().bold(),
+                    err
+                ));
+            }
+
+            Global::default()
+        })
+    }
+
+    pub fn from_file<T: AsRef<Path>>(path: T) -> DefaultResult<Global> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            return Ok(Global::default());
+        }
+
+        let contents = fs::read_to_string(path)?;
+
+        toml::from_str(&contents).map_err(|err| format_err!("unable to parse {:?}: {}", path, err))
+    }
+
+    pub fn file_path() -> DefaultResult<PathBuf> {
+        Ok(dirs::config_dir()?.join(GLOBAL_CONFIG_FILE_NAME))
+    }
+}
@@ -0,0 +1,45 @@
+use error::DefaultResult;
+use registry::IndexEntry;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use toml;
+
+pub const LOCK_FILE_NAME: &str = "hc.lock";
+
+/// The resolved DNA dependencies of a project, read from and written to `hc.lock`
+#[derive(Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct Lock {
+    #[serde(default)]
+    pub dna: Vec<IndexEntry>,
+}
+
+impl Lock {
+    pub fn from_dir<T: AsRef<Path>>(root: T) -> DefaultResult<Lock> {
+        let path = Lock::file_path(root);
+
+        if !path.exists() {
+            return Ok(Lock::default());
+        }
+
+        let contents = fs::read_to_string(&path)?;
+
+        toml::from_str(&contents).map_err(|err| format_err!("unable to parse {:?}: {}", path, err))
+    }
+
+    pub fn file_path<T: AsRef<Path>>(root: T) -> PathBuf {
+        root.as_ref().join(LOCK_FILE_NAME)
+    }
+
+    pub fn save_in<T: AsRef<Path>>(&self, root: T) -> DefaultResult<()> {
+        let contents = format!(
+            "# This file is generated by resolving the dependencies in hc.toml, do not edit it\n\n{}",
+            toml::to_string(self)?
+        );
+
+        fs::write(Lock::file_path(root), contents)?;
+
+        Ok(())
+    }
+}
@@ -1,7 +1,14 @@
 mod app;
 mod build;
 mod dht;
+pub mod dirs;
+mod global;
+mod lock;
+pub mod project;
 
 pub use self::app::{App, Author};
 pub use self::build::Build;
 pub use self::dht::Dht;
+pub use self::global::{Global, UnpackLimits};
+pub use self::lock::Lock;
+pub use self::project::{BuildCacheConfig, CargoLockPolicy, Project, SecretsConfig, UiConfig};
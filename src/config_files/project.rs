@@ -0,0 +1,338 @@
+use error::DefaultResult;
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    path::{Path, PathBuf},
+};
+use toml::{self, value::Table, Value};
+
+pub const PROJECT_CONFIG_FILE_NAME: &str = "hc.toml";
+
+/// Points at the project config file to use instead of discovering it, set by `--config`
+pub const CONFIG_ENV_VAR: &str = "HC_PROJECT_CONFIG";
+
+const VCS_DIR_NAME: &str = ".git";
+
+/// The per-project CLI configuration, read from an optional `hc.toml` at the project root.
+///
+/// Settings are inherited from the `hc.toml` files of parent directories up to the repository
+/// root, so monorepos can share them. Nested files override what they inherit.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Project {
+    /// Stops the inheritance of settings from parent directories
+    #[serde(default)]
+    pub root: bool,
+    #[serde(default)]
+    pub scripts: HashMap<String, String>,
+    #[serde(default)]
+    pub warnings: WarningsConfig,
+    /// DNAs from the registry this app depends on, as name and semver range
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    pub zomes: ZomesConfig,
+    /// SPDX license expression of the app, used for generated source headers and license audits
+    pub license: Option<String>,
+    #[serde(default)]
+    pub ui: UiConfig,
+    /// Commands standing for others along with some arguments, by their name
+    #[serde(default)]
+    pub alias: BTreeMap<String, String>,
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub bundle: BundleConfig,
+    #[serde(default)]
+    pub macos: MacosConfig,
+    #[serde(default)]
+    pub build_cache: BuildCacheConfig,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct WarningsConfig {
+    /// Warning codes that are treated as errors
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct ZomesConfig {
+    /// How the `Cargo.lock` files of Rust Zomes are treated
+    #[serde(default)]
+    pub cargo_lock: CargoLockPolicy,
+    /// The language of Zomes generated without one, e.g. `wat`
+    pub language: Option<String>,
+    /// The directory the Zomes are in, `zomes` unless set, see `cli::layout`
+    pub dir: Option<PathBuf>,
+    /// The directory of a Zome its code is in, `code` unless set
+    pub code_dir: Option<String>,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct UiConfig {
+    /// Appends the content hash to the file names of the UI assets
+    #[serde(default)]
+    pub hash_assets: bool,
+    /// Encodings to store precompressed variants of the UI assets in, e.g. `gzip`
+    #[serde(default)]
+    pub precompress: Vec<String>,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct SecretsConfig {
+    /// Base64 encoded public key (see keygen) the secret properties are encrypted to
+    pub recipient: Option<String>,
+    /// Dotted paths into the `properties` of `app.json` that don't go into the bundle in
+    /// plaintext, e.g. `api.key`
+    #[serde(default)]
+    pub properties: Vec<String>,
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct BundleConfig {
+    /// The order of the entries in every directory of the bundle file: entry names, `manifest`
+    /// for the settings of the directory's config file and `*` for the rest
+    #[serde(default)]
+    pub order: Vec<String>,
+    /// Files the project builds along with the bundle, like native launchers, which the
+    /// `postpackage` script gets listed with their hashes
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+/// How `macos sign` and `macos notarize` prepare native artifacts for distribution
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct MacosConfig {
+    /// The codesigning identity, e.g. `Developer ID Application: Example (TEAMID)`
+    pub identity: Option<String>,
+    /// The entitlements the artifacts are signed with
+    pub entitlements: Option<String>,
+    /// The keychain profile `xcrun notarytool store-credentials` saved the credentials in
+    pub notary_profile: Option<String>,
+}
+
+/// A build cache shared by the team, see `remote_cache`
+#[derive(Clone, Deserialize, Serialize)]
+pub struct BuildCacheConfig {
+    /// An http(s) URL, `s3://<bucket>/<prefix>` or a directory, e.g. on a network share
+    pub url: Option<String>,
+    /// Fetches the builds of changed Zomes from the cache before building them
+    #[serde(default = "default_read")]
+    pub read: bool,
+    /// Uploads the builds of Zomes the cache didn't have
+    #[serde(default)]
+    pub write: bool,
+}
+
+impl Default for BuildCacheConfig {
+    fn default() -> BuildCacheConfig {
+        BuildCacheConfig {
+            url: None,
+            read: default_read(),
+            write: false,
+        }
+    }
+}
+
+fn default_read() -> bool {
+    true
+}
+
+/// The hcdev commands the git hooks installed by `hooks install` run, with their arguments
+#[derive(Clone, Deserialize, Serialize)]
+pub struct HooksConfig {
+    #[serde(default = "default_pre_commit")]
+    pub pre_commit: Vec<String>,
+    #[serde(default)]
+    pub pre_push: Vec<String>,
+}
+
+impl Default for HooksConfig {
+    fn default() -> HooksConfig {
+        HooksConfig {
+            pre_commit: default_pre_commit(),
+            pre_push: Vec::new(),
+        }
+    }
+}
+
+fn default_pre_commit() -> Vec<String> {
+    vec!["lint".to_string(), "package --check".to_string()]
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CargoLockPolicy {
+    /// Cargo.lock is used and updated by cargo as usual
+    Use,
+    /// Builds fail if Cargo.lock is missing or out of date
+    Locked,
+    /// Builds resolve the dependencies anew, in a copy of the code directory without its
+    /// Cargo.lock, which stays as it is
+    Ignore,
+}
+
+impl Default for CargoLockPolicy {
+    fn default() -> CargoLockPolicy {
+        CargoLockPolicy::Use
+    }
+}
+
+impl Project {
+    /// Applies the `--config` and `--project-dir` options. The config path is kept in the
+    /// environment and the project directory becomes the working directory, so both carry
+    /// over to scripts, plugins and the CLI's own child processes.
+    pub fn init(config: Option<PathBuf>, project_dir: Option<PathBuf>) -> DefaultResult<()> {
+        if let Some(config) = config {
+            // relative to where the CLI was invoked, not to the project directory
+            env::set_var(CONFIG_ENV_VAR, env::current_dir()?.join(config));
+        }
+
+        if let Some(project_dir) = project_dir {
+            env::set_current_dir(&project_dir).map_err(|err| {
+                format_err!(
+                    "unable to enter the project directory {:?}: {}",
+                    project_dir,
+                    err
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// The project config file given with `--config` or `HC_PROJECT_CONFIG`, if any
+    pub fn config_override() -> Option<PathBuf> {
+        env::var_os(CONFIG_ENV_VAR)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// Loads the project config from the given project root along with everything it
+    /// inherits, falling back to the defaults if there are no config files. An explicit
+    /// config file replaces all of them.
+    pub fn from_dir<T: AsRef<Path>>(root: T) -> DefaultResult<Project> {
+        if let Some(path) = Project::config_override() {
+            return Project::from_file(path);
+        }
+
+        let mut settings = Table::new();
+
+        for path in Project::inherited_files(root)?.iter().rev() {
+            merge(&mut settings, read_table(path)?);
+        }
+
+        Value::Table(settings)
+            .try_into()
+            .map_err(|err| format_err!("invalid project settings: {}", err))
+    }
+
+    /// Loads the project config from the given file alone
+    pub fn from_file<T: AsRef<Path>>(path: T) -> DefaultResult<Project> {
+        let path = path.as_ref().to_path_buf();
+
+        ensure!(path.is_file(), "project config {:?} doesn't exist", path);
+
+        Value::Table(read_table(&path)?)
+            .try_into()
+            .map_err(|err| format_err!("invalid project settings: {}", err))
+    }
+
+    /// Returns the config files that apply to the given project root, nearest first
+    pub fn inherited_files<T: AsRef<Path>>(root: T) -> DefaultResult<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut dir = root.as_ref().canonicalize()?;
+
+        loop {
+            let path = Project::file_path(&dir);
+
+            if path.is_file() {
+                let is_root = read_table(&path)?
+                    .get("root")
+                    .and_then(|root| root.as_bool())
+                    .unwrap_or(false);
+
+                files.push(path);
+
+                if is_root {
+                    break;
+                }
+            }
+
+            if dir.join(VCS_DIR_NAME).exists() || !dir.pop() {
+                break;
+            }
+        }
+
+        Ok(files)
+    }
+
+    pub fn file_path<T: AsRef<Path>>(root: T) -> PathBuf {
+        root.as_ref().join(PROJECT_CONFIG_FILE_NAME)
+    }
+
+    /// Returns the command declared for the given script name, if any
+    pub fn script(&self, name: &str) -> Option<&String> {
+        self.scripts.get(name)
+    }
+}
+
+fn read_table(path: &PathBuf) -> DefaultResult<Table> {
+    let contents = fs::read_to_string(path)?;
+
+    toml::from_str(&contents).map_err(|err| format_err!("unable to parse {:?}: {}", path, err))
+}
+
+/// Deep merges `overrides` into `base`, tables are merged key by key and everything else is
+/// replaced
+fn merge(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base_table)), Value::Table(override_table)) => {
+                merge(base_table, override_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::Builder;
+
+    #[test]
+    fn inherits_settings_from_parent_directories() {
+        let repo = Builder::new().prefix("monorepo").tempdir().unwrap();
+        let project = repo.path().join("dnas").join("chat");
+
+        fs::create_dir_all(repo.path().join(VCS_DIR_NAME)).unwrap();
+        fs::create_dir_all(&project).unwrap();
+
+        fs::write(
+            Project::file_path(repo.path()),
+            "[scripts]\nprepackage = \"make\"\npostpackage = \"echo done\"\n\n[warnings]\ndeny = [\"warnings\"]\n",
+        ).unwrap();
+        fs::write(
+            Project::file_path(&project),
+            "[scripts]\npostpackage = \"echo chat\"\n",
+        ).unwrap();
+
+        let settings = Project::from_dir(&project).unwrap();
+
+        assert_eq!(settings.script("prepackage"), Some(&"make".to_string()));
+        assert_eq!(settings.script("postpackage"), Some(&"echo chat".to_string()));
+        assert_eq!(settings.warnings.deny, vec!["warnings".to_string()]);
+
+        fs::write(
+            Project::file_path(&project),
+            "root = true\n\n[scripts]\npostpackage = \"echo chat\"\n",
+        ).unwrap();
+
+        assert!(Project::from_dir(&project).unwrap().script("prepackage").is_none());
+    }
+}
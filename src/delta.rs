@@ -0,0 +1,207 @@
+//! Delta bundles only carry what changed compared to a base bundle.
+//!
+//! Bundles are compared as JSON trees: every value that differs from the base is stored
+//! together with its path, and every key that vanished is listed for removal. Both the base and
+//! the reconstructed bundle are referenced by hash, so a delta can only be applied to the exact
+//! bundle it was made from.
+
+use error::DefaultResult;
+use serde_json::{self, Value};
+use util;
+
+pub const DELTA_BUNDLE_KEY: &str = "delta_bundle";
+
+#[derive(Deserialize, Serialize)]
+pub struct Change {
+    pub path: Vec<String>,
+    pub value: Value,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct Delta {
+    /// Hash of the bundle this delta has to be applied to
+    pub base: String,
+    /// Hash of the bundle applying the delta results in
+    pub target: String,
+    pub set: Vec<Change>,
+    pub remove: Vec<Vec<String>>,
+}
+
+/// Returns the hash identifying a bundle, independent of how its JSON is formatted and of the
+/// order of its keys
+pub fn bundle_hash(bundle: &Value) -> DefaultResult<String> {
+    Ok(format!(
+        "sha256:{}",
+        util::sha256_hex(serde_json::to_string(&sorted(bundle))?.as_bytes())
+    ))
+}
+
+/// The value with the keys of all its objects in order, which maps keep in the order they were
+/// inserted when serde_json preserves it
+fn sorted(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut keys: Vec<&String> = object.keys().collect();
+
+            keys.sort();
+
+            Value::Object(
+                keys.into_iter()
+                    .map(|key| (key.clone(), sorted(&object[key])))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.iter().map(sorted).collect()),
+        value => value.clone(),
+    }
+}
+
+/// Creates the delta leading from `base` to `target`, ready to be written as a bundle file
+pub fn diff(base: &Value, target: &Value) -> DefaultResult<Value> {
+    let mut delta = Delta {
+        base: bundle_hash(base)?,
+        target: bundle_hash(target)?,
+        set: Vec::new(),
+        remove: Vec::new(),
+    };
+
+    diff_recurse(base, target, &mut Vec::new(), &mut delta);
+
+    Ok(json!({ DELTA_BUNDLE_KEY: delta }))
+}
+
+/// Returns the delta if the given bundle content is a delta bundle
+pub fn delta(bundle: &Value) -> DefaultResult<Option<Delta>> {
+    match bundle.get(DELTA_BUNDLE_KEY) {
+        Some(delta) => Ok(Some(serde_json::from_value(delta.clone())?)),
+        None => Ok(None),
+    }
+}
+
+/// Reconstructs the full bundle from its base and a delta
+pub fn apply(base: &Value, delta: &Delta) -> DefaultResult<Value> {
+    let base_hash = bundle_hash(base)?;
+
+    ensure!(
+        base_hash == delta.base,
+        "the delta was made for the bundle {}, but the given base bundle is {}",
+        delta.base,
+        base_hash
+    );
+
+    let mut bundle = base.clone();
+
+    for path in &delta.remove {
+        let (key, parent) = path
+            .split_last()
+            .ok_or_else(|| format_err!("invalid delta: empty removal path"))?;
+
+        lookup_mut(&mut bundle, parent)?
+            .as_object_mut()
+            .and_then(|object| object.remove(key))
+            .ok_or_else(|| format_err!("invalid delta: {} doesn't exist", path.join("/")))?;
+    }
+
+    for change in &delta.set {
+        match change.path.split_last() {
+            Some((key, parent)) => {
+                let object = lookup_mut(&mut bundle, parent)?
+                    .as_object_mut()
+                    .ok_or_else(|| {
+                        format_err!("invalid delta: {} is no directory", parent.join("/"))
+                    })?;
+
+                object.insert(key.clone(), change.value.clone());
+            }
+            None => bundle = change.value.clone(),
+        }
+    }
+
+    ensure!(
+        bundle_hash(&bundle)? == delta.target,
+        "applying the delta didn't result in the expected bundle {}",
+        delta.target
+    );
+
+    Ok(bundle)
+}
+
+fn diff_recurse(base: &Value, target: &Value, path: &mut Vec<String>, delta: &mut Delta) {
+    match (base, target) {
+        (Value::Object(base), Value::Object(target)) => {
+            for key in base.keys().filter(|key| !target.contains_key(*key)) {
+                let mut removed = path.clone();
+                removed.push(key.clone());
+
+                delta.remove.push(removed);
+            }
+
+            for (key, target_value) in target {
+                path.push(key.clone());
+
+                match base.get(key) {
+                    Some(base_value) => diff_recurse(base_value, target_value, path, delta),
+                    None => delta.set.push(Change {
+                        path: path.clone(),
+                        value: target_value.clone(),
+                    }),
+                }
+
+                path.pop();
+            }
+        }
+        (base, target) if base != target => delta.set.push(Change {
+            path: path.clone(),
+            value: target.clone(),
+        }),
+        _ => (),
+    }
+}
+
+fn lookup_mut<'a>(bundle: &'a mut Value, path: &[String]) -> DefaultResult<&'a mut Value> {
+    path.iter().fold(Ok(bundle), |value, key| {
+        value?
+            .get_mut(key)
+            .ok_or_else(|| format_err!("invalid delta: {} doesn't exist", path.join("/")))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconstructs_the_target_bundle() {
+        let base = json!({
+            "name": "app",
+            "zomes": { "chat": { "code": "AAA", "zome.json": "old" }, "old": { "code": "BBB" } }
+        });
+        let target = json!({
+            "name": "app",
+            "zomes": { "chat": { "code": "CCC", "zome.json": "old" }, "new": { "code": "DDD" } }
+        });
+
+        let delta_bundle = diff(&base, &target).unwrap();
+        let delta = delta(&delta_bundle).unwrap().unwrap();
+
+        assert_eq!(delta.set.len(), 2);
+        assert_eq!(
+            delta.remove,
+            vec![vec!["zomes".to_string(), "old".to_string()]]
+        );
+        assert_eq!(apply(&base, &delta).unwrap(), target);
+        assert!(apply(&target, &delta).is_err());
+    }
+
+    #[test]
+    fn hashes_ignore_the_order_of_keys() {
+        let parse = |json: &str| -> Value { serde_json::from_str(json).unwrap() };
+        let bundle = parse(r#"{ "b": 1, "a": [{ "y": 2, "x": 3 }] }"#);
+        let reordered = parse(r#"{ "a": [{ "x": 3, "y": 2 }], "b": 1 }"#);
+
+        assert_eq!(
+            bundle_hash(&bundle).unwrap(),
+            bundle_hash(&reordered).unwrap()
+        );
+    }
+}
@@ -0,0 +1,376 @@
+//! Just enough DWARF to map code offsets of a WebAssembly module to source lines.
+//!
+//! Only the line number program of `.debug_line` is read, in the versions 2 to 4 rustc emits
+//! for WebAssembly. Addresses are offsets into the content of the module's code section.
+
+use error::DefaultResult;
+
+/// Sequences of functions the linker removed start at address 0 or at a tombstone value
+const TOMBSTONE_ADDRESS: u64 = 0xffff_fffe;
+
+const DW_LNS_COPY: u8 = 1;
+const DW_LNS_ADVANCE_PC: u8 = 2;
+const DW_LNS_ADVANCE_LINE: u8 = 3;
+const DW_LNS_SET_FILE: u8 = 4;
+const DW_LNS_SET_COLUMN: u8 = 5;
+const DW_LNS_CONST_ADD_PC: u8 = 8;
+const DW_LNS_FIXED_ADVANCE_PC: u8 = 9;
+
+const DW_LNE_END_SEQUENCE: u8 = 1;
+const DW_LNE_SET_ADDRESS: u8 = 2;
+const DW_LNE_DEFINE_FILE: u8 = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub file: String,
+    pub line: u64,
+    pub column: u64,
+}
+
+#[derive(Clone, Debug)]
+struct Row {
+    address: u64,
+    location: Location,
+    end_sequence: bool,
+}
+
+/// The line table of a module, built from its `.debug_line` section
+pub struct LineTable {
+    /// One list of rows per sequence, ordered by address within each
+    sequences: Vec<Vec<Row>>,
+}
+
+impl LineTable {
+    pub fn parse(debug_line: &[u8]) -> DefaultResult<LineTable> {
+        let mut reader = Reader { bytes: debug_line };
+        let mut sequences = Vec::new();
+
+        while !reader.bytes.is_empty() {
+            let length = reader.uint(4)? as usize;
+
+            ensure!(
+                length != 0xffff_ffff,
+                "64-bit DWARF line tables aren't supported"
+            );
+
+            let mut unit = Reader {
+                bytes: reader.bytes(length)?,
+            };
+
+            sequences.extend(unit.line_program()?);
+        }
+
+        Ok(LineTable { sequences })
+    }
+
+    /// Looks up the source location the code at the given offset was compiled from
+    pub fn locate(&self, address: u64) -> Option<&Location> {
+        self.sequences
+            .iter()
+            .filter(|rows| {
+                rows.first().map_or(false, |row| {
+                    row.address != 0 && row.address < TOMBSTONE_ADDRESS
+                })
+            })
+            .flat_map(|rows| rows.windows(2))
+            .find(|pair| {
+                !pair[0].end_sequence && pair[0].address <= address && address < pair[1].address
+            })
+            .map(|pair| &pair[0].location)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Runs the line number program of one unit, returning its sequences
+    fn line_program(&mut self) -> DefaultResult<Vec<Vec<Row>>> {
+        let version = self.uint(2)?;
+
+        ensure!(
+            version >= 2 && version <= 4,
+            "DWARF {} line tables aren't supported, only versions 2 to 4",
+            version
+        );
+
+        let header_length = self.uint(4)? as usize;
+        let mut program = Reader {
+            bytes: &self.bytes[header_length.min(self.bytes.len())..],
+        };
+
+        let min_instruction_length = u64::from(self.byte()?);
+
+        if version >= 4 {
+            // maximum operations per instruction, always 1 outside of VLIW architectures
+            self.byte()?;
+        }
+
+        self.byte()?;
+        let line_base = i64::from(self.byte()? as i8);
+        let line_range = u64::from(self.byte()?);
+        let opcode_base = self.byte()?;
+        let opcode_lengths = self
+            .bytes(usize::from(opcode_base.saturating_sub(1)))?
+            .to_vec();
+
+        ensure!(line_range != 0, "invalid DWARF line range");
+
+        let mut directories = Vec::new();
+
+        loop {
+            let directory = self.string()?;
+
+            if directory.is_empty() {
+                break;
+            }
+
+            directories.push(directory);
+        }
+
+        let mut files = Vec::new();
+
+        loop {
+            let file = self.file_entry(&directories)?;
+
+            match file {
+                Some(file) => files.push(file),
+                None => break,
+            }
+        }
+
+        let mut sequences = Vec::new();
+        let mut rows = Vec::new();
+        let mut address = 0;
+        let mut file = 1;
+        let mut line: i64 = 1;
+        let mut column = 0;
+
+        while !program.bytes.is_empty() {
+            let opcode = program.byte()?;
+            let mut emit = false;
+            let mut end_sequence = false;
+
+            if opcode >= opcode_base {
+                let adjusted = u64::from(opcode - opcode_base);
+                address += adjusted / line_range * min_instruction_length;
+                line += line_base + (adjusted % line_range) as i64;
+                emit = true;
+            } else {
+                match opcode {
+                    0 => {
+                        let length = program.uleb()? as usize;
+                        let mut extended = Reader {
+                            bytes: program.bytes(length)?,
+                        };
+
+                        match extended.byte()? {
+                            DW_LNE_END_SEQUENCE => {
+                                emit = true;
+                                end_sequence = true;
+                            }
+                            DW_LNE_SET_ADDRESS => {
+                                let size = extended.bytes.len();
+                                address = extended.uint(size)?;
+                            }
+                            DW_LNE_DEFINE_FILE => {
+                                if let Some(defined) = extended.file_entry(&directories)? {
+                                    files.push(defined);
+                                }
+                            }
+                            _ => (),
+                        }
+                    }
+                    DW_LNS_COPY => emit = true,
+                    DW_LNS_ADVANCE_PC => address += program.uleb()? * min_instruction_length,
+                    DW_LNS_ADVANCE_LINE => line += program.sleb()?,
+                    DW_LNS_SET_FILE => file = program.uleb()?,
+                    DW_LNS_SET_COLUMN => column = program.uleb()?,
+                    DW_LNS_CONST_ADD_PC => {
+                        address +=
+                            u64::from(255 - opcode_base) / line_range * min_instruction_length
+                    }
+                    DW_LNS_FIXED_ADVANCE_PC => address += program.uint(2)?,
+                    _ => {
+                        // skip the operands of opcodes without any meaning for the lookup
+                        for _ in 0..opcode_lengths[usize::from(opcode - 1)] {
+                            program.uleb()?;
+                        }
+                    }
+                }
+            }
+
+            if emit {
+                rows.push(Row {
+                    address,
+                    location: Location {
+                        // file numbers start at 1 before DWARF 5
+                        file: files
+                            .get((file as usize).wrapping_sub(1))
+                            .cloned()
+                            .unwrap_or_else(|| "<unknown>".to_string()),
+                        line: line.max(0) as u64,
+                        column,
+                    },
+                    end_sequence,
+                });
+            }
+
+            if end_sequence {
+                sequences.push(rows);
+                rows = Vec::new();
+                address = 0;
+                file = 1;
+                line = 1;
+                column = 0;
+            }
+        }
+
+        Ok(sequences)
+    }
+
+    /// Reads a file name entry, `None` marks the end of the list
+    fn file_entry(&mut self, directories: &[String]) -> DefaultResult<Option<String>> {
+        let name = self.string()?;
+
+        if name.is_empty() {
+            return Ok(None);
+        }
+
+        let directory = self.uleb()? as usize;
+        // modification time and length
+        self.uleb()?;
+        self.uleb()?;
+
+        // directory 0 is the compilation directory, which only the debug info knows
+        Ok(Some(match directories.get(directory.wrapping_sub(1)) {
+            Some(directory) if !name.starts_with('/') => format!("{}/{}", directory, name),
+            _ => name,
+        }))
+    }
+
+    fn string(&mut self) -> DefaultResult<String> {
+        let end = self
+            .bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| format_err!("unterminated string in DWARF data"))?;
+        let string = String::from_utf8_lossy(&self.bytes[..end]).into_owned();
+        self.bytes = &self.bytes[end + 1..];
+
+        Ok(string)
+    }
+
+    fn uleb(&mut self) -> DefaultResult<u64> {
+        let mut result = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.byte()?;
+
+            if shift < 64 {
+                result |= u64::from(byte & 0x7f) << shift;
+            }
+
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> DefaultResult<i64> {
+        let mut result: i64 = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.byte()?;
+
+            if shift < 64 {
+                result |= i64::from(byte & 0x7f) << shift;
+            }
+
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+
+                return Ok(result);
+            }
+        }
+    }
+
+    /// Reads a little endian unsigned integer of the given number of bytes
+    fn uint(&mut self, size: usize) -> DefaultResult<u64> {
+        Ok(self
+            .bytes(size)?
+            .iter()
+            .rev()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte)))
+    }
+
+    fn byte(&mut self) -> DefaultResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, count: usize) -> DefaultResult<&'a [u8]> {
+        ensure!(count <= self.bytes.len(), "unexpected end of DWARF data");
+
+        let (head, tail) = self.bytes.split_at(count);
+        self.bytes = tail;
+
+        Ok(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A DWARF 4 line table with one sequence for src/lib.rs: line 10 at 0x20, line 12 at
+    /// 0x28, ending at 0x30
+    fn debug_line() -> Vec<u8> {
+        let mut header = vec![
+            1,    // minimum instruction length
+            1,    // maximum operations per instruction
+            1,    // default is_stmt
+            0xfb, // line base -5
+            14,   // line range
+            13,   // opcode base
+            0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1, // standard opcode lengths
+        ];
+        header.extend(b"/app/code\0\0");
+        header.extend(b"src/lib.rs\0\x01\0\0\0");
+
+        let mut program = vec![0, 5, DW_LNE_SET_ADDRESS, 0x20, 0, 0, 0];
+        program.extend(&[DW_LNS_ADVANCE_LINE, 9, DW_LNS_SET_COLUMN, 5, DW_LNS_COPY]);
+        program.extend(&[DW_LNS_ADVANCE_PC, 8, DW_LNS_ADVANCE_LINE, 2, DW_LNS_COPY]);
+        program.extend(&[DW_LNS_ADVANCE_PC, 8, 0, 1, DW_LNE_END_SEQUENCE]);
+
+        let mut unit = vec![4, 0];
+        unit.extend(&(header.len() as u32).to_le_bytes());
+        unit.extend(header);
+        unit.extend(program);
+
+        let mut section = (unit.len() as u32).to_le_bytes().to_vec();
+        section.extend(unit);
+        section
+    }
+
+    #[test]
+    fn maps_addresses_to_lines() {
+        let table = LineTable::parse(&debug_line()).unwrap();
+
+        let location = table.locate(0x24).unwrap();
+        assert_eq!(location.file, "/app/code/src/lib.rs");
+        assert_eq!((location.line, location.column), (10, 5));
+
+        assert_eq!(table.locate(0x2f).unwrap().line, 12);
+        assert!(table.locate(0x30).is_none());
+        assert!(table.locate(0x10).is_none());
+    }
+}
@@ -0,0 +1,182 @@
+//! Gives the code of a Zome the content of files listed in the `embed` of its `.build`:
+//!
+//! ```json
+//! { "steps": { "cargo": ["build", "--release"] }, "embed": ["../schemas/post.json"] }
+//! ```
+//!
+//! Before the Zome is built, their content is written into `src/embedded.rs` as a constant per
+//! file, named after it, and a `get` function by the path they're listed with. Unlike
+//! `include_str!` paths, which are relative to the source file and break once the code is
+//! built from somewhere else, the module is the same wherever the Zome is built.
+
+use config_files::Build;
+use error::DefaultResult;
+use snapshot::Snapshot;
+use std::{ascii, collections::BTreeMap, fs, path::Path};
+
+/// Where the module is written to, relative to the code directory
+pub const MODULE_PATH: &str = "src/embedded.rs";
+
+const HEADER: &str = "//! Generated by hcdev from the `embed` of `.build` every time the Zome is \
+                      packaged.\n//! Edit the embedded files instead of this one.\n";
+
+/// Writes the module with the files the build embeds, if it doesn't have their content already.
+/// Returns whether it was written.
+pub fn write_module(build: &Build, code_dir: &Path, snapshot: &Snapshot) -> DefaultResult<bool> {
+    if build.embed.is_empty() {
+        return Ok(false);
+    }
+
+    let mut constants = BTreeMap::new();
+    let mut module = HEADER.to_string();
+    let mut arms = String::new();
+
+    for file in &build.embed {
+        let path = code_dir.join(file);
+
+        ensure!(
+            path.is_file(),
+            "{:?} is to be embedded into the Zome, but it isn't a file",
+            path
+        );
+
+        let listed = file.to_string_lossy();
+        let name = constant_name(file);
+
+        if let Some(other) = constants.insert(name.clone(), listed.clone()) {
+            bail!(
+                "{} and {} would both be embedded as {}, rename one of them",
+                other,
+                listed,
+                name
+            );
+        }
+
+        let content = snapshot.read(&path)?;
+
+        // text is embedded as a string, so schemas and templates need no conversion
+        let (declaration, bytes) = match String::from_utf8(content) {
+            Ok(text) => (format!("&str = {:?}", text), format!("{}.as_bytes()", name)),
+            Err(err) => (
+                format!("&[u8] = {}", byte_string(err.as_bytes())),
+                name.clone(),
+            ),
+        };
+
+        module.push_str(&format!(
+            "\n/// `{}`\npub const {}: {};\n",
+            listed, name, declaration
+        ));
+        arms.push_str(&format!("        {:?} => Some({}),\n", listed, bytes));
+    }
+
+    module.push_str(&format!(
+        "\n/// The content of an embedded file, by the path `.build` lists it with\n\
+         pub fn get(path: &str) -> Option<&'static [u8]> {{\n    \
+         match path {{\n{}        _ => None,\n    }}\n}}\n",
+        arms
+    ));
+
+    let module_path = code_dir.join(MODULE_PATH);
+
+    // rewriting an unchanged module would rebuild the Zome for nothing
+    if fs::read_to_string(&module_path).ok().as_ref() == Some(&module) {
+        return Ok(false);
+    }
+
+    if let Some(parent) = module_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(&module_path, module)?;
+
+    Ok(true)
+}
+
+/// The name of the constant for the file: its name in capitals, like `POST_JSON`
+fn constant_name(file: &Path) -> String {
+    let name: String = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    match name.chars().next() {
+        Some(c) if !c.is_ascii_digit() => name,
+        _ => format!("_{}", name),
+    }
+}
+
+fn byte_string(bytes: &[u8]) -> String {
+    let escaped: Vec<u8> = bytes
+        .iter()
+        .flat_map(|byte| ascii::escape_default(*byte))
+        .collect();
+
+    format!("b\"{}\"", String::from_utf8_lossy(&escaped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use tempfile::Builder;
+
+    #[test]
+    fn embeds_files_as_constants_of_a_module() {
+        let tmp = Builder::new().prefix("embed").tempdir().unwrap();
+        let code_dir = tmp.path().join("code");
+
+        fs::create_dir_all(code_dir.join("sql")).unwrap();
+        fs::create_dir_all(tmp.path().join("schemas")).unwrap();
+        fs::write(
+            tmp.path().join("schemas/post.json"),
+            "{ \"title\": \"string\" }\n",
+        ).unwrap();
+        fs::write(code_dir.join("sql/1-init.sql"), b"\xff\0").unwrap();
+
+        let build: Build = serde_json::from_value(json!({
+            "steps": {},
+            "artifact": "code.wasm",
+            "embed": ["../schemas/post.json", "sql/1-init.sql"]
+        })).unwrap();
+        let snapshot = Snapshot::default();
+
+        assert!(write_module(&build, &code_dir, &snapshot).unwrap());
+
+        let module = fs::read_to_string(code_dir.join(MODULE_PATH)).unwrap();
+
+        assert!(module.contains(
+            "/// `../schemas/post.json`\n\
+             pub const POST_JSON: &str = \"{ \\\"title\\\": \\\"string\\\" }\\n\";\n"
+        ));
+        assert!(module.contains("pub const _1_INIT_SQL: &[u8] = b\"\\xff\\x00\";\n"));
+        assert!(
+            module.contains("        \"../schemas/post.json\" => Some(POST_JSON.as_bytes()),\n")
+        );
+        assert!(module.contains("        \"sql/1-init.sql\" => Some(_1_INIT_SQL),\n"));
+
+        // nothing changed, nothing to rebuild
+        assert!(!write_module(&build, &code_dir, &snapshot).unwrap());
+
+        let clashing: Build = serde_json::from_value(json!({
+            "steps": {},
+            "artifact": "code.wasm",
+            "embed": ["../schemas/post.json", "post.json"]
+        })).unwrap();
+        fs::write(code_dir.join("post.json"), "{}").unwrap();
+
+        assert!(write_module(&clashing, &code_dir, &snapshot)
+            .unwrap_err()
+            .to_string()
+            .contains("both be embedded as POST_JSON"));
+    }
+}
@@ -0,0 +1,162 @@
+//! Encryption of whole bundles to a single recipient.
+//!
+//! An ephemeral X25519 key is agreed with the recipient's public key, and the SHA-256 of the
+//! shared secret and both public keys is used as ChaCha20-Poly1305 key for the bundle bytes.
+
+use base64;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use error::DefaultResult;
+use rand::{rngs::OsRng, RngCore};
+use serde_json::{self, Value};
+use sha2::{Digest, Sha256};
+use std::{fs, path::Path};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+pub const ENCRYPTED_BUNDLE_KEY: &str = "encrypted_bundle";
+pub const ENCRYPTION_ALGORITHM: &str = "x25519-chacha20poly1305";
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Deserialize, Serialize)]
+pub struct Envelope {
+    pub algorithm: String,
+    pub ephemeral_public_key: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// Creates a new identity, returning the base64 encoded secret and public key
+pub fn generate_identity() -> (String, String) {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+
+    (
+        base64::encode(&secret.to_bytes()),
+        base64::encode(public.as_bytes()),
+    )
+}
+
+/// Reads the secret key from an identity file created by `keygen`
+pub fn read_identity<T: AsRef<Path>>(path: T) -> DefaultResult<StaticSecret> {
+    let contents = fs::read_to_string(&path)?;
+
+    Ok(StaticSecret::from(decode_key(contents.trim(), "identity")?))
+}
+
+/// Encrypts the given bytes to the owner of the base64 encoded public key
+pub fn encrypt(plaintext: &[u8], recipient: &str) -> DefaultResult<Value> {
+    Ok(json!({ ENCRYPTED_BUNDLE_KEY: seal(plaintext, recipient)? }))
+}
+
+/// Encrypts the given bytes to the owner of the base64 encoded public key, returning just the
+/// envelope
+pub fn seal(plaintext: &[u8], recipient: &str) -> DefaultResult<Envelope> {
+    let recipient = PublicKey::from(decode_key(recipient, "recipient")?);
+
+    let ephemeral_secret = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral_public = PublicKey::from(&ephemeral_secret);
+    let shared_secret = ephemeral_secret.diffie_hellman(&recipient);
+
+    let cipher = cipher(shared_secret.as_bytes(), &ephemeral_public, &recipient);
+
+    let mut nonce = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| format_err!("unable to encrypt bundle"))?;
+
+    Ok(Envelope {
+        algorithm: ENCRYPTION_ALGORITHM.into(),
+        ephemeral_public_key: base64::encode(ephemeral_public.as_bytes()),
+        nonce: base64::encode(&nonce),
+        ciphertext: base64::encode(&ciphertext),
+    })
+}
+
+/// Returns the envelope if the given bundle content is encrypted
+pub fn envelope(bundle: &Value) -> DefaultResult<Option<Envelope>> {
+    match bundle.get(ENCRYPTED_BUNDLE_KEY) {
+        Some(envelope) => Ok(Some(serde_json::from_value(envelope.clone())?)),
+        None => Ok(None),
+    }
+}
+
+pub fn decrypt(envelope: &Envelope, identity: &StaticSecret) -> DefaultResult<Vec<u8>> {
+    ensure!(
+        envelope.algorithm == ENCRYPTION_ALGORITHM,
+        "unsupported encryption algorithm: {}",
+        envelope.algorithm
+    );
+
+    let ephemeral_public = PublicKey::from(decode_key(
+        &envelope.ephemeral_public_key,
+        "ephemeral public",
+    )?);
+    let shared_secret = identity.diffie_hellman(&ephemeral_public);
+    let own_public = PublicKey::from(identity);
+
+    let cipher = cipher(shared_secret.as_bytes(), &ephemeral_public, &own_public);
+
+    let nonce = base64::decode(&envelope.nonce)?;
+    ensure!(nonce.len() == NONCE_LENGTH, "invalid nonce length");
+
+    let ciphertext = base64::decode(&envelope.ciphertext)?;
+
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), &ciphertext[..])
+        .map_err(|_| format_err!("unable to decrypt bundle, is this the right identity?"))
+}
+
+fn cipher(shared_secret: &[u8], ephemeral: &PublicKey, recipient: &PublicKey) -> ChaCha20Poly1305 {
+    let mut hasher = Sha256::new();
+    hasher.update(shared_secret);
+    hasher.update(ephemeral.as_bytes());
+    hasher.update(recipient.as_bytes());
+
+    ChaCha20Poly1305::new(Key::from_slice(&hasher.finalize()))
+}
+
+fn decode_key(encoded: &str, kind: &str) -> DefaultResult<[u8; KEY_LENGTH]> {
+    let bytes =
+        base64::decode(encoded).map_err(|err| format_err!("invalid {} key: {}", kind, err))?;
+
+    ensure!(
+        bytes.len() == KEY_LENGTH,
+        "invalid {} key: expected {} bytes",
+        kind,
+        KEY_LENGTH
+    );
+
+    let mut key = [0u8; KEY_LENGTH];
+    key.copy_from_slice(&bytes);
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_to_the_recipient_only() {
+        let (secret, public) = generate_identity();
+        let (other_secret, _) = generate_identity();
+
+        let encrypted = encrypt(b"{\"name\":\"app\"}", &public).unwrap();
+        let envelope = envelope(&encrypted).unwrap().unwrap();
+
+        let identity = StaticSecret::from(decode_key(&secret, "identity").unwrap());
+        let other_identity = StaticSecret::from(decode_key(&other_secret, "identity").unwrap());
+
+        assert_eq!(
+            decrypt(&envelope, &identity).unwrap(),
+            b"{\"name\":\"app\"}".to_vec()
+        );
+        assert!(decrypt(&envelope, &other_identity).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+//! Locking for the caches all `hcdev` processes of a user share.
+//!
+//! Parallel CI jobs on one machine work on the same cache directory. Every cache entry is
+//! guarded by its own file in the `locks` cache directory, held with an OS file lock so it is
+//! released even if the holder crashes. Entries are written to a temporary file and renamed into
+//! place, so readers never see a half-written entry, with or without taking the lock.
+//!
+//! Lock files nobody holds are removed by `hcdev prune`. A process that locked a lock file just
+//! before it was removed notices and locks the new one instead, so removing them is safe.
+
+use config_files::dirs;
+use error::DefaultResult;
+use fs2::{self, FileExt};
+use interrupt::PartialFile;
+use output;
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+use tempfile::Builder;
+use util;
+
+pub const LOCK_TIMEOUT_ENV_VAR: &str = "HC_LOCK_TIMEOUT";
+pub const LOCKS_DIR_NAME: &str = "locks";
+
+const TEMP_FILE_PREFIX: &str = ".hcdev-write";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An exclusive lock on a cache entry, released when dropped
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Locks the entry at `path`, waiting for other processes to release it up to the timeout
+    /// set in `HC_LOCK_TIMEOUT`
+    pub fn acquire(path: &Path) -> DefaultResult<FileLock> {
+        let timeout = match env::var(LOCK_TIMEOUT_ENV_VAR) {
+            Ok(timeout) => util::parse_duration(&timeout)
+                .map_err(|err| format_err!("invalid {}: {}", LOCK_TIMEOUT_ENV_VAR, err))?,
+            Err(_) => DEFAULT_TIMEOUT,
+        };
+        let started = Instant::now();
+        let mut waiting = false;
+
+        loop {
+            if let Some(lock) = FileLock::try_acquire(path)? {
+                return Ok(lock);
+            }
+
+            ensure!(
+                started.elapsed() < timeout,
+                "gave up waiting for another hcdev process to release {:?} after {}s, set {} to \
+                 wait longer",
+                path,
+                timeout.as_secs(),
+                LOCK_TIMEOUT_ENV_VAR
+            );
+
+            if !waiting {
+                output::print_err(format!(
+                    "waiting for another hcdev process to release {:?}",
+                    path
+                ));
+                waiting = true;
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Locks the entry at `path` if no other process holds the lock right now
+    pub fn try_acquire(path: &Path) -> DefaultResult<Option<FileLock>> {
+        FileLock::try_acquire_in(&locks_dir()?, path)
+    }
+
+    fn try_acquire_in(locks_dir: &Path, path: &Path) -> DefaultResult<Option<FileLock>> {
+        let lock_path = lock_path(locks_dir, path)?;
+
+        fs::create_dir_all(locks_dir)?;
+
+        loop {
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&lock_path)?;
+
+            match file.try_lock_exclusive() {
+                // prune may have removed the lock file between opening and locking it
+                Ok(()) if is_linked(&file, &lock_path) => {
+                    return Ok(Some(FileLock { _file: file }))
+                }
+                Ok(()) => continue,
+                Err(ref err) if is_contended(err) => return Ok(None),
+                Err(err) => bail!("unable to lock {:?}: {}", path, err),
+            }
+        }
+    }
+}
+
+/// Removes the lock files no process holds right now, returns how many
+pub fn prune_locks() -> DefaultResult<usize> {
+    prune_locks_in(&locks_dir()?)
+}
+
+fn prune_locks_in(locks_dir: &Path) -> DefaultResult<usize> {
+    if !locks_dir.is_dir() {
+        return Ok(0);
+    }
+
+    let mut removed_count = 0;
+
+    for entry in fs::read_dir(locks_dir)? {
+        let path = entry?.path();
+        let file = match OpenOptions::new().write(true).open(&path) {
+            Ok(file) => file,
+            // removed by another prune in the meantime
+            Err(_) => continue,
+        };
+
+        match file.try_lock_exclusive() {
+            // removed while locked, so the next process to lock the entry creates a new one
+            Ok(()) => {
+                fs::remove_file(&path)?;
+                removed_count += 1;
+            }
+            Err(ref err) if is_contended(err) => (),
+            Err(err) => bail!("unable to lock {:?}: {}", path, err),
+        }
+    }
+
+    Ok(removed_count)
+}
+
+/// Whether the file is an entry that is still being written
+pub fn is_partial_write(path: &Path) -> bool {
+    path.file_name().map_or(false, |name| {
+        name.to_string_lossy().starts_with(TEMP_FILE_PREFIX)
+    })
+}
+
+/// Replaces the file at `path` with the given content in one step
+pub fn write_atomic(path: &Path, content: &[u8]) -> DefaultResult<()> {
+    let parent = match path.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+
+    fs::create_dir_all(parent)?;
+
+    let mut file = Builder::new()
+        .prefix(TEMP_FILE_PREFIX)
+        .tempfile_in(parent)?;
+    let _partial = PartialFile::new(file.path());
+
+    file.write_all(content)?;
+    file.persist(path)
+        .map_err(|err| format_err!("unable to write {:?}: {}", path, err.error))?;
+
+    Ok(())
+}
+
+fn locks_dir() -> DefaultResult<PathBuf> {
+    Ok(dirs::cache_dir()?.join(LOCKS_DIR_NAME))
+}
+
+/// The lock file of an entry, named after the hash of the entry's absolute path
+fn lock_path(locks_dir: &Path, path: &Path) -> DefaultResult<PathBuf> {
+    let path = env::current_dir()?.join(path);
+    let name = util::sha256_hex(path.to_string_lossy().as_bytes());
+
+    Ok(locks_dir.join(format!("{}.lock", name)))
+}
+
+fn is_contended(err: &io::Error) -> bool {
+    err.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+}
+
+/// Whether the open file is still the one at `path`
+#[cfg(unix)]
+fn is_linked(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(open), Ok(linked)) => open.dev() == linked.dev() && open.ino() == linked.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_linked(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks_exclude_each_other() {
+        let tmp = Builder::new().prefix("file_lock").tempdir().unwrap();
+        let locks_dir = tmp.path().join(LOCKS_DIR_NAME);
+        let entry = tmp.path().join("builds").join("abc.json");
+        let other = tmp.path().join("builds").join("def.json");
+
+        let lock = FileLock::try_acquire_in(&locks_dir, &entry).unwrap();
+        assert!(lock.is_some());
+        assert!(FileLock::try_acquire_in(&locks_dir, &entry)
+            .unwrap()
+            .is_none());
+
+        drop(FileLock::try_acquire_in(&locks_dir, &other).unwrap());
+
+        // only the lock file of the other entry is free to go
+        assert_eq!(prune_locks_in(&locks_dir).unwrap(), 1);
+        assert_eq!(fs::read_dir(&locks_dir).unwrap().count(), 1);
+        assert!(FileLock::try_acquire_in(&locks_dir, &entry)
+            .unwrap()
+            .is_none());
+
+        drop(lock);
+        assert!(FileLock::try_acquire_in(&locks_dir, &entry)
+            .unwrap()
+            .is_some());
+
+        write_atomic(&entry, b"{}").unwrap();
+        assert_eq!(fs::read(&entry).unwrap(), b"{}");
+        assert_eq!(fs::read_dir(entry.parent().unwrap()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,164 @@
+//! The rules deciding which files of an app `package` leaves out.
+//!
+//! `.hcignore` files use the gitignore syntax and compose like `.gitignore` files do: the rules
+//! of a directory's file apply to everything below it, and deeper files come later, so they can
+//! re-include what an ancestor excluded with `!`. The user's own rules in the `ignore` file of
+//! the settings directory (`~/.hc/ignore` for older installs) come before all of them.
+
+use config_files::dirs;
+use error::DefaultResult;
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+pub const IGNORE_FILE_NAME: &str = ".hcignore";
+pub const GLOBAL_IGNORE_FILE_NAME: &str = "ignore";
+
+/// The pattern that decided about a path
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub file: PathBuf,
+    pub pattern: String,
+    /// The pattern starts with `!` and re-includes the path
+    pub whitelisted: bool,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.file.display(), self.pattern)
+    }
+}
+
+pub struct IgnoreRules {
+    root: PathBuf,
+    global: Gitignore,
+    /// The `.hcignore` file of each directory, read when first needed
+    dirs: HashMap<PathBuf, Gitignore>,
+}
+
+impl IgnoreRules {
+    /// The rules of the app in `root`, including the user's global ones
+    pub fn load(root: &Path) -> DefaultResult<IgnoreRules> {
+        IgnoreRules::with_global(root, &dirs::config_dir()?.join(GLOBAL_IGNORE_FILE_NAME))
+    }
+
+    fn with_global(root: &Path, global_file: &Path) -> DefaultResult<IgnoreRules> {
+        let mut builder = GitignoreBuilder::new(root);
+
+        if global_file.is_file() {
+            if let Some(err) = builder.add(global_file) {
+                bail!("{:?}: {}", global_file, err);
+            }
+        }
+
+        Ok(IgnoreRules {
+            root: root.to_path_buf(),
+            global: builder
+                .build()
+                .map_err(|err| format_err!("{:?}: {}", global_file, err))?,
+            dirs: HashMap::new(),
+        })
+    }
+
+    /// Whether `path`, somewhere below the app's root, is left out
+    pub fn is_ignored(&mut self, path: &Path, is_dir: bool) -> DefaultResult<bool> {
+        Ok(self
+            .explain(path, is_dir)?
+            .map_or(false, |rule| !rule.whitelisted))
+    }
+
+    /// The last rule matching `path`, which is the one that counts
+    pub fn explain(&mut self, path: &Path, is_dir: bool) -> DefaultResult<Option<Rule>> {
+        let mut rule = to_rule(self.global.matched(path, is_dir));
+
+        // from the root down to the directory `path` is in
+        let mut dirs: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.reverse();
+
+        for dir in dirs {
+            if !self.dirs.contains_key(&dir) {
+                let (rules, err) = Gitignore::new(dir.join(IGNORE_FILE_NAME));
+
+                if let Some(err) = err {
+                    bail!("{:?}: {}", dir.join(IGNORE_FILE_NAME), err);
+                }
+
+                self.dirs.insert(dir.clone(), rules);
+            }
+
+            if let Some(matched) = to_rule(self.dirs[&dir].matched(path, is_dir)) {
+                rule = Some(matched);
+            }
+        }
+
+        Ok(rule)
+    }
+}
+
+fn to_rule(matched: Match<&::ignore::gitignore::Glob>) -> Option<Rule> {
+    let (glob, whitelisted) = match matched {
+        Match::None => return None,
+        Match::Ignore(glob) => (glob, false),
+        Match::Whitelist(glob) => (glob, true),
+    };
+
+    Some(Rule {
+        file: glob.from().map_or_else(PathBuf::new, Path::to_path_buf),
+        pattern: glob.original().to_string(),
+        whitelisted,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn deeper_files_override_their_ancestors() {
+        let tmp = Builder::new().prefix("hcignore").tempdir().unwrap();
+        let root = tmp.path();
+        let global_file = root.join("global-ignore");
+
+        fs::create_dir_all(root.join("zomes/posts")).unwrap();
+        fs::write(&global_file, "*.swp\n").unwrap();
+        fs::write(root.join(IGNORE_FILE_NAME), "*.md\n").unwrap();
+        fs::write(
+            root.join("zomes/posts").join(IGNORE_FILE_NAME),
+            "!README.md\n",
+        )
+        .unwrap();
+
+        let mut rules = IgnoreRules::with_global(root, &global_file).unwrap();
+
+        assert!(rules
+            .is_ignored(&root.join("zomes/notes.md"), false)
+            .unwrap());
+        assert!(!rules
+            .is_ignored(&root.join("zomes/posts/README.md"), false)
+            .unwrap());
+        assert!(!rules.is_ignored(&root.join("app.json"), false).unwrap());
+        assert_eq!(
+            rules
+                .explain(&root.join("zomes/.app.json.swp"), false)
+                .unwrap(),
+            Some(Rule {
+                file: global_file.clone(),
+                pattern: "*.swp".to_string(),
+                whitelisted: false,
+            })
+        );
+    }
+}
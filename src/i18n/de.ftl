@@ -0,0 +1,65 @@
+# German messages
+
+status-created = Erstellt
+status-installed = Installiert
+status-pruned = Bereinigt
+status-fresh = Aktuell
+status-added = Hinzugefügt
+status-scaffolded = Eingerichtet
+status-exported = Exportiert
+status-imported = Importiert
+status-checked = Geprüft
+status-signed = Signiert
+status-notarized = Notarisiert
+status-fetched = Abgerufen
+status-uploaded = Hochgeladen
+status-restored = Wiederhergestellt
+
+created-bundle = {status}: Bundle-Datei unter {path}
+checked-app = {status}: die App, das Bundle wurde nicht geschrieben
+checked-call = {status}: die Argumente von {function}, sie sind gültig
+created-provenance = {status}: Herkunftsnachweis unter {path}
+created-project = {status}: neues Holochain-Projekt unter {path}
+created-example = {status}: die Beispiel-App {name} unter {path}
+created-git-repository = {status}: Git-Repository mit einem ersten Commit
+created-docs = {status}: API-Dokumentation unter {path}
+created-migration = {status}: Migration unter {path}
+created-changelog = {status}: Changelog unter {path}
+created-build-plan = {status}: Build-Plan für {count} Zome(s) unter {path}
+created-debug-info = {status}: Debug-Informationen unter {path}
+created-redacted-bundle = {status}: geschwärztes Bundle unter {path}, {count} WASM-Modul(e) ersetzt
+created-identity = {status}: Identität unter {path}
+created-signing-key = {status}: Signaturschlüssel unter {path}
+created-electron-shell = {status}: Electron-Hülle unter {path}, Start mit npm install && npm start
+created-entry-type = {status}: Eintragstyp {name} zu {path}
+created-test = {status}: Test unter {path}
+created-ui-page = {status}: UI-Seite unter {path}
+created-workflow = {status}: Workflow {name} unter {path}
+added-bridge = {status}: Bridge {handle} zu {path}
+scaffolded-zome = {status}: {path} mit {count} Eintragstyp(en)
+language-from-config = Verwende {language}, die Standardsprache des Projekts
+language-from-siblings = Verwende {language} wie die anderen Zomes neben {zome}
+exported-settings = {status}: Einstellungen nach {path}
+imported-settings = {status}: Einstellungen aus {path} nach {target}
+public-key = Öffentlicher Schlüssel: {key}
+installed-dna = {status}: {name} unter {path}
+installed-hook = {status}: {hook}-Hook unter {path}
+signed-artifact = {status}: {path} als {identity}
+notarized-artifact = {status}: {path}
+pruned-files = {status}: {count} Datei(en), {size} freigegeben
+fresh-zome = {status}: {path}, der letzte Build wird wiederverwendet
+fetched-zome = {status}: der Build von {path} aus dem entfernten Build-Cache
+uploaded-zome = {status}: der Build von {path} in den entfernten Build-Cache
+restored-zome = {status}: der Build von {path} aus einem früheren Build derselben Quellen
+added-license-headers = {status}: {license}-Header in {count} Datei(en)
+no-search-results = Keine DNAs für "{term}" gefunden
+kept-test-home = das Home-Verzeichnis der fehlgeschlagenen Tests von {name} bleibt unter {path} erhalten
+watching-app = Die App wird auf Änderungen überwacht, Strg-C beendet
+serving-app = Die App läuft unter {url}, Strg-C beendet den Conductor
+changed-files = {files} geändert, es wird erneut gepackt
+
+init-collisions = {paths} würde(n) überschrieben, Abbruch
+unsupported-language = nicht unterstützte Sprache: {language}
+script-failed = Skript {name} ist fehlgeschlagen
+warnings-denied = Abbruch wegen {count} verbotener Warnung(en)
+projects-failed = {count} von {total} App(s) fehlgeschlagen
@@ -0,0 +1,65 @@
+# English messages, the fallback for every other locale
+
+status-created = Created
+status-installed = Installed
+status-pruned = Pruned
+status-fresh = Fresh
+status-added = Added
+status-scaffolded = Scaffolded
+status-exported = Exported
+status-imported = Imported
+status-checked = Checked
+status-signed = Signed
+status-notarized = Notarized
+status-fetched = Fetched
+status-uploaded = Uploaded
+status-restored = Restored
+
+created-bundle = {status} bundle file at {path}
+checked-app = {status} the app, the bundle wasn't written
+checked-call = {status} the arguments of {function}, they are valid
+created-provenance = {status} provenance at {path}
+created-project = {status} new Holochain project at: {path}
+created-example = {status} the {name} example app at {path}
+created-git-repository = {status} git repository with an initial commit
+created-docs = {status} API docs at {path}
+created-migration = {status} migration at {path}
+created-changelog = {status} changelog at {path}
+created-build-plan = {status} build plan of {count} zome(s) at {path}
+created-debug-info = {status} debug info at {path}
+created-redacted-bundle = {status} redacted bundle at {path}, replaced {count} WASM module(s)
+created-identity = {status} identity at {path}
+created-signing-key = {status} signing key at {path}
+created-electron-shell = {status} Electron shell at {path}, start it with npm install && npm start
+created-entry-type = {status} entry type {name} to {path}
+created-test = {status} test at {path}
+created-ui-page = {status} UI page at {path}
+created-workflow = {status} workflow {name} at {path}
+added-bridge = {status} bridge {handle} to {path}
+scaffolded-zome = {status} {path} with {count} entry type(s)
+language-from-config = Using {language}, the default language of the project
+language-from-siblings = Using {language}, like the other zomes next to {zome}
+exported-settings = {status} the settings to {path}
+imported-settings = {status} the settings of {path} into {target}
+public-key = Public key: {key}
+installed-dna = {status} {name} at {path}
+installed-hook = {status} {hook} hook at {path}
+signed-artifact = {status} {path} as {identity}
+notarized-artifact = {status} {path}
+pruned-files = {status} {count} file(s), reclaimed {size}
+fresh-zome = {status} {path}, reusing the last build
+fetched-zome = {status} the build of {path} from the remote build cache
+uploaded-zome = {status} the build of {path} to the remote build cache
+restored-zome = {status} the build of {path} from an earlier build of the same sources
+added-license-headers = {status} {license} headers to {count} file(s)
+no-search-results = No DNAs found for "{term}"
+kept-test-home = kept the home of the failed tests of {name} at {path}
+watching-app = Watching the app for changes, press Ctrl-C to stop
+serving-app = Serving the app on {url}, press Ctrl-C to stop the conductor
+changed-files = {files} changed, packaging again
+
+init-collisions = refusing to overwrite {paths}
+unsupported-language = unsupported language: {language}
+script-failed = script {name} was not successful
+warnings-denied = aborting due to {count} denied warning(s)
+projects-failed = {count} of {total} app(s) failed
@@ -0,0 +1,102 @@
+//! A minimal message catalog for user-facing output.
+//!
+//! Catalogs are Fluent-style `key = message` files with `{placeholder}` arguments. The locale is
+//! picked from `HC_LANG` (e.g. `de` or `de_DE.UTF-8`), and missing keys fall back to English.
+
+use std::env;
+
+pub const LANG_ENV_VAR: &str = "HC_LANG";
+
+const DEFAULT_LOCALE: &str = "en";
+
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("en.ftl")),
+    ("de", include_str!("de.ftl")),
+];
+
+/// Returns the localized message for the given key with all placeholders substituted
+pub fn tr(key: &str, args: &[(&str, String)]) -> String {
+    let message = lookup(&current_locale(), key)
+        .or_else(|| lookup(DEFAULT_LOCALE, key))
+        .unwrap_or_else(|| key.to_string());
+
+    substitute(message, args)
+}
+
+fn substitute(message: String, args: &[(&str, String)]) -> String {
+    args.iter().fold(message, |message, (name, value)| {
+        message.replace(&format!("{{{}}}", name), value)
+    })
+}
+
+/// The language part of `HC_LANG`, if it names a known locale
+pub fn current_locale() -> String {
+    env::var(LANG_ENV_VAR)
+        .ok()
+        .map(|lang| {
+            lang.split(|c| c == '_' || c == '-' || c == '.')
+                .next()
+                .unwrap_or_default()
+                .to_lowercase()
+        })
+        .filter(|lang| CATALOGS.iter().any(|(locale, _)| locale == lang))
+        .unwrap_or_else(|| DEFAULT_LOCALE.into())
+}
+
+fn lookup(locale: &str, key: &str) -> Option<String> {
+    let catalog = CATALOGS
+        .iter()
+        .find(|(name, _)| *name == locale)
+        .map(|(_, catalog)| catalog)?;
+
+    catalog
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(message)) if name.trim() == key => {
+                    Some(message.trim().to_string())
+                }
+                _ => None,
+            }
+        })
+        .next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_locale_covers_the_english_catalog() {
+        let english_keys: Vec<_> = include_str!("en.ftl")
+            .lines()
+            .filter(|line| !line.starts_with('#') && line.contains('='))
+            .map(|line| line.split('=').next().unwrap().trim())
+            .collect();
+
+        for (locale, _) in CATALOGS {
+            for key in &english_keys {
+                assert!(
+                    lookup(locale, key).is_some(),
+                    "locale {} is missing {}",
+                    locale,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn substitutes_placeholders() {
+        let message = lookup("de", "unsupported-language").unwrap();
+
+        assert_eq!(
+            substitute(message, &[("language", "cobol".into())]),
+            "nicht unterstützte Sprache: cobol"
+        );
+    }
+}
@@ -0,0 +1,268 @@
+//! Cleaning up after Ctrl-C.
+//!
+//! Child processes and files are registered while they are running or being written. When the
+//! user interrupts `hcdev`, every registered process is killed along with everything it started
+//! and every registered file is removed before exiting, so no orphaned builds or truncated
+//! bundles are left behind.
+//!
+//! Children stay in the process group of `hcdev`, which is the terminal's foreground group, so
+//! they can read from the terminal and receive Ctrl-C and Ctrl-Z from it like `hcdev` itself.
+
+use ctrlc;
+use error::DefaultResult;
+use output;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{self, Child, Command, ExitStatus},
+    sync::Mutex,
+};
+
+/// The exit code shells use for processes terminated by SIGINT
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+struct Cleanup {
+    children: Vec<u32>,
+    partial_files: Vec<PathBuf>,
+}
+
+static CLEANUP: Mutex<Cleanup> = Mutex::new(Cleanup {
+    children: Vec::new(),
+    partial_files: Vec::new(),
+});
+
+/// Installs the handler for Ctrl-C and termination requests
+pub fn init() -> DefaultResult<()> {
+    ctrlc::set_handler(|| {
+        let cleanup = CLEANUP.lock().unwrap_or_else(|err| err.into_inner());
+
+        for child in &cleanup.children {
+            kill_tree(*child);
+        }
+
+        for path in &cleanup.partial_files {
+            if path.is_dir() {
+                let _ = fs::remove_dir_all(path);
+            } else {
+                let _ = fs::remove_file(path);
+            }
+        }
+
+        output::error("interrupted");
+
+        process::exit(INTERRUPTED_EXIT_CODE);
+    })
+    .map_err(|err| format_err!("unable to install the interrupt handler: {}", err))
+}
+
+/// Runs the command to completion, killing it along with all of its children on interrupt
+pub fn status(command: &mut Command) -> io::Result<ExitStatus> {
+    let child = spawn(command)?;
+
+    wait(child)
+}
+
+/// Starts the command in the background, it gets killed on interrupt until it is waited for
+pub fn spawn(command: &mut Command) -> io::Result<Child> {
+    let child = command.spawn()?;
+
+    lock().children.push(child.id());
+
+    Ok(child)
+}
+
+/// Waits for a child started with `spawn` to exit
+pub fn wait(mut child: Child) -> io::Result<ExitStatus> {
+    let id = child.id();
+    let status = child.wait();
+
+    lock().children.retain(|child| *child != id);
+
+    status
+}
+
+/// Terminates a child started with `spawn` along with everything it started
+pub fn kill(id: u32) {
+    kill_tree(id);
+}
+
+/// Whether a process with the ID is still running
+#[cfg(unix)]
+pub fn is_running(id: u32) -> bool {
+    use libc;
+
+    unsafe { libc::kill(id as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn is_running(_id: u32) -> bool {
+    false
+}
+
+/// A file or directory that is being written and gets removed if the process is interrupted
+/// meanwhile
+pub struct PartialFile {
+    path: PathBuf,
+}
+
+impl PartialFile {
+    pub fn new<T: AsRef<Path>>(path: T) -> PartialFile {
+        let path = path.as_ref().to_path_buf();
+
+        lock().partial_files.push(path.clone());
+
+        PartialFile { path }
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        let path = &self.path;
+
+        lock().partial_files.retain(|partial| partial != path);
+    }
+}
+
+fn lock() -> ::std::sync::MutexGuard<'static, Cleanup> {
+    CLEANUP.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(unix)]
+fn kill_tree(id: u32) {
+    use libc;
+
+    for pid in with_descendants(id) {
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_tree(_id: u32) {
+    // console processes receive Ctrl-C themselves on Windows
+}
+
+/// The process and everything it started that is still running. They share the process group
+/// of `hcdev`, so they are found by their parents instead.
+#[cfg(unix)]
+fn with_descendants(id: u32) -> Vec<u32> {
+    let table = Command::new("ps")
+        .args(&["-A", "-o", "pid=", "-o", "ppid="])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default();
+    let parents: Vec<(u32, u32)> = table
+        .lines()
+        .filter_map(|line| {
+            let mut ids = line.split_whitespace().map(|id| id.parse().ok());
+
+            Some((ids.next()??, ids.next()??))
+        })
+        .collect();
+
+    let mut tree = vec![id];
+    let mut next = 0;
+
+    while next < tree.len() {
+        let parent = tree[next];
+
+        tree.extend(
+            parents
+                .iter()
+                .filter(|(_, ppid)| *ppid == parent)
+                .map(|(pid, _)| *pid),
+        );
+        next += 1;
+    }
+
+    tree
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use assert_cmd::prelude::*;
+    use libc;
+    use std::{
+        fs,
+        io::{Read, Write},
+        process::{Command, Stdio},
+        thread,
+        time::Duration,
+    };
+    use tempfile::Builder;
+
+    /// Whether the process is still running, zombies waiting to be reaped don't count
+    fn is_alive(pid: i32) -> bool {
+        fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map(|stat| !stat.contains(") Z "))
+            .unwrap_or(false)
+    }
+
+    #[test]
+    fn interrupt_kills_running_scripts() {
+        let tmp = Builder::new().prefix("interrupt").tempdir().unwrap();
+        let home = Builder::new().prefix("interrupt-home").tempdir().unwrap();
+        let pid_file = tmp.path().join("pid");
+
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        fs::write(
+            tmp.path().join("hc.toml"),
+            "[scripts]\nprepackage = \"sleep 30 & echo $! > pid; wait\"\n",
+        ).unwrap();
+
+        let mut hcdev = Command::main_binary()
+            .unwrap()
+            .current_dir(tmp.path())
+            .env("HC_HOME", home.path())
+            .arg("package")
+            .spawn()
+            .unwrap();
+
+        while !fs::read_to_string(&pid_file).map_or(false, |pid| pid.ends_with('\n')) {
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let sleep_pid: i32 = fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+
+        unsafe {
+            libc::kill(hcdev.id() as i32, libc::SIGINT);
+        }
+
+        assert_eq!(hcdev.wait().unwrap().code(), Some(super::INTERRUPTED_EXIT_CODE));
+
+        // give the killed process a moment to actually exit
+        thread::sleep(Duration::from_millis(200));
+        assert!(!is_alive(sleep_pid));
+        assert!(!tmp.path().join("bundle.json").exists());
+    }
+
+    #[test]
+    fn children_can_read_from_stdin() {
+        let mut child = super::spawn(
+            Command::new("sh")
+                .args(&["-c", "read line; echo \"$line\""])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped()),
+        ).unwrap();
+
+        // in a group of their own, reading from the terminal would stop them with SIGTTIN
+        assert_eq!(
+            unsafe { libc::getpgid(child.id() as libc::pid_t) },
+            unsafe { libc::getpgrp() }
+        );
+
+        child.stdin.take().unwrap().write_all(b"hello\n").unwrap();
+
+        let mut read = String::new();
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut read)
+            .unwrap();
+
+        assert!(super::wait(child).unwrap().success());
+        assert_eq!(read, "hello\n");
+    }
+}
@@ -0,0 +1,123 @@
+//! JSON parsing that refuses duplicate keys.
+//!
+//! serde_json keeps the last of several values with the same key, so a manifest with a Zome
+//! listed twice would silently turn into a bundle with only one of them. Everything that
+//! describes an app is parsed with `from_slice` instead, which names the path of the duplicate.
+
+use error::DefaultResult;
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor,
+};
+use serde_json;
+use std::{collections::HashSet, fmt};
+
+/// Parses JSON like `serde_json::from_slice`, failing if an object has a key more than once
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> DefaultResult<T> {
+    let root = Node {
+        path: String::new(),
+    };
+    root.deserialize(&mut serde_json::Deserializer::from_slice(bytes))?;
+
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// A value somewhere in the document, known by its dotted path
+struct Node {
+    path: String,
+}
+
+impl Node {
+    fn child(&self, key: &str) -> Node {
+        Node {
+            path: match self.path.as_str() {
+                "" => key.to_string(),
+                path => format!("{}.{}", path, key),
+            },
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Node {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Node {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let mut index = 0;
+
+        while seq
+            .next_element_seed(self.child(&index.to_string()))?
+            .is_some()
+        {
+            index += 1;
+        }
+
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut keys = HashSet::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            let child = self.child(&key);
+
+            if !keys.insert(key) {
+                return Err(de::Error::custom(format!("duplicate key {}", child.path)));
+            }
+
+            map.next_value_seed(child)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn names_the_path_of_duplicate_keys() {
+        let json = br#"{"zomes": [{"name": "a"}, {"name": "b", "code": {}, "name": "c"}]}"#;
+        let err = from_slice::<Value>(json).unwrap_err().to_string();
+
+        assert!(err.starts_with("duplicate key zomes.1.name"), "{}", err);
+        assert!(from_slice::<Value>(br#"{"a": {"b": 1}, "b": {"b": 2}}"#).is_ok());
+        assert!(from_slice::<Value>(b"{").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! SPDX license expressions and a coarse check of which licenses get along.
+//!
+//! Licenses are put into three families: permissive licenses can be compiled into anything,
+//! weak copyleft licenses only come with obligations for their own files, and strong copyleft
+//! licenses require the whole app to be under a compatible copyleft license. This catches the
+//! common problems, but doesn't replace reading the licenses.
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Verdict {
+    Compatible,
+    /// Compatible, but the license comes with obligations worth knowing about
+    Notice,
+    /// The license isn't known or couldn't be parsed
+    Unknown,
+    Incompatible,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Family {
+    Permissive,
+    WeakCopyleft,
+    StrongCopyleft,
+    Unknown,
+}
+
+const PERMISSIVE: &[&str] = &[
+    "0BSD",
+    "Apache-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSL-1.0",
+    "CC0-1.0",
+    "ISC",
+    "MIT",
+    "MIT-0",
+    "Unicode-3.0",
+    "Unicode-DFS-2016",
+    "Unlicense",
+    "Zlib",
+];
+
+const WEAK_COPYLEFT: &[&str] = &[
+    "CDDL-1.0",
+    "EPL-2.0",
+    "LGPL-2.1",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "MPL-2.0",
+];
+
+const STRONG_COPYLEFT: &[&str] = &[
+    "AGPL-3.0",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "GPL-2.0",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+];
+
+/// Returns the family of a single SPDX license identifier
+pub fn family(id: &str) -> Family {
+    let id = id.trim_end_matches('+');
+
+    if PERMISSIVE.contains(&id) {
+        Family::Permissive
+    } else if WEAK_COPYLEFT.contains(&id) {
+        Family::WeakCopyleft
+    } else if STRONG_COPYLEFT.contains(&id) {
+        Family::StrongCopyleft
+    } else {
+        Family::Unknown
+    }
+}
+
+/// Judges whether code under the `dependency` license expression may be compiled into an app
+/// licensed under `app`. Without an app license the app is treated as proprietary.
+pub fn verdict(dependency: &str, app: Option<&str>) -> Verdict {
+    let app_is_copyleft = app.map_or(false, |app| {
+        identifiers(app)
+            .iter()
+            .any(|id| family(id) == Family::StrongCopyleft)
+    });
+
+    let tokens = tokenize(dependency);
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        app_is_copyleft,
+    };
+
+    match parser.expression() {
+        Some(verdict) if parser.position == tokens.len() => verdict,
+        _ => Verdict::Unknown,
+    }
+}
+
+/// Checks that the expression only consists of known license identifiers
+pub fn is_known(expression: &str) -> bool {
+    let ids = identifiers(expression);
+
+    !ids.is_empty() && ids.iter().all(|id| family(id) != Family::Unknown)
+}
+
+/// The license identifiers of an expression, leaving out operators and exceptions
+fn identifiers(expression: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut after_with = false;
+
+    for token in tokenize(expression) {
+        match token.as_str() {
+            "(" | ")" | "AND" | "OR" => (),
+            "WITH" => after_with = true,
+            _ if after_with => after_with = false,
+            _ => ids.push(token),
+        }
+    }
+
+    ids
+}
+
+fn tokenize(expression: &str) -> Vec<String> {
+    expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        // older crates separate alternatives with a slash, e.g. "MIT/Apache-2.0"
+        .replace('/', " OR ")
+        .split_whitespace()
+        .map(String::from)
+        .collect()
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    position: usize,
+    app_is_copyleft: bool,
+}
+
+impl<'a> Parser<'a> {
+    /// `or-expression := and-expression ("OR" and-expression)*`, the best alternative counts
+    fn expression(&mut self) -> Option<Verdict> {
+        let mut verdict = self.and_expression()?;
+
+        while self.accept("OR") {
+            verdict = verdict.min(self.and_expression()?);
+        }
+
+        Some(verdict)
+    }
+
+    /// `and-expression := term ("AND" term)*`, the worst of all terms counts
+    fn and_expression(&mut self) -> Option<Verdict> {
+        let mut verdict = self.term()?;
+
+        while self.accept("AND") {
+            verdict = verdict.max(self.term()?);
+        }
+
+        Some(verdict)
+    }
+
+    /// `term := "(" or-expression ")" | identifier ["WITH" exception]`
+    fn term(&mut self) -> Option<Verdict> {
+        if self.accept("(") {
+            let verdict = self.expression()?;
+
+            return if self.accept(")") {
+                Some(verdict)
+            } else {
+                None
+            };
+        }
+
+        let id = self.tokens.get(self.position)?.clone();
+        self.position += 1;
+
+        // exceptions only ever grant additional permissions
+        if self.accept("WITH") {
+            self.tokens.get(self.position)?;
+            self.position += 1;
+        }
+
+        Some(match family(&id) {
+            Family::Permissive => Verdict::Compatible,
+            Family::WeakCopyleft => Verdict::Notice,
+            Family::StrongCopyleft if self.app_is_copyleft => Verdict::Compatible,
+            Family::StrongCopyleft => Verdict::Incompatible,
+            Family::Unknown => Verdict::Unknown,
+        })
+    }
+
+    fn accept(&mut self, token: &str) -> bool {
+        let found = self
+            .tokens
+            .get(self.position)
+            .map_or(false, |next| next == token);
+
+        if found {
+            self.position += 1;
+        }
+
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn judges_license_expressions() {
+        assert_eq!(
+            verdict("MIT OR Apache-2.0", Some("MIT")),
+            Verdict::Compatible
+        );
+        assert_eq!(verdict("MIT/Apache-2.0", None), Verdict::Compatible);
+        assert_eq!(verdict("GPL-3.0", Some("MIT")), Verdict::Incompatible);
+        assert_eq!(verdict("GPL-3.0 OR MIT", Some("MIT")), Verdict::Compatible);
+        assert_eq!(
+            verdict("GPL-3.0-or-later", Some("GPL-3.0")),
+            Verdict::Compatible
+        );
+        assert_eq!(
+            verdict("(MIT OR Apache-2.0) AND MPL-2.0", None),
+            Verdict::Notice
+        );
+        assert_eq!(
+            verdict("Apache-2.0 WITH LLVM-exception", None),
+            Verdict::Compatible
+        );
+        assert_eq!(verdict("Custom-1.0", None), Verdict::Unknown);
+        assert_eq!(verdict("(MIT", None), Verdict::Unknown);
+
+        assert!(is_known("MIT OR Apache-2.0"));
+        assert!(is_known("Apache-2.0 WITH LLVM-exception"));
+        assert!(!is_known("Mit"));
+    }
+}
@@ -7,28 +7,136 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate assert_cmd;
+extern crate atty;
 extern crate base64;
+extern crate chacha20poly1305;
 extern crate colored;
+extern crate ctrlc;
+extern crate ed25519_dalek;
 extern crate dir_diff;
+extern crate flate2;
+extern crate fs2;
+extern crate futures;
 extern crate semver;
 #[macro_use]
 extern crate serde_json;
+extern crate serde_yaml;
 extern crate ignore;
+extern crate libc;
+extern crate rand;
+extern crate rustc_demangle;
+extern crate sha2;
 extern crate tempfile;
+extern crate tokio;
+extern crate tokio_threadpool;
+extern crate toml;
+extern crate ureq;
 extern crate uuid;
+extern crate x25519_dalek;
 
+mod alias;
+mod annotations;
+mod build_cache;
+mod bundle;
+mod cbor;
 mod cli;
 mod config_files;
+mod delta;
+mod dwarf;
+mod embed;
+mod encryption;
 mod error;
+mod file_lock;
+mod hcignore;
+mod i18n;
+mod interrupt;
+mod json;
+mod licenses;
+mod msgpack;
+mod net;
+mod output;
+mod parallel;
+mod provenance;
+mod registry;
+mod remote_cache;
+mod reproducible;
+mod resolver;
+mod scripts;
+mod secrets;
+mod snapshot;
+mod synthetic;
 mod util;
+mod warnings;
+mod wasm;
+mod zome_api;
 
-use error::{HolochainError, HolochainResult};
-use std::path::PathBuf;
-use structopt::StructOpt;
+use config_files::Project;
+use error::{DefaultResult, HolochainError, HolochainResult};
+use output::ColorChoice;
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use structopt::{clap::AppSettings, StructOpt};
 
 #[derive(StructOpt)]
-#[structopt(about = "A command line for Holochain")]
-enum Cli {
+#[structopt(
+    about = "A command line for Holochain",
+    after_help = "Other commands are run as plugins, e.g. hcdev foo runs the hc-foo executable on the PATH."
+)]
+struct Cli {
+    #[structopt(
+        long = "color",
+        help = "Whether to colorize the output: auto, always or never",
+        raw(global = "true")
+    )]
+    color: Option<ColorChoice>,
+    #[structopt(
+        long = "offline",
+        help = "Forbids all network access, failing instead of fetching anything",
+        raw(global = "true")
+    )]
+    offline: bool,
+    #[structopt(
+        long = "reproducible",
+        help = "Makes builds reproducible, with SOURCE_DATE_EPOCH or else the epoch as their time",
+        raw(global = "true")
+    )]
+    reproducible: bool,
+    #[structopt(
+        long = "quiet",
+        short = "q",
+        help = "Prints nothing but errors, for scripts that only look at the exit status",
+        raw(global = "true")
+    )]
+    quiet: bool,
+    #[structopt(
+        long = "config",
+        help = "Uses this project config file instead of looking for hc.toml files",
+        parse(from_os_str),
+        raw(global = "true")
+    )]
+    config: Option<PathBuf>,
+    #[structopt(
+        long = "project-dir",
+        help = "Runs as if started in this directory, the root of the app",
+        parse(from_os_str),
+        raw(global = "true")
+    )]
+    project_dir: Option<PathBuf>,
+    #[structopt(
+        long = "non-interactive",
+        help = "Never asks questions, e.g. prints the usage instead of the welcome menu"
+    )]
+    non_interactive: bool,
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+}
+
+#[derive(StructOpt)]
+enum Command {
     #[structopt(
         name = "web",
         alias = "w",
@@ -57,6 +165,141 @@ enum Cli {
         strip_meta: bool,
         #[structopt(long = "output", short = "o", parse(from_os_str))]
         output: Option<PathBuf>,
+        #[structopt(
+            long = "embed-docs",
+            help = "Embeds the generated zome API documentation into the __META__ sections of the bundle"
+        )]
+        embed_docs: bool,
+        #[structopt(
+            long = "deny",
+            help = "Treats the given warning code as an error, use \"warnings\" to deny all warnings"
+        )]
+        deny: Vec<String>,
+        #[structopt(
+            long = "encrypt",
+            help = "Encrypts the bundle so that only the owner of the --recipient key can unpack it",
+            raw(requires = r#""recipient""#)
+        )]
+        encrypt: bool,
+        #[structopt(
+            long = "recipient",
+            help = "The base64 encoded public key (see keygen) to encrypt the bundle to",
+            raw(requires = r#""encrypt""#)
+        )]
+        recipient: Option<String>,
+        #[structopt(
+            long = "delta",
+            help = "Only packages what changed compared to the given previous bundle, see apply-delta",
+            parse(from_os_str)
+        )]
+        delta: Option<PathBuf>,
+        #[structopt(
+            long = "locked",
+            help = "Fails if the Cargo.lock of a Zome is missing or out of date"
+        )]
+        locked: bool,
+        #[structopt(
+            long = "debug-info",
+            help = "Strips names and debug info off the bundled WASM and keeps them in a <zome>.debug.wasm file next to the bundle, see symbolize"
+        )]
+        debug_info: bool,
+        #[structopt(
+            long = "annotations",
+            help = "Reports errors and warnings as annotations CI shows inline: github or gitlab. Defaults to the detected CI system"
+        )]
+        annotations: Option<annotations::Flavor>,
+        #[structopt(
+            long = "profile",
+            help = "Settings for an environment: ci, which implies --locked and annotations"
+        )]
+        profile: Option<cli::Profile>,
+        #[structopt(
+            long = "force",
+            help = "Packages the current directory even if it isn't an app, is the home directory or has a huge number of files"
+        )]
+        force: bool,
+        #[structopt(
+            long = "with-tests",
+            help = "Runs the native tests of every Zome whose .build declares them before building it"
+        )]
+        with_tests: bool,
+        #[structopt(
+            long = "provenance",
+            help = "Writes a signed provenance statement next to the bundle, see keygen --signing"
+        )]
+        provenance: bool,
+        #[structopt(
+            long = "signing-key",
+            help = "The key (see keygen --signing) to sign the provenance with, defaults to the one in the data directory",
+            parse(from_os_str)
+        )]
+        signing_key: Option<PathBuf>,
+        #[structopt(
+            long = "check",
+            help = "Builds the Zomes and checks the app without writing the bundle, e.g. in a git hook",
+            raw(conflicts_with_all = r#"&["output", "provenance", "delta", "encrypt", "debug_info"]"#)
+        )]
+        check: bool,
+        #[structopt(
+            long = "format",
+            default_value = "json",
+            help = "The encoding of the bundle: json, or msgpack or cbor for smaller bundles that are faster to read"
+        )]
+        format: bundle::Encoding,
+        #[structopt(
+            long = "watch",
+            help = "Packages again whenever a file of the app changes, building only the Zomes whose sources changed",
+            conflicts_with = "annotations"
+        )]
+        watch: bool,
+    },
+    #[structopt(
+        name = "build-plan",
+        about = "Writes the commands, inputs and outputs of every Zome build to build-plan.json, for other build systems to run"
+    )]
+    BuildPlan {
+        #[structopt(long = "output", short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+        #[structopt(
+            long = "locked",
+            help = "Plans builds that fail if the Cargo.lock of a Zome is missing or out of date"
+        )]
+        locked: bool,
+    },
+    #[structopt(
+        name = "apply-delta",
+        about = "Reconstructs a full bundle from a delta bundle and the bundle it was made for"
+    )]
+    ApplyDelta {
+        #[structopt(help = "The previous bundle the delta was made for", parse(from_os_str))]
+        base: PathBuf,
+        #[structopt(help = "The delta bundle created by package --delta", parse(from_os_str))]
+        delta: PathBuf,
+        #[structopt(long = "output", short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "compat",
+        about = "Checks a bundle against a release of the conductor and reports what keeps it from running there"
+    )]
+    Compat {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(
+            long = "conductor",
+            help = "The release of the conductor to check against, leave it out to check all releases hcdev knows"
+        )]
+        conductor: Option<String>,
+        #[structopt(long = "json", help = "Prints the incompatibilities as JSON")]
+        json: bool,
+    },
+    #[structopt(
+        name = "explain-bundle",
+        about = "Tells in prose what a bundle contains and what it does once instantiated, for reviewers who don't read Rust"
+    )]
+    ExplainBundle {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
     },
     #[structopt(
         name = "unpack",
@@ -67,6 +310,57 @@ enum Cli {
         path: PathBuf,
         #[structopt(parse(from_os_str))]
         to: PathBuf,
+        #[structopt(
+            long = "identity",
+            help = "The identity file (see keygen) to decrypt an encrypted bundle with, defaults to the one in the data directory",
+            parse(from_os_str)
+        )]
+        identity: Option<PathBuf>,
+        #[structopt(
+            long = "flatten-zomes",
+            help = "Gives every Zome a code directory whose .build copies the bundled WASM, so the unpacked app can be packaged again"
+        )]
+        flatten_zomes: bool,
+    },
+    #[structopt(
+        name = "export",
+        about = "Translates a bundle into the bundle format of other Holochain versions"
+    )]
+    Export {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(long = "format", help = "The format to translate to: dna-v2")]
+        format: cli::Format,
+        #[structopt(long = "output", short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "import",
+        about = "Translates a bundle of another Holochain version into a bundle hcdev can unpack"
+    )]
+    Import {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(long = "format", help = "The format to translate from: dna-v2")]
+        format: cli::Format,
+        #[structopt(long = "output", short = "o", parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "keygen",
+        about = "Creates an identity for receiving encrypted bundles, or a key for signing provenance"
+    )]
+    Keygen {
+        #[structopt(
+            help = "The file to store the secret key in, defaults to identity.key or signing.key in the data directory",
+            parse(from_os_str)
+        )]
+        path: Option<PathBuf>,
+        #[structopt(
+            long = "signing",
+            help = "Creates a key for signing the provenance of bundles instead"
+        )]
+        signing: bool,
     },
     #[structopt(
         name = "init",
@@ -81,6 +375,133 @@ enum Cli {
             help = "Specifies the hash of the DNA the new app should be scaffolded from"
         )]
         from: Option<String>,
+        #[structopt(
+            long = "example",
+            help = "Sets up a complete example app instead of an empty one, see examples"
+        )]
+        example: Option<String>,
+    },
+    #[structopt(name = "examples", about = "Lists the example apps init --example sets up")]
+    Examples,
+    #[structopt(
+        name = "status",
+        about = "Sums up the zomes, manifests, bundle and toolchain of the app"
+    )]
+    Status {
+        #[structopt(long = "files", help = "Lists the files package puts into the bundle")]
+        files: bool,
+        #[structopt(
+            long = "ignored",
+            help = "Lists the files package leaves out, with the .hcignore rule responsible"
+        )]
+        ignored: bool,
+    },
+    #[structopt(
+        name = "lint",
+        about = "Checks manifests and .build files for absolute paths and host-specific values"
+    )]
+    Lint {
+        #[structopt(
+            long = "fix",
+            help = "Rewrites paths into the app relative to the file they're in"
+        )]
+        fix: bool,
+        #[structopt(
+            long = "wasm-imports",
+            help = "Also checks that the built Zomes only import host functions the conductor provides"
+        )]
+        wasm_imports: bool,
+    },
+    #[structopt(
+        name = "hooks",
+        about = "Runs checks of the app before git commits and pushes"
+    )]
+    Hooks {
+        #[structopt(subcommand)]
+        cmd: HooksCmd,
+    },
+    #[structopt(
+        name = "macos",
+        about = "Signs and notarizes native artifacts like desktop launchers for distribution on macOS"
+    )]
+    Macos {
+        #[structopt(subcommand)]
+        cmd: MacosCmd,
+    },
+    #[structopt(
+        name = "verify",
+        about = "Checks a bundle's structure, checksum, manifest and unpack safety for CI"
+    )]
+    Verify {
+        #[structopt(help = "The bundle file to check", parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(
+            long = "checksum",
+            help = "The published digest the bundle has to match, as sha256:<hex>"
+        )]
+        checksum: Option<String>,
+        #[structopt(
+            long = "identity",
+            help = "The identity file (see keygen) to decrypt an encrypted bundle with, defaults to the one in the data directory",
+            parse(from_os_str)
+        )]
+        identity: Option<PathBuf>,
+        #[structopt(
+            long = "provenance",
+            help = "The public key of the builder, to check the signed provenance next to the bundle"
+        )]
+        provenance: Option<String>,
+    },
+    #[structopt(
+        name = "identical",
+        about = "Exits with 0 if two bundles carry the same app, ignoring formatting and __META__, and 1 if not"
+    )]
+    Identical {
+        #[structopt(parse(from_os_str))]
+        a: PathBuf,
+        #[structopt(parse(from_os_str))]
+        b: PathBuf,
+    },
+    #[structopt(
+        name = "redact",
+        about = "Writes a copy of a bundle with every WASM module replaced by its hash and size, for bug reports"
+    )]
+    Redact {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(
+            long = "output",
+            short = "o",
+            help = "Where to write the copy, defaults to <bundle>.redacted.json next to the bundle",
+            parse(from_os_str)
+        )]
+        output: Option<PathBuf>,
+        #[structopt(
+            long = "obfuscate",
+            help = "Also replaces every other string, like config values and file contents, with a placeholder"
+        )]
+        obfuscate: bool,
+    },
+    #[structopt(
+        name = "new",
+        about = "Creates a new app directory with a first zome and, with --git, a repository"
+    )]
+    New {
+        #[structopt(help = "The directory of the app to create", parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(long = "zome", help = "The name of the first zome to generate")]
+        zome: Option<String>,
+        #[structopt(
+            long = "lang",
+            help = "The language of the generated zome: rust or wat",
+            default_value = "rust"
+        )]
+        language: String,
+        #[structopt(
+            long = "git",
+            help = "Initializes a git repository and commits the new app"
+        )]
+        git: bool,
     },
     #[structopt(
         name = "generate",
@@ -89,43 +510,1034 @@ enum Cli {
     )]
     Generate {
         #[structopt(
-            help = "The path to the zome that should be generated (usually in ./zomes/)",
+            help = "The path to the zome that should be generated (usually in ./zomes/), asked for in a terminal if omitted",
             parse(from_os_str)
         )]
+        zome: Option<PathBuf>,
+        #[structopt(
+            help = "The language of the generated zome: rust or wat, by default the project's \
+                    default language or the one of the other zomes next to it"
+        )]
+        language: Option<String>,
+        #[structopt(
+            long = "tiny",
+            help = "Sets the Zome up for a minimal binary size (wee_alloc, size optimized release profile)"
+        )]
+        tiny: bool,
+        #[structopt(
+            long = "crate",
+            help = "Adds a dependency to the Rust Zome as <crate>@<version>, crates that need \
+                    JavaScript glue like wasm-bindgen are refused"
+        )]
+        crates: Vec<String>,
+        #[structopt(
+            long = "from-spec",
+            help = "Generates the Zomes declared in hc-scaffold.yaml and updates their entry types and links"
+        )]
+        from_spec: bool,
+        #[structopt(subcommand)]
+        cmd: Option<GenerateCmd>,
+    },
+    #[structopt(
+        name = "config",
+        about = "Reads and edits the project or global settings"
+    )]
+    Config {
+        #[structopt(subcommand)]
+        cmd: ConfigCmd,
+    },
+    #[structopt(
+        name = "search",
+        about = "Searches the registry for published DNAs"
+    )]
+    Search {
+        #[structopt(help = "The text to look for in DNA names and descriptions")]
+        term: String,
+    },
+    #[structopt(
+        name = "install",
+        about = "Downloads a DNA bundle from the registry"
+    )]
+    Install {
+        #[structopt(help = "The DNA to install as name@version, where the version may be a range")]
+        spec: String,
+        #[structopt(
+            long = "output",
+            short = "o",
+            help = "Where to store the bundle instead of the global store",
+            parse(from_os_str)
+        )]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "tree",
+        about = "Resolves the DNA dependencies into hc.lock and prints the dependency graph"
+    )]
+    Tree {
+        #[structopt(
+            long = "update",
+            help = "Ignores the versions pinned in hc.lock and picks the newest compatible ones"
+        )]
+        update: bool,
+    },
+    #[structopt(
+        name = "prune",
+        about = "Frees disk space by deleting old entries from the global caches and stores"
+    )]
+    Prune {
+        #[structopt(
+            long = "max-size",
+            help = "Deletes the least recently used entries until all caches fit into this size, e.g. 2GB",
+            parse(try_from_str = "util::parse_size")
+        )]
+        max_size: Option<u64>,
+        #[structopt(
+            long = "older-than",
+            help = "Deletes entries that haven't been touched for this long, e.g. 30d",
+            parse(try_from_str = "util::parse_duration")
+        )]
+        older_than: Option<Duration>,
+    },
+    #[structopt(
+        name = "call",
+        about = "Checks the arguments of a zome function call against the Zome's declared interface"
+    )]
+    Call {
+        #[structopt(help = "The name of the Zome in ./zomes/")]
+        zome: String,
+        #[structopt(help = "The name of the zome function")]
+        function: String,
+        #[structopt(help = "The arguments as JSON object")]
+        args: Option<String>,
+        #[structopt(
+            long = "example",
+            help = "Prints a skeleton argument object for the function instead"
+        )]
+        example: bool,
+    },
+    #[structopt(
+        name = "analyze",
+        about = "Analyzes build artifacts to find out what makes them large"
+    )]
+    Analyze {
+        #[structopt(subcommand)]
+        cmd: AnalyzeCmd,
+    },
+    #[structopt(
+        name = "audit",
+        about = "Audits what gets compiled into the app"
+    )]
+    Audit {
+        #[structopt(subcommand)]
+        cmd: AuditCmd,
+    },
+    #[structopt(
+        name = "lock",
+        about = "Pins dependencies by creating or updating lockfiles"
+    )]
+    Lock {
+        #[structopt(subcommand)]
+        cmd: LockCmd,
+    },
+    #[structopt(
+        name = "bench",
+        about = "Measures how fast packaging is"
+    )]
+    Bench {
+        #[structopt(subcommand)]
+        cmd: BenchCmd,
+    },
+    #[structopt(
+        name = "cache",
+        about = "Inspects the cache of Zome builds"
+    )]
+    Cache {
+        #[structopt(subcommand)]
+        cmd: CacheCmd,
+    },
+    #[structopt(
+        name = "stats",
+        about = "Shows statistics about Holochain artifacts"
+    )]
+    Stats {
+        #[structopt(subcommand)]
+        cmd: StatsCmd,
+    },
+    #[structopt(
+        name = "symbolize",
+        about = "Translates the WASM frames of a trap log into Rust function names and source locations"
+    )]
+    Symbolize {
+        #[structopt(
+            help = "The <zome>.debug.wasm file written by package --debug-info",
+            parse(from_os_str)
+        )]
+        debug_file: PathBuf,
+        #[structopt(help = "The trap log, read from stdin if omitted", parse(from_os_str))]
+        log: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "doctor",
+        about = "Checks that everything needed to build Zomes is set up"
+    )]
+    Doctor,
+    #[structopt(
+        name = "docs",
+        about = "Generates Markdown documentation of the zome functions and entry types"
+    )]
+    Docs {
+        #[structopt(
+            long = "output",
+            short = "o",
+            help = "The file to write the documentation to instead of stdout",
+            parse(from_os_str)
+        )]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "test",
+        about = "Builds the app and runs its test script"
+    )]
+    Test {
+        #[structopt(
+            long = "workspace",
+            help = "Tests every app below the current directory, running the test suites in parallel"
+        )]
+        workspace: bool,
+        #[structopt(
+            long = "fail-fast",
+            help = "Cancels the remaining apps as soon as one fails"
+        )]
+        fail_fast: bool,
+    },
+    #[structopt(
+        name = "run",
+        about = "Packages the app and serves it from a development conductor"
+    )]
+    Run {
+        #[structopt(
+            long = "port",
+            short = "p",
+            default_value = "8888",
+            help = "The port of the websocket interface, 0 lets the OS pick a free one"
+        )]
+        port: u16,
+        #[structopt(
+            long = "port-file",
+            help = "Writes the ports of the interfaces to this file as JSON once they are chosen",
+            parse(from_os_str)
+        )]
+        port_file: Option<PathBuf>,
+        #[structopt(
+            long = "persist",
+            help = "Keeps the data of the instance between runs instead of in memory"
+        )]
+        persist: bool,
+        #[structopt(
+            long = "identity",
+            help = "The identity file (see keygen) to decrypt the secret properties with, defaults to the one in the data directory",
+            parse(from_os_str)
+        )]
+        identity: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "plugins",
+        about = "Lists the plugins found on the PATH"
+    )]
+    Plugins,
+    #[structopt(
+        name = "completions",
+        about = "Prints the completion script for a shell"
+    )]
+    Completions {
+        #[structopt(help = "The shell: bash, zsh, fish, powershell, elvish or nushell")]
+        shell: cli::Shell,
+    },
+    #[structopt(
+        name = "env",
+        about = "Shows the environment variables the CLI reacts to"
+    )]
+    Env {
+        #[structopt(
+            long = "paths",
+            help = "Shows where settings, data and caches are kept instead"
+        )]
+        paths: bool,
+    },
+}
+
+impl Command {
+    /// Whether the command works on the app the current directory belongs to, and so has to
+    /// run from the app's root directory
+    fn works_on_project(&self) -> bool {
+        match self {
+            Command::Package { .. }
+            | Command::BuildPlan { .. }
+            | Command::Generate { .. }
+            | Command::Tree { .. }
+            | Command::Call { .. }
+            | Command::Analyze { .. }
+            | Command::Audit { .. }
+            | Command::Lock { .. }
+            | Command::Cache { .. }
+            | Command::Docs { .. }
+            | Command::Lint { .. }
+            | Command::Status { .. }
+            | Command::Hooks { .. }
+            | Command::Macos { .. }
+            | Command::Run { .. }
+            | Command::Doctor => true,
+            Command::Config { cmd } => !cmd.is_global(),
+            Command::Test { workspace, .. } => !workspace,
+            _ => false,
+        }
+    }
+
+    /// Makes the paths given on the command line absolute, so they keep pointing to the same
+    /// place when the command runs from another directory
+    fn absolutize_paths(&mut self, base: &Path) {
+        let paths: Vec<&mut PathBuf> = match self {
+            Command::Package { output, delta, .. } => {
+                output.iter_mut().chain(delta.iter_mut()).collect()
+            }
+            Command::Generate { zome, cmd, .. } => {
+                let mut paths: Vec<_> = zome.iter_mut().collect();
+
+                match cmd {
+                    Some(GenerateCmd::Migration { zome, .. })
+                    | Some(GenerateCmd::Entry { zome, .. })
+                    | Some(GenerateCmd::Electron { path: zome }) => paths.push(zome),
+                    // the bundle path is looked up in git, relative to the app root
+                    Some(GenerateCmd::Changelog { output, .. }) => paths.extend(output.iter_mut()),
+                    _ => (),
+                }
+
+                paths
+            }
+            Command::Analyze {
+                cmd: AnalyzeCmd::Wasm { zome },
+            } => vec![zome],
+            Command::Cache {
+                cmd: CacheCmd::Explain { path },
+            } => vec![path],
+            Command::Config {
+                cmd: ConfigCmd::Export { path, .. },
+            }
+            | Command::Config {
+                cmd: ConfigCmd::Import { path, .. },
+            } => vec![path],
+            Command::Docs { output } | Command::BuildPlan { output, .. } => {
+                output.iter_mut().collect()
+            }
+            Command::Run {
+                port_file,
+                identity,
+                ..
+            } => port_file.iter_mut().chain(identity.iter_mut()).collect(),
+            Command::Macos {
+                cmd: MacosCmd::Sign { paths, .. },
+            }
+            | Command::Macos {
+                cmd: MacosCmd::Notarize { paths, .. },
+            } => paths.iter_mut().collect(),
+            _ => Vec::new(),
+        };
+
+        for path in paths {
+            *path = base.join(&path);
+        }
+    }
+}
+
+#[derive(StructOpt)]
+enum ConfigCmd {
+    #[structopt(name = "get", about = "Prints the value of a setting")]
+    Get {
+        #[structopt(help = "The dotted name of the setting, e.g. scripts.postpackage")]
+        key: String,
+        #[structopt(
+            long = "global",
+            help = "Uses the user-wide settings instead of the project's"
+        )]
+        global: bool,
+    },
+    #[structopt(name = "set", about = "Changes the value of a setting")]
+    Set {
+        #[structopt(help = "The dotted name of the setting, e.g. scripts.postpackage")]
+        key: String,
+        #[structopt(help = "The new value, parsed as TOML if possible and as a string otherwise")]
+        value: String,
+        #[structopt(
+            long = "global",
+            help = "Uses the user-wide settings instead of the project's"
+        )]
+        global: bool,
+    },
+    #[structopt(name = "list", about = "Prints all settings")]
+    List {
+        #[structopt(
+            long = "global",
+            help = "Uses the user-wide settings instead of the project's"
+        )]
+        global: bool,
+    },
+    #[structopt(
+        name = "export",
+        about = "Writes the settings to a preset file to share them with a team or another machine"
+    )]
+    Export {
+        #[structopt(help = "The preset file to write, e.g. preset.toml", parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(
+            long = "global",
+            help = "Uses the user-wide settings instead of the project's"
+        )]
+        global: bool,
+        #[structopt(
+            long = "include-secrets",
+            help = "Exports values that look like credentials as well"
+        )]
+        include_secrets: bool,
+    },
+    #[structopt(
+        name = "import",
+        about = "Merges the settings of a preset file into the current ones"
+    )]
+    Import {
+        #[structopt(help = "The preset file to read", parse(from_os_str))]
+        path: PathBuf,
+        #[structopt(
+            long = "global",
+            help = "Uses the user-wide settings instead of the project's"
+        )]
+        global: bool,
+    },
+}
+
+impl ConfigCmd {
+    fn is_global(&self) -> bool {
+        match self {
+            ConfigCmd::Get { global, .. }
+            | ConfigCmd::Set { global, .. }
+            | ConfigCmd::List { global }
+            | ConfigCmd::Export { global, .. }
+            | ConfigCmd::Import { global, .. } => *global,
+        }
+    }
+}
+
+#[derive(StructOpt)]
+enum GenerateCmd {
+    #[structopt(
+        name = "electron",
+        about = "Scaffolds an Electron shell that starts a conductor with the app and shows its UI"
+    )]
+    Electron {
+        #[structopt(
+            help = "The directory of the shell",
+            default_value = "electron",
+            parse(from_os_str)
+        )]
+        path: PathBuf,
+    },
+    #[structopt(
+        name = "entry",
+        about = "Declares a new entry type in the manifest of a zome"
+    )]
+    Entry {
+        #[structopt(help = "The path to the zome", parse(from_os_str))]
         zome: PathBuf,
+        #[structopt(help = "The name of the entry type")]
+        name: String,
         #[structopt(
-            help = "The language of the generated zome",
-            default_value = "rust"
+            long = "sharing",
+            help = "Who gets the entries: public or private",
+            default_value = "public"
         )]
-        language: String,
+        sharing: String,
+    },
+    #[structopt(name = "test", about = "Adds a test file to the tests of the app")]
+    Test {
+        #[structopt(help = "The name of the test")]
+        name: String,
+    },
+    #[structopt(
+        name = "workflow",
+        about = "Scaffolds a workflow that calls functions of several zomes one after another"
+    )]
+    Workflow {
+        #[structopt(help = "The name of the workflow, e.g. create-profile-and-post")]
+        name: String,
+        #[structopt(
+            help = "The zomes it calls in order, the first runs it; bridge/zome for one of another DNA"
+        )]
+        zomes: Vec<String>,
+    },
+    #[structopt(name = "ui", about = "Adds a page to the UI of the app")]
+    Ui {
+        #[structopt(help = "The name of the page", default_value = "index")]
+        name: String,
+    },
+    #[structopt(
+        name = "migration",
+        about = "Scaffolds the migration of an entry type to its next schema version"
+    )]
+    Migration {
+        #[structopt(help = "The path to the zome the entry type belongs to", parse(from_os_str))]
+        zome: PathBuf,
+        #[structopt(help = "The name of the entry type that changes shape")]
+        entry_type: String,
+    },
+    #[structopt(
+        name = "license",
+        about = "Adds SPDX license headers to the Rust sources of all Zomes"
+    )]
+    License {
+        #[structopt(help = "The SPDX license expression, defaults to the license in hc.toml")]
+        license: Option<String>,
+    },
+    #[structopt(
+        name = "changelog",
+        about = "Writes Markdown release notes from the bundles of two git revisions"
+    )]
+    Changelog {
+        #[structopt(help = "The git revision of the previous release, e.g. its tag")]
+        from: String,
+        #[structopt(help = "The git revision of the release, defaults to the current bundle file")]
+        to: Option<String>,
+        #[structopt(
+            long = "bundle",
+            help = "The path of the bundle file in the repository, defaults to bundle.json",
+            parse(from_os_str)
+        )]
+        bundle: Option<PathBuf>,
+        #[structopt(
+            long = "output",
+            short = "o",
+            help = "The file to write the changelog to instead of printing it",
+            parse(from_os_str)
+        )]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(StructOpt)]
+enum AuditCmd {
+    #[structopt(
+        name = "licenses",
+        about = "Checks the licenses of the crates compiled into the Zomes against the app's license"
+    )]
+    Licenses,
+}
+
+#[derive(StructOpt)]
+enum AnalyzeCmd {
+    #[structopt(
+        name = "wasm",
+        about = "Reports the largest functions, generic bloat and crates of a Zome's WASM"
+    )]
+    Wasm {
+        #[structopt(help = "The path to the zome (usually in ./zomes/)", parse(from_os_str))]
+        zome: PathBuf,
+    },
+}
+
+#[derive(StructOpt)]
+enum LockCmd {
+    #[structopt(
+        name = "zomes",
+        about = "Creates or updates the Cargo.lock of every Rust Zome"
+    )]
+    Zomes,
+}
+
+#[derive(StructOpt)]
+enum BenchCmd {
+    #[structopt(
+        name = "self",
+        about = "Packages synthetic apps of different shapes and compares the times to a baseline"
+    )]
+    SelfBench {
+        #[structopt(
+            long = "runs",
+            help = "How often each app is packaged, the median time counts",
+            default_value = "5"
+        )]
+        runs: usize,
+        #[structopt(
+            long = "baseline",
+            help = "The results of an earlier run to compare to, see --save-baseline",
+            parse(from_os_str)
+        )]
+        baseline: Option<PathBuf>,
+        #[structopt(
+            long = "save-baseline",
+            help = "Writes the results to this file, for later runs to compare to",
+            parse(from_os_str)
+        )]
+        save_baseline: Option<PathBuf>,
+        #[structopt(
+            long = "max-regression",
+            help = "Fails if an app takes more than this many percent longer than in the baseline",
+            default_value = "20"
+        )]
+        max_regression: f64,
+    },
+}
+
+#[derive(StructOpt)]
+enum CacheCmd {
+    #[structopt(
+        name = "status",
+        about = "Explains which Zomes the next package run rebuilds and why"
+    )]
+    Status,
+    #[structopt(
+        name = "explain",
+        about = "Shows which build a file goes into, or what a Zome is built from"
+    )]
+    Explain {
+        #[structopt(parse(from_os_str), help = "A file or directory of the app")]
+        path: PathBuf,
+    },
+}
+
+#[derive(StructOpt)]
+enum HooksCmd {
+    #[structopt(
+        name = "install",
+        about = "Installs pre-commit and pre-push hooks that run the [hooks] settings"
+    )]
+    Install {
+        #[structopt(long = "force", help = "Replaces hooks that weren't installed by hcdev")]
+        force: bool,
+    },
+    #[structopt(name = "run", about = "Runs the commands configured for a hook")]
+    Run {
+        #[structopt(help = "pre-commit or pre-push")]
+        hook: String,
+    },
+}
+
+#[derive(StructOpt)]
+enum MacosCmd {
+    #[structopt(
+        name = "sign",
+        about = "Codesigns the artifacts with the hardened runtime, using the [macos] settings"
+    )]
+    Sign {
+        #[structopt(
+            help = "The artifacts to sign, defaults to the artifacts of the [bundle] settings",
+            parse(from_os_str)
+        )]
+        paths: Vec<PathBuf>,
+        #[structopt(long = "dry-run", help = "Only shows the commands that would run")]
+        dry_run: bool,
+    },
+    #[structopt(
+        name = "notarize",
+        about = "Submits the signed artifacts to Apple's notary service and staples the ticket"
+    )]
+    Notarize {
+        #[structopt(
+            help = "The artifacts to notarize, defaults to the artifacts of the [bundle] settings",
+            parse(from_os_str)
+        )]
+        paths: Vec<PathBuf>,
+        #[structopt(long = "dry-run", help = "Only shows the commands that would run")]
+        dry_run: bool,
+    },
+}
+
+#[derive(StructOpt)]
+enum StatsCmd {
+    #[structopt(
+        name = "bundle",
+        about = "Breaks the size of a bundle down by Zomes, assets and configuration"
+    )]
+    Bundle {
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
     },
 }
 
 fn main() {
     if let Err(err) = run() {
-        eprintln!("{}", err);
+        output::error(err);
 
         ::std::process::exit(1);
     }
 }
 
 fn run() -> HolochainResult<()> {
-    let args = Cli::from_args();
+    let argv = expand_alias(env::args_os().collect())
+        .or_else(|err| Err(HolochainError::Default(err)))?;
+    let matches = Cli::clap()
+        .setting(AppSettings::AllowExternalSubcommands)
+        .get_matches_from(argv);
+    let args = Cli::from_clap(&matches);
+
+    let explicit_project_dir = args.project_dir.is_some();
+
+    output::set_quiet(args.quiet);
+
+    Project::init(args.config, args.project_dir).or_else(|err| Err(HolochainError::Default(err)))?;
+    output::init(args.color).or_else(|err| Err(HolochainError::Default(err)))?;
+    net::init(args.offline).or_else(|err| Err(HolochainError::Default(err)))?;
+    reproducible::init(args.reproducible).or_else(|err| Err(HolochainError::Default(err)))?;
+    interrupt::init().or_else(|err| Err(HolochainError::Default(err)))?;
+
+    let non_interactive = args.non_interactive;
+
+    let mut cmd = match (args.cmd, matches.subcommand()) {
+        (Some(cmd), _) => cmd,
+        (None, (name, Some(plugin_args))) => {
+            let plugin_args = plugin_args
+                .values_of("")
+                .map(|values| values.map(String::from).collect())
+                .unwrap_or_default();
 
-    match args {
-        Cli::Web { port } => cli::web(port).or_else(|err| Err(HolochainError::Default(err)))?,
-        Cli::Agent => cli::agent().or_else(|err| Err(HolochainError::Default(err)))?,
-        Cli::Package { strip_meta, output } => {
-            cli::package(strip_meta, output).or_else(|err| Err(HolochainError::Default(err)))?
+            let code =
+                cli::plugin(name, plugin_args).or_else(|err| Err(HolochainError::Default(err)))?;
+
+            if code != 0 {
+                ::std::process::exit(code);
+            }
+
+            return Ok(());
+        }
+        (None, _) => {
+            let welcomed =
+                cli::welcome(non_interactive).or_else(|err| Err(HolochainError::Default(err)))?;
+
+            if !welcomed {
+                // fail with the usual usage message
+                Cli::clap()
+                    .setting(AppSettings::SubcommandRequired)
+                    .get_matches();
+            }
+
+            return Ok(());
+        }
+    };
+
+    if !explicit_project_dir && cmd.works_on_project() {
+        enter_project_root(&mut cmd).or_else(|err| Err(HolochainError::Default(err)))?;
+    }
+
+    match cmd {
+        Command::Web { port } => cli::web(port).or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Agent => cli::agent().or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Package {
+            strip_meta,
+            output,
+            embed_docs,
+            deny,
+            encrypt,
+            recipient,
+            delta,
+            locked,
+            debug_info,
+            annotations,
+            profile,
+            force,
+            with_tests,
+            provenance,
+            signing_key,
+            check,
+            format,
+            watch,
+        } => {
+            let options = cli::PackageOptions {
+                strip_meta,
+                output,
+                embed_docs,
+                deny,
+                recipient: if encrypt { recipient } else { None },
+                delta,
+                locked,
+                debug_info,
+                annotations,
+                profile,
+                force,
+                with_tests,
+                provenance,
+                signing_key,
+                check,
+                format,
+            };
+
+            if watch {
+                cli::package_watch(options)
+            } else {
+                cli::package(options)
+            }.or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::BuildPlan { output, locked } => {
+            cli::build_plan(output, locked).or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::ApplyDelta {
+            base,
+            delta,
+            output,
+        } => cli::apply_delta(&base, &delta, output)
+            .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Compat {
+            path,
+            conductor,
+            json,
+        } => cli::compat(&path, conductor, json).or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::ExplainBundle { path } => {
+            cli::explain_bundle(&path).or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::Unpack {
+            path,
+            to,
+            identity,
+            flatten_zomes,
+        } => cli::unpack(&path, &to, identity, flatten_zomes)
+            .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Export {
+            path,
+            format,
+            output,
+        } => cli::export(&path, format, output).or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Import {
+            path,
+            format,
+            output,
+        } => cli::import(&path, format, output).or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Keygen { path, signing } => {
+            cli::keygen(path, signing).or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::Init {
+            path,
+            from,
+            example,
+        } => match example {
+            Some(example) => cli::init_example(&path, &example),
+            None => cli::new(&path, &from),
         }
-        Cli::Unpack { path, to } => {
-            cli::unpack(&path, &to).or_else(|err| Err(HolochainError::Default(err)))?
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Examples => cli::examples().or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Status { files, ignored } => {
+            cli::status(files, ignored).or_else(|err| Err(HolochainError::Default(err)))?
         }
-        Cli::Init { path, from } => {
-            cli::new(&path, &from).or_else(|err| Err(HolochainError::Default(err)))?
+        Command::Lint { fix, wasm_imports } => {
+            cli::lint(fix, wasm_imports).or_else(|err| Err(HolochainError::Default(err)))?
         }
-        Cli::Generate { zome, language } => {
-            cli::generate(&zome, &language).or_else(|err| Err(HolochainError::Default(err)))?
+        Command::Verify {
+            path,
+            checksum,
+            identity,
+            provenance,
+        } => {
+            let code = cli::verify(&path, checksum, identity, provenance)
+                .or_else(|err| Err(HolochainError::Default(err)))?;
+
+            if code != 0 {
+                ::std::process::exit(code);
+            }
+        }
+        Command::Identical { a, b } => match cli::identical(&a, &b) {
+            Ok(true) => (),
+            Ok(false) => ::std::process::exit(1),
+            // like diff and cmp, trouble is told apart from a difference
+            Err(err) => {
+                output::error(err);
+                ::std::process::exit(2);
+            }
+        },
+        Command::Redact {
+            path,
+            output,
+            obfuscate,
+        } => cli::redact(&path, output, obfuscate)
+            .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::New {
+            path,
+            zome,
+            language,
+            git,
+        } => cli::new_app(&path, zome, &language, git)
+            .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Generate {
+            zome,
+            language,
+            tiny,
+            crates,
+            from_spec,
+            cmd,
+        } => match cmd {
+            Some(GenerateCmd::Migration { zome, entry_type }) => {
+                cli::generate_migration(&zome, &entry_type)
+            }
+            Some(GenerateCmd::License { license }) => cli::generate_license(license),
+            Some(GenerateCmd::Electron { path }) => cli::generate_electron(&path),
+            Some(GenerateCmd::Entry {
+                zome,
+                name,
+                sharing,
+            }) => cli::generate_entry_type(&zome, &name, &sharing),
+            Some(GenerateCmd::Test { name }) => cli::generate_test(&name),
+            Some(GenerateCmd::Ui { name }) => cli::generate_ui_page(&name),
+            Some(GenerateCmd::Workflow { name, zomes }) => cli::generate_workflow(&name, &zomes),
+            Some(GenerateCmd::Changelog {
+                from,
+                to,
+                bundle,
+                output,
+            }) => cli::generate_changelog(&from, to, bundle, output),
+            None if from_spec => cli::generate_from_spec(),
+            None => match zome {
+                Some(zome) => cli::zome_language(&zome, language)
+                    .and_then(|language| cli::generate(&zome, &language, tiny, &crates)),
+                None => cli::generate_interactive(non_interactive, tiny, &crates),
+            },
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Config { cmd } => match cmd {
+            ConfigCmd::Get { key, global } => cli::config_get(&key, global),
+            ConfigCmd::Set { key, value, global } => cli::config_set(&key, &value, global),
+            ConfigCmd::List { global } => cli::config_list(global),
+            ConfigCmd::Export {
+                path,
+                global,
+                include_secrets,
+            } => cli::config_export(&path, global, include_secrets),
+            ConfigCmd::Import { path, global } => cli::config_import(&path, global),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Search { term } => {
+            cli::search(&term).or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::Install { spec, output } => {
+            cli::install(&spec, output).or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::Tree { update } => {
+            cli::tree(update).or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::Prune {
+            max_size,
+            older_than,
+        } => cli::prune(max_size, older_than).or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Call {
+            zome,
+            function,
+            args,
+            example,
+        } => cli::call(&zome, &function, args, example)
+            .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Analyze { cmd } => match cmd {
+            AnalyzeCmd::Wasm { zome } => cli::analyze_wasm(&zome),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Audit { cmd } => match cmd {
+            AuditCmd::Licenses => cli::audit_licenses(),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Lock { cmd } => match cmd {
+            LockCmd::Zomes => cli::lock_zomes(),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Bench { cmd } => match cmd {
+            BenchCmd::SelfBench {
+                runs,
+                baseline,
+                save_baseline,
+                max_regression,
+            } => cli::bench_self(runs, baseline, save_baseline, max_regression),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Cache { cmd } => match cmd {
+            CacheCmd::Status => cli::cache_status(),
+            CacheCmd::Explain { path } => cli::cache_explain(&path),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Hooks { cmd } => match cmd {
+            HooksCmd::Install { force } => cli::hooks_install(force),
+            HooksCmd::Run { hook } => cli::hooks_run(&hook),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Macos { cmd } => match cmd {
+            MacosCmd::Sign { paths, dry_run } => cli::macos_sign(paths, dry_run),
+            MacosCmd::Notarize { paths, dry_run } => cli::macos_notarize(paths, dry_run),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Stats { cmd } => match cmd {
+            StatsCmd::Bundle { path } => cli::stats_bundle(&path),
+        }
+        .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Symbolize { debug_file, log } => {
+            cli::symbolize(&debug_file, log).or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::Doctor => cli::doctor().or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Docs { output } => {
+            cli::docs(output).or_else(|err| Err(HolochainError::Default(err)))?
+        }
+        Command::Test {
+            workspace,
+            fail_fast,
+        } => cli::test(workspace, fail_fast).or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Run {
+            port,
+            port_file,
+            persist,
+            identity,
+        } => cli::run(port, port_file, persist, identity, non_interactive)
+            .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Plugins => cli::plugins().or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Completions { shell } => cli::completions(Cli::clap(), shell)
+            .or_else(|err| Err(HolochainError::Default(err)))?,
+        Command::Env { paths } => cli::env(paths).or_else(|err| Err(HolochainError::Default(err)))?,
+    }
+
+    Ok(())
+}
+
+/// Replaces an alias from the settings with the command it stands for. Only what would be run
+/// as a plugin can be an alias, so built-in commands can't be shadowed.
+fn expand_alias(argv: Vec<OsString>) -> DefaultResult<Vec<OsString>> {
+    // errors and --help are left to the actual parse
+    let matches = match Cli::clap()
+        .setting(AppSettings::AllowExternalSubcommands)
+        .get_matches_from_safe(&argv)
+    {
+        Ok(matches) => matches,
+        Err(_) => return Ok(argv),
+    };
+    let args = Cli::from_clap(&matches);
+
+    let (name, arg_count) = match (&args.cmd, matches.subcommand()) {
+        (None, (name, Some(plugin_args))) => (
+            name.to_string(),
+            plugin_args.values_of_os("").map_or(0, |values| values.count()),
+        ),
+        _ => return Ok(argv),
+    };
+
+    let expansion = alias::lookup(
+        &name,
+        args.config.as_ref().map(PathBuf::as_path),
+        args.project_dir.as_ref().map(PathBuf::as_path),
+    )?;
+
+    Ok(match expansion {
+        // everything after an unknown command is passed on to it, the alias is right before
+        Some(expansion) => alias::splice(&argv, argv.len() - arg_count - 1, expansion),
+        None => argv,
+    })
+}
+
+/// Changes into the root of the app the current directory belongs to, like cargo and git
+/// do, so running a command in `zomes/foo/code/src` works on the whole app
+fn enter_project_root(cmd: &mut Command) -> DefaultResult<()> {
+    let current_dir = env::current_dir()?;
+
+    if let Some(root) = cli::find_project_root(&current_dir) {
+        if root != current_dir {
+            cmd.absolutize_paths(&current_dir);
+            env::set_current_dir(&root)?;
         }
     }
 
@@ -0,0 +1,399 @@
+//! Just enough MessagePack to read and write the bundles of newer Holochain versions.
+//!
+//! Values are kept in a small tree of their own instead of `serde_json::Value`, because
+//! MessagePack tells binary data apart from strings and the WASM of a bundle has to stay binary.
+
+use base64;
+use error::DefaultResult;
+use serde_json::{self, Map};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<Value>),
+    /// Entries in the order they were read or are to be written
+    Map(Vec<(Value, Value)>),
+}
+
+impl Value {
+    /// Looks up the value of a string key, if this is a map
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key.as_str() == Some(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        *self == Value::Nil
+    }
+
+    /// Converts to JSON, where binary data becomes a base64 string
+    pub fn to_json(&self) -> DefaultResult<serde_json::Value> {
+        Ok(match self {
+            Value::Nil => serde_json::Value::Null,
+            Value::Bool(value) => (*value).into(),
+            Value::Int(value) => (*value).into(),
+            Value::Float(value) => (*value).into(),
+            Value::String(value) => value.clone().into(),
+            Value::Binary(value) => base64::encode(value).into(),
+            Value::Array(values) => serde_json::Value::Array(
+                values
+                    .iter()
+                    .map(|value| value.to_json())
+                    .collect::<DefaultResult<_>>()?,
+            ),
+            Value::Map(entries) => {
+                let mut map = Map::new();
+
+                for (key, value) in entries {
+                    let key = key
+                        .as_str()
+                        .ok_or_else(|| format_err!("only string keys can be converted to JSON"))?;
+
+                    ensure!(
+                        map.insert(key.to_string(), value.to_json()?).is_none(),
+                        "duplicate key {}",
+                        key
+                    );
+                }
+
+                serde_json::Value::Object(map)
+            }
+        })
+    }
+
+    /// Encodes the value, always picking the smallest representation
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> DefaultResult<Value> {
+        let mut reader = Reader { bytes };
+        let value = reader.value()?;
+
+        ensure!(
+            reader.bytes.is_empty(),
+            "unexpected data after the MessagePack value"
+        );
+
+        Ok(value)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Value::Nil => out.push(0xc0),
+            Value::Bool(false) => out.push(0xc2),
+            Value::Bool(true) => out.push(0xc3),
+            Value::Int(value) => write_int(*value, out),
+            Value::Float(value) => {
+                out.push(0xcb);
+                out.extend_from_slice(&value.to_bits().to_be_bytes());
+            }
+            Value::String(value) => {
+                write_length(value.len(), Some(0xa0), 32, [0xd9, 0xda, 0xdb], out);
+                out.extend_from_slice(value.as_bytes());
+            }
+            Value::Binary(value) => {
+                write_length(value.len(), None, 0, [0xc4, 0xc5, 0xc6], out);
+                out.extend_from_slice(value);
+            }
+            Value::Array(values) => {
+                write_length(values.len(), Some(0x90), 16, [0, 0xdc, 0xdd], out);
+
+                for value in values {
+                    value.write(out);
+                }
+            }
+            Value::Map(entries) => {
+                write_length(entries.len(), Some(0x80), 16, [0, 0xde, 0xdf], out);
+
+                for (key, value) in entries {
+                    key.write(out);
+                    value.write(out);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a serde_json::Value> for Value {
+    fn from(json: &serde_json::Value) -> Value {
+        match json {
+            serde_json::Value::Null => Value::Nil,
+            serde_json::Value::Bool(value) => Value::Bool(*value),
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(value) => Value::Int(value),
+                None => Value::Float(number.as_f64().unwrap_or_default()),
+            },
+            serde_json::Value::String(value) => Value::String(value.clone()),
+            serde_json::Value::Array(values) => {
+                Value::Array(values.iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(map) => Value::Map(
+                map.iter()
+                    .map(|(key, value)| (Value::String(key.clone()), Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+}
+
+fn write_int(value: i64, out: &mut Vec<u8>) {
+    if value >= 0 {
+        if value <= 0x7f {
+            out.push(value as u8);
+        } else if value <= i64::from(u8::MAX) {
+            out.push(0xcc);
+            out.push(value as u8);
+        } else if value <= i64::from(u16::MAX) {
+            out.push(0xcd);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        } else if value <= i64::from(u32::MAX) {
+            out.push(0xce);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        } else {
+            out.push(0xcf);
+            out.extend_from_slice(&(value as u64).to_be_bytes());
+        }
+    } else if value >= -32 {
+        out.push(value as u8);
+    } else if value >= i64::from(i8::MIN) {
+        out.push(0xd0);
+        out.push(value as u8);
+    } else if value >= i64::from(i16::MIN) {
+        out.push(0xd1);
+        out.extend_from_slice(&(value as i16).to_be_bytes());
+    } else if value >= i64::from(i32::MIN) {
+        out.push(0xd2);
+        out.extend_from_slice(&(value as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Writes the length of a string, binary, array or map, using the fix variant with the given
+/// marker if the length fits below `fix_limit` and otherwise the 8, 16 or 32 bit variant
+fn write_length(
+    length: usize,
+    fix_marker: Option<u8>,
+    fix_limit: usize,
+    markers: [u8; 3],
+    out: &mut Vec<u8>,
+) {
+    match fix_marker {
+        Some(marker) if length < fix_limit => out.push(marker | length as u8),
+        _ if markers[0] != 0 && length <= usize::from(u8::MAX) => {
+            out.push(markers[0]);
+            out.push(length as u8);
+        }
+        _ if length <= usize::from(u16::MAX) => {
+            out.push(markers[1]);
+            out.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(markers[2]);
+            out.extend_from_slice(&(length as u32).to_be_bytes());
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn value(&mut self) -> DefaultResult<Value> {
+        let marker = self.byte()?;
+
+        Ok(match marker {
+            0x00..=0x7f => Value::Int(i64::from(marker)),
+            0x80..=0x8f => self.map(usize::from(marker & 0x0f))?,
+            0x90..=0x9f => self.array(usize::from(marker & 0x0f))?,
+            0xa0..=0xbf => self.string(usize::from(marker & 0x1f))?,
+            0xc0 => Value::Nil,
+            0xc2 => Value::Bool(false),
+            0xc3 => Value::Bool(true),
+            0xc4 => {
+                let length = self.uint(1)? as usize;
+                Value::Binary(self.bytes(length)?.to_vec())
+            }
+            0xc5 => {
+                let length = self.uint(2)? as usize;
+                Value::Binary(self.bytes(length)?.to_vec())
+            }
+            0xc6 => {
+                let length = self.uint(4)? as usize;
+                Value::Binary(self.bytes(length)?.to_vec())
+            }
+            0xca => {
+                let bits = self.uint(4)? as u32;
+                Value::Float(f64::from(f32::from_bits(bits)))
+            }
+            0xcb => Value::Float(f64::from_bits(self.uint(8)?)),
+            0xcc => Value::Int(self.uint(1)? as i64),
+            0xcd => Value::Int(self.uint(2)? as i64),
+            0xce => Value::Int(self.uint(4)? as i64),
+            0xcf => {
+                let value = self.uint(8)?;
+
+                ensure!(value <= i64::MAX as u64, "MessagePack integer too large");
+
+                Value::Int(value as i64)
+            }
+            0xd0 => Value::Int(i64::from(self.uint(1)? as u8 as i8)),
+            0xd1 => Value::Int(i64::from(self.uint(2)? as u16 as i16)),
+            0xd2 => Value::Int(i64::from(self.uint(4)? as u32 as i32)),
+            0xd3 => Value::Int(self.uint(8)? as i64),
+            0xd9 => {
+                let length = self.uint(1)? as usize;
+                self.string(length)?
+            }
+            0xda => {
+                let length = self.uint(2)? as usize;
+                self.string(length)?
+            }
+            0xdb => {
+                let length = self.uint(4)? as usize;
+                self.string(length)?
+            }
+            0xdc => {
+                let length = self.uint(2)? as usize;
+                self.array(length)?
+            }
+            0xdd => {
+                let length = self.uint(4)? as usize;
+                self.array(length)?
+            }
+            0xde => {
+                let length = self.uint(2)? as usize;
+                self.map(length)?
+            }
+            0xdf => {
+                let length = self.uint(4)? as usize;
+                self.map(length)?
+            }
+            0xe0..=0xff => Value::Int(i64::from(marker as i8)),
+            _ => bail!("unsupported MessagePack type 0x{:02x}", marker),
+        })
+    }
+
+    fn array(&mut self, length: usize) -> DefaultResult<Value> {
+        let mut values = Vec::new();
+
+        for _ in 0..length {
+            values.push(self.value()?);
+        }
+
+        Ok(Value::Array(values))
+    }
+
+    fn map(&mut self, length: usize) -> DefaultResult<Value> {
+        let mut entries = Vec::new();
+
+        for _ in 0..length {
+            let key = self.value()?;
+            entries.push((key, self.value()?));
+        }
+
+        Ok(Value::Map(entries))
+    }
+
+    fn string(&mut self, length: usize) -> DefaultResult<Value> {
+        let bytes = self.bytes(length)?;
+
+        Ok(Value::String(String::from_utf8(bytes.to_vec()).map_err(
+            |_| format_err!("MessagePack string isn't valid UTF-8"),
+        )?))
+    }
+
+    /// Reads a big endian unsigned integer of the given number of bytes
+    fn uint(&mut self, size: usize) -> DefaultResult<u64> {
+        Ok(self
+            .bytes(size)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | u64::from(*byte)))
+    }
+
+    fn byte(&mut self) -> DefaultResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, count: usize) -> DefaultResult<&'a [u8]> {
+        ensure!(
+            count <= self.bytes.len(),
+            "unexpected end of MessagePack data"
+        );
+
+        let (head, tail) = self.bytes.split_at(count);
+        self.bytes = tail;
+
+        Ok(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_all_types() {
+        let value = Value::Map(vec![
+            ("nil".into(), Value::Nil),
+            (
+                "flags".into(),
+                Value::Array(vec![Value::Bool(true), Value::Bool(false)]),
+            ),
+            (
+                "ints".into(),
+                Value::Array(
+                    [0, 127, 128, 65_536, -1, -33, -40_000, i64::MIN]
+                        .iter()
+                        .map(|int| Value::Int(*int))
+                        .collect(),
+                ),
+            ),
+            ("float".into(), Value::Float(1.5)),
+            ("long".into(), Value::String("x".repeat(300))),
+            ("wasm".into(), Value::Binary(vec![0, 97, 115, 109])),
+        ]);
+
+        let encoded = value.encode();
+
+        assert_eq!(Value::decode(&encoded).unwrap(), value);
+        assert_eq!(Value::Int(-1).encode(), vec![0xff]);
+        assert_eq!(Value::from("a").encode(), vec![0xa1, b'a']);
+        assert!(Value::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! All network access of the CLI goes through here.
+
+use config_files::Global;
+use error::DefaultResult;
+use std::{
+    env, fs,
+    io::Read,
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use ureq;
+
+pub const OFFLINE_ENV_VAR: &str = "HC_OFFLINE";
+
+const FILE_SCHEME: &str = "file://";
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Enables offline mode if requested by the flag, `HC_OFFLINE` or the `offline` global setting
+pub fn init(offline_flag: bool) -> DefaultResult<()> {
+    let from_env = env::var(OFFLINE_ENV_VAR)
+        .map(|value| value != "" && value != "0" && value != "false")
+        .unwrap_or(false);
+
+    let offline = offline_flag || from_env || Global::load_or_default().offline.unwrap_or(false);
+
+    OFFLINE.store(offline, Ordering::SeqCst);
+
+    Ok(())
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::SeqCst)
+}
+
+/// Fetches the resource at the given location, which is either an http(s) URL, a `file://` URL
+/// or a plain file path
+pub fn fetch(location: &str) -> DefaultResult<Vec<u8>> {
+    fetch_accepting(location, "*/*")
+}
+
+/// Like `fetch`, but tells http(s) servers which media type is expected
+pub fn fetch_accepting(location: &str, media_type: &str) -> DefaultResult<Vec<u8>> {
+    if is_remote(location) {
+        ensure!(
+            !is_offline(),
+            "refusing to fetch {} in offline mode",
+            location
+        );
+
+        let response = ureq::get(location)
+            .set("Accept", media_type)
+            .call()
+            .map_err(|err| format_err!("unable to fetch {}: {}", location, err))?;
+
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+
+        Ok(body)
+    } else {
+        let path = location.trim_start_matches(FILE_SCHEME);
+
+        fs::read(path).map_err(|err| format_err!("unable to read {}: {}", path, err))
+    }
+}
+
+/// Fetches an http(s) URL with an optional bearer token, nothing if the server doesn't have it
+pub fn fetch_if_exists(url: &str, token: Option<&str>) -> DefaultResult<Option<Vec<u8>>> {
+    ensure!(!is_offline(), "refusing to fetch {} in offline mode", url);
+
+    let mut request = ureq::get(url);
+
+    if let Some(token) = token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    match request.call() {
+        Ok(response) => {
+            let mut body = Vec::new();
+            response.into_reader().read_to_end(&mut body)?;
+
+            Ok(Some(body))
+        }
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(err) => bail!("unable to fetch {}: {}", url, err),
+    }
+}
+
+/// Uploads the body to an http(s) URL with a PUT request and an optional bearer token
+pub fn put(url: &str, body: &[u8], token: Option<&str>) -> DefaultResult<()> {
+    ensure!(!is_offline(), "refusing to upload to {} in offline mode", url);
+
+    let mut request = ureq::put(url).set("Content-Type", "application/octet-stream");
+
+    if let Some(token) = token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+
+    request
+        .send_bytes(body)
+        .map_err(|err| format_err!("unable to upload to {}: {}", url, err))?;
+
+    Ok(())
+}
+
+pub fn is_remote(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/// Resolves a possibly relative location against the location of the document referencing it
+pub fn join(base: &str, location: &str) -> String {
+    if is_remote(location) || location.starts_with(FILE_SCHEME) || location.starts_with('/') {
+        return location.to_string();
+    }
+
+    if is_remote(base) {
+        match base.rfind('/') {
+            Some(end) => format!("{}/{}", &base[..end], location),
+            None => location.to_string(),
+        }
+    } else {
+        let base = PathBuf::from(base.trim_start_matches(FILE_SCHEME));
+
+        base.parent()
+            .map(|parent| parent.join(location))
+            .unwrap_or_else(|| PathBuf::from(location))
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::{fs, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn offline_mode_refuses_remote_locations() {
+        let home = Builder::new().prefix("offline").tempdir().unwrap();
+
+        fs::create_dir(home.path().join(".hc")).unwrap();
+        fs::write(
+            home.path().join(".hc").join("config.toml"),
+            "registry_url = \"http://127.0.0.1:1/index.json\"\n",
+        ).unwrap();
+
+        let output = Command::main_binary()
+            .unwrap()
+            .env("HOME", home.path())
+            .args(&["--offline", "search", "chat"])
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("offline mode"));
+    }
+}
@@ -0,0 +1,107 @@
+//! The single place human-readable output goes through, so that every command honors the
+//! same color policy and `--quiet`.
+
+use atty;
+use colored::{self, *};
+use config_files::Global;
+use error::DefaultResult;
+use i18n;
+use std::{
+    env,
+    fmt::Display,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+pub const NO_COLOR_ENV_VAR: &str = "NO_COLOR";
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColorChoice, String> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!(
+                "invalid color choice: {} (expected auto, always or never)",
+                s
+            )),
+        }
+    }
+}
+
+/// Decides on the color policy for this run. An explicit `--color` wins, then `NO_COLOR`,
+/// then the `color` global setting. Otherwise colors are used when stdout is a terminal.
+pub fn init(choice: Option<ColorChoice>) -> DefaultResult<()> {
+    let choice = match choice {
+        Some(choice) => choice,
+        None if env::var_os(NO_COLOR_ENV_VAR).map_or(false, |value| !value.is_empty()) => {
+            ColorChoice::Never
+        }
+        None => match Global::load_or_default().color {
+            Some(color) => color
+                .parse()
+                .map_err(|err: String| format_err!("{}", err))?,
+            None => ColorChoice::Auto,
+        },
+    };
+
+    let colorize = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => atty::is(atty::Stream::Stdout),
+    };
+
+    colored::control::set_override(colorize);
+
+    Ok(())
+}
+
+/// Suppresses all output but errors, for scripts and Makefiles that only look at the exit
+/// status
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Prints a line of regular output
+pub fn print<D: Display>(message: D) {
+    if !is_quiet() {
+        println!("{}", message);
+    }
+}
+
+/// Prints a line of diagnostic output that shouldn't end up in piped stdout
+pub fn print_err<D: Display>(message: D) {
+    if !is_quiet() {
+        eprintln!("{}", message);
+    }
+}
+
+/// Prints an error, which even `--quiet` lets through
+pub fn error<D: Display>(message: D) {
+    eprintln!("{}", message);
+}
+
+/// Returns the localized status word for the given key, highlighted like cargo does
+pub fn status(key: &str) -> String {
+    i18n::tr(key, &[]).green().bold().to_string()
+}
+
+/// Echoes a command that is about to be run
+pub fn command<D: Display>(bin: &str, args: D) {
+    print(format!("> {} {}", bin.green(), args.to_string().cyan()));
+}
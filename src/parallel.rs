@@ -0,0 +1,99 @@
+//! Spreads independent work over all cores, like reading and encoding the files of an app.
+//!
+//! Packaging is mostly waiting for the disk and base64 encoding, one file after the other. The
+//! items stream through a tokio runtime instead, each mapped on its blocking pool since reading
+//! files blocks. At most one item per core is in flight: the next one is only taken from the
+//! stream once an earlier one is done, so a large, asset heavy app keeps the disk busy without
+//! holding more of its files in memory than there are cores.
+
+use futures::{future, stream, sync::oneshot, Future, Stream};
+use std::{
+    sync::{Arc, OnceLock},
+    thread,
+};
+use tokio::runtime::{Builder, Runtime};
+use tokio_threadpool;
+
+/// The runtime all pipelines run on, started with the first one
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+/// How many workers run at once, one per core
+pub fn jobs() -> usize {
+    thread::available_parallelism().map_or(1, |jobs| jobs.get())
+}
+
+/// Maps every item on the runtime, returning the results in the order of the items
+pub fn map<T, R, F>(items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    map_in_flight(jobs(), items, f)
+}
+
+fn map_in_flight<T, R, F>(in_flight: usize, items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    if in_flight <= 1 || items.len() <= 1 {
+        return items.into_iter().map(f).collect();
+    }
+
+    let f = Arc::new(f);
+    let pipeline = stream::iter_ok::<_, ()>(items)
+        .map(move |item| {
+            let f = Arc::clone(&f);
+            let mut item = Some(item);
+
+            // polled again until the blocking pool has room, the item is only mapped once it has
+            future::poll_fn(move || {
+                tokio_threadpool::blocking(|| f(item.take().expect("every item is mapped once")))
+                    .map_err(|_| ())
+            })
+        })
+        // keeps the order of the items, with up to one of them per core in flight
+        .buffered(in_flight)
+        .collect();
+
+    oneshot::spawn(pipeline, &runtime().executor())
+        .wait()
+        .expect("every item was mapped")
+}
+
+fn runtime() -> &'static Runtime {
+    RUNTIME.get_or_init(|| {
+        Builder::new()
+            .core_threads(jobs())
+            .blocking_threads(jobs())
+            .name_prefix("hcdev-worker-")
+            .build()
+            .expect("unable to start the workers")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_order_of_the_items() {
+        let items: Vec<u64> = (0..1000).collect();
+
+        // more in flight than there are cores on small machines too
+        let squares = map_in_flight(4, items.clone(), |item| {
+            if item % 7 == 0 {
+                thread::yield_now();
+            }
+
+            item * item
+        });
+
+        assert_eq!(
+            squares,
+            items.iter().map(|item| item * item).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! Provenance of bundles as an [in-toto](https://in-toto.io) statement with an
+//! [SLSA](https://slsa.dev/provenance/v1) provenance predicate: the bundle's digest, the commit
+//! it was built from, who built it, the versions of the tools and the digest of every input.
+//!
+//! The statement is signed with the builder's ed25519 key and written next to the bundle as a
+//! DSSE envelope, the format other in-toto and SLSA tools read as well.
+
+use base64;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use error::DefaultResult;
+use rand::{rngs::OsRng, RngCore};
+use reproducible;
+use serde_json::{self, Value};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use util;
+
+/// Appended to the name of the bundle to get the name of its provenance file
+pub const PROVENANCE_FILE_SUFFIX: &str = ".intoto.jsonl";
+pub const SIGNING_KEY_FILE_NAME: &str = "signing.key";
+/// Identifies the builder instead of the CLI itself, e.g. the URL of the CI workflow
+pub const BUILDER_ID_ENV_VAR: &str = "HC_BUILDER_ID";
+
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+const PREDICATE_TYPE: &str = "https://slsa.dev/provenance/v1";
+const BUILD_TYPE: &str = "https://github.com/holochain/holochain-cmd/package/v1";
+const DEFAULT_BUILDER_ID: &str = "https://github.com/holochain/holochain-cmd";
+const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+const KEY_LENGTH: usize = 32;
+const SIGNATURE_LENGTH: usize = 64;
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    pub payload_type: String,
+    /// The base64 encoded statement
+    pub payload: String,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct EnvelopeSignature {
+    /// The SHA-256 of the public key
+    pub keyid: String,
+    pub sig: String,
+}
+
+/// Creates a new signing key, returning the base64 encoded secret and public key
+pub fn generate_signing_key() -> (String, String) {
+    let mut secret = [0u8; KEY_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+
+    let key = SigningKey::from_bytes(&secret);
+
+    (
+        base64::encode(&key.to_bytes()),
+        base64::encode(key.verifying_key().as_bytes()),
+    )
+}
+
+/// Reads the secret key from a file created by `keygen --signing`
+pub fn read_signing_key<T: AsRef<Path>>(path: T) -> DefaultResult<SigningKey> {
+    let contents = fs::read_to_string(&path).map_err(|err| {
+        format_err!(
+            "{:?}: {}, create a signing key with keygen --signing",
+            path.as_ref(),
+            err
+        )
+    })?;
+
+    Ok(SigningKey::from_bytes(&decode_key(
+        contents.trim(),
+        "signing key",
+    )?))
+}
+
+/// Where the provenance of the given bundle is kept
+pub fn provenance_path(bundle: &Path) -> PathBuf {
+    let mut path = bundle.as_os_str().to_owned();
+    path.push(PROVENANCE_FILE_SUFFIX);
+
+    PathBuf::from(path)
+}
+
+/// Describes how the bundle was built from the inputs, which are relative to the app's root
+pub fn statement(
+    bundle: &Path,
+    inputs: &[PathBuf],
+    toolchain: &BTreeMap<String, String>,
+) -> DefaultResult<Value> {
+    let mut inputs = inputs.to_vec();
+    inputs.sort();
+
+    let mut dependencies = Vec::new();
+
+    for input in &inputs {
+        let name = input.strip_prefix(".").unwrap_or(input);
+
+        dependencies.push(json!({
+            "name": name.to_string_lossy().replace('\\', "/"),
+            "digest": { "sha256": util::sha256_hex(&fs::read(input)?) }
+        }));
+    }
+
+    let mut external_parameters = json!({});
+
+    if let Some(source) = source() {
+        external_parameters["source"] = source;
+    }
+
+    Ok(json!({
+        "_type": STATEMENT_TYPE,
+        "subject": [{
+            "name": util::file_name_string(&bundle.to_path_buf())?,
+            "digest": { "sha256": util::sha256_hex(&fs::read(bundle)?) }
+        }],
+        "predicateType": PREDICATE_TYPE,
+        "predicate": {
+            "buildDefinition": {
+                "buildType": BUILD_TYPE,
+                "externalParameters": external_parameters,
+                "internalParameters": { "toolchain": toolchain },
+                "resolvedDependencies": dependencies
+            },
+            "runDetails": {
+                "builder": {
+                    "id": env::var(BUILDER_ID_ENV_VAR)
+                        .unwrap_or_else(|_| DEFAULT_BUILDER_ID.to_string()),
+                    "version": { "hcdev": env!("CARGO_PKG_VERSION") }
+                },
+                "metadata": { "finishedOn": rfc3339(reproducible::now()) }
+            }
+        }
+    }))
+}
+
+/// Signs the statement and wraps it in an envelope
+pub fn sign(statement: &Value, key: &SigningKey) -> DefaultResult<Envelope> {
+    let payload = serde_json::to_vec(statement)?;
+    let signature = key.sign(&pae(PAYLOAD_TYPE, &payload));
+
+    Ok(Envelope {
+        payload_type: PAYLOAD_TYPE.to_string(),
+        payload: base64::encode(&payload),
+        signatures: vec![EnvelopeSignature {
+            keyid: util::sha256_hex(key.verifying_key().as_bytes()),
+            sig: base64::encode(&signature.to_bytes()[..]),
+        }],
+    })
+}
+
+/// Writes the signed provenance of the bundle next to it and returns where
+pub fn write(
+    bundle: &Path,
+    inputs: &[PathBuf],
+    toolchain: &BTreeMap<String, String>,
+    key: &SigningKey,
+) -> DefaultResult<PathBuf> {
+    let envelope = sign(&statement(bundle, inputs, toolchain)?, key)?;
+    let path = provenance_path(bundle);
+
+    fs::write(&path, serde_json::to_string(&envelope)? + "\n")?;
+
+    Ok(path)
+}
+
+/// Checks that the envelope is signed by the owner of the base64 encoded public key and that
+/// its statement is about the given bundle
+pub fn verify(bundle: &[u8], envelope: &[u8], public_key: &str) -> DefaultResult<()> {
+    let envelope: Envelope = serde_json::from_slice(envelope)
+        .map_err(|err| format_err!("the provenance is no DSSE envelope: {}", err))?;
+
+    ensure!(
+        envelope.payload_type == PAYLOAD_TYPE,
+        "the provenance has the payload type {}, expected {}",
+        envelope.payload_type,
+        PAYLOAD_TYPE
+    );
+
+    let key = VerifyingKey::from_bytes(&decode_key(public_key, "public key")?)
+        .map_err(|err| format_err!("invalid public key: {}", err))?;
+    let payload = base64::decode(&envelope.payload)?;
+    let signed = pae(&envelope.payload_type, &payload);
+
+    let signed_by_key = envelope.signatures.iter().any(|signature| {
+        base64::decode(&signature.sig)
+            .ok()
+            .filter(|sig| sig.len() == SIGNATURE_LENGTH)
+            .map_or(false, |sig| {
+                let mut raw_signature = [0u8; SIGNATURE_LENGTH];
+                raw_signature.copy_from_slice(&sig);
+
+                key.verify(&signed, &Signature::from_bytes(&raw_signature))
+                    .is_ok()
+            })
+    });
+
+    ensure!(
+        signed_by_key,
+        "the provenance isn't signed by the given key"
+    );
+
+    let statement: Value = serde_json::from_slice(&payload)?;
+    let digest = util::sha256_hex(bundle);
+
+    ensure!(
+        statement["_type"] == STATEMENT_TYPE,
+        "the provenance is no in-toto statement"
+    );
+    ensure!(
+        statement["subject"]
+            .as_array()
+            .map_or(false, |subjects| subjects
+                .iter()
+                .any(|subject| subject["digest"]["sha256"] == digest.as_str())),
+        "the provenance is about another bundle, this one's digest is sha256:{}",
+        digest
+    );
+
+    Ok(())
+}
+
+/// The pre-authentication encoding of DSSE, which is what actually gets signed
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    encoded.extend_from_slice(payload);
+
+    encoded
+}
+
+/// The commit the app is built from, if it is a git repository
+fn source() -> Option<Value> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let mut source = json!({
+        "digest": { "gitCommit": commit },
+        "uncommittedChanges": git(&["status", "--porcelain"]).map_or(false, |status| !status.is_empty())
+    });
+
+    if let Some(uri) = git(&["remote", "get-url", "origin"]) {
+        source["uri"] = uri.into();
+    }
+
+    Some(source)
+}
+
+fn decode_key(encoded: &str, what: &str) -> DefaultResult<[u8; KEY_LENGTH]> {
+    let bytes = base64::decode(encoded).map_err(|err| format_err!("invalid {}: {}", what, err))?;
+
+    ensure!(
+        bytes.len() == KEY_LENGTH,
+        "invalid {}: expected {} bytes, got {}",
+        what,
+        KEY_LENGTH,
+        bytes.len()
+    );
+
+    let mut key = [0u8; KEY_LENGTH];
+    key.copy_from_slice(&bytes);
+
+    Ok(key)
+}
+
+/// Formats the time in UTC like `2019-01-02T03:04:05Z`
+fn rfc3339(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, seconds_of_day) = ((seconds / 86_400) as i64, seconds % 86_400);
+
+    // the civil date of the days since the epoch, after Howard Hinnant's days_from_civil
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::Builder;
+
+    #[test]
+    fn signed_provenance_verifies_for_its_bundle_only() {
+        let tmp = Builder::new().prefix("provenance").tempdir().unwrap();
+        let bundle = tmp.path().join("bundle.json");
+        let input = tmp.path().join("app.json");
+
+        fs::write(&bundle, "{}").unwrap();
+        fs::write(&input, "{\"name\": \"chat\"}").unwrap();
+
+        let (secret, public) = generate_signing_key();
+        let key = SigningKey::from_bytes(&decode_key(&secret, "signing key").unwrap());
+        let path = write(&bundle, &[input], &BTreeMap::new(), &key).unwrap();
+        let envelope = fs::read(&path).unwrap();
+
+        assert_eq!(path, tmp.path().join("bundle.json.intoto.jsonl"));
+        assert!(verify(b"{}", &envelope, &public).is_ok());
+        assert!(verify(b"{ }", &envelope, &public).is_err());
+
+        let (_, other) = generate_signing_key();
+        assert!(verify(b"{}", &envelope, &other).is_err());
+
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(951_827_696)),
+            "2000-02-29T12:34:56Z"
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Client side of the DNA registry: a signed JSON index listing published bundles.
+
+use base64;
+use colored::*;
+use config_files::Global;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use error::DefaultResult;
+use net;
+use output;
+use semver::{Version, VersionReq};
+use serde_json::{self, value::RawValue};
+use std::collections::BTreeMap;
+
+/// The index published by a registry
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Index {
+    pub dnas: Vec<IndexEntry>,
+    /// Base64 encoded ed25519 signature over the `dnas` array, byte for byte as it is written
+    /// in the index file
+    pub signature: Option<String>,
+    /// The `dnas` array as the index file has it, which is what the signature is checked
+    /// against, so fields this client doesn't know about don't invalidate it
+    #[serde(skip)]
+    signed: Vec<u8>,
+}
+
+/// Only the `dnas` of an index, unparsed
+#[derive(Deserialize)]
+struct RawIndex<'a> {
+    #[serde(borrow)]
+    dnas: &'a RawValue,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct IndexEntry {
+    pub name: String,
+    pub version: Version,
+    #[serde(default)]
+    pub description: String,
+    /// `sha256:<hex digest>` of the bundle file
+    pub hash: String,
+    /// Location of the bundle, relative locations are resolved against the index
+    pub url: String,
+    /// Other DNAs this one depends on, as name and semver range
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+    /// What the Zomes of the DNA say about themselves, by their name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub zomes: BTreeMap<String, ZomeInfo>,
+}
+
+/// The description of a Zome and the attribution of its `zome.json`
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ZomeInfo {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+}
+
+impl ZomeInfo {
+    /// Version, author and license as far as they are known, like `0.2.0, by Alice, MIT`
+    pub fn attribution(&self) -> String {
+        let mut parts = Vec::new();
+
+        parts.extend(self.version.clone());
+        parts.extend(self.author.as_ref().map(|author| format!("by {}", author)));
+        parts.extend(self.license.clone());
+
+        parts.join(", ")
+    }
+}
+
+/// A registry index together with the location it was fetched from
+pub struct Registry {
+    pub location: String,
+    pub index: Index,
+}
+
+impl Registry {
+    /// Fetches and verifies the index of the registry configured in the global settings
+    pub fn open() -> DefaultResult<Registry> {
+        let global = Global::load()?;
+
+        let location = global.registry_url.ok_or_else(|| {
+            format_err!(
+                "no registry configured, set one with: config set registry_url <url> --global"
+            )
+        })?;
+
+        let index = Index::parse(&net::fetch(&location)?)
+            .map_err(|err| format_err!("invalid registry index at {}: {}", location, err))?;
+
+        match global.registry_public_key {
+            Some(public_key) => index.verify(&public_key)?,
+            None => output::print_err(format!(
+                "{}: registry_public_key is not set, the index signature was not verified",
+                "warning".yellow().bold()
+            )),
+        }
+
+        Ok(Registry { location, index })
+    }
+
+    /// All entries whose name or description contains the given term
+    pub fn search(&self, term: &str) -> Vec<&IndexEntry> {
+        let term = term.to_lowercase();
+
+        self.index
+            .dnas
+            .iter()
+            .filter(|entry| {
+                entry.name.to_lowercase().contains(&term)
+                    || entry.description.to_lowercase().contains(&term)
+                    || entry.zomes.iter().any(|(name, zome)| {
+                        name.to_lowercase().contains(&term)
+                            || zome.description.to_lowercase().contains(&term)
+                    })
+            })
+            .collect()
+    }
+
+    /// Picks the newest entry of the given name matching the version requirement
+    pub fn resolve(&self, name: &str, requirement: &VersionReq) -> Option<&IndexEntry> {
+        self.index
+            .dnas
+            .iter()
+            .filter(|entry| entry.name == name && requirement.matches(&entry.version))
+            .max_by(|a, b| a.version.cmp(&b.version))
+    }
+
+    pub fn bundle_location(&self, entry: &IndexEntry) -> String {
+        net::join(&self.location, &entry.url)
+    }
+}
+
+impl Index {
+    /// Parses the content of an index file, keeping the bytes its signature is over
+    pub fn parse(content: &[u8]) -> DefaultResult<Index> {
+        let raw: RawIndex = serde_json::from_slice(content)?;
+        let mut index: Index = serde_json::from_slice(content)?;
+
+        index.signed = raw.dnas.get().as_bytes().to_vec();
+
+        Ok(index)
+    }
+
+    pub fn verify(&self, public_key: &str) -> DefaultResult<()> {
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or_else(|| format_err!("registry index is not signed"))?;
+
+        let key_bytes = base64::decode(public_key)?;
+        ensure!(key_bytes.len() == 32, "invalid registry public key");
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&key_bytes);
+
+        let signature_bytes = base64::decode(signature)?;
+        ensure!(
+            signature_bytes.len() == 64,
+            "invalid registry index signature"
+        );
+        let mut raw_signature = [0u8; 64];
+        raw_signature.copy_from_slice(&signature_bytes);
+
+        VerifyingKey::from_bytes(&key)
+            .map_err(|err| format_err!("invalid registry public key: {}", err))?
+            .verify(&self.signed, &Signature::from_bytes(&raw_signature))
+            .map_err(|_| format_err!("registry index signature is invalid"))
+    }
+}
+
+/// Splits `name@version` into the name and a version requirement, where a missing version
+/// means any version
+pub fn parse_spec(spec: &str) -> DefaultResult<(String, VersionReq)> {
+    let mut parts = spec.splitn(2, '@');
+    let name = parts.next().unwrap_or_default().to_string();
+
+    ensure!(!name.is_empty(), "missing DNA name in {}", spec);
+
+    let requirement = match parts.next() {
+        Some(version) => VersionReq::parse(version)
+            .map_err(|err| format_err!("invalid version {}: {}", version, err))?,
+        None => VersionReq::any(),
+    };
+
+    Ok((name, requirement))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn entry(name: &str, version: &str) -> IndexEntry {
+        IndexEntry {
+            name: name.into(),
+            version: Version::parse(version).unwrap(),
+            description: format!("The {} DNA", name),
+            hash: "sha256:00".into(),
+            url: format!("{}-{}.json", name, version),
+            dependencies: BTreeMap::new(),
+            zomes: BTreeMap::new(),
+        }
+    }
+
+    fn registry() -> Registry {
+        Registry {
+            location: "https://example.com/registry/index.json".into(),
+            index: Index {
+                dnas: vec![
+                    entry("chat", "0.1.0"),
+                    entry("chat", "0.2.1"),
+                    entry("chat", "1.0.0"),
+                    entry("files", "0.1.0"),
+                ],
+                signature: None,
+                signed: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn resolves_newest_matching_version() {
+        let registry = registry();
+
+        let (name, requirement) = parse_spec("chat@^0.2").unwrap();
+        assert_eq!(
+            registry.resolve(&name, &requirement),
+            Some(&entry("chat", "0.2.1"))
+        );
+
+        let (name, requirement) = parse_spec("chat").unwrap();
+        assert_eq!(
+            registry.resolve(&name, &requirement),
+            Some(&entry("chat", "1.0.0"))
+        );
+
+        assert_eq!(
+            registry.bundle_location(&entry("files", "0.1.0")),
+            "https://example.com/registry/files-0.1.0.json"
+        );
+        assert_eq!(registry.search("FILE").len(), 1);
+    }
+
+    #[test]
+    fn finds_dnas_by_their_zomes() {
+        let mut registry = registry();
+        let zome = ZomeInfo {
+            description: "Stores files as chunks".into(),
+            author: Some("Alice".into()),
+            version: None,
+            license: Some("MIT".into()),
+        };
+
+        registry.index.dnas[3]
+            .zomes
+            .insert("storage".into(), zome.clone());
+
+        assert_eq!(registry.search("chunks")[0].name, "files");
+        assert_eq!(registry.search("storage").len(), 1);
+        assert_eq!(zome.attribution(), "by Alice, MIT");
+    }
+
+    #[test]
+    fn verifies_index_signature() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let public_key = base64::encode(signing_key.verifying_key().as_bytes());
+
+        // formatted and with a field this client doesn't know, as another tool might write it
+        let dnas = r#"[
+  { "name": "chat", "version": "0.1.0", "hash": "sha256:00", "url": "chat.json", "mirrors": [] }
+]"#;
+        let signature = base64::encode(&signing_key.sign(dnas.as_bytes()).to_bytes()[..]);
+        let content = format!(r#"{{ "dnas": {}, "signature": "{}" }}"#, dnas, signature);
+
+        let index = Index::parse(content.as_bytes()).unwrap();
+        assert_eq!(index.dnas[0].name, "chat");
+        assert!(index.verify(&public_key).is_ok());
+
+        let tampered = Index::parse(content.replace("0.1.0", "0.1.1").as_bytes()).unwrap();
+        assert!(tampered.verify(&public_key).is_err());
+    }
+}
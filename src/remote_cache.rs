@@ -0,0 +1,246 @@
+//! A build cache shared by a team, so CI and teammates reuse each other's Zome builds instead
+//! of building the same code again.
+//!
+//! Builds are stored under the same key as in the local store of `build_cache`, a digest of
+//! everything that goes into them: the sources, the build steps, the versions of the tools and
+//! the environment variables that influence the compiler. The same key builds the same WASM, so
+//! fetching it is as good as building it. The cache is set up in the `[build_cache]` settings:
+//!
+//! ```toml
+//! [build_cache]
+//! url = "https://cache.example.com/chat"
+//! read = true
+//! write = false
+//! ```
+//!
+//! Besides http(s) URLs, which get `HC_BUILD_CACHE_TOKEN` as bearer token, the cache can be an
+//! S3 bucket as `s3://<bucket>/<prefix>`, reached with the `aws` CLI and its credentials, or a
+//! directory, e.g. on a network share. `HC_BUILD_CACHE_WRITE` turns on `write` for a single
+//! run, usually on CI.
+
+use config_files::BuildCacheConfig;
+use error::DefaultResult;
+use file_lock;
+use net;
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+pub const TOKEN_ENV_VAR: &str = "HC_BUILD_CACHE_TOKEN";
+pub const WRITE_ENV_VAR: &str = "HC_BUILD_CACHE_WRITE";
+
+const S3_SCHEME: &str = "s3://";
+
+pub struct RemoteCache {
+    url: String,
+    read: bool,
+    write: bool,
+    token: Option<String>,
+}
+
+impl RemoteCache {
+    /// The cache of the settings, none if there is none or the CLI is offline
+    pub fn from_config(config: &BuildCacheConfig) -> Option<RemoteCache> {
+        let url = config.url.as_ref()?;
+        let write = match env::var(WRITE_ENV_VAR) {
+            Ok(value) => !value.is_empty() && value != "0" && value != "false",
+            Err(_) => config.write,
+        };
+
+        if net::is_offline() && !is_directory(url) {
+            return None;
+        }
+
+        Some(RemoteCache {
+            url: url.trim_end_matches('/').to_string(),
+            read: config.read,
+            write,
+            token: env::var(TOKEN_ENV_VAR).ok(),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn reads(&self) -> bool {
+        self.read
+    }
+
+    pub fn writes(&self) -> bool {
+        self.write
+    }
+
+    /// The build stored under the key, nothing if the cache doesn't have it
+    pub fn fetch(&self, key: &str) -> DefaultResult<Option<Vec<u8>>> {
+        let location = self.location(key);
+
+        if net::is_remote(&self.url) {
+            net::fetch_if_exists(&location, self.token.as_ref().map(String::as_str))
+        } else if self.url.starts_with(S3_SCHEME) {
+            let output = Command::new("aws")
+                .args(&["s3", "cp", "--only-show-errors", &location, "-"])
+                .output()
+                .map_err(|err| format_err!("unable to run the aws CLI: {}", err))?;
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            if output.status.success() {
+                Ok(Some(output.stdout))
+            } else if stderr.contains("(404)") || stderr.contains("NoSuchKey") {
+                Ok(None)
+            } else {
+                bail!("aws s3 cp {} failed: {}", location, stderr.trim())
+            }
+        } else {
+            match fs::read(&location) {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(_) if !Path::new(&location).exists() => Ok(None),
+                Err(err) => bail!("unable to read {}: {}", location, err),
+            }
+        }
+    }
+
+    /// Stores the build under the key
+    pub fn store(&self, key: &str, bytes: &[u8]) -> DefaultResult<()> {
+        let location = self.location(key);
+
+        if net::is_remote(&self.url) {
+            net::put(&location, bytes, self.token.as_ref().map(String::as_str))
+        } else if self.url.starts_with(S3_SCHEME) {
+            let mut aws = Command::new("aws")
+                .args(&["s3", "cp", "--only-show-errors", "-", &location])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|err| format_err!("unable to run the aws CLI: {}", err))?;
+
+            aws.stdin
+                .take()
+                .ok_or_else(|| format_err!("unable to write to the aws CLI"))?
+                .write_all(bytes)?;
+
+            let output = aws.wait_with_output()?;
+
+            ensure!(
+                output.status.success(),
+                "aws s3 cp {} failed: {}",
+                location,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+
+            Ok(())
+        } else {
+            let path = PathBuf::from(&location);
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            // teammates may be reading from the same directory
+            file_lock::write_atomic(&path, bytes)
+        }
+    }
+
+    fn location(&self, key: &str) -> String {
+        format!("{}/{}.wasm", self.url, key)
+    }
+}
+
+/// Whether the cache is a directory rather than a server, which works offline as well
+fn is_directory(url: &str) -> bool {
+    !net::is_remote(url) && !url.starts_with(S3_SCHEME)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_cmd::prelude::*;
+    use std::{fs, process::Command};
+    use tempfile::Builder;
+
+    #[test]
+    fn teammates_reuse_builds_from_the_shared_cache() {
+        let tmp = Builder::new().prefix("remote-cache").tempdir().unwrap();
+        let code_dir = tmp.path().join("zomes/chat/code");
+
+        fs::create_dir_all(&code_dir).unwrap();
+        fs::write(tmp.path().join("app.json"), "{}").unwrap();
+        fs::write(code_dir.join("lib.rs"), "// chat").unwrap();
+        // the homes of the teammates and the log of the builds aren't part of the app
+        fs::write(
+            tmp.path().join(".hcignore"),
+            "/alice\n/bob\n/carol\n/ci\n/shared\nbuilds\n",
+        )
+        .unwrap();
+        fs::write(
+            code_dir.join(".build"),
+            r#"{
+                "steps": { "sh": ["-c", "echo built >> ../builds; printf '\\0asm\\1\\0\\0\\0' > code.wasm"] },
+                "artifact": "code.wasm"
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("hc.toml"),
+            format!(
+                "[build_cache]\nurl = \"{}\"\n",
+                tmp.path().join("shared").display()
+            ),
+        )
+        .unwrap();
+
+        // every run has a local cache of its own, like another machine
+        let package = |home: &str, write: &str| {
+            Command::main_binary()
+                .unwrap()
+                .current_dir(tmp.path())
+                .env("HC_HOME", tmp.path().join(home))
+                .env("HC_BUILD_CACHE_WRITE", write)
+                .args(&["package"])
+                .output()
+                .unwrap()
+        };
+        let builds = || fs::read_to_string(tmp.path().join("zomes/chat/builds")).unwrap();
+
+        assert!(package("alice", "0").status.success());
+        assert!(!tmp.path().join("shared").exists());
+
+        fs::remove_file(code_dir.join("code.wasm")).unwrap();
+        assert!(package("ci", "1").status.success());
+        assert_eq!(builds(), "built\nbuilt\n");
+        assert_eq!(fs::read_dir(tmp.path().join("shared")).unwrap().count(), 1);
+
+        fs::remove_file(code_dir.join("code.wasm")).unwrap();
+        let output = package("bob", "0");
+
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("remote build cache"));
+        assert_eq!(builds(), "built\nbuilt\n");
+        assert_eq!(
+            fs::read(code_dir.join("code.wasm")).unwrap(),
+            b"\0asm\x01\0\0\0"
+        );
+
+        // whatever else is stored under the key is built anew
+        let stored = fs::read_dir(tmp.path().join("shared"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        fs::write(&stored, "<html>502 Bad Gateway</html>").unwrap();
+        fs::remove_file(code_dir.join("code.wasm")).unwrap();
+        let output = package("carol", "0");
+
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("is no valid WASM"));
+        assert_eq!(builds(), "built\nbuilt\nbuilt\n");
+
+        fs::write(code_dir.join("lib.rs"), "// chat v2").unwrap();
+        assert!(package("bob", "0").status.success());
+        assert_eq!(builds(), "built\nbuilt\nbuilt\nbuilt\n");
+    }
+}
@@ -0,0 +1,121 @@
+//! Reproducible builds: the same sources give byte for byte the same artifacts wherever and
+//! whenever they are built, so that distro packagers can verify them.
+//!
+//! Turned on by `--reproducible` or by setting `SOURCE_DATE_EPOCH`, the convention of
+//! reproducible-builds.org. Timestamps the CLI writes then come from `SOURCE_DATE_EPOCH`, the
+//! epoch itself if only the flag is given, and the tools run for builds get the same time and
+//! don't see where the Cargo home of the machine is.
+
+use error::DefaultResult;
+use std::{
+    env,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub const SOURCE_DATE_EPOCH_ENV_VAR: &str = "SOURCE_DATE_EPOCH";
+
+/// What the Cargo home is replaced with in the paths compiled into the WASM
+const REMAPPED_CARGO_HOME: &str = "/cargo";
+
+/// Seconds since the epoch that stand in for the current time, if builds are reproducible
+static SOURCE_DATE_EPOCH: Mutex<Option<u64>> = Mutex::new(None);
+
+/// Makes builds reproducible if requested by the flag or `SOURCE_DATE_EPOCH`
+pub fn init(reproducible_flag: bool) -> DefaultResult<()> {
+    let epoch = epoch(env::var(SOURCE_DATE_EPOCH_ENV_VAR).ok(), reproducible_flag)?;
+
+    *SOURCE_DATE_EPOCH.lock().unwrap() = epoch;
+
+    Ok(())
+}
+
+/// The epoch given by the value of `SOURCE_DATE_EPOCH`, or the epoch itself if only the flag is
+/// given
+fn epoch(from_env: Option<String>, reproducible_flag: bool) -> DefaultResult<Option<u64>> {
+    let from_env = match from_env {
+        Some(ref value) if !value.is_empty() => Some(value.parse().map_err(|_| {
+            format_err!(
+                "{} has to be the number of seconds since the epoch, not {:?}",
+                SOURCE_DATE_EPOCH_ENV_VAR,
+                value
+            )
+        })?),
+        _ => None,
+    };
+
+    Ok(from_env.or(if reproducible_flag { Some(0) } else { None }))
+}
+
+fn source_date_epoch() -> Option<u64> {
+    *SOURCE_DATE_EPOCH.lock().unwrap()
+}
+
+/// The time to put into generated files: the current one, or `SOURCE_DATE_EPOCH` for
+/// reproducible builds
+pub fn now() -> SystemTime {
+    match source_date_epoch() {
+        Some(epoch) => UNIX_EPOCH + Duration::from_secs(epoch),
+        None => SystemTime::now(),
+    }
+}
+
+/// The environment variables that make the tools run for builds reproducible as well
+pub fn command_env() -> Vec<(String, String)> {
+    command_env_at(source_date_epoch())
+}
+
+fn command_env_at(epoch: Option<u64>) -> Vec<(String, String)> {
+    let epoch = match epoch {
+        Some(epoch) => epoch,
+        None => return Vec::new(),
+    };
+
+    let mut env = vec![(SOURCE_DATE_EPOCH_ENV_VAR.to_string(), epoch.to_string())];
+
+    // the sources of dependencies are compiled in with their absolute paths, e.g. for panics
+    if let Some(cargo_home) = cargo_home() {
+        let remap = format!(
+            "--remap-path-prefix={}={}",
+            cargo_home.display(),
+            REMAPPED_CARGO_HOME
+        );
+        let rustflags = match env::var("RUSTFLAGS") {
+            Ok(ref rustflags) if !rustflags.is_empty() => format!("{} {}", rustflags, remap),
+            _ => remap,
+        };
+
+        env.push(("RUSTFLAGS".to_string(), rustflags));
+    }
+
+    env
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    env::var_os("CARGO_HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uses_the_source_date_epoch() {
+        assert_eq!(epoch(None, true).unwrap(), Some(0));
+        assert_eq!(
+            epoch(Some("1700000000".to_string()), false).unwrap(),
+            Some(1_700_000_000)
+        );
+        assert_eq!(epoch(Some(String::new()), false).unwrap(), None);
+        assert!(epoch(Some("yesterday".to_string()), true).is_err());
+
+        assert!(command_env_at(Some(0))
+            .iter()
+            .any(|(name, value)| name == SOURCE_DATE_EPOCH_ENV_VAR && value == "0"));
+        assert!(command_env_at(None).is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+//! Resolution of DNA dependencies declared with semver ranges.
+//!
+//! The resolver repeatedly picks the newest candidate satisfying every requirement placed on a
+//! name, preferring versions pinned by the lockfile, until the selection no longer changes.
+
+use error::DefaultResult;
+use registry::IndexEntry;
+use semver::VersionReq;
+use std::collections::BTreeMap;
+
+pub const ROOT_REQUIRER: &str = "the app";
+
+const MAX_ROUNDS: usize = 100;
+
+#[derive(Debug, PartialEq)]
+pub struct Resolution {
+    pub root: BTreeMap<String, String>,
+    pub dnas: BTreeMap<String, IndexEntry>,
+}
+
+/// Resolves the root requirements against the candidates, keeping locked versions where possible
+pub fn resolve(
+    root: &BTreeMap<String, String>,
+    candidates: &[IndexEntry],
+    locked: &[IndexEntry],
+) -> DefaultResult<Resolution> {
+    let mut selected: BTreeMap<String, IndexEntry> = BTreeMap::new();
+
+    for _ in 0..MAX_ROUNDS {
+        let requirements = collect_requirements(root, &selected)?;
+        let mut next = BTreeMap::new();
+
+        for (name, reqs) in &requirements {
+            let satisfies_all = |entry: &&IndexEntry| {
+                entry.name == *name && reqs.iter().all(|(_, req)| req.matches(&entry.version))
+            };
+
+            let is_locked = |entry: &&IndexEntry| {
+                locked
+                    .iter()
+                    .any(|pin| pin.name == entry.name && pin.version == entry.version)
+            };
+
+            let choice = candidates
+                .iter()
+                .find(|entry| satisfies_all(entry) && is_locked(entry))
+                .or_else(|| {
+                    candidates
+                        .iter()
+                        .filter(satisfies_all)
+                        .max_by(|a, b| a.version.cmp(&b.version))
+                });
+
+            match choice {
+                Some(entry) => {
+                    next.insert(name.clone(), entry.clone());
+                }
+                None => bail!(conflict_message(name, reqs, candidates)),
+            }
+        }
+
+        if next == selected {
+            return Ok(Resolution {
+                root: root.clone(),
+                dnas: selected,
+            });
+        }
+
+        selected = next;
+    }
+
+    bail!("dependency resolution did not settle, are there circular requirements?")
+}
+
+fn collect_requirements(
+    root: &BTreeMap<String, String>,
+    selected: &BTreeMap<String, IndexEntry>,
+) -> DefaultResult<BTreeMap<String, Vec<(String, VersionReq)>>> {
+    let mut requirements: BTreeMap<String, Vec<(String, VersionReq)>> = BTreeMap::new();
+
+    let requirers = root
+        .iter()
+        .map(|(name, range)| (ROOT_REQUIRER.to_string(), name, range))
+        .chain(selected.values().flat_map(|entry| {
+            entry.dependencies.iter().map(move |(name, range)| {
+                (format!("{}@{}", entry.name, entry.version), name, range)
+            })
+        }));
+
+    for (requirer, name, range) in requirers {
+        let requirement = VersionReq::parse(range).map_err(|err| {
+            format_err!(
+                "{} has an invalid requirement on {}: {}",
+                requirer,
+                name,
+                err
+            )
+        })?;
+
+        requirements
+            .entry(name.clone())
+            .or_insert_with(Vec::new)
+            .push((requirer, requirement));
+    }
+
+    Ok(requirements)
+}
+
+fn conflict_message(
+    name: &str,
+    reqs: &[(String, VersionReq)],
+    candidates: &[IndexEntry],
+) -> String {
+    let available: Vec<_> = candidates
+        .iter()
+        .filter(|entry| entry.name == name)
+        .map(|entry| entry.version.to_string())
+        .collect();
+
+    let wanted: Vec<_> = reqs
+        .iter()
+        .map(|(requirer, req)| format!("{} requires {}", requirer, req))
+        .collect();
+
+    if available.is_empty() {
+        format!("no DNA named {} is available ({})", name, wanted.join(", "))
+    } else {
+        format!(
+            "conflicting requirements for {}: {} (available: {})",
+            name,
+            wanted.join(", "),
+            available.join(", ")
+        )
+    }
+}
+
+impl Resolution {
+    /// Renders the resolved graph as an indented tree below the given root name
+    pub fn tree(&self, root_name: &str) -> String {
+        let mut out = format!("{}\n", root_name);
+        let names: Vec<_> = self.root.keys().collect();
+
+        self.render_children(&names, "", &mut vec![], &mut out);
+
+        out
+    }
+
+    fn render_children(
+        &self,
+        names: &[&String],
+        prefix: &str,
+        path: &mut Vec<String>,
+        out: &mut String,
+    ) {
+        for (i, name) in names.iter().enumerate() {
+            let last = i == names.len() - 1;
+            let entry = &self.dnas[*name];
+
+            let cycle = path.contains(name);
+            out.push_str(&format!(
+                "{}{} {}@{}{}\n",
+                prefix,
+                if last { "└──" } else { "├──" },
+                entry.name,
+                entry.version,
+                if cycle { " (cycle)" } else { "" }
+            ));
+
+            if cycle {
+                continue;
+            }
+
+            let children: Vec<_> = entry.dependencies.keys().collect();
+            let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+
+            path.push((*name).clone());
+            self.render_children(&children, &child_prefix, path, out);
+            path.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semver::Version;
+
+    fn entry(name: &str, version: &str, deps: &[(&str, &str)]) -> IndexEntry {
+        IndexEntry {
+            name: name.into(),
+            version: Version::parse(version).unwrap(),
+            description: String::new(),
+            hash: String::new(),
+            url: String::new(),
+            dependencies: deps
+                .iter()
+                .map(|(name, range)| (name.to_string(), range.to_string()))
+                .collect(),
+            zomes: BTreeMap::new(),
+        }
+    }
+
+    fn root(deps: &[(&str, &str)]) -> BTreeMap<String, String> {
+        deps.iter()
+            .map(|(name, range)| (name.to_string(), range.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn picks_versions_compatible_with_transitive_requirements() {
+        let candidates = vec![
+            entry("chat", "0.2.0", &[("files", "^0.1")]),
+            entry("files", "0.1.0", &[]),
+            entry("files", "0.1.4", &[]),
+            entry("files", "0.2.0", &[]),
+        ];
+
+        let resolution =
+            resolve(&root(&[("chat", "^0.2"), ("files", "*")]), &candidates, &[]).unwrap();
+
+        assert_eq!(
+            resolution.dnas["files"].version,
+            Version::parse("0.1.4").unwrap()
+        );
+        assert_eq!(
+            resolution.tree("app"),
+            "app\n├── chat@0.2.0\n│   └── files@0.1.4\n└── files@0.1.4\n"
+        );
+    }
+
+    #[test]
+    fn prefers_locked_versions() {
+        let candidates = vec![entry("files", "0.1.0", &[]), entry("files", "0.1.4", &[])];
+        let locked = vec![entry("files", "0.1.0", &[])];
+
+        let resolution = resolve(&root(&[("files", "^0.1")]), &candidates, &locked).unwrap();
+
+        assert_eq!(
+            resolution.dnas["files"].version,
+            Version::parse("0.1.0").unwrap()
+        );
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let candidates = vec![
+            entry("chat", "0.2.0", &[("files", "^0.2")]),
+            entry("files", "0.1.0", &[]),
+            entry("files", "0.2.0", &[]),
+        ];
+
+        let err = resolve(
+            &root(&[("chat", "^0.2"), ("files", "^0.1")]),
+            &candidates,
+            &[],
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(err.contains("conflicting requirements for files"));
+        assert!(err.contains("chat@0.2.0 requires ^0.2"));
+    }
+}
@@ -0,0 +1,76 @@
+use config_files::Project;
+use error::DefaultResult;
+use i18n;
+use interrupt;
+use output;
+use std::{
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+pub const PRE_GENERATE: &str = "pregenerate";
+pub const POST_GENERATE: &str = "postgenerate";
+pub const PRE_PACKAGE: &str = "prepackage";
+pub const POST_PACKAGE: &str = "postpackage";
+/// Not a lifecycle hook, but the project's test suite run by `hcdev test`
+pub const TEST: &str = "test";
+
+/// Runs the project script registered for the given lifecycle point, if there is one.
+/// The script is run through the system shell from the project root, with `HC_SCRIPT`,
+/// `HC_PROJECT_ROOT` and the supplied variables set in its environment.
+pub fn run_hook(root: &PathBuf, name: &str, env: &[(&str, String)]) -> DefaultResult<()> {
+    let project = Project::from_dir(root)?;
+
+    let script = match project.script(name) {
+        Some(script) => script,
+        None => return Ok(()),
+    };
+
+    output::command(name, script);
+
+    let mut command = script_command(root, name, script)?;
+
+    for (key, value) in env {
+        command.env(key, value);
+    }
+
+    if output::is_quiet() {
+        command.stdout(Stdio::null());
+    }
+
+    let status = interrupt::status(&mut command)?;
+
+    ensure!(
+        status.success(),
+        i18n::tr("script-failed", &[("name", name.to_owned())])
+    );
+
+    Ok(())
+}
+
+/// Prepares the command running the given script from the project root, with `HC_SCRIPT` and
+/// `HC_PROJECT_ROOT` set in its environment
+pub fn script_command(root: &PathBuf, name: &str, script: &str) -> DefaultResult<Command> {
+    let mut command = shell_command(script);
+
+    command
+        .current_dir(root)
+        .env("HC_SCRIPT", name)
+        .env("HC_PROJECT_ROOT", root.canonicalize()?);
+
+    Ok(command)
+}
+
+#[cfg(not(windows))]
+fn shell_command(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script);
+    command
+}
+
+#[cfg(windows)]
+fn shell_command(script: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").arg(script);
+    command
+}
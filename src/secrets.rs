@@ -0,0 +1,141 @@
+//! Properties of the app that don't go into the bundle in plaintext, like the API keys a DNA
+//! needs.
+//!
+//! The properties listed in the `[secrets]` settings are each encrypted to the recipient on
+//! their own and replaced by `{ "encrypted_property": <envelope> }`, so the rest of the manifest
+//! stays readable. Whoever instantiates the DNA decrypts them with the recipient's identity.
+
+use encryption::{self, Envelope};
+use error::DefaultResult;
+use serde_json::{self, Map, Value};
+use x25519_dalek::StaticSecret;
+
+pub const ENCRYPTED_PROPERTY_KEY: &str = "encrypted_property";
+
+const PROPERTIES_KEY: &str = "properties";
+
+/// Encrypts the properties at the dotted paths of the app's manifest to the recipient
+pub fn encrypt_properties(
+    manifest: &mut Map<String, Value>,
+    paths: &[String],
+    recipient: &str,
+) -> DefaultResult<()> {
+    for path in paths {
+        let property = path
+            .split('.')
+            .fold(manifest.get_mut(PROPERTIES_KEY), |value, key| {
+                value.and_then(|value| value.get_mut(key))
+            })
+            .filter(|property| !property.is_null())
+            .ok_or_else(|| format_err!("secret property {} isn't in the manifest", path))?;
+
+        if encrypted(property).is_some() {
+            continue;
+        }
+
+        let envelope = encryption::seal(&serde_json::to_vec(property)?, recipient)?;
+
+        *property = json!({ ENCRYPTED_PROPERTY_KEY: envelope });
+    }
+
+    Ok(())
+}
+
+/// Decrypts the secret properties of the app's manifest in place and returns how many there
+/// were
+pub fn decrypt_properties(
+    manifest: &mut Map<String, Value>,
+    identity: &StaticSecret,
+) -> DefaultResult<usize> {
+    match manifest.get_mut(PROPERTIES_KEY) {
+        Some(properties) => decrypt_recurse(properties, identity),
+        None => Ok(0),
+    }
+}
+
+/// Whether the app's manifest has secret properties
+pub fn has_encrypted_properties(manifest: &Map<String, Value>) -> bool {
+    fn any_encrypted(value: &Value) -> bool {
+        encrypted(value).is_some()
+            || match value {
+                Value::Object(obj) => obj.values().any(any_encrypted),
+                Value::Array(values) => values.iter().any(any_encrypted),
+                _ => false,
+            }
+    }
+
+    manifest.get(PROPERTIES_KEY).map_or(false, any_encrypted)
+}
+
+fn decrypt_recurse(value: &mut Value, identity: &StaticSecret) -> DefaultResult<usize> {
+    if let Some(envelope) = encrypted(value) {
+        *value = serde_json::from_slice(&encryption::decrypt(&envelope, identity)?)?;
+
+        return Ok(1);
+    }
+
+    let mut count = 0;
+
+    match value {
+        Value::Object(obj) => {
+            for value in obj.values_mut() {
+                count += decrypt_recurse(value, identity)?;
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                count += decrypt_recurse(value, identity)?;
+            }
+        }
+        _ => (),
+    }
+
+    Ok(count)
+}
+
+fn encrypted(value: &Value) -> Option<Envelope> {
+    match value {
+        Value::Object(obj) if obj.len() == 1 => obj
+            .get(ENCRYPTED_PROPERTY_KEY)
+            .and_then(|envelope| serde_json::from_value(envelope.clone()).ok()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64;
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn secret_properties_round_trip() {
+        let (secret, public) = encryption::generate_identity();
+        let tmp = Builder::new().prefix("secrets").tempdir().unwrap();
+        let identity_path = tmp.path().join("identity.key");
+
+        fs::write(&identity_path, secret).unwrap();
+
+        let identity = encryption::read_identity(&identity_path).unwrap();
+        let original = json!({
+            "name": "chat",
+            "properties": { "api": { "key": "s3cr3t", "url": "https://example.com" } }
+        });
+        let mut manifest = original.as_object().unwrap().clone();
+
+        encrypt_properties(&mut manifest, &["api.key".to_string()], &public).unwrap();
+
+        assert!(has_encrypted_properties(&manifest));
+        assert_eq!(manifest["properties"]["api"]["url"], "https://example.com");
+
+        let encrypted = Value::from(manifest.clone()).to_string();
+
+        assert!(!encrypted.contains("s3cr3t"));
+        assert!(!encrypted.contains(&base64::encode("s3cr3t")));
+        assert!(encrypt_properties(&mut manifest, &["api.nope".to_string()], &public).is_err());
+
+        assert_eq!(decrypt_properties(&mut manifest, &identity).unwrap(), 1);
+        assert_eq!(Value::from(manifest), original);
+    }
+}
@@ -0,0 +1,148 @@
+//! Keeps a bundle from mixing old and new versions of the files of an app.
+//!
+//! Packaging reads files for a while: the ones it encodes and the sources of the Zomes it
+//! builds. An editor saving in between, like one in watch mode, would otherwise end up with
+//! some files of the bundle from before the save and some from after. Every file packaging
+//! reads is remembered with its size and modification time, a read that overlaps a save is
+//! retried, and before the bundle is written all of them are checked to still be the same.
+
+use error::DefaultResult;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How often a file that changes while it is read is read again before giving up
+pub const READ_ATTEMPTS: usize = 3;
+
+/// Editors save in one go, waiting this long usually sees the save done
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> DefaultResult<Stamp> {
+        let metadata = fs::metadata(path)?;
+
+        Ok(Stamp {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// The files read so far, shared by the workers reading them
+#[derive(Default)]
+pub struct Snapshot {
+    stamps: Mutex<BTreeMap<PathBuf, Stamp>>,
+}
+
+impl Snapshot {
+    /// Reads the file and remembers it as read. If it changes while it is read, it is read
+    /// again, up to `READ_ATTEMPTS` times.
+    pub fn read(&self, path: &Path) -> DefaultResult<Vec<u8>> {
+        for attempt in 1..=READ_ATTEMPTS {
+            let before = Stamp::of(path)?;
+            let content = fs::read(path)?;
+
+            if Stamp::of(path)? == before {
+                self.insert(path, before);
+
+                return Ok(content);
+            }
+
+            if attempt < READ_ATTEMPTS {
+                thread::sleep(RETRY_DELAY);
+            }
+        }
+
+        bail!(
+            "{:?} kept changing while it was read for the bundle, is it saved by an editor? \
+             package again once it is saved",
+            path
+        )
+    }
+
+    /// Remembers files that are read by something else, like the sources a build compiles
+    pub fn add(&self, paths: &[PathBuf]) -> DefaultResult<()> {
+        for path in paths {
+            self.insert(path, Stamp::of(path)?);
+        }
+
+        Ok(())
+    }
+
+    /// Fails naming the files that changed or disappeared since they were read
+    pub fn check(&self) -> DefaultResult<()> {
+        let changed: Vec<_> = self
+            .stamps
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, stamp)| Stamp::of(path).ok().as_ref() != Some(stamp))
+            .map(|(path, _)| format!("{:?}", path))
+            .collect();
+
+        ensure!(
+            changed.is_empty(),
+            "{} changed while packaging, the bundle would mix their old and new content; \
+             package again once they are saved",
+            changed.join(", ")
+        );
+
+        Ok(())
+    }
+
+    fn insert(&self, path: &Path, stamp: Stamp) {
+        self.stamps
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), stamp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snapshot;
+    use std::fs;
+    use tempfile::Builder;
+
+    #[test]
+    fn notices_files_saved_after_they_were_read() {
+        let tmp = Builder::new().prefix("snapshot").tempdir().unwrap();
+        let read = tmp.path().join("read.txt");
+        let built = tmp.path().join("built.rs");
+
+        fs::write(&read, "before").unwrap();
+        fs::write(&built, "fn main() {}").unwrap();
+
+        let snapshot = Snapshot::default();
+
+        assert_eq!(snapshot.read(&read).unwrap(), b"before");
+        snapshot.add(&[built.clone()]).unwrap();
+        snapshot.check().unwrap();
+
+        fs::write(&built, "fn main() { println!(); }").unwrap();
+
+        let err = snapshot.check().unwrap_err().to_string();
+
+        assert!(err.contains("built.rs"));
+        assert!(!err.contains("read.txt"));
+
+        fs::remove_file(&read).unwrap();
+
+        assert!(snapshot
+            .check()
+            .unwrap_err()
+            .to_string()
+            .contains("read.txt"));
+    }
+}
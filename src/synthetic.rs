@@ -0,0 +1,91 @@
+//! Apps of the shapes that stress packaging in different ways, for `bench self` and the
+//! criterion benches in `benches/`, which include this file as it only needs `std`.
+//!
+//! The apps have no Zomes, so only walking, reading, encoding and writing is measured and not
+//! the compilers. Their files are filled with pseudo random bytes, always the same ones.
+
+use std::{fs, io, path::Path};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    /// An asset heavy UI: thousands of files of a few KB
+    ManySmallFiles,
+    /// A handful of media files of many MB
+    FewHugeFiles,
+    /// Directories nested deep, with a few files on every level
+    DeepNesting,
+}
+
+pub const SHAPES: &[Shape] = &[
+    Shape::ManySmallFiles,
+    Shape::FewHugeFiles,
+    Shape::DeepNesting,
+];
+
+const APP_CONFIG: &str =
+    "{\n  \"name\": \"synthetic\",\n  \"description\": \"A synthetic app\"\n}\n";
+
+impl Shape {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::ManySmallFiles => "many-small-files",
+            Shape::FewHugeFiles => "few-huge-files",
+            Shape::DeepNesting => "deep-nesting",
+        }
+    }
+
+    /// Writes an app of this shape into the directory
+    pub fn generate(self, dir: &Path) -> io::Result<()> {
+        fs::write(dir.join("app.json"), APP_CONFIG)?;
+
+        let assets = dir.join("assets");
+        let mut seed = 1;
+
+        match self {
+            Shape::ManySmallFiles => {
+                for i in 0..2000 {
+                    let subdir = assets.join(format!("{:02}", i % 20));
+                    fs::create_dir_all(&subdir)?;
+                    fs::write(subdir.join(format!("{}.js", i)), bytes(&mut seed, 2 << 10))?;
+                }
+            }
+            Shape::FewHugeFiles => {
+                fs::create_dir_all(&assets)?;
+
+                for i in 0..4 {
+                    fs::write(
+                        assets.join(format!("{}.mp4", i)),
+                        bytes(&mut seed, 16 << 20),
+                    )?;
+                }
+            }
+            Shape::DeepNesting => {
+                let mut subdir = assets;
+
+                for depth in 0..24 {
+                    subdir = subdir.join(format!("level{}", depth));
+                    fs::create_dir_all(&subdir)?;
+
+                    for i in 0..4 {
+                        fs::write(subdir.join(format!("{}.bin", i)), bytes(&mut seed, 1 << 10))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Bytes from a linear congruential generator, cheap to make and not compressible
+fn bytes(seed: &mut u64, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            *seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+
+            (*seed >> 56) as u8
+        })
+        .collect()
+}
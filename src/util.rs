@@ -1,16 +1,48 @@
-use colored::*;
+use annotations;
 use error::DefaultResult;
-use std::{path::PathBuf, process::Command};
+use interrupt;
+use net;
+use output;
+use reproducible;
+use sha2::{Digest, Sha256};
+use std::{
+    io::{self, BufRead, BufReader},
+    path::{Component, Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::Duration,
+};
 
 pub fn run_cmd(base_path: PathBuf, bin: String, args: Vec<String>) -> DefaultResult<()> {
-    let pretty_command = format!("{} {}", bin.green(), args.join(" ").cyan());
+    let pretty_command = format!("{} {}", bin, args.join(" "));
 
-    println!("> {}", pretty_command);
+    output::command(&bin, args.join(" "));
 
-    let status = Command::new(bin)
-        .args(args)
-        .current_dir(base_path)
-        .status()?;
+    let mut command = Command::new(bin);
+    command.args(args).current_dir(&base_path);
+
+    command.envs(command_env());
+
+    // compiler errors are turned into annotations and quiet runs only show the output of a
+    // failed command, so it has to be read along the way
+    if annotations::is_enabled() || output::is_quiet() {
+        command.stderr(Stdio::piped());
+    }
+
+    if output::is_quiet() {
+        command.stdout(Stdio::null());
+    }
+
+    let mut child = interrupt::spawn(&mut command)?;
+    let stderr = pass_through_stderr(&mut child)?;
+    let status = interrupt::wait(child)?;
+
+    for diagnostic in annotations::compiler_diagnostics(&stderr, &base_path) {
+        annotations::annotate(diagnostic);
+    }
+
+    if !status.success() && output::is_quiet() {
+        output::error(stderr.trim_end());
+    }
 
     ensure!(
         status.success(),
@@ -21,6 +53,35 @@ pub fn run_cmd(base_path: PathBuf, bin: String, args: Vec<String>) -> DefaultRes
     Ok(())
 }
 
+/// The environment variables set for the tools `run_cmd` runs
+pub fn command_env() -> Vec<(String, String)> {
+    let mut env = reproducible::command_env();
+
+    // keep cargo and friends from hitting the network as well
+    if net::is_offline() {
+        env.push(("CARGO_NET_OFFLINE".to_string(), "true".to_string()));
+    }
+
+    env
+}
+
+/// Passes on what the child writes to a piped stderr, returning it as well
+fn pass_through_stderr(child: &mut Child) -> io::Result<String> {
+    let mut captured = String::new();
+
+    if let Some(stderr) = child.stderr.take() {
+        for line in BufReader::new(stderr).split(b'\n') {
+            let line = String::from_utf8_lossy(&line?).into_owned();
+
+            output::print_err(&line);
+            captured.push_str(&line);
+            captured.push('\n');
+        }
+    }
+
+    Ok(captured)
+}
+
 /// Helper method for obtaining the file name of a path as a String
 pub fn file_name_string(path: &PathBuf) -> DefaultResult<String> {
     let file_name = path
@@ -31,3 +92,115 @@ pub fn file_name_string(path: &PathBuf) -> DefaultResult<String> {
 
     Ok(file_name.into())
 }
+
+/// Resolves the `.` and `..` of a path without touching the file system, so `ui/../desktop`
+/// becomes `desktop` even if it doesn't exist yet
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
+/// Returns the SHA-256 digest of the given bytes as lowercase hex string
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+const SIZE_UNITS: &[(&str, u64)] = &[
+    ("TB", 1 << 40),
+    ("GB", 1 << 30),
+    ("MB", 1 << 20),
+    ("KB", 1 << 10),
+    ("B", 1),
+];
+
+const DURATION_UNITS: &[(&str, u64)] = &[
+    ("w", 604_800),
+    ("d", 86_400),
+    ("h", 3_600),
+    ("m", 60),
+    ("s", 1),
+];
+
+/// Parses a size like "2GB" or "500KB" into bytes, plain numbers are bytes
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    parse_with_units(size, SIZE_UNITS).ok_or_else(|| {
+        format!(
+            "invalid size: {} (expected a number followed by B, KB, MB, GB or TB)",
+            size
+        )
+    })
+}
+
+/// Parses a duration like "30d" or "12h" into a `Duration`, plain numbers are seconds
+pub fn parse_duration(duration: &str) -> Result<Duration, String> {
+    parse_with_units(duration, DURATION_UNITS)
+        .map(Duration::from_secs)
+        .ok_or_else(|| {
+            format!(
+                "invalid duration: {} (expected a number followed by s, m, h, d or w)",
+                duration
+            )
+        })
+}
+
+/// Formats a number of bytes with the largest unit that keeps it at least 1
+pub fn format_size(bytes: u64) -> String {
+    SIZE_UNITS
+        .iter()
+        .find(|(_, factor)| bytes >= *factor)
+        .map(|(unit, factor)| match *factor {
+            1 => format!("{} {}", bytes, unit),
+            _ => format!("{:.1} {}", bytes as f64 / *factor as f64, unit),
+        })
+        .unwrap_or_else(|| "0 B".into())
+}
+
+fn parse_with_units(value: &str, units: &[(&str, u64)]) -> Option<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+
+    if unit.is_empty() {
+        return Some(number);
+    }
+
+    units
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(unit.trim()))
+        .and_then(|(_, factor)| number.checked_mul(*factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes_and_durations() {
+        assert_eq!(parse_size("2GB"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("512"), Ok(512));
+        assert!(parse_size("2 parsecs").is_err());
+
+        assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * 86_400)));
+        assert!(parse_duration("d").is_err());
+
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(0), "0 B");
+    }
+}
@@ -0,0 +1,110 @@
+use annotations::{self, Annotation, Severity};
+use colored::*;
+use error::DefaultResult;
+use i18n;
+use output;
+use std::path::Path;
+
+/// Denying this pseudo code promotes every warning to an error
+pub const ALL_WARNINGS: &str = "warnings";
+
+pub const MISSING_HCIGNORE: &str = "missing-hcignore";
+pub const LARGE_ARTIFACT: &str = "large-artifact";
+pub const MISSING_DESCRIPTION: &str = "missing-description";
+pub const HOST_SPECIFIC_VALUE: &str = "host-specific-value";
+pub const REMOTE_CACHE_UNAVAILABLE: &str = "remote-cache-unavailable";
+
+pub const KNOWN_CODES: &[&str] = &[
+    MISSING_HCIGNORE,
+    LARGE_ARTIFACT,
+    MISSING_DESCRIPTION,
+    HOST_SPECIFIC_VALUE,
+    REMOTE_CACHE_UNAVAILABLE,
+];
+
+/// Artifacts bigger than this trigger a `large-artifact` warning
+pub const LARGE_ARTIFACT_THRESHOLD: u64 = 2 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct Warning {
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Collects the warnings emitted during a command and decides which of them are errors
+pub struct Warnings {
+    deny: Vec<String>,
+    emitted: Vec<Warning>,
+}
+
+impl Warnings {
+    pub fn new(deny: Vec<String>) -> DefaultResult<Warnings> {
+        for code in &deny {
+            ensure!(
+                code == ALL_WARNINGS || KNOWN_CODES.contains(&code.as_str()),
+                "unknown warning code: {} (known codes are {} and {})",
+                code,
+                KNOWN_CODES.join(", "),
+                ALL_WARNINGS
+            );
+        }
+
+        Ok(Warnings {
+            deny,
+            emitted: Vec::new(),
+        })
+    }
+
+    pub fn is_denied(&self, code: &str) -> bool {
+        self.deny
+            .iter()
+            .any(|denied| denied == code || denied == ALL_WARNINGS)
+    }
+
+    /// Emits a warning about `file`, which CI annotations point at
+    pub fn warn<S: Into<String>>(&mut self, code: &'static str, file: &Path, message: S) {
+        let warning = Warning {
+            code,
+            message: message.into(),
+        };
+
+        let (label, severity) = if self.is_denied(code) {
+            (format!("error[{}]", code).red().bold(), Severity::Error)
+        } else {
+            (
+                format!("warning[{}]", code).yellow().bold(),
+                Severity::Warning,
+            )
+        };
+
+        let line = format!("{}: {}", label, warning.message);
+
+        if severity == Severity::Error {
+            output::error(line);
+        } else {
+            output::print_err(line);
+        }
+
+        annotations::annotate(
+            Annotation::new(severity, code, warning.message.clone()).in_file(file),
+        );
+
+        self.emitted.push(warning);
+    }
+
+    /// Fails if any of the emitted warnings was denied
+    pub fn finish(&self) -> DefaultResult<()> {
+        let denied = self
+            .emitted
+            .iter()
+            .filter(|warning| self.is_denied(warning.code))
+            .count();
+
+        ensure!(
+            denied == 0,
+            i18n::tr("warnings-denied", &[("count", denied.to_string())])
+        );
+
+        Ok(())
+    }
+}
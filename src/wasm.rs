@@ -0,0 +1,542 @@
+//! Just enough of a WebAssembly binary parser to tell how large each function is and what it
+//! is called.
+
+use error::DefaultResult;
+
+const MAGIC: &[u8] = b"\0asm";
+
+const CUSTOM_SECTION_ID: u8 = 0;
+const IMPORT_SECTION_ID: u8 = 2;
+const MEMORY_SECTION_ID: u8 = 5;
+const EXPORT_SECTION_ID: u8 = 7;
+const CODE_SECTION_ID: u8 = 10;
+const DATA_SECTION_ID: u8 = 11;
+
+const FUNCTION_IMPORT_KIND: u8 = 0;
+const NAME_SECTION_NAME: &str = "name";
+const FUNCTION_NAMES_SUBSECTION_ID: u8 = 1;
+
+pub struct Section {
+    pub name: String,
+    pub size: u64,
+    /// Where the content of the section starts within the module
+    pub offset: u64,
+}
+
+pub struct Function {
+    pub index: u32,
+    pub name: Option<String>,
+    /// Size of the function body in bytes
+    pub size: u64,
+    /// Where the body starts within the content of the code section, which is what DWARF
+    /// addresses refer to
+    pub offset: u64,
+}
+
+/// A function the module imports
+pub struct Import {
+    /// The module it is imported from, `env` for the host functions of the conductor
+    pub module: String,
+    pub name: String,
+}
+
+/// The size of a memory, in pages of 64 KiB
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    pub initial: u32,
+    /// How far the memory may grow, if it is limited at all
+    pub maximum: Option<u32>,
+}
+
+pub struct Module {
+    pub sections: Vec<Section>,
+    pub functions: Vec<Function>,
+    /// The functions the module imports from the host
+    pub imports: Vec<Import>,
+    /// The names of everything the module exports
+    pub exports: Vec<String>,
+    /// The memories the module defines or imports
+    pub memories: Vec<Limits>,
+}
+
+impl Module {
+    pub fn parse(bytes: &[u8]) -> DefaultResult<Module> {
+        ensure!(
+            bytes.len() >= 8 && &bytes[..4] == MAGIC,
+            "not a WebAssembly binary"
+        );
+
+        let mut reader = Reader::new(&bytes[8..]);
+        let mut sections = Vec::new();
+        let mut bodies = Vec::new();
+        let mut imports = Vec::new();
+        let mut exports = Vec::new();
+        let mut memories = Vec::new();
+        let mut names = Vec::new();
+
+        while !reader.is_empty() {
+            let id = reader.byte()?;
+            let size = reader.u32()? as usize;
+            let offset = (bytes.len() - reader.bytes.len()) as u64;
+            let mut content = Reader::new(reader.bytes(size)?);
+
+            let name = match id {
+                CUSTOM_SECTION_ID => {
+                    let name = content.name()?;
+
+                    if name == NAME_SECTION_NAME {
+                        // names are a nice to have, a broken name section shouldn't stop us
+                        names = function_names(&mut content).unwrap_or_default();
+                    }
+
+                    format!("custom \"{}\"", name)
+                }
+                IMPORT_SECTION_ID => {
+                    imports = imported_functions(&mut content, &mut memories)?;
+                    "import".into()
+                }
+                MEMORY_SECTION_ID => {
+                    for _ in 0..content.u32()? {
+                        memories.push(content.limits()?);
+                    }
+                    "memory".into()
+                }
+                EXPORT_SECTION_ID => {
+                    for _ in 0..content.u32()? {
+                        exports.push(content.name()?);
+                        content.byte()?;
+                        content.u32()?;
+                    }
+                    "export".into()
+                }
+                CODE_SECTION_ID => {
+                    for _ in 0..content.u32()? {
+                        let body_size = content.u32()? as usize;
+                        let body_offset = (size - content.bytes.len()) as u64;
+                        content.bytes(body_size)?;
+                        bodies.push((body_offset, body_size as u64));
+                    }
+                    "code".into()
+                }
+                DATA_SECTION_ID => "data".into(),
+                1 => "type".into(),
+                3 => "function".into(),
+                4 => "table".into(),
+                6 => "global".into(),
+                8 => "start".into(),
+                9 => "element".into(),
+                12 => "data count".into(),
+                _ => format!("unknown ({})", id),
+            };
+
+            sections.push(Section {
+                name,
+                size: size as u64,
+                offset,
+            });
+        }
+
+        let functions = bodies
+            .into_iter()
+            .enumerate()
+            .map(|(position, (offset, size))| {
+                let index = imports.len() as u32 + position as u32;
+
+                Function {
+                    index,
+                    name: names
+                        .iter()
+                        .find(|(name_index, _)| *name_index == index)
+                        .map(|(_, name)| name.clone()),
+                    size,
+                    offset,
+                }
+            })
+            .collect();
+
+        Ok(Module {
+            sections,
+            functions,
+            imports,
+            exports,
+            memories,
+        })
+    }
+
+    pub fn has_names(&self) -> bool {
+        self.functions
+            .iter()
+            .any(|function| function.name.is_some())
+    }
+
+    /// Where the content of the code section starts within the module
+    pub fn code_offset(&self) -> Option<u64> {
+        self.sections
+            .iter()
+            .find(|section| section.name == "code")
+            .map(|section| section.offset)
+    }
+
+    /// The defined function whose body contains the given offset into the code section
+    pub fn function_at(&self, code_offset: u64) -> Option<&Function> {
+        self.functions.iter().find(|function| {
+            function.offset <= code_offset && code_offset < function.offset + function.size
+        })
+    }
+}
+
+/// Returns the content of the custom section with the given name, like `.debug_line`
+pub fn custom_section<'a>(bytes: &'a [u8], name: &str) -> DefaultResult<Option<&'a [u8]>> {
+    ensure!(
+        bytes.len() >= 8 && &bytes[..4] == MAGIC,
+        "not a WebAssembly binary"
+    );
+
+    let mut reader = Reader::new(&bytes[8..]);
+
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        let mut content = Reader::new(reader.bytes(size)?);
+
+        if id == CUSTOM_SECTION_ID && content.name()? == name {
+            return Ok(Some(content.bytes));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Removes all custom sections, like the function names, DWARF debug info and producer notes.
+/// Function indices and code offsets within the code section stay the same.
+pub fn strip_custom_sections(bytes: &[u8]) -> DefaultResult<Vec<u8>> {
+    ensure!(
+        bytes.len() >= 8 && &bytes[..4] == MAGIC,
+        "not a WebAssembly binary"
+    );
+
+    let mut stripped = bytes[..8].to_vec();
+    let mut reader = Reader::new(&bytes[8..]);
+
+    while !reader.is_empty() {
+        let section_start = reader.bytes;
+        let id = reader.byte()?;
+        let size = reader.u32()? as usize;
+        reader.bytes(size)?;
+
+        if id != CUSTOM_SECTION_ID {
+            let section_size = section_start.len() - reader.bytes.len();
+            stripped.extend_from_slice(&section_start[..section_size]);
+        }
+    }
+
+    Ok(stripped)
+}
+
+fn imported_functions(
+    content: &mut Reader,
+    memories: &mut Vec<Limits>,
+) -> DefaultResult<Vec<Import>> {
+    let mut functions = Vec::new();
+
+    for _ in 0..content.u32()? {
+        let module = content.name()?;
+        let name = content.name()?;
+
+        match content.byte()? {
+            FUNCTION_IMPORT_KIND => {
+                content.u32()?;
+                functions.push(Import { module, name });
+            }
+            // table: element type and limits
+            1 => {
+                content.byte()?;
+                content.limits()?;
+            }
+            // memory: limits
+            2 => memories.push(content.limits()?),
+            // global: value type and mutability
+            3 => {
+                content.bytes(2)?;
+            }
+            kind => bail!("unknown import kind {}", kind),
+        }
+    }
+
+    Ok(functions)
+}
+
+fn function_names(content: &mut Reader) -> DefaultResult<Vec<(u32, String)>> {
+    while !content.is_empty() {
+        let id = content.byte()?;
+        let size = content.u32()? as usize;
+        let mut subsection = Reader::new(content.bytes(size)?);
+
+        if id == FUNCTION_NAMES_SUBSECTION_ID {
+            let mut names = Vec::new();
+
+            for _ in 0..subsection.u32()? {
+                let index = subsection.u32()?;
+                names.push((index, subsection.name()?));
+            }
+
+            return Ok(names);
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn byte(&mut self) -> DefaultResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, count: usize) -> DefaultResult<&'a [u8]> {
+        ensure!(
+            count <= self.bytes.len(),
+            "unexpected end of WebAssembly binary"
+        );
+
+        let (head, tail) = self.bytes.split_at(count);
+        self.bytes = tail;
+
+        Ok(head)
+    }
+
+    /// Reads an unsigned LEB128 encoded integer
+    fn u32(&mut self) -> DefaultResult<u32> {
+        let mut result: u64 = 0;
+
+        for shift in (0..5).map(|i| i * 7) {
+            let byte = self.byte()?;
+            result |= u64::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                ensure!(result <= u64::from(u32::max_value()), "integer too large");
+
+                return Ok(result as u32);
+            }
+        }
+
+        bail!("integer too large")
+    }
+
+    fn name(&mut self) -> DefaultResult<String> {
+        let length = self.u32()? as usize;
+
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+
+    fn limits(&mut self) -> DefaultResult<Limits> {
+        let flags = self.byte()?;
+        let initial = self.u32()?;
+        let maximum = if flags & 1 == 1 {
+            Some(self.u32()?)
+        } else {
+            None
+        };
+
+        Ok(Limits { initial, maximum })
+    }
+}
+
+/// Assembles WebAssembly binaries for tests
+#[cfg(test)]
+pub mod test_support {
+    fn leb(mut value: u32, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+
+            out.push(byte | 0x80);
+        }
+    }
+
+    fn section(id: u8, content: Vec<u8>, out: &mut Vec<u8>) {
+        out.push(id);
+        leb(content.len() as u32, out);
+        out.extend(content);
+    }
+
+    fn name(value: &str, out: &mut Vec<u8>) {
+        leb(value.len() as u32, out);
+        out.extend(value.as_bytes());
+    }
+
+    /// Creates a module with one imported function and a defined function per given name,
+    /// whose bodies are padded to the given size
+    pub fn module(functions: &[(&str, u32)]) -> Vec<u8> {
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+
+        section(2, imports(&["imported"]), &mut bytes);
+
+        let mut code = Vec::new();
+        leb(functions.len() as u32, &mut code);
+
+        for (_, size) in functions {
+            leb(*size, &mut code);
+            code.extend(vec![0u8; *size as usize]);
+        }
+
+        section(10, code, &mut bytes);
+
+        let mut names = Vec::new();
+        leb(functions.len() as u32, &mut names);
+
+        for (index, (function_name, _)) in functions.iter().enumerate() {
+            leb(index as u32 + 1, &mut names);
+            name(function_name, &mut names);
+        }
+
+        let mut name_section = Vec::new();
+        name("name", &mut name_section);
+        name_section.push(1);
+        leb(names.len() as u32, &mut name_section);
+        name_section.extend(names);
+        section(0, name_section, &mut bytes);
+
+        bytes
+    }
+
+    /// Creates a module that only imports and exports functions of the given names, imported
+    /// from `env` unless given as `<module>/<name>`
+    pub fn linked_module(imported: &[&str], exported: &[&str]) -> Vec<u8> {
+        linked_module_with_memory(imported, exported, &[])
+    }
+
+    /// Like `linked_module`, defining memories of the given initial and maximum pages too
+    pub fn linked_module_with_memory(
+        imported: &[&str],
+        exported: &[&str],
+        memories: &[(u32, Option<u32>)],
+    ) -> Vec<u8> {
+        let mut bytes = b"\0asm\x01\0\0\0".to_vec();
+
+        section(2, imports(imported), &mut bytes);
+
+        if !memories.is_empty() {
+            let mut memory = Vec::new();
+            leb(memories.len() as u32, &mut memory);
+
+            for (initial, maximum) in memories {
+                match maximum {
+                    Some(maximum) => {
+                        memory.push(1);
+                        leb(*initial, &mut memory);
+                        leb(*maximum, &mut memory);
+                    }
+                    None => {
+                        memory.push(0);
+                        leb(*initial, &mut memory);
+                    }
+                }
+            }
+
+            section(5, memory, &mut bytes);
+        }
+
+        let mut exports = Vec::new();
+        leb(exported.len() as u32, &mut exports);
+
+        for export in exported {
+            name(export, &mut exports);
+            exports.extend(&[0, 0]);
+        }
+
+        section(7, exports, &mut bytes);
+
+        bytes
+    }
+
+    fn imports(imported: &[&str]) -> Vec<u8> {
+        let mut imports = Vec::new();
+        leb(imported.len() as u32, &mut imports);
+
+        for import in imported {
+            let (module, function) = match import.rfind('/') {
+                Some(slash) => (&import[..slash], &import[slash + 1..]),
+                None => ("env", *import),
+            };
+
+            name(module, &mut imports);
+            name(function, &mut imports);
+            imports.extend(&[0, 0]);
+        }
+
+        imports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_function_sizes_and_names() {
+        let module = Module::parse(&test_support::module(&[("small", 3), ("large", 300)])).unwrap();
+
+        assert!(module.has_names());
+        assert_eq!(module.functions.len(), 2);
+        assert_eq!(module.functions[1].index, 2);
+        assert_eq!(module.functions[1].name, Some("large".to_string()));
+        assert_eq!(module.functions[1].size, 300);
+        assert!(module.sections.iter().any(|section| section.name == "code"));
+        assert_eq!(module.imports[0].module, "env");
+        assert_eq!(module.imports[0].name, "imported");
+
+        let linked = Module::parse(&test_support::linked_module(
+            &["hc_debug", "__wbindgen_placeholder__/__wbindgen_describe"],
+            &["main"],
+        ))
+        .unwrap();
+        assert_eq!(linked.exports, vec!["main".to_string()]);
+        assert_eq!(linked.imports[1].module, "__wbindgen_placeholder__");
+        assert!(linked.memories.is_empty());
+
+        let with_memory = Module::parse(&test_support::linked_module_with_memory(
+            &[],
+            &[],
+            &[(17, Some(32))],
+        ))
+        .unwrap();
+        assert_eq!(
+            with_memory.memories,
+            vec![Limits {
+                initial: 17,
+                maximum: Some(32)
+            }]
+        );
+
+        assert!(Module::parse(b"{\"not\": \"wasm\"}").is_err());
+    }
+
+    #[test]
+    fn stripping_keeps_the_code() {
+        let bytes = test_support::module(&[("small", 3), ("large", 300)]);
+        let stripped = strip_custom_sections(&bytes).unwrap();
+        let module = Module::parse(&stripped).unwrap();
+
+        assert!(stripped.len() < bytes.len());
+        assert!(!module.has_names());
+        assert_eq!(module.functions[1].size, 300);
+        assert!(module.sections.iter().all(|section| section.name != "custom \"name\""));
+    }
+}
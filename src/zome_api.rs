@@ -0,0 +1,617 @@
+//! Best-effort extraction of a Rust zome's public interface from its `define_zome!` invocation.
+//!
+//! This is not a Rust parser. It understands the shape of the hdk macros well enough to list
+//! entry types and zome functions, and skips anything it doesn't recognize.
+
+use error::DefaultResult;
+use ignore::WalkBuilder;
+use serde_json::{Map, Value};
+use std::{fs, path::Path};
+
+const DEFINE_ZOME_MACRO: &str = "define_zome!";
+const ENTRY_MACRO: &str = "entry!";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ZomeApi {
+    pub entries: Vec<EntryType>,
+    pub functions: Vec<Function>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct EntryType {
+    pub name: String,
+    pub description: String,
+    pub native_type: Option<String>,
+    pub has_validation: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub capability: String,
+    pub membrane: String,
+    pub doc: String,
+    pub inputs: Vec<Param>,
+    pub outputs: Vec<Param>,
+    pub handler: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Param {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+impl ZomeApi {
+    /// Scans all Rust sources below the given code directory and collects the interface
+    /// declared by its `define_zome!` invocation
+    pub fn from_code_dir<T: AsRef<Path>>(code_dir: T) -> DefaultResult<ZomeApi> {
+        let mut sources: Vec<_> = WalkBuilder::new(code_dir.as_ref().join("src"))
+            .build()
+            .filter_map(|e| e.ok())
+            .map(|e| e.path().to_path_buf())
+            .filter(|path| path.is_file() && path.extension().map_or(false, |ext| ext == "rs"))
+            .collect();
+
+        sources.sort();
+
+        let mut api = ZomeApi::default();
+
+        for source in sources {
+            let contents = fs::read_to_string(&source)?;
+
+            if let Some(body) = macro_body(&contents, DEFINE_ZOME_MACRO) {
+                api = ZomeApi::from_define_zome(&body);
+                break;
+            }
+        }
+
+        Ok(api)
+    }
+
+    /// Extracts the interface from the body of a `define_zome!` invocation
+    pub fn from_define_zome(body: &str) -> ZomeApi {
+        let mut entries = Vec::new();
+        let mut rest = body;
+
+        while let Some(entry_body) = macro_body(rest, ENTRY_MACRO) {
+            entries.push(EntryType::from_entry_macro(&entry_body));
+
+            let consumed = rest.find(ENTRY_MACRO).unwrap() + ENTRY_MACRO.len() + entry_body.len();
+            rest = &rest[consumed..];
+        }
+
+        let functions = find_key(body, "functions")
+            .and_then(|pos| block_after(body, pos, '{', '}'))
+            .map(|block| parse_function_groups(&block))
+            .unwrap_or_default();
+
+        ZomeApi { entries, functions }
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.functions.is_empty()
+    }
+
+    /// Returns the `entry_types` section of a zome manifest describing this interface
+    pub fn entry_types_manifest(&self) -> Value {
+        let entry_types: Map<String, Value> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.name.clone(),
+                    json!({ "description": entry.description }),
+                )
+            })
+            .collect();
+
+        entry_types.into()
+    }
+
+    /// Returns the `capabilities` section of a zome manifest describing this interface
+    pub fn capabilities_manifest(&self) -> Value {
+        let mut capabilities = Map::new();
+
+        for function in &self.functions {
+            let capability = capabilities
+                .entry(function.capability.clone())
+                .or_insert_with(|| {
+                    json!({
+                        "capability": { "membrane": function.membrane },
+                        "functions": []
+                    })
+                });
+
+            let declaration = json!({
+                "name": function.name,
+                "inputs": params_manifest(&function.inputs),
+                "outputs": params_manifest(&function.outputs),
+            });
+
+            capability["functions"]
+                .as_array_mut()
+                .unwrap()
+                .push(declaration);
+        }
+
+        capabilities.into()
+    }
+}
+
+fn params_manifest(params: &[Param]) -> Value {
+    params
+        .iter()
+        .map(|param| json!({ "name": param.name, "parameter_type": param.ty }))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+impl Function {
+    /// Returns an argument object with a placeholder value for every input
+    pub fn example_args(&self) -> Value {
+        let args: Map<String, Value> = self
+            .inputs
+            .iter()
+            .map(|param| (param.name.clone(), param.placeholder()))
+            .collect();
+
+        args.into()
+    }
+
+    /// Checks the given arguments against the declared inputs and describes every mismatch
+    pub fn check_args(&self, args: &Value) -> Vec<String> {
+        let args = match args.as_object() {
+            Some(args) => args,
+            None => return vec!["arguments have to be a JSON object".into()],
+        };
+
+        let mut problems = Vec::new();
+
+        for param in &self.inputs {
+            match args.get(&param.name) {
+                None if !param.is_optional() => problems.push(format!(
+                    "missing field `{}` of type {}",
+                    param.name, param.ty
+                )),
+                Some(value) if !param.accepts(value) => problems.push(format!(
+                    "field `{}` should be of type {}, got {}",
+                    param.name, param.ty, value
+                )),
+                _ => (),
+            }
+        }
+
+        for name in args.keys() {
+            if !self.inputs.iter().any(|param| &param.name == name) {
+                let expected: Vec<_> = self
+                    .inputs
+                    .iter()
+                    .map(|param| format!("`{}`", param.name))
+                    .collect();
+
+                problems.push(match expected.len() {
+                    0 => format!("unexpected field `{}`, {} takes no inputs", name, self.name),
+                    _ => format!(
+                        "unexpected field `{}`, expected one of {}",
+                        name,
+                        expected.join(", ")
+                    ),
+                });
+            }
+        }
+
+        problems
+    }
+}
+
+impl Param {
+    fn is_optional(&self) -> bool {
+        self.ty.starts_with("Option<")
+    }
+
+    /// Whether the given JSON value could be deserialized into this parameter's type. Types
+    /// that aren't known are assumed to accept anything.
+    fn accepts(&self, value: &Value) -> bool {
+        match (json_kind(&self.ty), value) {
+            (_, Value::Null) => self.is_optional(),
+            (Some("string"), value) => value.is_string(),
+            (Some("number"), value) => value.is_number(),
+            (Some("boolean"), value) => value.is_boolean(),
+            (Some("array"), value) => value.is_array(),
+            (Some("object"), value) => value.is_object(),
+            _ => true,
+        }
+    }
+
+    fn placeholder(&self) -> Value {
+        match json_kind(&self.ty) {
+            _ if self.is_optional() => Value::Null,
+            Some("string") => "".into(),
+            Some("number") => 0.into(),
+            Some("boolean") => false.into(),
+            Some("array") => json!([]),
+            Some("object") => json!({}),
+            _ => format!("<{}>", self.ty).into(),
+        }
+    }
+}
+
+/// Maps a Rust type to the kind of JSON value it is serialized as, if known
+fn json_kind(ty: &str) -> Option<&'static str> {
+    let base = ty.split('<').next().unwrap_or(ty).trim();
+
+    match base {
+        "String" | "&str" | "str" | "char" | "Address" | "HashString" => Some("string"),
+        "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize"
+        | "f32" | "f64" => Some("number"),
+        "bool" => Some("boolean"),
+        "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => Some("array"),
+        "HashMap" | "BTreeMap" => Some("object"),
+        _ => None,
+    }
+}
+
+impl EntryType {
+    fn from_entry_macro(body: &str) -> EntryType {
+        EntryType {
+            name: string_value(body, "name").unwrap_or_default(),
+            description: string_value(body, "description").unwrap_or_default(),
+            native_type: ident_value(body, "native_type"),
+            has_validation: find_key(body, "validation").is_some(),
+        }
+    }
+}
+
+/// Returns the text between the delimiters following the first occurrence of the given macro
+fn macro_body(source: &str, name: &str) -> Option<String> {
+    let pos = source.find(name)? + name.len();
+    let open = source[pos..].chars().find(|c| !c.is_whitespace())?;
+
+    let close = match open {
+        '{' => '}',
+        '(' => ')',
+        '[' => ']',
+        _ => return None,
+    };
+
+    block_after(source, pos, open, close)
+}
+
+/// Returns the contents of the first delimited block starting at or after `pos`
+fn block_after(source: &str, pos: usize, open: char, close: char) -> Option<String> {
+    let start = pos + source[pos..].find(open)? + open.len_utf8();
+    let mut depth = 1;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (offset, c) in source[start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            _ if c == open => depth += 1,
+            _ if c == close => {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some(source[start..start + offset].to_string());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// Finds `key:` as a whole word and returns the position right after the colon
+fn find_key(source: &str, key: &str) -> Option<usize> {
+    let mut search_from = 0;
+
+    while let Some(found) = source[search_from..].find(key) {
+        let start = search_from + found;
+        let end = start + key.len();
+
+        let preceded_by_ident = source[..start]
+            .chars()
+            .last()
+            .map_or(false, |c| c.is_alphanumeric() || c == '_');
+
+        if !preceded_by_ident && source[end..].trim_start().starts_with(':') {
+            let colon = end + source[end..].find(':').unwrap();
+
+            return Some(colon + 1);
+        }
+
+        search_from = end;
+    }
+
+    None
+}
+
+fn string_value(source: &str, key: &str) -> Option<String> {
+    let pos = find_key(source, key)?;
+    let rest = source[pos..].trim_start();
+
+    if !rest.starts_with('"') {
+        return None;
+    }
+
+    rest[1..].find('"').map(|end| rest[1..=end].to_string())
+}
+
+fn ident_value(source: &str, key: &str) -> Option<String> {
+    let pos = find_key(source, key)?;
+    let ident: String = source[pos..]
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == ':')
+        .collect();
+
+    if ident.is_empty() {
+        None
+    } else {
+        Some(ident)
+    }
+}
+
+/// Parses `capability (Visibility) { fn_name: { ... } ... }` groups
+fn parse_function_groups(block: &str) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let mut rest = block;
+
+    while let Some(open) = rest.find('{') {
+        let header = rest[..open].trim();
+        let capability = header
+            .split(|c: char| c == '(' || c.is_whitespace())
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let membrane = header
+            .find('(')
+            .and_then(|start| block_after(header, start, '(', ')'))
+            .map(|membrane| membrane.trim().to_lowercase())
+            .unwrap_or_else(|| "public".into());
+
+        let group = match block_after(rest, open, '{', '}') {
+            Some(group) => group,
+            None => break,
+        };
+
+        functions.extend(parse_functions(&group, &capability, &membrane));
+
+        rest = &rest[open + group.len() + 2..];
+    }
+
+    functions
+}
+
+/// Parses `/// doc \n fn_name: { inputs: |..|, outputs: |..|, handler: ident }` definitions
+fn parse_functions(group: &str, capability: &str, membrane: &str) -> Vec<Function> {
+    let mut functions = Vec::new();
+    let mut rest = group;
+
+    while let Some(open) = rest.find('{') {
+        let header = &rest[..open];
+        let mut doc_lines = Vec::new();
+        let mut name = String::new();
+
+        for line in header.lines().map(str::trim) {
+            if line.starts_with("///") {
+                doc_lines.push(line[3..].trim().to_string());
+            } else if line.ends_with(':') {
+                name = line.trim_end_matches(':').trim().to_string();
+            } else if !line.is_empty() && !line.starts_with("//") {
+                doc_lines.clear();
+            }
+        }
+
+        let definition = match block_after(rest, open, '{', '}') {
+            Some(definition) => definition,
+            None => break,
+        };
+
+        if !name.is_empty() {
+            functions.push(Function {
+                name,
+                capability: capability.to_string(),
+                membrane: membrane.to_string(),
+                doc: doc_lines.join(" "),
+                inputs: pipe_params(&definition, "inputs"),
+                outputs: pipe_params(&definition, "outputs"),
+                handler: ident_value(&definition, "handler"),
+            });
+        }
+
+        rest = &rest[open + definition.len() + 2..];
+    }
+
+    functions
+}
+
+/// Parses a `key: |name: Type, other: Type|` parameter list
+fn pipe_params(source: &str, key: &str) -> Vec<Param> {
+    let list = find_key(source, key).and_then(|pos| {
+        let rest = source[pos..].trim_start();
+
+        if rest.starts_with('|') {
+            rest[1..].find('|').map(|end| rest[1..=end].to_string())
+        } else {
+            None
+        }
+    });
+
+    let list = match list {
+        Some(list) => list,
+        None => return Vec::new(),
+    };
+
+    split_top_level(&list)
+        .into_iter()
+        .filter_map(|param| {
+            let mut parts = param.splitn(2, ':');
+            let name = parts.next()?.trim().to_string();
+            let ty = parts.next()?.trim().to_string();
+
+            Some(Param { name, ty })
+        })
+        .collect()
+}
+
+/// Splits on commas that aren't nested inside generics, tuples or arrays
+fn split_top_level(list: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+
+    for c in list.chars() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(current.clone());
+                current.clear();
+                continue;
+            }
+            _ => (),
+        }
+
+        current.push(c);
+    }
+
+    parts.push(current);
+
+    parts
+        .into_iter()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZOME_SOURCE: &str = r#"
+        define_zome! {
+            entries: [
+                entry!(
+                    name: "post",
+                    description: "A blog post",
+                    sharing: Sharing::Public,
+                    native_type: Post,
+                    validation_package: || hdk::ValidationPackageDefinition::Entry,
+                    validation: |post: Post, _ctx: hdk::ValidationData| { Ok(()) }
+                )
+            ]
+
+            genesis: || { Ok(()) }
+
+            functions: {
+                main (Public) {
+                    /// Creates a new post
+                    create_post: {
+                        inputs: |content: String, tags: HashMap<String, u32>|,
+                        outputs: |result: JsonString|,
+                        handler: handle_create_post
+                    }
+                    get_post: {
+                        inputs: |address: Address|,
+                        outputs: |post: Option<Post>|,
+                        handler: handle_get_post
+                    }
+                }
+            }
+        }
+    "#;
+
+    #[test]
+    fn extracts_entries_and_functions() {
+        let api = ZomeApi::from_define_zome(&macro_body(ZOME_SOURCE, DEFINE_ZOME_MACRO).unwrap());
+
+        assert_eq!(
+            api.entries,
+            vec![EntryType {
+                name: "post".into(),
+                description: "A blog post".into(),
+                native_type: Some("Post".into()),
+                has_validation: true,
+            }]
+        );
+
+        assert_eq!(api.functions.len(), 2);
+
+        let create_post = &api.functions[0];
+
+        assert_eq!(create_post.capability, "main");
+        assert_eq!(create_post.membrane, "public");
+        assert_eq!(create_post.doc, "Creates a new post");
+        assert_eq!(create_post.handler, Some("handle_create_post".into()));
+        assert_eq!(
+            create_post.inputs,
+            vec![
+                Param {
+                    name: "content".into(),
+                    ty: "String".into(),
+                },
+                Param {
+                    name: "tags".into(),
+                    ty: "HashMap<String, u32>".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_manifest_sections() {
+        let api = ZomeApi::from_define_zome(&macro_body(ZOME_SOURCE, DEFINE_ZOME_MACRO).unwrap());
+
+        assert_eq!(
+            api.entry_types_manifest(),
+            json!({ "post": { "description": "A blog post" } })
+        );
+
+        let capabilities = api.capabilities_manifest();
+
+        assert_eq!(capabilities["main"]["capability"]["membrane"], "public");
+        assert_eq!(capabilities["main"]["functions"][1]["name"], "get_post");
+        assert_eq!(
+            capabilities["main"]["functions"][1]["outputs"],
+            json!([{ "name": "post", "parameter_type": "Option<Post>" }])
+        );
+    }
+
+    #[test]
+    fn checks_call_arguments() {
+        let api = ZomeApi::from_define_zome(&macro_body(ZOME_SOURCE, DEFINE_ZOME_MACRO).unwrap());
+        let create_post = api.function("create_post").unwrap();
+
+        assert_eq!(
+            create_post.example_args(),
+            json!({ "content": "", "tags": {} })
+        );
+        assert!(create_post.check_args(&create_post.example_args()).is_empty());
+        assert_eq!(
+            create_post.check_args(&json!({ "content": 1, "title": "" })),
+            vec![
+                "field `content` should be of type String, got 1".to_string(),
+                "missing field `tags` of type HashMap<String, u32>".to_string(),
+                "unexpected field `title`, expected one of `content`, `tags`".to_string(),
+            ]
+        );
+    }
+}